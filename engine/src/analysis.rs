@@ -0,0 +1,206 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::num::NonZeroU8;
+use std::path::Path;
+use std::sync::Arc;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use model::{CheckersBitBoard, Move, MoveDirection, PieceColor};
+use thiserror::Error;
+
+use crate::{Evaluation, TranspositionTable};
+
+const MAGIC: u32 = u32::from_be_bytes(*b".aan");
+const SUPPORTED_VERSION: u16 = 0;
+
+/// The state of an analysis, which can be saved to a file and resumed later
+#[derive(Clone)]
+pub struct Analysis {
+	pub position: CheckersBitBoard,
+	/// The deepest iteration that was completed
+	pub depth: u8,
+	/// The root moves and their scores, from best to worst
+	pub root_moves: Vec<(Move, Evaluation)>,
+	/// The transposition table, if it was saved
+	pub transposition_table: Option<Arc<TranspositionTable>>,
+}
+
+#[derive(Debug, Error)]
+pub enum AnalysisFileError {
+	#[error("Invalid analysis file: the magic header field was incorrect")]
+	MagicError,
+	#[error("This version of the analysis file format is unsupported. Only {SUPPORTED_VERSION} is supported")]
+	UnsupportedVersion(u16),
+	#[error("Invalid analysis file: a transposition table entry has a depth of zero")]
+	ZeroDepth,
+	#[error(transparent)]
+	IoError(#[from] io::Error),
+}
+
+fn read_board(reader: &mut impl Read) -> io::Result<CheckersBitBoard> {
+	let pieces = reader.read_u32::<BigEndian>()?;
+	let color = reader.read_u32::<BigEndian>()?;
+	let kings = reader.read_u32::<BigEndian>()?;
+	let turn = if reader.read_u8()? == PieceColor::Dark as u8 {
+		PieceColor::Dark
+	} else {
+		PieceColor::Light
+	};
+
+	Ok(CheckersBitBoard::new(pieces, color, kings, turn))
+}
+
+fn write_board(writer: &mut impl Write, board: CheckersBitBoard) -> io::Result<()> {
+	let pieces = board.pieces_bits();
+	writer.write_u32::<BigEndian>(pieces)?;
+	writer.write_u32::<BigEndian>(board.color_bits() & pieces)?;
+	writer.write_u32::<BigEndian>(board.king_bits() & pieces)?;
+	writer.write_u8(board.turn() as u8)
+}
+
+fn read_move(reader: &mut impl Read) -> io::Result<Move> {
+	let start = reader.read_u8()?;
+	let direction = match reader.read_u8()? {
+		0 => MoveDirection::ForwardLeft,
+		1 => MoveDirection::ForwardRight,
+		2 => MoveDirection::BackwardLeft,
+		_ => MoveDirection::BackwardRight,
+	};
+	let jump = reader.read_u8()? != 0;
+
+	Ok(Move::new(start as usize % 32, direction, jump))
+}
+
+fn write_move(writer: &mut impl Write, checker_move: Move) -> io::Result<()> {
+	writer.write_u8(checker_move.start() as u8)?;
+	writer.write_u8(checker_move.direction() as u8)?;
+	writer.write_u8(checker_move.is_jump() as u8)
+}
+
+impl Analysis {
+	pub fn read(reader: &mut impl Read) -> Result<Self, AnalysisFileError> {
+		let magic = reader.read_u32::<BigEndian>()?;
+		if magic != MAGIC {
+			return Err(AnalysisFileError::MagicError);
+		}
+
+		let version = reader.read_u16::<BigEndian>()?;
+		if version != SUPPORTED_VERSION {
+			return Err(AnalysisFileError::UnsupportedVersion(version));
+		}
+
+		let position = read_board(reader)?;
+		let depth = reader.read_u8()?;
+
+		let len = reader.read_u16::<BigEndian>()?;
+		let mut root_moves = Vec::with_capacity(len as usize);
+		for _ in 0..len {
+			let checker_move = read_move(reader)?;
+			let score = Evaluation::from_bits(reader.read_i16::<BigEndian>()?);
+			root_moves.push((checker_move, score));
+		}
+
+		let transposition_table = if reader.read_u8()? != 0 {
+			let table = TranspositionTable::new(reader.read_u64::<BigEndian>()? as usize);
+			let table_ref = table.get_ref();
+			let len = reader.read_u64::<BigEndian>()?;
+			for _ in 0..len {
+				let board = read_board(reader)?;
+				let eval = Evaluation::from_bits(reader.read_i16::<BigEndian>()?);
+				let best_move = read_move(reader)?;
+				let depth =
+					NonZeroU8::new(reader.read_u8()?).ok_or(AnalysisFileError::ZeroDepth)?;
+				table_ref.insert(board, eval, best_move, depth);
+			}
+
+			Some(Arc::new(table))
+		} else {
+			None
+		};
+
+		Ok(Self {
+			position,
+			depth,
+			root_moves,
+			transposition_table,
+		})
+	}
+
+	pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+		writer.write_u32::<BigEndian>(MAGIC)?;
+		writer.write_u16::<BigEndian>(SUPPORTED_VERSION)?;
+		write_board(writer, self.position)?;
+		writer.write_u8(self.depth)?;
+
+		writer.write_u16::<BigEndian>(self.root_moves.len() as u16)?;
+		for (checker_move, score) in &self.root_moves {
+			write_move(writer, *checker_move)?;
+			writer.write_i16::<BigEndian>(score.to_bits())?;
+		}
+
+		match &self.transposition_table {
+			Some(table) => {
+				writer.write_u8(1)?;
+				writer.write_u64::<BigEndian>(table.size() as u64)?;
+				writer.write_u64::<BigEndian>(table.entries().count() as u64)?;
+				for (board, eval, best_move, depth) in table.entries() {
+					write_board(writer, board)?;
+					writer.write_i16::<BigEndian>(eval.to_bits())?;
+					write_move(writer, best_move)?;
+					writer.write_u8(depth.get())?;
+				}
+			}
+			None => writer.write_u8(0)?,
+		}
+
+		Ok(())
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, AnalysisFileError> {
+		Self::read(&mut BufReader::new(File::open(path)?))
+	}
+
+	pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		self.write(&mut writer)?;
+		writer.flush()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn round_trip() {
+		let table = TranspositionTable::new(1 << 12);
+		let position = CheckersBitBoard::starting_position();
+		let best_move = Move::new(9, MoveDirection::ForwardRight, false);
+		table.get_ref().insert(
+			position,
+			Evaluation::DRAW,
+			best_move,
+			NonZeroU8::new(3).unwrap(),
+		);
+
+		let analysis = Analysis {
+			position,
+			depth: 3,
+			root_moves: vec![(best_move, Evaluation::new(0.25))],
+			transposition_table: Some(Arc::new(table)),
+		};
+
+		let mut bytes = Vec::new();
+		analysis.write(&mut bytes).unwrap();
+		let read = Analysis::read(&mut bytes.as_slice()).unwrap();
+
+		assert_eq!(read.position, position);
+		assert_eq!(read.depth, 3);
+		assert_eq!(read.root_moves, analysis.root_moves);
+		let table = read.transposition_table.unwrap();
+		assert_eq!(
+			table.get_ref().get(position, 3),
+			Some((Evaluation::DRAW, best_move))
+		);
+	}
+}
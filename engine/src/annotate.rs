@@ -0,0 +1,273 @@
+use std::fmt::{self, Display};
+
+use model::{CheckersBitBoard, PieceColor};
+
+use crate::notation::{write_movetext, FullMove};
+use crate::{Engine, Evaluation, EvaluationSettings, PdnGame};
+
+/// How much worse than the best move a move has to be, to be dubious
+const DUBIOUS_THRESHOLD: f32 = 0.02;
+
+/// How much worse than the best move a move has to be, to be a mistake. This
+/// is about the value of a man in the opening.
+const MISTAKE_THRESHOLD: f32 = 0.05;
+
+/// How much worse than the best move a move has to be, to be a blunder
+const BLUNDER_THRESHOLD: f32 = 0.15;
+
+/// How bad a move was, compared to the best move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Judgement {
+	Dubious,
+	Mistake,
+	Blunder,
+}
+
+impl Judgement {
+	/// Judges a move by the evaluation of the best move, and the evaluation
+	/// of the move that was played, both from the perspective of the player
+	/// who moved. Returns `None` if the move was good enough.
+	pub fn judge(best: Evaluation, played: Evaluation) -> Option<Self> {
+		let swing = score(best) - score(played);
+		if swing >= BLUNDER_THRESHOLD || (!best.is_force_loss() && played.is_force_loss()) {
+			Some(Self::Blunder)
+		} else if swing >= MISTAKE_THRESHOLD {
+			Some(Self::Mistake)
+		} else if swing >= DUBIOUS_THRESHOLD {
+			Some(Self::Dubious)
+		} else {
+			None
+		}
+	}
+
+	/// The numeric annotation glyph, which is written in PDN as `$2`
+	pub const fn nag(self) -> u8 {
+		match self {
+			Self::Dubious => 6,
+			Self::Mistake => 2,
+			Self::Blunder => 4,
+		}
+	}
+
+	pub const fn symbol(self) -> &'static str {
+		match self {
+			Self::Dubious => "?!",
+			Self::Mistake => "?",
+			Self::Blunder => "??",
+		}
+	}
+}
+
+impl Display for Judgement {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = match self {
+			Self::Dubious => "dubious",
+			Self::Mistake => "mistake",
+			Self::Blunder => "blunder",
+		};
+		f.write_str(name)
+	}
+}
+
+/// The evaluation as a number, where forced wins are one and forced losses
+/// are negative one
+pub(crate) fn score(eval: Evaluation) -> f32 {
+	eval.to_f32()
+		.unwrap_or(if eval.is_force_win() { 1.0 } else { -1.0 })
+}
+
+/// An evaluation, rounded so that it fits in a comment
+pub(crate) fn comment_score(eval: Evaluation) -> String {
+	match eval.to_f32() {
+		Some(eval) => format!("{eval:+.3}"),
+		None => eval.to_string(),
+	}
+}
+
+/// A move of a game, along with the engine's opinion of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotatedMove {
+	pub played: FullMove,
+	/// The evaluation after the move, from the perspective of the player who
+	/// made it
+	pub eval: Evaluation,
+	/// The move the engine would have played, if it's different from the
+	/// move that was played, and its evaluation
+	pub best_move: Option<(FullMove, Evaluation)>,
+	pub judgement: Option<Judgement>,
+}
+
+impl AnnotatedMove {
+	/// The NAG and comment which are written after the move. Evaluations
+	/// are written from black's perspective.
+	pub fn annotation(&self, mover: PieceColor) -> String {
+		let black_score = |eval: Evaluation| {
+			if mover == PieceColor::Dark {
+				eval
+			} else {
+				-eval
+			}
+		};
+
+		let mut annotation = String::new();
+		if let Some(judgement) = self.judgement {
+			annotation.push_str(&format!("${} ", judgement.nag()));
+		}
+		annotation.push_str(&format!("{{{}", comment_score(black_score(self.eval))));
+		if let (Some(_), Some((best_move, eval))) = (self.judgement, &self.best_move) {
+			annotation.push_str(&format!(
+				", {best_move} was better ({})",
+				comment_score(black_score(*eval))
+			));
+		}
+		annotation.push('}');
+		annotation
+	}
+}
+
+/// Searches the position and returns the evaluation, from the perspective of
+/// the player whose turn it is, and the best move
+fn search(
+	engine: &Engine,
+	position: CheckersBitBoard,
+	settings: &EvaluationSettings,
+) -> (Evaluation, Option<FullMove>) {
+	engine.set_position(position);
+	let (eval, best_move) = engine.evaluate(None, settings.clone());
+
+	let best_move = best_move.and_then(|best_move| engine.best_full_move(position, best_move));
+	(eval, best_move)
+}
+
+/// Searches every position of a game, and judges each move by how much worse
+/// it is than the engine's best move. `on_move` is called with the index of
+/// each move once it's been annotated.
+pub fn annotate(
+	engine: &Engine,
+	game: &PdnGame,
+	settings: &EvaluationSettings,
+	mut on_move: impl FnMut(usize, &AnnotatedMove),
+) -> Vec<AnnotatedMove> {
+	let mut annotated = Vec::with_capacity(game.moves.len());
+	let (mut best_eval, mut best_move) = search(engine, game.start, settings);
+	for (index, played) in game.moves.iter().enumerate() {
+		let (next_eval, next_best_move) = search(engine, played.result, settings);
+
+		// if the best move was played, then the evaluations should match
+		let found_best = best_move
+			.as_ref()
+			.is_some_and(|best_move| best_move.result == played.result);
+		let eval = if found_best { best_eval } else { -next_eval };
+		let judgement = if found_best {
+			None
+		} else {
+			Judgement::judge(best_eval, eval)
+		};
+
+		let annotated_move = AnnotatedMove {
+			played: played.clone(),
+			eval,
+			best_move: best_move
+				.take()
+				.filter(|_| !found_best)
+				.map(|best_move| (best_move, best_eval)),
+			judgement,
+		};
+		on_move(index, &annotated_move);
+		annotated.push(annotated_move);
+
+		best_eval = next_eval;
+		best_move = next_best_move;
+	}
+
+	annotated
+}
+
+/// Writes an annotated game in PDN. The `tags` are written before the moves
+/// as they are, and should include the `FEN` tag if the game doesn't start
+/// from the starting position.
+pub fn to_pdn(
+	tags: &str,
+	start: CheckersBitBoard,
+	moves: &[AnnotatedMove],
+	result: &str,
+) -> String {
+	let mut pdn = String::new();
+	for tag in tags.lines().map(str::trim).filter(|tag| !tag.is_empty()) {
+		pdn.push_str(tag);
+		pdn.push('\n');
+	}
+	pdn.push('\n');
+
+	let mut position = start;
+	let mut annotations = Vec::with_capacity(moves.len());
+	for annotated_move in moves {
+		annotations.push(annotated_move.annotation(position.turn()));
+		position = annotated_move.played.result;
+	}
+
+	let played: Vec<FullMove> = moves.iter().map(|m| m.played.clone()).collect();
+	write_movetext(&mut pdn, start, &played, &annotations, result);
+	pdn
+}
+
+#[cfg(test)]
+mod tests {
+	use std::num::NonZeroU8;
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::notation::parse_pdn;
+	use crate::{ActualLimit, SearchLimit, TextFrontend};
+
+	#[test]
+	fn judges_by_eval_swing() {
+		let even = Evaluation::new(0.0);
+		assert_eq!(Judgement::judge(even, Evaluation::new(-0.01)), None);
+		assert_eq!(
+			Judgement::judge(even, Evaluation::new(-0.03)),
+			Some(Judgement::Dubious)
+		);
+		assert_eq!(
+			Judgement::judge(even, Evaluation::new(-0.1)),
+			Some(Judgement::Mistake)
+		);
+		assert_eq!(
+			Judgement::judge(Evaluation::WIN, even),
+			Some(Judgement::Blunder)
+		);
+		assert_eq!(
+			Judgement::judge(Evaluation::new(-0.9), Evaluation::LOSS),
+			Some(Judgement::Blunder)
+		);
+		assert_eq!(Judgement::judge(Evaluation::LOSS, Evaluation::LOSS), None);
+	}
+
+	#[test]
+	fn annotated_games_can_be_read() {
+		let engine = Engine::new(1 << 20, Arc::new(TextFrontend::new(|_| {})));
+		let settings = EvaluationSettings {
+			search_until: SearchLimit::Limited(ActualLimit {
+				nodes: None,
+				depth: NonZeroU8::new(3),
+				time: None,
+			}),
+			..EvaluationSettings::default()
+		};
+
+		let game = parse_pdn("1. 11-15 23-19 2. 8-11 22-17 3. 9-13").unwrap();
+		let mut calls = 0;
+		let moves = annotate(&engine, &game, &settings, |index, _| {
+			assert_eq!(index, calls);
+			calls += 1;
+		});
+		assert_eq!(moves.len(), 5);
+		assert!(moves
+			.iter()
+			.all(|m| m.judgement.is_none() || m.best_move.is_some()));
+
+		let pdn = to_pdn("[Event \"test\"]", game.start, &moves, "*");
+		assert!(pdn.starts_with("[Event \"test\"]\n\n1. 11-15"));
+		assert_eq!(parse_pdn(&pdn).unwrap(), game);
+	}
+}
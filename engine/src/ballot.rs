@@ -0,0 +1,148 @@
+use std::fmt::{self, Display};
+
+use model::CheckersBitBoard;
+
+use crate::notation::{full_moves, square_from_number, FullMove};
+
+/// The squares a black man can be removed from
+const BLACK_SQUARES: std::ops::RangeInclusive<usize> = 1..=12;
+
+/// The squares a white man can be removed from
+const WHITE_SQUARES: std::ops::RangeInclusive<usize> = 21..=32;
+
+/// An eleven-man ballot. One man is removed from each side, and then the
+/// first move of each side is balloted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElevenManBallot {
+	/// The square of the black man that is removed, from 1 to 12
+	pub black_removed: usize,
+	/// The square of the white man that is removed, from 21 to 32
+	pub white_removed: usize,
+	/// Black's first move, and then white's reply
+	pub moves: [FullMove; 2],
+}
+
+impl ElevenManBallot {
+	/// The position after the ballot, with black to move
+	pub fn position(&self) -> CheckersBitBoard {
+		self.moves[1].result
+	}
+}
+
+/// Writes the ballot as the removed men, followed by the moves, such as
+/// `-5 -28 11-15 23-19`
+impl Display for ElevenManBallot {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"-{} -{} {} {}",
+			self.black_removed, self.white_removed, self.moves[0], self.moves[1]
+		)
+	}
+}
+
+/// The starting position with one man removed from each side. Returns `None`
+/// if either square doesn't start with a man of that color.
+pub fn eleven_man_position(black_removed: usize, white_removed: usize) -> Option<CheckersBitBoard> {
+	if !BLACK_SQUARES.contains(&black_removed) || !WHITE_SQUARES.contains(&white_removed) {
+		return None;
+	}
+
+	let black = square_from_number(black_removed)?;
+	let white = square_from_number(white_removed)?;
+	Some(
+		CheckersBitBoard::starting_position()
+			.clear_piece(black)
+			.clear_piece(white),
+	)
+}
+
+/// Lists every legal eleven-man ballot. Neither balloted move may be a
+/// capture, and black must not be able to capture right after the ballot, so
+/// that every game starts with equal material.
+pub fn eleven_man_ballots() -> Vec<ElevenManBallot> {
+	let mut ballots = Vec::new();
+	for black_removed in BLACK_SQUARES {
+		for white_removed in WHITE_SQUARES {
+			let Some(start) = eleven_man_position(black_removed, white_removed) else {
+				continue;
+			};
+
+			for first in full_moves(start).into_iter().filter(|m| !m.is_capture()) {
+				for reply in full_moves(first.result) {
+					let next_moves = full_moves(reply.result);
+					if reply.is_capture()
+						|| next_moves.is_empty()
+						|| next_moves.iter().any(FullMove::is_capture)
+					{
+						continue;
+					}
+
+					ballots.push(ElevenManBallot {
+						black_removed,
+						white_removed,
+						moves: [first.clone(), reply],
+					});
+				}
+			}
+		}
+	}
+
+	ballots
+}
+
+/// Reads a ballot in the format it's written in, such as
+/// `-5 -28 11-15 23-19`. Returns `None` if the ballot isn't legal.
+pub fn parse_eleven_man_ballot(ballot: &str) -> Option<ElevenManBallot> {
+	let mut parts = ballot.split_whitespace();
+	let black_removed: usize = parts.next()?.strip_prefix('-')?.parse().ok()?;
+	let white_removed: usize = parts.next()?.strip_prefix('-')?.parse().ok()?;
+	let (first, reply) = (parts.next()?, parts.next()?);
+	if parts.next().is_some() {
+		return None;
+	}
+
+	eleven_man_ballots().into_iter().find(|ballot| {
+		ballot.black_removed == black_removed
+			&& ballot.white_removed == white_removed
+			&& [first, reply]
+				.iter()
+				.zip(&ballot.moves)
+				.all(|(notation, full_move)| {
+					full_move.to_string() == *notation || full_move.long_notation() == *notation
+				})
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn removes_one_man_per_side() {
+		let position = eleven_man_position(5, 28).unwrap();
+		assert!(!position.piece_at(square_from_number(5).unwrap()));
+		assert!(!position.piece_at(square_from_number(28).unwrap()));
+		assert!(position.piece_at(square_from_number(6).unwrap()));
+		assert_eq!(eleven_man_position(13, 28), None);
+		assert_eq!(eleven_man_position(5, 20), None);
+	}
+
+	#[test]
+	fn ballots_are_legal() {
+		let ballots = eleven_man_ballots();
+		assert!(ballots.len() > 144);
+		for ballot in &ballots {
+			assert!(!ballot.moves[0].is_capture());
+			assert!(!ballot.moves[1].is_capture());
+			assert!(!full_moves(ballot.position()).is_empty());
+		}
+
+		let ballot = &ballots[0];
+		assert_eq!(
+			parse_eleven_man_ballot(&ballot.to_string()).as_ref(),
+			Some(ballot)
+		);
+		assert_eq!(parse_eleven_man_ballot("-5 -28 11-14 23-19"), None);
+	}
+}
@@ -0,0 +1,89 @@
+use std::num::NonZeroU8;
+use std::sync::Arc;
+use std::time::Duration;
+
+use model::{CheckersBitBoard, PieceColor};
+
+use crate::engine::EvaluationTask;
+use crate::frontend::SilentFrontend;
+use crate::options::MEGABYTE;
+use crate::search::search;
+use crate::time::Instant;
+use crate::{ActualLimit, Frontend, TranspositionTable};
+
+/// The depth that each bench position is searched to
+pub const BENCH_DEPTH: u8 = 10;
+
+/// The size of the transposition table used by the bench, in bytes
+const BENCH_HASH_SIZE: usize = 16 * MEGABYTE;
+
+/// Positions from the opening to the endgame, reached by playing random moves
+/// from the starting position
+const BENCH_POSITIONS: [CheckersBitBoard; 9] = [
+	CheckersBitBoard::starting_position(),
+	CheckersBitBoard::new(0x978ebdf7, 0x040cb1c3, 0x00000000, PieceColor::Dark),
+	CheckersBitBoard::new(0x87cc5de7, 0x040c11c3, 0x00000000, PieceColor::Dark),
+	CheckersBitBoard::new(0xcfa51ec6, 0x0c2412c2, 0x00000000, PieceColor::Dark),
+	CheckersBitBoard::new(0xd59a9d86, 0x041a9182, 0x00000000, PieceColor::Dark),
+	CheckersBitBoard::new(0xa40c3c25, 0x040c1000, 0x00000001, PieceColor::Dark),
+	CheckersBitBoard::new(0xea104138, 0x08100000, 0x00000100, PieceColor::Dark),
+	CheckersBitBoard::new(0x0a033480, 0x08003400, 0x00000400, PieceColor::Dark),
+	CheckersBitBoard::new(0x29415092, 0x28000002, 0x00001000, PieceColor::Dark),
+];
+
+/// The result of running the bench
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchResult {
+	/// The total number of nodes searched. This only changes if the search
+	/// or evaluation changes, so it can be used to check that a change didn't
+	/// affect the search.
+	pub nodes: usize,
+	/// How long the bench took
+	pub time: Duration,
+}
+
+impl BenchResult {
+	pub fn nodes_per_second(&self) -> u64 {
+		(self.nodes as f64 / self.time.as_secs_f64().max(f64::EPSILON)) as u64
+	}
+}
+
+/// Searches each bench position to a fixed depth, using the default
+/// evaluation at full strength. A fresh transposition table is used, so the
+/// node count doesn't depend on any earlier searches.
+pub fn run() -> BenchResult {
+	let transposition_table = Arc::new(TranspositionTable::new(BENCH_HASH_SIZE));
+	let limits = ActualLimit {
+		nodes: None,
+		depth: NonZeroU8::new(BENCH_DEPTH),
+		time: None,
+	};
+
+	let frontend: Arc<dyn Frontend> = Arc::new(SilentFrontend);
+	let mut nodes = 0;
+	let start = Instant::now();
+	for position in BENCH_POSITIONS {
+		let task = Arc::new(EvaluationTask {
+			limits,
+			..EvaluationTask::new(position, transposition_table.clone(), frontend.clone())
+		});
+
+		search(task.clone(), None);
+		nodes += task.stats().nodes;
+	}
+
+	BenchResult {
+		nodes,
+		time: start.elapsed(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn node_count_is_stable() {
+		assert_eq!(run().nodes, run().nodes);
+	}
+}
@@ -0,0 +1,253 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use model::{CheckersBitBoard, Move, PieceColor};
+use rand::seq::SliceRandom;
+use thiserror::Error;
+
+use crate::notation::{full_moves, FullMove};
+
+const MAGIC: u32 = u32::from_be_bytes(*b".amb");
+const SUPPORTED_VERSION: u16 = 0;
+
+/// An opening book. Each entry is a position that a book move leads to, with
+/// a weight for how often the move should be played, compared to the other
+/// book moves in the same position. Moves which transpose into the same
+/// position share an entry.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OpeningBook {
+	entries: HashMap<CheckersBitBoard, u32>,
+}
+
+#[derive(Debug, Error)]
+pub enum BookFileError {
+	#[error("Invalid book file: the magic header field was incorrect")]
+	MagicError,
+	#[error("This version of the book file format is unsupported. Only {SUPPORTED_VERSION} is supported")]
+	UnsupportedVersion(u16),
+	#[error(transparent)]
+	IoError(#[from] io::Error),
+}
+
+impl OpeningBook {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// The weight of the book moves which lead to `position`
+	pub fn get(&self, position: CheckersBitBoard) -> Option<u32> {
+		self.entries.get(&position).copied()
+	}
+
+	pub fn add(&mut self, position: CheckersBitBoard, weight: u32) {
+		let entry = self.entries.entry(position).or_default();
+		*entry = entry.saturating_add(weight);
+	}
+
+	/// Adds the first `plies` moves of a game, each with a weight of one
+	pub fn add_game(&mut self, moves: &[FullMove], plies: usize) {
+		for full_move in moves.iter().take(plies) {
+			self.add(full_move.result, 1);
+		}
+	}
+
+	/// The book moves in a position, from the highest weight to the lowest
+	pub fn probe(&self, position: CheckersBitBoard) -> Vec<(FullMove, u32)> {
+		let mut moves: Vec<_> = full_moves(position)
+			.into_iter()
+			.filter_map(|full_move| self.get(full_move.result).map(|weight| (full_move, weight)))
+			.collect();
+		moves.sort_by_key(|(_, weight)| std::cmp::Reverse(*weight));
+		moves
+	}
+
+	/// Picks a book move at random, with each move chosen in proportion to
+	/// its weight. If there are `allowed_moves`, then only book moves which
+	/// start with one of them are picked.
+	pub fn choose_move(
+		&self,
+		position: CheckersBitBoard,
+		allowed_moves: Option<&[Move]>,
+	) -> Option<FullMove> {
+		self.choose_move_by(position, allowed_moves, |_, weight| weight as f32)
+	}
+
+	/// Like [`Self::choose_move`], but each move is chosen in proportion to
+	/// the weight returned by `weigh`
+	pub fn choose_move_by(
+		&self,
+		position: CheckersBitBoard,
+		allowed_moves: Option<&[Move]>,
+		weigh: impl Fn(&FullMove, u32) -> f32,
+	) -> Option<FullMove> {
+		let moves: Vec<_> = self
+			.probe(position)
+			.into_iter()
+			.filter(|(full_move, _)| {
+				allowed_moves.is_none_or(|allowed| allowed.contains(&full_move.hops[0]))
+			})
+			.map(|(full_move, weight)| {
+				let weight = weigh(&full_move, weight);
+				(full_move, weight)
+			})
+			.collect();
+		moves
+			.choose_weighted(&mut rand::thread_rng(), |(_, weight)| *weight)
+			.ok()
+			.map(|(full_move, _)| full_move.clone())
+	}
+
+	/// Adds every entry of another book. The weights of entries which are in
+	/// both books are added together.
+	pub fn merge(&mut self, other: &Self) {
+		for (&position, &weight) in &other.entries {
+			self.add(position, weight);
+		}
+	}
+
+	/// Removes the entries with a weight below `min_weight`. If there's a
+	/// `root`, then the entries which can't be reached from it by playing
+	/// book moves are also removed. Returns the number of entries removed.
+	pub fn prune(&mut self, min_weight: u32, root: Option<CheckersBitBoard>) -> usize {
+		let len = self.entries.len();
+		self.entries.retain(|_, weight| *weight >= min_weight);
+
+		if let Some(root) = root {
+			let mut reachable = HashSet::new();
+			let mut stack = vec![root];
+			while let Some(position) = stack.pop() {
+				for (full_move, _) in self.probe(position) {
+					if reachable.insert(full_move.result) {
+						stack.push(full_move.result);
+					}
+				}
+			}
+
+			self.entries
+				.retain(|position, _| reachable.contains(position));
+		}
+
+		len - self.entries.len()
+	}
+
+	pub fn read(reader: &mut impl Read) -> Result<Self, BookFileError> {
+		let magic = reader.read_u32::<BigEndian>()?;
+		if magic != MAGIC {
+			return Err(BookFileError::MagicError);
+		}
+
+		let version = reader.read_u16::<BigEndian>()?;
+		if version != SUPPORTED_VERSION {
+			return Err(BookFileError::UnsupportedVersion(version));
+		}
+
+		let len = reader.read_u64::<BigEndian>()?;
+		let mut entries = HashMap::new();
+		for _ in 0..len {
+			let pieces = reader.read_u32::<BigEndian>()?;
+			let color = reader.read_u32::<BigEndian>()?;
+			let kings = reader.read_u32::<BigEndian>()?;
+			let turn = if reader.read_u8()? == PieceColor::Dark as u8 {
+				PieceColor::Dark
+			} else {
+				PieceColor::Light
+			};
+			let weight = reader.read_u32::<BigEndian>()?;
+
+			entries.insert(CheckersBitBoard::new(pieces, color, kings, turn), weight);
+		}
+
+		Ok(Self { entries })
+	}
+
+	pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+		writer.write_u32::<BigEndian>(MAGIC)?;
+		writer.write_u16::<BigEndian>(SUPPORTED_VERSION)?;
+		writer.write_u64::<BigEndian>(self.entries.len() as u64)?;
+
+		for (board, &weight) in &self.entries {
+			let pieces = board.pieces_bits();
+			writer.write_u32::<BigEndian>(pieces)?;
+			writer.write_u32::<BigEndian>(board.color_bits() & pieces)?;
+			writer.write_u32::<BigEndian>(board.king_bits() & pieces)?;
+			writer.write_u8(board.turn() as u8)?;
+			writer.write_u32::<BigEndian>(weight)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, BookFileError> {
+		Self::read(&mut BufReader::new(File::open(path)?))
+	}
+
+	pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		self.write(&mut writer)?;
+		writer.flush()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::notation::parse_pdn;
+
+	fn book() -> OpeningBook {
+		let mut book = OpeningBook::new();
+		for game in ["11-15 23-19 8-11", "11-15 22-18", "9-13 22-18"] {
+			book.add_game(&parse_pdn(game).unwrap().moves, 2);
+		}
+		book
+	}
+
+	#[test]
+	fn probes_moves_by_weight() {
+		let moves = book().probe(CheckersBitBoard::starting_position());
+		let moves: Vec<_> = moves
+			.iter()
+			.map(|(full_move, weight)| (full_move.to_string(), *weight))
+			.collect();
+		assert_eq!(moves, [("11-15".to_string(), 2), ("9-13".to_string(), 1)]);
+	}
+
+	#[test]
+	fn merges_and_prunes() {
+		let mut book = book();
+		let len = book.len();
+		book.merge(&self::book());
+		assert_eq!(book.len(), len);
+		assert_eq!(book.probe(CheckersBitBoard::starting_position())[0].1, 4);
+
+		let mut book = self::book();
+		let unreachable = parse_pdn("9-13 22-18").unwrap().moves[1].result;
+		book.add(unreachable, 5);
+		assert_eq!(book.clone().prune(2, None), 3);
+
+		// 9-13 is removed, so 9-13 22-18 can't be reached anymore
+		assert_eq!(
+			book.prune(2, Some(CheckersBitBoard::starting_position())),
+			4
+		);
+		assert_eq!(book.len(), 1);
+	}
+
+	#[test]
+	fn file_round_trip() {
+		let book = book();
+		let mut buffer = Vec::new();
+		book.write(&mut buffer).unwrap();
+		assert_eq!(OpeningBook::read(&mut buffer.as_slice()).unwrap(), book);
+	}
+}
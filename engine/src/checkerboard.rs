@@ -0,0 +1,357 @@
+use std::ffi::{c_char, c_double, c_int, CStr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use model::{CheckersBitBoard, Move, PieceColor, SquareCoordinate};
+use parking_lot::Mutex;
+
+use crate::notation::FullMove;
+use crate::options::{DEFAULT_HASH_SIZE, HASH_OPTION, MEGABYTE};
+use crate::{Clock, Engine, EvaluationSettings, Frontend, SearchInfo, ENGINE_ABOUT, ENGINE_NAME};
+
+// piece values used by CheckerBoard
+const WHITE: c_int = 1;
+const BLACK: c_int = 2;
+const MAN: c_int = 4;
+const KING: c_int = 8;
+
+// results returned by getmove
+const WIN: c_int = 1;
+const LOSS: c_int = 2;
+const UNKNOWN: c_int = 3;
+
+/// CheckerBoard's game type for American checkers
+const GAME_TYPE: &str = "21";
+
+/// The version of the CheckerBoard engine API that is implemented
+const PROTOCOL_VERSION: &str = "2";
+
+/// The size of the status text buffer passed to getmove
+const STATUS_LENGTH: usize = 1024;
+
+/// The size of the reply buffer passed to enginecommand
+const REPLY_LENGTH: usize = 1024;
+
+/// A square on CheckerBoard's board, where `x` is the column from the left
+/// and `y` is the row from the bottom, with white at the bottom
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct Coordinate {
+	pub x: c_int,
+	pub y: c_int,
+}
+
+/// The move that getmove played, in the format CheckerBoard expects
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct CbMove {
+	pub jumps: c_int,
+	pub newpiece: c_int,
+	pub oldpiece: c_int,
+	pub from: Coordinate,
+	pub to: Coordinate,
+	pub path: [Coordinate; 12],
+	pub del: [Coordinate; 12],
+	pub delpiece: [c_int; 12],
+}
+
+/// Keeps the latest search information, so that it can be shown in
+/// CheckerBoard's status bar
+#[derive(Default)]
+struct StatusFrontend {
+	status: Mutex<String>,
+}
+
+impl Frontend for StatusFrontend {
+	fn on_depth_complete(&self, info: &SearchInfo) {
+		*self.status.lock() = format!(
+			"depth {}/{} eval {} nodes {} time {:.2}s",
+			info.depth,
+			info.selective_depth,
+			info.eval,
+			info.nodes,
+			info.time.as_secs_f32()
+		);
+	}
+
+	fn on_string(&self, _msg: &str) {}
+
+	fn report_best_move(&self, _best_move: Move) {}
+}
+
+struct CheckerBoardEngine {
+	engine: Engine,
+	frontend: Arc<StatusFrontend>,
+}
+
+/// The engine used by every call from CheckerBoard
+fn engine() -> &'static CheckerBoardEngine {
+	static ENGINE: OnceLock<CheckerBoardEngine> = OnceLock::new();
+	ENGINE.get_or_init(|| {
+		let frontend = Arc::new(StatusFrontend::default());
+		CheckerBoardEngine {
+			engine: Engine::new(DEFAULT_HASH_SIZE as usize * MEGABYTE, frontend.clone()),
+			frontend,
+		}
+	})
+}
+
+fn coordinate(square: usize) -> Coordinate {
+	let square = SquareCoordinate::from_ampere_value(square);
+	Coordinate {
+		x: 7 - square.file() as c_int,
+		y: 7 - square.rank() as c_int,
+	}
+}
+
+fn square(coordinate: Coordinate) -> Option<usize> {
+	if !(0..8).contains(&coordinate.x) || !(0..8).contains(&coordinate.y) {
+		return None;
+	}
+
+	SquareCoordinate::new(7 - coordinate.y as u8, 7 - coordinate.x as u8).to_ampere_value()
+}
+
+/// The value CheckerBoard uses for the piece on a square
+fn piece_value(board: CheckersBitBoard, square: usize) -> c_int {
+	let Some(color) = board.color_at(square) else {
+		return 0;
+	};
+
+	let color = match color {
+		PieceColor::Dark => BLACK,
+		PieceColor::Light => WHITE,
+	};
+	let kind = if board.king_at(square) == Some(true) {
+		KING
+	} else {
+		MAN
+	};
+
+	color | kind
+}
+
+fn read_board(board: &[[c_int; 8]; 8], color: c_int) -> CheckersBitBoard {
+	let (mut pieces, mut dark, mut kings) = (0u32, 0u32, 0u32);
+	for x in 0..8 {
+		for y in 0..8 {
+			let value = board[x as usize][y as usize];
+			let Some(square) = square(Coordinate { x, y }) else {
+				continue;
+			};
+
+			if value & (WHITE | BLACK) == 0 {
+				continue;
+			}
+
+			pieces |= 1 << square;
+			if value & BLACK != 0 {
+				dark |= 1 << square;
+			}
+			if value & KING != 0 {
+				kings |= 1 << square;
+			}
+		}
+	}
+
+	let turn = if color == BLACK {
+		PieceColor::Dark
+	} else {
+		PieceColor::Light
+	};
+
+	CheckersBitBoard::new(pieces, dark, kings, turn)
+}
+
+fn write_board(board: &mut [[c_int; 8]; 8], position: CheckersBitBoard) {
+	for row in board.iter_mut() {
+		row.fill(0);
+	}
+
+	for square in 0..32 {
+		let Coordinate { x, y } = coordinate(square);
+		board[x as usize][y as usize] = piece_value(position, square);
+	}
+}
+
+fn cb_move(position: CheckersBitBoard, full_move: &FullMove) -> CbMove {
+	let mut cb_move = CbMove {
+		jumps: full_move.hops.iter().filter(|hop| hop.is_jump()).count() as c_int,
+		oldpiece: piece_value(position, full_move.from()),
+		newpiece: piece_value(full_move.result, full_move.to()),
+		from: coordinate(full_move.from()),
+		to: coordinate(full_move.to()),
+		..CbMove::default()
+	};
+
+	cb_move.path[0] = cb_move.from;
+	for (index, hop) in full_move.hops.iter().enumerate().take(11) {
+		cb_move.path[index + 1] = coordinate(hop.end_position());
+	}
+
+	let captured = (0..32).filter(|square| full_move.captured & (1 << square) != 0);
+	for (index, square) in captured.enumerate().take(12) {
+		cb_move.del[index] = coordinate(square);
+		cb_move.delpiece[index] = piece_value(position, square);
+	}
+
+	cb_move
+}
+
+/// Copies a string into a C buffer of `length` bytes, truncating it if needed
+///
+/// # Safety
+///
+/// `buffer` must be valid for writes of `length` bytes
+unsafe fn write_string(buffer: *mut c_char, length: usize, value: &str) {
+	if buffer.is_null() || length == 0 {
+		return;
+	}
+
+	let bytes = &value.as_bytes()[..value.len().min(length - 1)];
+	std::ptr::copy_nonoverlapping(bytes.as_ptr().cast(), buffer, bytes.len());
+	*buffer.add(bytes.len()) = 0;
+}
+
+/// Finds the best move for `color`, and plays it on `board`. The search stops
+/// after about `maxtime` seconds, or when CheckerBoard sets `playnow`.
+///
+/// # Safety
+///
+/// The pointers must be valid as described by the CheckerBoard engine API.
+/// `status` must have room for 1024 bytes, and `cb_move` may be null.
+#[no_mangle]
+pub unsafe extern "system" fn getmove(
+	board: *mut [[c_int; 8]; 8],
+	color: c_int,
+	maxtime: c_double,
+	status: *mut c_char,
+	playnow: *const c_int,
+	_info: c_int,
+	_moreinfo: c_int,
+	cb_move: *mut CbMove,
+) -> c_int {
+	let CheckerBoardEngine { engine, frontend } = engine();
+	let board = &mut *board;
+	let position = read_board(board, color);
+
+	engine.set_position(position);
+	let settings = EvaluationSettings {
+		clock: Clock::TimePerMove(Duration::from_secs_f64(maxtime.max(0.0))),
+		..EvaluationSettings::default()
+	};
+
+	let playnow = playnow.as_ref();
+	let cancel = AtomicBool::new(false);
+	let finished = AtomicBool::new(false);
+	let (eval, best_move) = std::thread::scope(|scope| {
+		// the search is stopped early if CheckerBoard asks for a move now
+		scope.spawn(|| {
+			while !finished.load(Ordering::Acquire) {
+				if playnow.is_some_and(|playnow| std::ptr::read_volatile(playnow) != 0) {
+					cancel.store(true, Ordering::Release);
+					break;
+				}
+
+				std::thread::sleep(Duration::from_millis(10));
+			}
+		});
+
+		let result = engine.evaluate(Some(&cancel), settings);
+		finished.store(true, Ordering::Release);
+		result
+	});
+
+	write_string(status, STATUS_LENGTH, &frontend.status.lock());
+
+	let Some(best_move) = best_move else {
+		return LOSS;
+	};
+
+	let Some(full_move) = engine.best_full_move(position, best_move) else {
+		return UNKNOWN;
+	};
+
+	if !cb_move.is_null() {
+		*cb_move = self::cb_move(position, &full_move);
+	}
+
+	write_board(board, full_move.result);
+	engine.set_position(full_move.result);
+
+	if eval.is_force_win() {
+		WIN
+	} else if eval.is_force_loss() {
+		LOSS
+	} else {
+		UNKNOWN
+	}
+}
+
+/// Answers a command from CheckerBoard, such as `name` or `set hashsize 64`.
+/// Returns 1 if the command was understood, and 0 otherwise.
+///
+/// # Safety
+///
+/// `command` must be a null-terminated string, and `reply` must have room for
+/// 1024 bytes
+#[no_mangle]
+pub unsafe extern "system" fn enginecommand(command: *const c_char, reply: *mut c_char) -> c_int {
+	let command = CStr::from_ptr(command).to_string_lossy();
+	let words: Vec<&str> = command.split_whitespace().collect();
+	let engine = &engine().engine;
+
+	let answer = match words.as_slice() {
+		["name"] => Some(ENGINE_NAME.to_string()),
+		["about"] => Some(ENGINE_ABOUT.to_string()),
+		["get", "protocolversion"] => Some(PROTOCOL_VERSION.to_string()),
+		["get", "gametype"] => Some(GAME_TYPE.to_string()),
+		["get", "hashsize"] => Some((engine.transposition_table_size() / MEGABYTE).to_string()),
+		["set", "hashsize", size] => engine
+			.set_option(HASH_OPTION, size)
+			.ok()
+			.map(|()| size.to_string()),
+		_ => None,
+	};
+
+	match answer {
+		Some(answer) => {
+			write_string(reply, REPLY_LENGTH, &answer);
+			1
+		}
+		None => {
+			write_string(reply, REPLY_LENGTH, "?");
+			0
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::ffi::CString;
+
+	use super::*;
+
+	#[test]
+	fn board_round_trip() {
+		let position = CheckersBitBoard::starting_position();
+		let mut board = [[0; 8]; 8];
+		write_board(&mut board, position);
+
+		// black starts at the top of the board
+		assert_eq!(board[1][7], BLACK | MAN);
+		assert_eq!(board[0][0], WHITE | MAN);
+		assert_eq!(read_board(&board, BLACK), position);
+	}
+
+	#[test]
+	fn name_command() {
+		let command = CString::new("name").unwrap();
+		let mut reply = [0; REPLY_LENGTH];
+		let understood = unsafe { enginecommand(command.as_ptr(), reply.as_mut_ptr()) };
+		let reply = unsafe { CStr::from_ptr(reply.as_ptr()) };
+		assert_eq!(understood, 1);
+		assert_eq!(reply.to_str().unwrap(), ENGINE_NAME);
+	}
+}
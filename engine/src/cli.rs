@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+pub mod analyze;
+pub mod annotate;
+pub mod ballots;
+pub mod book;
+pub mod completions;
+pub mod config;
+pub mod convert;
+pub mod db;
+pub mod diagram;
+pub mod dxp;
+pub mod endgame;
+pub mod export;
+#[cfg(all(feature = "lidraughts", not(target_arch = "wasm32")))]
+pub mod lidraughts;
+pub mod openings;
+pub mod pdn;
+pub mod perft;
+pub mod rate;
+pub mod selfplay;
+pub mod solve;
+pub mod tablebase;
+pub mod testsuite;
+pub mod tournament;
+#[cfg(all(feature = "tui", not(target_arch = "wasm32")))]
+pub mod tui;
+
+pub const DEPTH: u8 = 19;
+
+/// The number of characters in a progress bar
+const PROGRESS_WIDTH: usize = 30;
+
+/// The shortest time between redrawing a progress bar
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A progress bar on stderr, with an estimate of how much longer a command
+/// will take
+struct ProgressBar {
+	total: usize,
+	started: Instant,
+	drawn: Option<Instant>,
+	hidden: bool,
+}
+
+impl ProgressBar {
+	/// A hidden progress bar is never drawn, so that it doesn't get mixed up
+	/// with JSON output
+	fn new(total: usize, hidden: bool) -> Self {
+		Self {
+			total,
+			started: Instant::now(),
+			drawn: None,
+			hidden,
+		}
+	}
+
+	/// Shows that `done` out of the total are finished
+	fn update(&mut self, label: &str, done: usize) {
+		let now = Instant::now();
+		let recently_drawn = self
+			.drawn
+			.is_some_and(|drawn| now - drawn < PROGRESS_INTERVAL);
+		if self.hidden || (recently_drawn && done < self.total) {
+			return;
+		}
+		self.drawn = Some(now);
+
+		let fraction = (done as f64 / self.total.max(1) as f64).min(1.0);
+		let filled = (fraction * PROGRESS_WIDTH as f64) as usize;
+		let eta = if done == 0 {
+			"?".to_string()
+		} else {
+			let elapsed = now - self.started;
+			format_duration(elapsed.mul_f64((1.0 - fraction) / fraction))
+		};
+		eprint!(
+			"\r\x1b[K{label} [{}{}] {:>3.0}% {done}/{} eta {eta}",
+			"#".repeat(filled),
+			"-".repeat(PROGRESS_WIDTH - filled),
+			fraction * 100.0,
+			self.total
+		);
+	}
+
+	/// Erases the progress bar, so that something else can be written
+	fn clear(&mut self) {
+		if self.drawn.take().is_some() {
+			eprint!("\r\x1b[K");
+		}
+	}
+}
+
+/// A duration to the second, such as `1h02m03s`
+fn format_duration(duration: Duration) -> String {
+	let seconds = duration.as_secs();
+	match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+		(0, 0, seconds) => format!("{seconds}s"),
+		(0, minutes, seconds) => format!("{minutes}m{seconds:02}s"),
+		(hours, minutes, seconds) => format!("{hours}h{minutes:02}m{seconds:02}s"),
+	}
+}
+
+/// The files that are given, and the PDN files in the directories that are
+/// given, including their subdirectories, in sorted order
+fn pdn_files(mut paths: Vec<PathBuf>) -> Vec<PathBuf> {
+	let mut files = Vec::new();
+	while let Some(path) = paths.pop() {
+		if !path.is_dir() {
+			files.push(path);
+			continue;
+		}
+
+		let entries = match std::fs::read_dir(&path) {
+			Ok(entries) => entries,
+			Err(error) => {
+				tracing::warn!(path = %path.display(), %error, "skipped a directory");
+				continue;
+			}
+		};
+		for entry in entries.flatten() {
+			let path = entry.path();
+			let is_pdn = path
+				.extension()
+				.is_some_and(|extension| extension.eq_ignore_ascii_case("pdn"));
+			if path.is_dir() || is_pdn {
+				paths.push(path);
+			}
+		}
+	}
+	files.sort();
+	files
+}
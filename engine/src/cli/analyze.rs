@@ -0,0 +1,375 @@
+use std::fmt::{self, Display};
+use std::io::BufRead;
+use std::num::{NonZeroU8, NonZeroUsize};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use engine::annotate;
+use engine::{
+	complete_line, ActualLimit, Clock, Config, Engine, Evaluation, EvaluationSettings, Frontend,
+	FullMove, GameReport, InfoFrontend, PdnGame, SearchInfo, SearchLimit, TextFrontend, MEGABYTE,
+};
+use model::CheckersBitBoard;
+use serde_json::{json, Value};
+
+use crate::cli::annotate::{report_title, write_report};
+use crate::cli::DEPTH;
+
+/// The search limits given on the command line. If there's only a time
+/// limit, the engine may stop early, like it would in a game.
+pub fn search_settings(
+	depth: Option<NonZeroU8>,
+	nodes: Option<NonZeroUsize>,
+	time: Option<Duration>,
+) -> EvaluationSettings {
+	let search_until = if depth.is_some() || nodes.is_some() {
+		SearchLimit::Limited(ActualLimit { nodes, depth, time })
+	} else {
+		SearchLimit::Auto
+	};
+
+	EvaluationSettings {
+		clock: time.map_or_else(Clock::default, Clock::TimePerMove),
+		search_until,
+		..EvaluationSettings::default()
+	}
+}
+
+/// The result of searching one position
+struct SearchResult {
+	eval: Evaluation,
+	/// The expected line, starting with the best move
+	pv: Vec<FullMove>,
+	/// The information from the last completed depth
+	info: Option<SearchInfo>,
+	time: Duration,
+}
+
+impl SearchResult {
+	fn to_json(&self) -> Value {
+		json!({
+			"score": self.eval.to_string(),
+			"best_move": self.pv.first().map(FullMove::long_notation),
+			"pv": self.pv.iter().map(FullMove::long_notation).collect::<Vec<_>>(),
+			"depth": self.info.map_or(0, |info| info.depth),
+			"nodes": self.info.map_or(0, |info| info.nodes),
+			"seconds": self.time.as_secs_f64(),
+		})
+	}
+}
+
+impl Display for SearchResult {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let line: Vec<String> = self.pv.iter().map(FullMove::to_string).collect();
+		match self.pv.first() {
+			Some(best_move) => write!(f, "score {}, best move {best_move}", self.eval)?,
+			None => write!(f, "score {}, best move none", self.eval)?,
+		}
+		write!(f, ", pv {}", line.join(" "))
+	}
+}
+
+/// Searches a position. The engine's frontend should be `frontend`, or else
+/// the depth and node count are missing.
+fn analyse(
+	engine: &Engine,
+	frontend: &InfoFrontend,
+	position: CheckersBitBoard,
+	settings: EvaluationSettings,
+) -> SearchResult {
+	frontend.clear();
+	engine.set_position(position);
+	let start = Instant::now();
+	let (eval, best_move) = engine.evaluate(None, settings);
+	let time = start.elapsed();
+
+	SearchResult {
+		eval,
+		pv: complete_line(position, &engine.expected_line(best_move)),
+		info: frontend.latest(),
+		time,
+	}
+}
+
+/// Analyses each FEN in the input, and writes one line for each of them. The
+/// transposition table is kept between positions.
+fn analyse_lines(
+	engine: &Engine,
+	frontend: &InfoFrontend,
+	input: impl BufRead,
+	settings: &EvaluationSettings,
+	json: bool,
+) -> std::io::Result<()> {
+	for line in input.lines() {
+		let line = line?;
+		let Some(position) = engine::parse_fen(&line) else {
+			if json {
+				println!("{}", json!({ "fen": line, "error": "invalid FEN" }));
+			} else {
+				println!("error: invalid FEN");
+			}
+			continue;
+		};
+
+		let result = analyse(engine, frontend, position, settings.clone());
+		if json {
+			let mut output = result.to_json();
+			output["fen"] = Value::String(line);
+			println!("{output}");
+		} else {
+			println!("{result}");
+		}
+	}
+
+	Ok(())
+}
+
+pub const USAGE: &str =
+	"usage: ampere analyze <fen | pdn file> [--depth <n>] [--time <seconds>] [--nodes <n>] [--all]
+                            [--report <html file>]
+       ampere analyze --stdin [--depth <n>] [--time <seconds>] [--nodes <n>]";
+
+/// Analyses a FEN, or the game in a PDN file. The position at the end of the
+/// game is searched, or with `--all`, the position before every move. With
+/// `--stdin`, each line of the input is analysed as a FEN instead.
+pub fn run_analyze(args: &[String], config: &Config, json: bool) {
+	let mut input = None;
+	let (mut depth, mut nodes, mut time) = (None, None, config.time);
+	let mut all = false;
+	let mut stdin = false;
+	let mut report_path = None;
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--depth" => {
+				depth = args.next().and_then(|n| n.parse().ok());
+				depth.is_some()
+			}
+			"--nodes" => {
+				nodes = args.next().and_then(|n| n.parse().ok());
+				nodes.is_some()
+			}
+			"--time" => {
+				time = args
+					.next()
+					.and_then(|seconds| seconds.parse().ok())
+					.and_then(|seconds| Duration::try_from_secs_f64(seconds).ok());
+				time.is_some()
+			}
+			"--all" => {
+				all = true;
+				true
+			}
+			"--stdin" => {
+				stdin = true;
+				true
+			}
+			"--report" => {
+				report_path = args.next();
+				report_path.is_some()
+			}
+			_ => input.replace(arg.as_str()).is_none(),
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			return;
+		}
+	}
+
+	let frontend = Arc::new(InfoFrontend::default());
+	let engine = Engine::new(config.hash_megabytes() * MEGABYTE, frontend.clone());
+	let settings = search_settings(depth, nodes, time);
+
+	if stdin && input.is_none() && !all {
+		let stdin = std::io::stdin().lock();
+		if let Err(error) = analyse_lines(&engine, &frontend, stdin, &settings, json) {
+			eprintln!("{error}");
+		}
+		return;
+	}
+
+	let (Some(input), false) = (input, stdin) else {
+		eprintln!("{USAGE}");
+		return;
+	};
+	if let Some(report_path) = report_path {
+		analyse_report(&engine, input, report_path, &settings);
+	}
+
+	let game = if Path::new(input).is_file() {
+		let pdn = match std::fs::read_to_string(input) {
+			Ok(pdn) => pdn,
+			Err(error) => {
+				eprintln!("{error}");
+				return;
+			}
+		};
+
+		match engine::parse_pdn(&pdn) {
+			Ok(game) => game,
+			Err(error) => {
+				eprintln!("{error}");
+				return;
+			}
+		}
+	} else {
+		let Some(start) = engine::parse_fen(input) else {
+			eprintln!("{input:?} is neither a file nor a valid FEN");
+			return;
+		};
+		PdnGame {
+			start,
+			moves: Vec::new(),
+		}
+	};
+
+	if all {
+		let mut position = game.start;
+		for (ply, played) in game.moves.iter().enumerate() {
+			let result = analyse(&engine, &frontend, position, settings.clone());
+			if json {
+				let mut output = result.to_json();
+				output["ply"] = json!(ply + 1);
+				output["played"] = json!(played.long_notation());
+				println!("{output}");
+			} else {
+				println!("{}. {played}: {result}", ply / 2 + 1);
+			}
+			position = played.result;
+		}
+		return;
+	}
+
+	let position = game.moves.last().map_or(game.start, |last| last.result);
+	let result = analyse(&engine, &frontend, position, settings);
+	if json {
+		println!("{}", result.to_json());
+		return;
+	}
+
+	let line: Vec<String> = result.pv.iter().map(FullMove::to_string).collect();
+	println!("score: {}", result.eval);
+	match result.pv.first() {
+		Some(best_move) => println!("best move: {best_move}"),
+		None => println!("best move: none"),
+	}
+	println!("pv: {}", line.join(" "));
+}
+
+/// Annotates every move of a game, or a single position, and writes an HTML
+/// report of it
+fn analyse_report(engine: &Engine, input: &str, path: &str, settings: &EvaluationSettings) {
+	let (game, title, result) = if Path::new(input).is_file() {
+		let parsed = std::fs::read_to_string(input)
+			.map_err(|error| error.to_string())
+			.and_then(|pdn| {
+				let game = engine::parse_pdn(&pdn).map_err(|error| error.to_string())?;
+				Ok((game, pdn))
+			});
+		match parsed {
+			Ok((game, pdn)) => {
+				let result = engine::pdn_tag(&pdn, "Result").unwrap_or_else(|| "*".to_string());
+				(game, report_title(&pdn, 1), result)
+			}
+			Err(error) => {
+				eprintln!("{input}: {error}");
+				std::process::exit(1);
+			}
+		}
+	} else {
+		// the invalid input is reported by the analysis
+		let Some(start) = engine::parse_fen(input) else {
+			return;
+		};
+		let game = PdnGame {
+			start,
+			moves: Vec::new(),
+		};
+		(game, input.to_string(), "*".to_string())
+	};
+
+	let moves = annotate::annotate(engine, &game, settings, |_, _| {});
+	write_report(
+		path,
+		&[GameReport {
+			title,
+			start: game.start,
+			moves,
+			result,
+		}],
+	);
+}
+
+pub const EVAL_USAGE: &str = "usage: ampere eval [--fen <fen>] [--moves <moves>] [--depth <n>]";
+
+/// Searches a position to a fixed depth, and prints the evaluation. The
+/// position is the starting position, or `--fen`, after playing `--moves`.
+pub fn run_eval(args: &[String], config: &Config, json: bool) {
+	let mut start = CheckersBitBoard::starting_position();
+	let mut moves = "";
+	let mut depth = NonZeroU8::new(DEPTH).unwrap();
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--fen" => match args.next().and_then(|fen| engine::parse_fen(fen)) {
+				Some(position) => {
+					start = position;
+					true
+				}
+				None => false,
+			},
+			"--moves" => match args.next() {
+				Some(line) => {
+					moves = line;
+					true
+				}
+				None => false,
+			},
+			"--depth" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					depth = n;
+					true
+				}
+				None => false,
+			},
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{EVAL_USAGE}");
+			return;
+		}
+	}
+
+	// the search information is only printed as it comes in for people
+	let info = Arc::new(InfoFrontend::default());
+	let frontend: Arc<dyn Frontend> = if json {
+		info.clone()
+	} else {
+		Arc::new(TextFrontend::new(|line| println!("{line}")))
+	};
+
+	let engine = Engine::new(config.hash_megabytes() * MEGABYTE, frontend);
+	if let Err(error) = engine.play_notation(start, moves) {
+		eprintln!("{error}");
+		return;
+	}
+
+	let settings = EvaluationSettings {
+		clock: Clock::Unlimited,
+		search_until: SearchLimit::Limited(ActualLimit {
+			nodes: None,
+			depth: Some(depth),
+			time: None,
+		}),
+		..EvaluationSettings::default()
+	};
+	let result = analyse(&engine, &info, engine.current_position(), settings);
+	if json {
+		println!("{}", result.to_json());
+	} else {
+		println!("{}", result.eval);
+	}
+}
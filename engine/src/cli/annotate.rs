@@ -0,0 +1,219 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use engine::annotate::{self, AnnotatedMove};
+use engine::{Config, Engine, GameReport, PdnGame, TextFrontend, MEGABYTE};
+use model::{CheckersBitBoard, PieceColor};
+use serde_json::json;
+
+use crate::cli::analyze::search_settings;
+use crate::cli::ProgressBar;
+
+pub const USAGE: &str = "usage: ampere annotate <pdn file> [--time <seconds>] [--depth <n>] \
+	[--nodes <n>] [--hash <megabytes>] [--out <pdn file>] \
+	[--report <html file>]";
+
+/// Analyzes every move of the games in a PDN file, and writes the games with
+/// evaluations, NAGs for mistakes, and better moves as comments
+pub fn run_annotate(args: &[String], config: &Config, json: bool) {
+	let mut path = None;
+	let (mut depth, mut nodes, mut time) = (None, None, Some(config.time()));
+	let mut hash_megabytes = config.hash_megabytes();
+	let mut out_path = None;
+	let mut report_path = None;
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--depth" => {
+				depth = args.next().and_then(|n| n.parse().ok());
+				depth.is_some()
+			}
+			"--nodes" => {
+				nodes = args.next().and_then(|n| n.parse().ok());
+				nodes.is_some()
+			}
+			"--time" => {
+				time = args
+					.next()
+					.and_then(|seconds| seconds.parse().ok())
+					.and_then(|seconds| Duration::try_from_secs_f64(seconds).ok());
+				time.is_some()
+			}
+			"--hash" => match args.next().and_then(|mb| mb.parse().ok()) {
+				Some(mb) => {
+					hash_megabytes = mb;
+					true
+				}
+				None => false,
+			},
+			"--out" => {
+				out_path = args.next();
+				out_path.is_some()
+			}
+			"--report" => {
+				report_path = args.next();
+				report_path.is_some()
+			}
+			_ if path.is_none() && !arg.starts_with("--") => {
+				path = Some(arg);
+				true
+			}
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			return;
+		}
+	}
+
+	let Some(path) = path else {
+		eprintln!("{USAGE}");
+		return;
+	};
+	let pdn = match std::fs::read_to_string(path) {
+		Ok(pdn) => pdn,
+		Err(error) => {
+			eprintln!("{path}: {error}");
+			std::process::exit(1);
+		}
+	};
+
+	let engine = Engine::new(
+		hash_megabytes * MEGABYTE,
+		Arc::new(TextFrontend::new(|_| {})),
+	);
+	let settings = search_settings(depth, nodes, time);
+	// the games are read first, so that the progress can be shown
+	let games: Vec<(usize, &str, PdnGame)> = engine::split_pdn_games(&pdn)
+		.into_iter()
+		.enumerate()
+		.filter_map(|(number, text)| match engine::parse_pdn(text) {
+			Ok(game) => Some((number, text, game)),
+			Err(error) => {
+				tracing::warn!(game = number + 1, %error, "skipped a game");
+				None
+			}
+		})
+		.collect();
+	let mut progress = ProgressBar::new(
+		games.iter().map(|(_, _, game)| game.moves.len()).sum(),
+		json,
+	);
+	let mut done = 0;
+
+	let mut annotated_pdn = Vec::new();
+	let mut reports = Vec::new();
+	for (number, text, game) in games {
+		progress.update("annotating", done);
+		let tags: Vec<&str> = text
+			.lines()
+			.map(str::trim)
+			.filter(|line| line.starts_with('['))
+			.collect();
+		let result = tags
+			.iter()
+			.find_map(|tag| {
+				let value = tag.strip_prefix("[Result")?.trim_end().strip_suffix(']')?;
+				Some(value.trim().trim_matches('"'))
+			})
+			.unwrap_or("*");
+
+		let mut position = game.start;
+		let moves = annotate::annotate(&engine, &game, &settings, |index, annotated| {
+			progress.clear();
+			report_annotated_move(number + 1, index, position, annotated, json);
+			position = annotated.played.result;
+			done += 1;
+			progress.update("annotating", done);
+		});
+		annotated_pdn.push(annotate::to_pdn(
+			&tags.join("\n"),
+			game.start,
+			&moves,
+			result,
+		));
+		reports.push(GameReport {
+			title: report_title(text, number + 1),
+			start: game.start,
+			moves,
+			result: result.to_string(),
+		});
+	}
+
+	progress.clear();
+
+	if let Some(report_path) = report_path {
+		write_report(report_path, &reports);
+	}
+
+	let annotated_pdn = annotated_pdn.join("\n");
+	match out_path {
+		Some(out_path) => {
+			if let Err(error) = std::fs::write(out_path, annotated_pdn) {
+				eprintln!("{out_path}: {error}");
+				std::process::exit(1);
+			}
+		}
+		None if json => println!("{}", json!({ "pdn": annotated_pdn })),
+		None => print!("{annotated_pdn}"),
+	}
+}
+
+/// The heading of a game in a report, from the names of its players
+pub fn report_title(pdn: &str, number: usize) -> String {
+	match (engine::pdn_tag(pdn, "Black"), engine::pdn_tag(pdn, "White")) {
+		(Some(black), Some(white)) => format!("{black} vs. {white}"),
+		_ => engine::pdn_tag(pdn, "Event").unwrap_or_else(|| format!("Game {number}")),
+	}
+}
+
+pub fn write_report(path: &str, reports: &[GameReport]) {
+	if let Err(error) = std::fs::write(path, engine::html_report(reports)) {
+		eprintln!("{path}: {error}");
+		std::process::exit(1);
+	}
+}
+
+/// Reports the engine's opinion of a move, to stderr, or as JSON to stdout
+fn report_annotated_move(
+	game: usize,
+	index: usize,
+	position: CheckersBitBoard,
+	annotated: &AnnotatedMove,
+	json: bool,
+) {
+	let best_move = annotated.best_move.as_ref();
+	if json {
+		let output = json!({
+			"game": game,
+			"ply": index + 1,
+			"played": annotated.played.long_notation(),
+			"score": annotated.eval.to_string(),
+			"best_move": best_move.map(|(best_move, _)| best_move.long_notation()),
+			"best_score": best_move.map(|(_, eval)| eval.to_string()),
+			"judgement": annotated.judgement.map(|judgement| judgement.to_string()),
+		});
+		println!("{output}");
+		return;
+	}
+
+	let dots = if position.turn() == PieceColor::Dark {
+		"."
+	} else {
+		"..."
+	};
+	let symbol = annotated
+		.judgement
+		.map_or("", |judgement| judgement.symbol());
+	let mut line = format!(
+		"game {game}, {}{dots} {}{symbol}: {}",
+		index / 2 + 1,
+		annotated.played,
+		annotated.eval
+	);
+	if let (Some(judgement), Some((best_move, eval))) = (annotated.judgement, best_move) {
+		line.push_str(&format!(", {judgement}, {best_move} was {eval}"));
+	}
+	eprintln!("{line}");
+}
@@ -0,0 +1,57 @@
+use engine::eleven_man_ballots;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde_json::json;
+
+pub const USAGE: &str = "usage: ampere ballots [--random <n>] [--seed <n>]";
+
+/// Lists the eleven-man ballots. With `--random`, only that many ballots are
+/// chosen, and the list can be used as an opening suite.
+pub fn run_ballots(args: &[String], json: bool) {
+	let mut count = None;
+	let mut seed = None;
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--random" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					count = Some(n);
+					true
+				}
+				None => false,
+			},
+			"--seed" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					seed = Some(n);
+					true
+				}
+				None => false,
+			},
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			return;
+		}
+	}
+
+	let mut ballots = eleven_man_ballots();
+	if let Some(count) = count {
+		let mut rng = seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+		ballots = ballots.choose_multiple(&mut rng, count).cloned().collect();
+	}
+
+	for ballot in ballots {
+		if json {
+			let output = json!({
+				"ballot": ballot.to_string(),
+				"fen": engine::fen(ballot.position()),
+			});
+			println!("{output}");
+		} else {
+			println!("{ballot}");
+		}
+	}
+}
@@ -0,0 +1,255 @@
+use std::path::Path;
+
+use engine::{Config, OpeningBook};
+use model::CheckersBitBoard;
+use serde_json::{json, Value};
+
+use crate::cli::ProgressBar;
+
+/// The number of plies of each game that are added to a book
+const BOOK_PLIES: usize = 16;
+
+fn load_book(path: &str) -> Option<OpeningBook> {
+	match OpeningBook::load(path) {
+		Ok(book) => Some(book),
+		Err(error) => {
+			eprintln!("{path}: {error}");
+			None
+		}
+	}
+}
+
+fn save_book(book: &OpeningBook, path: &str) {
+	if let Err(error) = book.save(path) {
+		eprintln!("{path}: {error}");
+		std::process::exit(1);
+	}
+}
+
+pub const USAGE: &str = "usage: ampere book probe [<book>] [--fen <fen>] [--moves <moves>]
+       ampere book build <book> <pdn file>... [--plies <n>]
+       ampere book merge <output> <book>...
+       ampere book prune [<book>] [--min-weight <n>] [--from-start] [--output <file>]";
+
+/// Inspects and maintains opening books.
+///
+/// - `probe` lists the book moves in a position, with their weights.
+/// - `build` adds the opening moves of every game in PDN files to a book,
+///   creating it if it doesn't exist yet.
+/// - `merge` combines books, adding the weights of their shared moves.
+/// - `prune` removes the moves below a weight, and with `--from-start`, the
+///   moves which can't be reached from the starting position anymore.
+pub fn run_book(args: &[String], config: &Config, json: bool) {
+	let Some(command) = args.first() else {
+		eprintln!("{USAGE}");
+		std::process::exit(1);
+	};
+
+	// probing and pruning use the configured book if there isn't one given
+	let configured_book = config.book.as_ref().map(|book| book.display().to_string());
+	let (path, args) = match (args.get(1), &configured_book) {
+		(Some(path), _) if !path.starts_with("--") => (path, &args[2..]),
+		(_, Some(book)) if command == "probe" || command == "prune" => (book, &args[1..]),
+		_ => {
+			eprintln!("{USAGE}");
+			std::process::exit(1);
+		}
+	};
+
+	let mut start = CheckersBitBoard::starting_position();
+	let mut moves = "";
+	let mut plies = BOOK_PLIES;
+	let mut min_weight = 1;
+	let mut from_start = false;
+	let mut output = path;
+	let mut files = Vec::new();
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--fen" => match args.next().and_then(|fen| engine::parse_fen(fen)) {
+				Some(position) => {
+					start = position;
+					true
+				}
+				None => false,
+			},
+			"--moves" => match args.next() {
+				Some(line) => {
+					moves = line;
+					true
+				}
+				None => false,
+			},
+			"--plies" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					plies = n;
+					true
+				}
+				None => false,
+			},
+			"--min-weight" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					min_weight = n;
+					true
+				}
+				None => false,
+			},
+			"--from-start" => {
+				from_start = true;
+				true
+			}
+			"--output" => match args.next() {
+				Some(path) => {
+					output = path;
+					true
+				}
+				None => false,
+			},
+			file if !file.starts_with("--") => {
+				files.push(file);
+				true
+			}
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			std::process::exit(1);
+		}
+	}
+
+	match command.as_str() {
+		"probe" => {
+			let Some(book) = load_book(path) else {
+				return;
+			};
+			let position = moves.split_whitespace().try_fold(start, |board, notation| {
+				engine::find_move(board, notation)
+					.map(|full_move| full_move.result)
+					.ok_or(notation)
+			});
+			let position = match position {
+				Ok(position) => position,
+				Err(notation) => {
+					eprintln!("{notation:?} is not a legal move");
+					return;
+				}
+			};
+
+			let book_moves = book.probe(position);
+			let total: u64 = book_moves.iter().map(|(_, weight)| *weight as u64).sum();
+			let share = |weight: u32| weight as f64 / total.max(1) as f64;
+			if json {
+				let book_moves: Vec<Value> = book_moves
+					.iter()
+					.map(|(full_move, weight)| {
+						json!({
+							"move": full_move.long_notation(),
+							"weight": weight,
+							"share": share(*weight),
+						})
+					})
+					.collect();
+				let output = json!({
+					"fen": engine::fen(position),
+					"moves": book_moves,
+				});
+				println!("{output}");
+			} else if book_moves.is_empty() {
+				println!("no book moves");
+			} else {
+				for (full_move, weight) in book_moves {
+					println!(
+						"{:<8} {weight:>8} {:>6.1}%",
+						full_move.to_string(),
+						share(weight) * 100.0
+					);
+				}
+			}
+		}
+		"build" => {
+			let mut book = if Path::new(path).exists() {
+				match load_book(path) {
+					Some(book) => book,
+					None => return,
+				}
+			} else {
+				OpeningBook::new()
+			};
+
+			let mut pdns = Vec::new();
+			for file in files {
+				match std::fs::read_to_string(file) {
+					Ok(pdn) => pdns.push((file, pdn)),
+					Err(error) => {
+						eprintln!("{file}: {error}");
+						return;
+					}
+				}
+			}
+
+			// the games are split up first, so that the progress can be shown
+			let texts: Vec<(&str, usize, &str)> = pdns
+				.iter()
+				.flat_map(|(file, pdn)| {
+					engine::split_pdn_games(pdn)
+						.into_iter()
+						.enumerate()
+						.map(move |(index, game)| (*file, index, game))
+				})
+				.collect();
+			let mut progress = ProgressBar::new(texts.len(), json);
+			let mut games = 0;
+			for (done, (file, index, game)) in texts.into_iter().enumerate() {
+				progress.update("building the book", done);
+				match engine::parse_pdn(game) {
+					Ok(game) => {
+						book.add_game(&game.moves, plies);
+						games += 1;
+					}
+					Err(error) => {
+						progress.clear();
+						tracing::warn!(file, game = index + 1, %error, "skipped a game");
+					}
+				}
+			}
+			progress.clear();
+
+			save_book(&book, path);
+			report_book(json, &book, format!("added {games} games"));
+		}
+		"merge" => {
+			let mut book = OpeningBook::new();
+			for file in &files {
+				match load_book(file) {
+					Some(other) => book.merge(&other),
+					None => return,
+				}
+			}
+
+			save_book(&book, path);
+			report_book(json, &book, format!("merged {} books", files.len()));
+		}
+		"prune" => {
+			let Some(mut book) = load_book(path) else {
+				return;
+			};
+			let root = from_start.then_some(start);
+			let removed = book.prune(min_weight, root);
+			save_book(&book, output);
+			report_book(json, &book, format!("removed {removed} positions"));
+		}
+		_ => {
+			eprintln!("{USAGE}");
+			std::process::exit(1);
+		}
+	}
+}
+
+fn report_book(json: bool, book: &OpeningBook, message: String) {
+	if json {
+		println!("{}", json!({ "message": message, "positions": book.len() }));
+	} else {
+		println!("{message}, the book has {} positions", book.len());
+	}
+}
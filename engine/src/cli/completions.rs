@@ -0,0 +1,59 @@
+use engine::completions::{self, Shell};
+
+use crate::cli;
+
+/// The usage messages of every command, which the completed subcommands and
+/// flags are read from
+const USAGES: &[&str] = &[
+	cli::analyze::USAGE,
+	cli::annotate::USAGE,
+	cli::ballots::USAGE,
+	cli::book::USAGE,
+	USAGE,
+	// these commands don't take any arguments
+	"usage: ampere config",
+	cli::convert::USAGE,
+	cli::db::USAGE,
+	cli::diagram::USAGE,
+	cli::dxp::USAGE,
+	cli::endgame::USAGE,
+	cli::analyze::EVAL_USAGE,
+	cli::export::USAGE,
+	"usage: ampere hub",
+	"usage: ampere json",
+	#[cfg(all(feature = "lidraughts", not(target_arch = "wasm32")))]
+	cli::lidraughts::USAGE,
+	cli::openings::USAGE,
+	cli::pdn::USAGE,
+	cli::perft::USAGE,
+	cli::rate::USAGE,
+	cli::selfplay::USAGE,
+	"usage: ampere serve [<address>]",
+	cli::solve::USAGE,
+	cli::tablebase::USAGE,
+	cli::testsuite::USAGE,
+	cli::tournament::USAGE,
+	#[cfg(all(feature = "tui", not(target_arch = "wasm32")))]
+	cli::tui::USAGE,
+];
+
+pub const USAGE: &str = "usage: ampere completions <bash | zsh | fish | powershell>";
+
+/// Prints a script which completes the commands and flags in a shell
+pub fn run_completions(args: &[String]) {
+	let shell = match args {
+		[shell] => shell.parse::<Shell>().ok(),
+		_ => None,
+	};
+	let Some(shell) = shell else {
+		eprintln!("{USAGE}");
+		std::process::exit(1);
+	};
+
+	let commands = completions::commands_from_usage("ampere", USAGES);
+	let global_flags = completions::usage_flags(crate::USAGE);
+	print!(
+		"{}",
+		completions::generate(shell, "ampere", &commands, &global_flags)
+	);
+}
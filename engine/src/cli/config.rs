@@ -0,0 +1,65 @@
+use engine::Config;
+use serde_json::json;
+
+/// Prints the settings which are used when they aren't given on the command
+/// line
+pub fn run_config(config: &Config, json: bool) {
+	let path = Config::path();
+	let book = config.book.as_ref().map(|book| book.display().to_string());
+	let tablebases = config
+		.tablebases
+		.as_ref()
+		.map(|tablebases| tablebases.display().to_string());
+	let database = config
+		.database
+		.as_ref()
+		.map(|database| database.display().to_string());
+	if json {
+		let output = json!({
+			"file": path.as_ref().map(|path| path.display().to_string()),
+			"found": path.as_ref().is_some_and(|path| path.is_file()),
+			"hash": config.hash_megabytes(),
+			"threads": config.threads(),
+			"book": book,
+			"tablebases": tablebases,
+			"database": database,
+			"time": config.time().as_secs_f64(),
+		});
+		println!("{output}");
+		return;
+	}
+
+	match &path {
+		Some(path) if path.is_file() => println!("# {}", path.display()),
+		Some(path) => println!("# {} (not found)", path.display()),
+		None => println!("# no config file"),
+	}
+	let source = |configured: bool| if configured { "" } else { " # default" };
+	println!(
+		"hash = {}{}",
+		config.hash_megabytes(),
+		source(config.hash_megabytes.is_some())
+	);
+	println!(
+		"threads = {}{}",
+		config.threads(),
+		source(config.threads.is_some())
+	);
+	match book {
+		Some(book) => println!("book = {book:?}"),
+		None => println!("# book is not set"),
+	}
+	match tablebases {
+		Some(tablebases) => println!("tablebases = {tablebases:?}"),
+		None => println!("# tablebases is not set"),
+	}
+	match database {
+		Some(database) => println!("database = {database:?}"),
+		None => println!("# database is not set"),
+	}
+	println!(
+		"time = {}{}",
+		config.time().as_secs_f64(),
+		source(config.time.is_some())
+	);
+}
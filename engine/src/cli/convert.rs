@@ -0,0 +1,84 @@
+use engine::PdnGame;
+use serde_json::json;
+
+pub const USAGE: &str = "usage: ampere convert fens <pdn file>
+       ampere convert pdn <fen> [<move>...]";
+
+/// Converts a PDN game into the FEN of every position, or a FEN and moves
+/// into a PDN game
+pub fn run_convert(args: &[String], json: bool) {
+	let (Some(command), Some(input)) = (args.first(), args.get(1)) else {
+		eprintln!("{USAGE}");
+		std::process::exit(1);
+	};
+
+	match command.as_str() {
+		"fens" if args.len() == 2 => {
+			let pdn = match std::fs::read_to_string(input) {
+				Ok(pdn) => pdn,
+				Err(error) => {
+					eprintln!("{input}: {error}");
+					std::process::exit(1);
+				}
+			};
+
+			let games = engine::split_pdn_games(&pdn);
+			for (index, text) in games.iter().enumerate() {
+				let game = match engine::parse_pdn(text) {
+					Ok(game) => game,
+					Err(error) => {
+						eprintln!("game {}: {error}", index + 1);
+						std::process::exit(1);
+					}
+				};
+
+				let fens: Vec<String> = std::iter::once(game.start)
+					.chain(game.moves.iter().map(|full_move| full_move.result))
+					.map(engine::fen)
+					.collect();
+				if json {
+					println!("{}", json!({ "game": index + 1, "fens": fens }));
+				} else {
+					if index > 0 {
+						println!();
+					}
+					for fen in fens {
+						println!("{fen}");
+					}
+				}
+			}
+		}
+		"pdn" => {
+			let Some(start) = engine::parse_fen(input) else {
+				eprintln!("{input:?} is not a valid FEN");
+				std::process::exit(1);
+			};
+
+			let mut position = start;
+			let mut moves = Vec::new();
+			// the moves may be given as one argument, or as many
+			for notation in args[2..].iter().flat_map(|moves| moves.split_whitespace()) {
+				let Some(full_move) = engine::find_move(position, notation) else {
+					eprintln!(
+						"{notation} is not a legal move in {}",
+						engine::fen(position)
+					);
+					std::process::exit(1);
+				};
+				position = full_move.result;
+				moves.push(full_move);
+			}
+
+			let pdn = PdnGame { start, moves }.to_pdn();
+			if json {
+				println!("{}", json!({ "pdn": pdn }));
+			} else {
+				print!("{pdn}");
+			}
+		}
+		_ => {
+			eprintln!("{USAGE}");
+			std::process::exit(1);
+		}
+	}
+}
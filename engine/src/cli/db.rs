@@ -0,0 +1,259 @@
+use std::path::{Path, PathBuf};
+
+use engine::{Config, GameDatabase, GameInfo, ResultCounts};
+use model::CheckersBitBoard;
+use serde_json::{json, Value};
+
+use crate::cli::pdn_files;
+use crate::cli::ProgressBar;
+
+/// The number of games that are listed by a database query, by default
+const DATABASE_GAMES: usize = 10;
+
+pub const USAGE: &str = "usage: ampere db import <file or directory>... [--db <database>]
+       ampere db query [--fen <fen>] [--moves <moves>] [--games <n>] [--db <database>]";
+
+/// Builds and searches an index of the positions in PDN files.
+///
+/// - `import` adds every game in PDN files, or directories of them, to the
+///   index.
+/// - `query` shows the games which reached a position, how they ended, and
+///   what was played next.
+pub fn run_db(args: &[String], config: &Config, json: bool) {
+	let Some(command) = args.first() else {
+		eprintln!("{USAGE}");
+		std::process::exit(1);
+	};
+
+	let mut path = config.database.clone();
+	let mut position = CheckersBitBoard::starting_position();
+	let mut moves = "";
+	let mut games_shown = DATABASE_GAMES;
+	let mut paths = Vec::new();
+	let mut args = args[1..].iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--db" => match args.next() {
+				Some(database) => {
+					path = Some(database.into());
+					true
+				}
+				None => false,
+			},
+			"--fen" => match args.next().and_then(|fen| engine::parse_fen(fen)) {
+				Some(fen) => {
+					position = fen;
+					true
+				}
+				None => false,
+			},
+			"--moves" => match args.next() {
+				Some(line) => {
+					moves = line;
+					true
+				}
+				None => false,
+			},
+			"--games" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					games_shown = n;
+					true
+				}
+				None => false,
+			},
+			_ if command == "import" && !arg.starts_with("--") => {
+				paths.push(PathBuf::from(arg));
+				true
+			}
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			std::process::exit(1);
+		}
+	}
+
+	let Some(path) = path else {
+		eprintln!("{USAGE}");
+		std::process::exit(1);
+	};
+	let load = |path: &Path| match GameDatabase::load(path) {
+		Ok(database) => database,
+		Err(error) => {
+			eprintln!("{}: {error}", path.display());
+			std::process::exit(1);
+		}
+	};
+
+	match command.as_str() {
+		"import" if !paths.is_empty() => {
+			let mut database = if path.exists() {
+				load(&path)
+			} else {
+				GameDatabase::new()
+			};
+
+			let mut pdns = Vec::new();
+			for file in pdn_files(paths) {
+				match std::fs::read_to_string(&file) {
+					Ok(pdn) => pdns.push((file.display().to_string(), pdn)),
+					Err(error) => {
+						tracing::warn!(file = %file.display(), %error, "skipped a file");
+					}
+				}
+			}
+
+			// the games are split up first, so that the progress can be shown
+			let texts: Vec<(&str, usize, &str)> = pdns
+				.iter()
+				.flat_map(|(file, pdn)| {
+					engine::split_pdn_games(pdn)
+						.into_iter()
+						.enumerate()
+						.map(move |(index, game)| (file.as_str(), index, game))
+				})
+				.collect();
+			let mut progress = ProgressBar::new(texts.len(), json);
+			let mut imported = 0;
+			for (done, (file, index, game)) in texts.into_iter().enumerate() {
+				progress.update("importing", done);
+				match database.add_game(file, index, game) {
+					Ok(_) => imported += 1,
+					Err(error) => {
+						progress.clear();
+						tracing::warn!(file, game = index + 1, %error, "skipped a game");
+					}
+				}
+			}
+			progress.clear();
+
+			if let Err(error) = database.save(&path) {
+				eprintln!("{}: {error}", path.display());
+				std::process::exit(1);
+			}
+			if json {
+				let output = json!({
+					"imported": imported,
+					"games": database.len(),
+					"positions": database.positions(),
+				});
+				println!("{output}");
+			} else {
+				println!(
+					"imported {imported} games, the database has {} games and {} positions",
+					database.len(),
+					database.positions()
+				);
+			}
+		}
+		"query" => {
+			let position = moves
+				.split_whitespace()
+				.try_fold(position, |board, notation| {
+					engine::find_move(board, notation)
+						.map(|full_move| full_move.result)
+						.ok_or(notation)
+				});
+			let position = match position {
+				Ok(position) => position,
+				Err(notation) => {
+					eprintln!("{notation:?} is not a legal move");
+					return;
+				}
+			};
+
+			let database = load(&path);
+			let stats = database.get(position).unwrap_or_default();
+			let percent = |count: u32, results: &ResultCounts| {
+				count as f64 / results.games().max(1) as f64 * 100.0
+			};
+			let moves = database.moves(position);
+			let games: Vec<&GameInfo> = stats
+				.games
+				.iter()
+				.take(games_shown)
+				.filter_map(|&id| database.game(id))
+				.collect();
+
+			if json {
+				let results_json = |results: &ResultCounts| {
+					json!({
+						"games": results.games(),
+						"black_wins": results.black_wins,
+						"white_wins": results.white_wins,
+						"draws": results.draws,
+						"unfinished": results.unfinished,
+					})
+				};
+				let moves: Vec<Value> = moves
+					.iter()
+					.map(|(full_move, results)| {
+						let mut output = results_json(results);
+						output["move"] = Value::String(full_move.long_notation());
+						output
+					})
+					.collect();
+				let games: Vec<Value> = games
+					.iter()
+					.map(|game| {
+						json!({
+							"file": game.file,
+							"game": game.index + 1,
+							"black": game.black,
+							"white": game.white,
+							"result": game.result.notation(),
+						})
+					})
+					.collect();
+				let output = json!({
+					"fen": engine::fen(position),
+					"results": results_json(&stats.results),
+					"moves": moves,
+					"games": games,
+				});
+				println!("{output}");
+				return;
+			}
+
+			let results = stats.results;
+			println!(
+				"{} games: black {:.1}%, draw {:.1}%, white {:.1}%",
+				results.games(),
+				percent(results.black_wins, &results),
+				percent(results.draws, &results),
+				percent(results.white_wins, &results)
+			);
+			if !moves.is_empty() {
+				println!(
+					"{:<8} {:>8} {:>7} {:>7} {:>7}",
+					"move", "games", "black", "draw", "white"
+				);
+			}
+			for (full_move, results) in &moves {
+				println!(
+					"{:<8} {:>8} {:>6.1}% {:>6.1}% {:>6.1}%",
+					full_move.to_string(),
+					results.games(),
+					percent(results.black_wins, results),
+					percent(results.draws, results),
+					percent(results.white_wins, results)
+				);
+			}
+			for game in games {
+				println!(
+					"{} game {}: {} - {}, {}",
+					game.file,
+					game.index + 1,
+					game.black,
+					game.white,
+					game.result.notation()
+				);
+			}
+		}
+		_ => {
+			eprintln!("{USAGE}");
+			std::process::exit(1);
+		}
+	}
+}
@@ -0,0 +1,53 @@
+use model::CheckersBitBoard;
+use serde_json::json;
+
+pub const USAGE: &str = "usage: ampere diagram [--fen <fen>] [--svg <file>]";
+
+/// Prints a diagram of a position, and optionally writes it as an SVG image
+pub fn run_diagram(args: &[String], json: bool) {
+	let mut position = Some(CheckersBitBoard::starting_position());
+	let mut svg_path = None;
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--fen" => {
+				position = args.next().and_then(|fen| engine::parse_fen(fen));
+				position.is_some()
+			}
+			"--svg" => {
+				svg_path = args.next();
+				svg_path.is_some()
+			}
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			return;
+		}
+	}
+
+	let Some(position) = position else {
+		eprintln!("{USAGE}");
+		return;
+	};
+
+	if let Some(path) = svg_path {
+		if let Err(error) = std::fs::write(path, engine::svg_diagram(position)) {
+			eprintln!("{path}: {error}");
+			std::process::exit(1);
+		}
+	}
+
+	let diagram = engine::text_diagram(position);
+	if json {
+		let output = json!({
+			"fen": engine::fen(position),
+			"diagram": diagram,
+			"svg": svg_path,
+		});
+		println!("{output}");
+	} else {
+		print!("{diagram}");
+	}
+}
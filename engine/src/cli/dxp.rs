@@ -0,0 +1,38 @@
+use std::sync::Arc;
+
+use engine::dxp::{self, GameSettings};
+use engine::{Config, Engine, TextFrontend, ENGINE_NAME, MEGABYTE};
+
+pub const USAGE: &str = "usage: ampere dxp listen [<address>]
+       ampere dxp connect <address>";
+
+/// Plays one DXP game. `ampere dxp listen [address]` waits for an opponent to
+/// ask for a game, and `ampere dxp connect <address>` asks an opponent for one.
+pub fn play_dxp(args: &[String], config: &Config) {
+	let engine = Engine::new(
+		config.hash_megabytes() * MEGABYTE,
+		Arc::new(TextFrontend::new(|_| {})),
+	);
+	let output = |line: &str| println!("{line}");
+	let result = match args {
+		[mode] if mode == "listen" => {
+			dxp::listen(&engine, ("0.0.0.0", dxp::DEFAULT_PORT), ENGINE_NAME, output)
+		}
+		[mode, address] if mode == "listen" => {
+			dxp::listen(&engine, address.as_str(), ENGINE_NAME, output)
+		}
+		[mode, address] if mode == "connect" => {
+			dxp::connect(&engine, address.as_str(), &GameSettings::default(), output)
+		}
+		_ => {
+			eprintln!("{USAGE}");
+			return;
+		}
+	};
+
+	match result {
+		Ok(Some(result)) => println!("result: {result:?}"),
+		Ok(None) => println!("the game ended without a result"),
+		Err(error) => eprintln!("{error}"),
+	}
+}
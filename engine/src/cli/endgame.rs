@@ -0,0 +1,113 @@
+use std::time::Instant;
+
+use engine::retrograde::Material;
+use engine::tablebase::Outcome;
+use engine::{Config, EndgameValue, FullMove, ProofResult};
+use model::PieceColor;
+use serde_json::json;
+
+use crate::cli::solve::PROOF_NODES;
+
+pub const USAGE: &str =
+	"usage: ampere endgame --fen <fen> [--tablebases <dir>] [--nodes <proof nodes>]";
+
+/// Solves an endgame with the tablebases, or with a proof search if its
+/// tablebase is missing, and prints which tablebases were used
+pub fn run_endgame(args: &[String], config: &Config, json: bool) {
+	let mut position = None;
+	let mut directory = config.tablebases.clone();
+	let mut max_nodes = PROOF_NODES;
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--fen" => {
+				position = args.next().and_then(|fen| engine::parse_fen(fen));
+				position.is_some()
+			}
+			"--tablebases" => match args.next() {
+				Some(path) => {
+					directory = Some(path.into());
+					true
+				}
+				None => false,
+			},
+			"--nodes" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					max_nodes = n;
+					true
+				}
+				None => false,
+			},
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			return;
+		}
+	}
+
+	let Some(position) = position else {
+		eprintln!("{USAGE}");
+		return;
+	};
+
+	let start = Instant::now();
+	let mut tables = engine::TablebaseSet::new(directory);
+	let solution = engine::solve_endgame(&mut tables, position, max_nodes);
+	let time = start.elapsed();
+
+	let (value, distance, source) = match solution.value {
+		EndgameValue::Exact(Outcome::Win(moves)) => ("win", Some(moves), Some("tablebase")),
+		EndgameValue::Exact(Outcome::Loss(moves)) => ("loss", Some(moves), Some("tablebase")),
+		EndgameValue::Exact(Outcome::Draw) => ("draw", None, Some("tablebase")),
+		EndgameValue::Proven(ProofResult::Win) => ("win", None, Some("proof search")),
+		EndgameValue::Proven(_) => ("loss", None, Some("proof search")),
+		EndgameValue::Unknown => ("unknown", None, None),
+	};
+	let file_names = |materials: Vec<Material>| -> Vec<String> {
+		materials
+			.iter()
+			.map(|material| material.file_name())
+			.collect()
+	};
+	let consulted = file_names(tables.consulted());
+	let missing = file_names(tables.missing());
+
+	if json {
+		let output = json!({
+			"value": value,
+			"distance": distance,
+			"source": source,
+			"line": solution.line.iter().map(FullMove::long_notation).collect::<Vec<_>>(),
+			"consulted": consulted,
+			"missing": missing,
+			"seconds": time.as_secs_f64(),
+		});
+		println!("{output}");
+		return;
+	}
+
+	let side = match position.turn() {
+		PieceColor::Dark => "black",
+		PieceColor::Light => "white",
+	};
+	match (distance, source) {
+		(Some(moves), Some(source)) => {
+			println!("{value} for {side} to move in {moves} moves ({source})")
+		}
+		(None, Some(source)) => println!("{value} for {side} to move ({source})"),
+		(_, None) => println!("not solved: no tablebase, and not proven within {max_nodes} nodes"),
+	}
+	if !solution.line.is_empty() {
+		let line: Vec<String> = solution.line.iter().map(FullMove::to_string).collect();
+		println!("line: {}", line.join(" "));
+	}
+	if !consulted.is_empty() {
+		println!("consulted: {}", consulted.join(" "));
+	}
+	if !missing.is_empty() {
+		println!("missing: {}", missing.join(" "));
+	}
+	println!("time: {:.3}s", time.as_secs_f64());
+}
@@ -0,0 +1,73 @@
+use engine::export::{self, ExportFormat};
+use engine::selfplay::TrainingDataError;
+
+pub const USAGE: &str = "usage: ampere export <training data | pdn file> [--out <file>] \
+	[--format <csv | parquet>]";
+
+/// Converts self-play training data, or annotated PDN, into CSV or Parquet,
+/// with the features of each position, the evaluation, and the result. The
+/// format is chosen from the extension of `--out` if it isn't given.
+pub fn run_export(args: &[String]) {
+	let mut path = None;
+	let mut out_path = None;
+	let mut format = None;
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--out" => {
+				out_path = args.next();
+				out_path.is_some()
+			}
+			"--format" => {
+				format = args.next().and_then(|format| format.parse().ok());
+				format.is_some()
+			}
+			_ if path.is_none() && !arg.starts_with("--") => {
+				path = Some(arg);
+				true
+			}
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			return;
+		}
+	}
+
+	let Some(path) = path else {
+		eprintln!("{USAGE}");
+		return;
+	};
+	let data = match std::fs::read(path) {
+		Ok(data) => data,
+		Err(error) => {
+			eprintln!("{path}: {error}");
+			std::process::exit(1);
+		}
+	};
+
+	// training data starts with a magic number, and anything else is PDN
+	let rows = match export::training_rows(&mut data.as_slice()) {
+		Ok(rows) => rows,
+		Err(TrainingDataError::MagicError) => export::pdn_rows(&String::from_utf8_lossy(&data)),
+		Err(error) => {
+			eprintln!("{path}: {error}");
+			std::process::exit(1);
+		}
+	};
+
+	let format =
+		format.unwrap_or_else(|| out_path.map_or(ExportFormat::Csv, ExportFormat::from_path));
+	let result = match out_path {
+		Some(out_path) => match std::fs::File::create(out_path) {
+			Ok(file) => export::write_rows(&rows, format, std::io::BufWriter::new(file)),
+			Err(error) => Err(error.into()),
+		},
+		None => export::write_rows(&rows, format, std::io::BufWriter::new(std::io::stdout())),
+	};
+	if let Err(error) = result {
+		eprintln!("{}: {error}", out_path.map_or("stdout", String::as_str));
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,39 @@
+use engine::lidraughts::{self, BotSettings};
+
+pub const USAGE: &str = "usage: ampere lidraughts [--upgrade] [--server <url>] --variant <key>...";
+
+/// Runs a lidraughts bot. The API token is read from `LIDRAUGHTS_TOKEN`, and
+/// each `--variant` is accepted. `--upgrade` turns the account into a bot
+/// account first.
+pub fn run_lidraughts(args: &[String]) {
+	let Ok(token) = std::env::var("LIDRAUGHTS_TOKEN") else {
+		eprintln!("LIDRAUGHTS_TOKEN must be set to the bot's API token");
+		return;
+	};
+
+	let mut settings = BotSettings::new(token);
+	let mut upgrade = false;
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--upgrade" => upgrade = true,
+			"--variant" => settings.variants.extend(args.next().cloned()),
+			"--server" => settings.server = args.next().cloned().unwrap_or(settings.server),
+			_ => {
+				eprintln!("{USAGE}");
+				return;
+			}
+		}
+	}
+
+	if upgrade {
+		if let Err(error) = lidraughts::upgrade_account(&settings) {
+			eprintln!("{error}");
+			return;
+		}
+	}
+
+	if let Err(error) = lidraughts::run(&settings) {
+		eprintln!("{error}");
+	}
+}
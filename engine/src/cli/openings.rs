@@ -0,0 +1,123 @@
+use std::path::PathBuf;
+
+use engine::OpeningStats;
+use model::CheckersBitBoard;
+use pdn::statistics::Statistics;
+use serde_json::json;
+
+use crate::cli::pdn_files;
+
+/// The number of plies that openings are grouped by, by default. Three plies
+/// is a three-move ballot.
+const OPENING_PLIES: usize = 3;
+
+pub const USAGE: &str =
+	"usage: ampere openings <file or directory>... [--plies <n>] [--min-games <n>]";
+
+/// Adds up the results of the games in PDN files by their openings, so that
+/// balanced openings can be chosen for a match
+pub fn run_openings(args: &[String], json: bool) {
+	let mut plies = OPENING_PLIES;
+	let mut min_games = 1;
+	let mut paths = Vec::new();
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--plies" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					plies = n;
+					true
+				}
+				None => false,
+			},
+			"--min-games" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					min_games = n;
+					true
+				}
+				None => false,
+			},
+			_ if !arg.starts_with("--") => {
+				paths.push(PathBuf::from(arg));
+				true
+			}
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			return;
+		}
+	}
+
+	if paths.is_empty() {
+		eprintln!("{USAGE}");
+		return;
+	}
+
+	let mut pdns = Vec::new();
+	for file in pdn_files(paths) {
+		match std::fs::read_to_string(&file) {
+			Ok(pdn) => pdns.push(pdn),
+			Err(error) => tracing::warn!(file = %file.display(), %error, "skipped a file"),
+		}
+	}
+	let games = pdns.iter().flat_map(|pdn| engine::split_pdn_games(pdn));
+	let openings: Vec<_> = engine::opening_stats(games, plies)
+		.into_iter()
+		.filter(|opening| opening.statistics.games() >= min_games)
+		.collect();
+
+	// openings from another position start with its FEN
+	let line = |opening: &OpeningStats, long: bool| {
+		let moves = opening.moves.iter().map(|full_move| match long {
+			true => full_move.long_notation(),
+			false => full_move.to_string(),
+		});
+		let start = (opening.start != CheckersBitBoard::starting_position())
+			.then(|| engine::fen(opening.start));
+		start.into_iter().chain(moves).collect::<Vec<_>>().join(" ")
+	};
+	let percent = |count: usize, statistics: &Statistics| {
+		count as f64 / statistics.games().max(1) as f64 * 100.0
+	};
+
+	if json {
+		for opening in &openings {
+			let output = json!({
+				"opening": line(opening, true),
+				"games": opening.statistics.games(),
+				"black_wins": opening.statistics.black_wins(),
+				"white_wins": opening.statistics.white_wins(),
+				"draws": opening.statistics.draws(),
+				"unfinished": opening.statistics.unfinished(),
+				"average_length": opening.average_length(),
+			});
+			println!("{output}");
+		}
+		return;
+	}
+
+	let width = openings
+		.iter()
+		.map(|opening| line(opening, false).len())
+		.max()
+		.unwrap_or(0)
+		.max("opening".len());
+	println!(
+		"{:<width$} {:>8} {:>7} {:>7} {:>7} {:>7}",
+		"opening", "games", "black", "draw", "white", "length"
+	);
+	for opening in &openings {
+		let statistics = &opening.statistics;
+		println!(
+			"{:<width$} {:>8} {:>6.1}% {:>6.1}% {:>6.1}% {:>7.1}",
+			line(opening, false),
+			statistics.games(),
+			percent(statistics.black_wins(), statistics),
+			percent(statistics.draws(), statistics),
+			percent(statistics.white_wins(), statistics),
+			opening.average_length()
+		);
+	}
+}
@@ -0,0 +1,119 @@
+use std::path::Path;
+
+use engine::GameQuery;
+use serde_json::json;
+
+use crate::cli::pdn_files;
+
+pub const USAGE: &str = "usage: ampere pdn find <file or directory>... [--player <name>] \
+	[--result <result>] [--fen <fen>] [--opening <moves>] [--out <pdn file>]";
+
+/// Searches PDN files for games
+pub fn run_pdn(args: &[String], json: bool) {
+	if args.first().map(String::as_str) != Some("find") {
+		eprintln!("{USAGE}");
+		std::process::exit(1);
+	}
+
+	let mut query = GameQuery::default();
+	let mut paths = Vec::new();
+	let mut out_path = None;
+	let mut args = args[1..].iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--player" => {
+				query.player = args.next().cloned();
+				query.player.is_some()
+			}
+			"--result" => {
+				query.result = args.next().cloned();
+				query.result.is_some()
+			}
+			"--fen" => {
+				query.position = args.next().and_then(|fen| engine::parse_fen(fen));
+				query.position.is_some()
+			}
+			"--opening" => match args.next().map(|moves| engine::parse_pdn(moves)) {
+				Some(Ok(game)) if !game.moves.is_empty() => {
+					query.opening = game.moves;
+					true
+				}
+				Some(Err(error)) => {
+					eprintln!("{error}");
+					return;
+				}
+				_ => false,
+			},
+			"--out" => {
+				out_path = args.next();
+				out_path.is_some()
+			}
+			_ if !arg.starts_with("--") => {
+				paths.push(Path::new(arg).to_path_buf());
+				true
+			}
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			std::process::exit(1);
+		}
+	}
+
+	if paths.is_empty() {
+		eprintln!("{USAGE}");
+		std::process::exit(1);
+	}
+
+	let files = pdn_files(paths);
+	let mut found = Vec::new();
+	for file in &files {
+		let pdn = match std::fs::read_to_string(file) {
+			Ok(pdn) => pdn,
+			Err(error) => {
+				tracing::warn!(file = %file.display(), %error, "skipped a file");
+				continue;
+			}
+		};
+
+		for (index, game) in engine::split_pdn_games(&pdn).into_iter().enumerate() {
+			if !query.matches(game) {
+				continue;
+			}
+
+			let tag = |name| engine::pdn_tag(game, name).unwrap_or_else(|| "?".to_string());
+			let (black, white, result) = (tag("Black"), tag("White"), tag("Result"));
+			if json {
+				let output = json!({
+					"file": file.display().to_string(),
+					"game": index + 1,
+					"black": black,
+					"white": white,
+					"result": result,
+				});
+				println!("{output}");
+			} else {
+				println!(
+					"{} game {}: {black} - {white}, {result}",
+					file.display(),
+					index + 1
+				);
+			}
+			found.push(game.trim().to_string());
+		}
+	}
+
+	if let Some(out_path) = out_path {
+		let mut pdn = found.join("\n\n");
+		pdn.push('\n');
+		if let Err(error) = std::fs::write(out_path, pdn) {
+			eprintln!("{out_path}: {error}");
+			std::process::exit(1);
+		}
+	}
+
+	if !json {
+		println!("found {} games in {} files", found.len(), files.len());
+	}
+}
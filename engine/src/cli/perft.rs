@@ -0,0 +1,244 @@
+use std::num::NonZeroUsize;
+use std::time::Instant;
+
+use engine::{Config, PerftTable, PERFT_SUITE};
+use model::{CheckersBitBoard, PossibleMoves};
+use serde_json::{json, Value};
+
+use crate::cli::ProgressBar;
+
+pub const USAGE: &str =
+	"usage: ampere perft <depth> [--fen <fen>] [--divide] [--threads <n>] [--hash <mb>]
+       ampere perft --check [max depth]";
+
+/// The arguments of the perft command
+#[derive(Debug, PartialEq, Eq)]
+struct PerftArgs {
+	depth: Option<u8>,
+	board: CheckersBitBoard,
+	divide: bool,
+	check: bool,
+	threads: NonZeroUsize,
+	hash_megabytes: Option<usize>,
+}
+
+/// Reads the perft arguments. A depth is needed unless `--check` is given.
+/// Returns the message to print if the arguments are invalid.
+fn parse_args(args: &[String], config: &Config) -> Result<PerftArgs, &'static str> {
+	let mut parsed = PerftArgs {
+		depth: None,
+		board: CheckersBitBoard::starting_position(),
+		divide: false,
+		check: false,
+		threads: config.threads(),
+		hash_megabytes: None,
+	};
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		match arg.as_str() {
+			"--divide" => parsed.divide = true,
+			"--fen" => match args.next().and_then(|fen| engine::parse_fen(fen)) {
+				Some(position) => parsed.board = position,
+				None => return Err("invalid FEN"),
+			},
+			"--threads" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => parsed.threads = n,
+				None => return Err(USAGE),
+			},
+			"--hash" => match args.next().and_then(|mb| mb.parse().ok()) {
+				Some(mb) => parsed.hash_megabytes = Some(mb),
+				None => return Err(USAGE),
+			},
+			"--check" => parsed.check = true,
+			_ => match arg.parse() {
+				Ok(n) if parsed.depth.is_none() => parsed.depth = Some(n),
+				_ => return Err(USAGE),
+			},
+		}
+	}
+
+	if parsed.depth.is_none() && !parsed.check {
+		return Err(USAGE);
+	}
+
+	Ok(parsed)
+}
+
+/// Counts the positions `depth` plies from the starting position, or from
+/// `--fen`. With `--divide`, the count under each root move is also printed.
+/// `--threads` counts the root moves in parallel, and `--hash` remembers the
+/// counts of transpositions in a table with that many megabytes. `--check`
+/// compares the move generator to the known counts, up to the depth if one
+/// is given. Exits with an error if the arguments are invalid.
+pub fn run_perft(args: &[String], config: &Config, json: bool) {
+	let PerftArgs {
+		depth,
+		board,
+		divide,
+		check,
+		threads,
+		hash_megabytes,
+	} = match parse_args(args, config) {
+		Ok(args) => args,
+		Err(message) => {
+			eprintln!("{message}");
+			std::process::exit(1);
+		}
+	};
+
+	if check {
+		if !check_perft(depth, json) {
+			std::process::exit(1);
+		}
+		return;
+	}
+
+	let depth = depth.expect("the depth is checked with the arguments");
+
+	let table = hash_megabytes.map(PerftTable::with_megabytes);
+	let count = |board, depth| match &table {
+		Some(table) => engine::perft_hashed(board, depth, threads, table),
+		None if threads.get() > 1 => engine::perft_parallel(board, depth, threads),
+		None => Ok(engine::perft(board, depth)),
+	};
+
+	let start = Instant::now();
+	let mut divided = Vec::new();
+	let nodes = if depth > 0 {
+		// the root moves are counted one at a time, so that the progress can
+		// be shown
+		let root_moves: Vec<_> = PossibleMoves::moves(board).into_iter().collect();
+		let mut progress = ProgressBar::new(root_moves.len(), json);
+		let mut total = 0;
+		for (done, root_move) in root_moves.into_iter().enumerate() {
+			progress.update(&format!("perft {depth}"), done);
+			let nodes = match count(unsafe { root_move.apply_to(board) }, depth - 1) {
+				Ok(nodes) => nodes,
+				Err(error) => {
+					progress.clear();
+					eprintln!("{error}");
+					return;
+				}
+			};
+			if divide {
+				if !json {
+					progress.clear();
+					println!("{root_move}: {nodes}");
+				}
+				divided.push(json!({ "move": root_move.to_string(), "nodes": nodes }));
+			}
+			total += nodes;
+		}
+		progress.clear();
+		total
+	} else {
+		match count(board, depth) {
+			Ok(nodes) => nodes,
+			Err(error) => {
+				eprintln!("{error}");
+				return;
+			}
+		}
+	};
+
+	let seconds = start.elapsed().as_secs_f64();
+	if json {
+		let mut output = json!({ "depth": depth, "nodes": nodes, "seconds": seconds });
+		if divide {
+			output["moves"] = Value::Array(divided);
+		}
+		println!("{output}");
+	} else {
+		println!("nodes: {nodes}");
+		println!("time: {seconds:.3}s");
+	}
+}
+
+/// Counts the positions in the perft suite at each depth, and compares them
+/// to the known counts. Returns whether every count was correct.
+fn check_perft(max_depth: Option<u8>, json: bool) -> bool {
+	let mut passed = true;
+	for position in PERFT_SUITE {
+		let Some(board) = engine::parse_fen(position.fen) else {
+			eprintln!("{}: invalid FEN", position.name);
+			passed = false;
+			continue;
+		};
+
+		for (depth, &expected) in (1..).zip(position.counts) {
+			if max_depth.is_some_and(|max_depth| depth > max_depth) {
+				break;
+			}
+
+			let nodes = engine::perft_complete_moves(board, depth);
+			if json {
+				let output = json!({
+					"position": position.name,
+					"depth": depth,
+					"nodes": nodes,
+					"expected": expected,
+					"passed": nodes == expected,
+				});
+				println!("{output}");
+			} else if nodes == expected {
+				println!("{} depth {depth}: {nodes} ok", position.name);
+			} else {
+				println!(
+					"{} depth {depth}: {nodes} FAILED, expected {expected}",
+					position.name
+				);
+			}
+			passed &= nodes == expected;
+		}
+	}
+
+	passed
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn args(args: &[&str]) -> Vec<String> {
+		args.iter().map(ToString::to_string).collect()
+	}
+
+	#[test]
+	fn reads_every_argument() {
+		let config = Config::default();
+		let parsed = parse_args(
+			&args(&["5", "--divide", "--threads", "4", "--hash", "16"]),
+			&config,
+		)
+		.unwrap();
+		assert_eq!(parsed.depth, Some(5));
+		assert!(parsed.divide);
+		assert!(!parsed.check);
+		assert_eq!(parsed.threads.get(), 4);
+		assert_eq!(parsed.hash_megabytes, Some(16));
+
+		let parsed = parse_args(&args(&["--check"]), &config).unwrap();
+		assert!(parsed.check);
+		assert_eq!(parsed.depth, None);
+	}
+
+	#[test]
+	fn rejects_bad_arguments() {
+		let config = Config::default();
+		for bad in [
+			&[][..],
+			&["--threads"],
+			&["5", "--threads", "zero"],
+			&["5", "--hash"],
+			&["5", "6"],
+			&["deep"],
+		] {
+			assert_eq!(parse_args(&args(bad), &config), Err(USAGE), "{bad:?}");
+		}
+
+		assert_eq!(
+			parse_args(&args(&["5", "--fen", "not a fen"]), &config),
+			Err("invalid FEN")
+		);
+	}
+}
@@ -0,0 +1,97 @@
+use engine::tournament::{self, Standings};
+use serde_json::{json, Value};
+
+use crate::cli::tournament::{score_json, standings_json};
+
+pub const USAGE: &str = "usage: ampere rate <pdn file>...";
+
+/// Reads the results of games in PDN files, and reports the Elo difference,
+/// likelihood of superiority, and draw rate of each pairing
+pub fn run_rate(args: &[String], json: bool) {
+	if args.is_empty() {
+		eprintln!("{USAGE}");
+		return;
+	}
+
+	let mut results = Vec::new();
+	for path in args {
+		match std::fs::read_to_string(path) {
+			Ok(pdn) => results.extend(tournament::parse_results(&pdn)),
+			Err(error) => {
+				eprintln!("{path}: {error}");
+				std::process::exit(1);
+			}
+		}
+	}
+
+	let mut names: Vec<String> = Vec::new();
+	let mut index = |name: &String| match names.iter().position(|known| known == name) {
+		Some(index) => index,
+		None => {
+			names.push(name.clone());
+			names.len() - 1
+		}
+	};
+	let games: Vec<_> = results
+		.iter()
+		.map(|game| (index(&game.black), index(&game.white), game.result))
+		.collect();
+
+	let mut standings = Standings::new(names);
+	for (black, white, result) in games {
+		standings.add_result(black, white, result);
+	}
+
+	let ranking = standings.ranking();
+	let pairings: Vec<(usize, usize)> = ranking
+		.iter()
+		.enumerate()
+		.flat_map(|(rank, &player)| {
+			ranking[rank + 1..]
+				.iter()
+				.map(move |&opponent| (player, opponent))
+		})
+		.filter(|&(player, opponent)| standings.scores[player][opponent].games() > 0)
+		.collect();
+
+	if json {
+		let pairings: Vec<Value> = pairings
+			.iter()
+			.map(|&(player, opponent)| {
+				let score = standings.scores[player][opponent];
+				let mut output = score_json(score);
+				output["player"] = json!(standings.names[player]);
+				output["opponent"] = json!(standings.names[opponent]);
+				output["los"] = json!(score.los());
+				output["draw_rate"] = json!(score.draw_rate());
+				output
+			})
+			.collect();
+		let output = json!({
+			"games": results.len(),
+			"standings": standings_json(&standings),
+			"pairings": pairings,
+		});
+		println!("{output}");
+		return;
+	}
+
+	println!("{standings}");
+	println!();
+	for (player, opponent) in pairings {
+		let score = standings.scores[player][opponent];
+		let (elo, error) = score
+			.elo()
+			.map_or((0.0, 0.0), |estimate| (estimate.elo, estimate.error));
+		println!(
+			"{} vs {}: +{} ={} -{}, elo {elo:+.0} +/- {error:.0}, los {:.1}%, draws {:.1}%",
+			standings.names[player],
+			standings.names[opponent],
+			score.wins,
+			score.draws,
+			score.losses,
+			score.los() * 100.0,
+			score.draw_rate() * 100.0,
+		);
+	}
+}
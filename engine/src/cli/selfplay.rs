@@ -0,0 +1,217 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::num::NonZeroU8;
+use std::sync::Arc;
+use std::time::Duration;
+
+use engine::selfplay::{self, SelfPlaySettings};
+use engine::tournament::{self, Score};
+use engine::{ActualLimit, Config, Engine, SearchLimit, TextFrontend, ENGINE_NAME, MEGABYTE};
+use model::CheckersBitBoard;
+use pdn::GameResult;
+use serde_json::json;
+
+pub const USAGE: &str = "usage: ampere selfplay [--games <n>] [--time <seconds>] [--depth <n>] \
+	[--nodes <n>] [--opening-moves <n>] [--skill <level>] \
+	[--max-plies <n>] [--hash <megabytes>] [--out <pdn file>] \
+	[--data <training file>]";
+
+/// Plays games of the engine against itself, writing the games to a PDN
+/// file, and the evaluated positions to a training data file
+pub fn run_selfplay(args: &[String], config: &Config, json: bool) {
+	let mut games = 1;
+	let mut time = None;
+	let mut depth = None;
+	let mut nodes = None;
+	let mut settings = SelfPlaySettings::default();
+	let mut hash_megabytes = config.hash_megabytes();
+	let mut pdn_file = None;
+	let mut data_path = None;
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--games" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					games = n;
+					true
+				}
+				None => false,
+			},
+			"--time" => match args
+				.next()
+				.and_then(|seconds| Duration::try_from_secs_f64(seconds.parse().ok()?).ok())
+			{
+				Some(seconds) => {
+					time = Some(seconds);
+					true
+				}
+				None => false,
+			},
+			"--depth" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					depth = Some(n);
+					true
+				}
+				None => false,
+			},
+			"--nodes" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					nodes = Some(n);
+					true
+				}
+				None => false,
+			},
+			"--opening-moves" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					settings.random_opening_moves = n;
+					true
+				}
+				None => false,
+			},
+			"--skill" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(level) => {
+					settings.skill_level = level;
+					true
+				}
+				None => false,
+			},
+			"--max-plies" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					settings.max_plies = n;
+					true
+				}
+				None => false,
+			},
+			"--hash" => match args.next().and_then(|mb| mb.parse().ok()) {
+				Some(mb) => {
+					hash_megabytes = mb;
+					true
+				}
+				None => false,
+			},
+			"--out" => match args.next() {
+				Some(path) => {
+					let file = OpenOptions::new().create(true).append(true).open(path);
+					match file {
+						Ok(file) => pdn_file = Some(file),
+						Err(error) => {
+							eprintln!("{path}: {error}");
+							return;
+						}
+					}
+					true
+				}
+				None => false,
+			},
+			"--data" => match args.next() {
+				Some(path) => {
+					data_path = Some(path);
+					true
+				}
+				None => false,
+			},
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			return;
+		}
+	}
+
+	if time.is_none() && depth.is_none() && nodes.is_none() {
+		time = config.time;
+		if time.is_none() {
+			depth = NonZeroU8::new(8);
+		}
+	}
+	settings.search_until = SearchLimit::Limited(ActualLimit { nodes, depth, time });
+
+	let mut data_file = None;
+	if let Some(path) = data_path {
+		let file = std::fs::File::create(path).map(std::io::BufWriter::new);
+		match file.and_then(|mut file| selfplay::write_header(&mut file).map(|()| file)) {
+			Ok(file) => data_file = Some(file),
+			Err(error) => {
+				eprintln!("{path}: {error}");
+				std::process::exit(1);
+			}
+		}
+	}
+
+	let engine = Engine::new(
+		hash_megabytes * MEGABYTE,
+		Arc::new(TextFrontend::new(|_| {})),
+	);
+	let event = format!("{ENGINE_NAME} self-play");
+	let mut score = Score::default();
+	let mut samples = 0;
+	for played in 1..=games {
+		let game = selfplay::play_game(&engine, CheckersBitBoard::starting_position(), &settings);
+		match game.result {
+			GameResult::BlackWin => score.wins += 1,
+			GameResult::WhiteWin => score.losses += 1,
+			GameResult::Draw => score.draws += 1,
+			GameResult::Unfinished => (),
+		}
+		samples += game.samples.len();
+
+		if let Some(file) = &mut pdn_file {
+			let pdn = tournament::write_pdn(
+				&event,
+				ENGINE_NAME,
+				ENGINE_NAME,
+				game.start,
+				&game.moves,
+				game.result,
+			);
+			if let Err(error) = writeln!(file, "{pdn}") {
+				tracing::error!(%error, "failed to write the game");
+			}
+		}
+
+		if let Some(file) = &mut data_file {
+			let written = game
+				.samples
+				.iter()
+				.try_for_each(|sample| sample.write(file));
+			if let Err(error) = written.and_then(|()| file.flush()) {
+				eprintln!("failed to write the training data: {error}");
+				std::process::exit(1);
+			}
+		}
+
+		let result = tournament::result_string(game.result);
+		if json {
+			let output = json!({
+				"game": played,
+				"result": result,
+				"moves": game.moves.len(),
+				"samples": game.samples.len(),
+			});
+			println!("{output}");
+		} else {
+			println!(
+				"game {played} of {games}: {result} in {} moves, {} samples",
+				game.moves.len(),
+				game.samples.len()
+			);
+		}
+	}
+
+	if json {
+		let output = json!({
+			"games": games,
+			"black_wins": score.wins,
+			"white_wins": score.losses,
+			"draws": score.draws,
+			"samples": samples,
+		});
+		println!("{output}");
+	} else {
+		println!(
+			"black +{} white +{} ={}, {samples} samples",
+			score.wins, score.losses, score.draws
+		);
+	}
+}
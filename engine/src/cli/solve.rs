@@ -0,0 +1,89 @@
+use std::time::Instant;
+
+use engine::{complete_line, FullMove, ProofResult};
+use model::PieceColor;
+use serde_json::json;
+
+/// The most nodes that are used to prove each position, by default
+pub const PROOF_NODES: usize = 1_000_000;
+
+pub const USAGE: &str =
+	"usage: ampere solve --fen <fen> [--win-in <moves>] [--nodes <proof nodes>]";
+
+/// Proves the result of a problem, and prints the line that proves it
+pub fn run_solve(args: &[String], json: bool) {
+	let mut position = None;
+	let mut win_in = None;
+	let mut max_nodes = PROOF_NODES;
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--fen" => {
+				position = args.next().and_then(|fen| engine::parse_fen(fen));
+				position.is_some()
+			}
+			"--win-in" => {
+				win_in = args.next().and_then(|n| n.parse().ok());
+				win_in.is_some()
+			}
+			"--nodes" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					max_nodes = n;
+					true
+				}
+				None => false,
+			},
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			return;
+		}
+	}
+
+	let Some(position) = position else {
+		eprintln!("{USAGE}");
+		return;
+	};
+
+	let color_name = |color| match color {
+		PieceColor::Dark => "black",
+		PieceColor::Light => "white",
+	};
+	let start = Instant::now();
+	let (winner, line) = if let Some(moves) = win_in {
+		let line = engine::forced_win(position, moves);
+		(line.is_some().then(|| position.turn()), line)
+	} else {
+		match engine::proven_line(position, max_nodes) {
+			Some((ProofResult::Win, line)) => (Some(position.turn()), Some(line)),
+			Some((_, line)) => (Some(position.turn().flip()), Some(line)),
+			None => (None, None),
+		}
+	};
+	let time = start.elapsed();
+
+	let line = line.map_or_else(Vec::new, |line| complete_line(position, &line));
+	if json {
+		let output = json!({
+			"proven": winner.is_some(),
+			"winner": winner.map(color_name),
+			"line": line.iter().map(FullMove::long_notation).collect::<Vec<_>>(),
+			"seconds": time.as_secs_f64(),
+		});
+		println!("{output}");
+		return;
+	}
+
+	match (winner, win_in) {
+		(Some(winner), _) => println!("proven win for {}", color_name(winner)),
+		(None, Some(moves)) => println!("no win in {moves} moves"),
+		(None, None) => println!("not proven within {max_nodes} nodes"),
+	}
+	if winner.is_some() {
+		let line: Vec<String> = line.iter().map(FullMove::to_string).collect();
+		println!("line: {}", line.join(" "));
+	}
+	println!("time: {:.3}s", time.as_secs_f64());
+}
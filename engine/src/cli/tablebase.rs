@@ -0,0 +1,256 @@
+use std::path::Path;
+use std::time::Instant;
+
+use engine::retrograde::{Generator, Material};
+use engine::tablebase::{Outcome, Tablebase};
+use engine::{Config, ENGINE_AUTHOR, ENGINE_NAME};
+use serde_json::json;
+
+use crate::cli::ProgressBar;
+
+/// The default memory limit for generating tablebases, in megabytes
+const TABLEBASE_MEMORY: usize = 1024;
+
+/// The number of wins, losses and draws for the side to move, and the
+/// longest win or loss
+fn tablebase_summary(outcomes: impl Iterator<Item = Outcome>) -> (usize, usize, usize, u16) {
+	let (mut wins, mut losses, mut draws, mut longest) = (0, 0, 0, 0);
+	for outcome in outcomes {
+		match outcome {
+			Outcome::Win(moves) => {
+				wins += 1;
+				longest = longest.max(moves);
+			}
+			Outcome::Loss(moves) => {
+				losses += 1;
+				longest = longest.max(moves);
+			}
+			Outcome::Draw => draws += 1,
+		}
+	}
+
+	(wins, losses, draws, longest)
+}
+
+pub const USAGE: &str = "usage: ampere tablebase gen --pieces <n> [--out <dir>] [--memory <mb>]
+       ampere tablebase stats [<dir>]";
+
+/// Generates endgame tablebases, or summarizes a directory of them.
+/// `tablebase gen` writes one file for each material, and the files which
+/// already exist are loaded instead of being generated again, so an
+/// interrupted generation can be continued.
+pub fn run_tablebase(args: &[String], config: &Config, json: bool) {
+	// the configured directory is used if there isn't one given
+	let configured_directory = config.tablebases.as_deref();
+	match (args.first().map(String::as_str), args.get(1)) {
+		(Some("stats"), directory) => {
+			match directory.map(Path::new).or(configured_directory) {
+				Some(directory) => tablebase_stats(directory, json),
+				None => {
+					eprintln!("{USAGE}");
+					std::process::exit(1);
+				}
+			}
+			return;
+		}
+		(Some("gen"), _) => {}
+		_ => {
+			eprintln!("{USAGE}");
+			std::process::exit(1);
+		}
+	}
+
+	let mut pieces = None;
+	let mut directory = configured_directory;
+	let mut memory = TABLEBASE_MEMORY;
+	let mut args = args[1..].iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--pieces" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) if n >= 2 => {
+					pieces = Some(n);
+					true
+				}
+				_ => false,
+			},
+			"--out" => match args.next() {
+				Some(path) => {
+					directory = Some(Path::new(path));
+					true
+				}
+				None => false,
+			},
+			"--memory" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					memory = n;
+					true
+				}
+				None => false,
+			},
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			std::process::exit(1);
+		}
+	}
+
+	let (Some(pieces), Some(directory)) = (pieces, directory) else {
+		eprintln!("{USAGE}");
+		std::process::exit(1);
+	};
+
+	let materials = Material::up_to(pieces);
+	let mut generator = Generator::new();
+	if let Err(error) = generator.check_memory(&materials, memory) {
+		eprintln!("{error}");
+		std::process::exit(1);
+	}
+	if let Err(error) = std::fs::create_dir_all(directory) {
+		eprintln!("{}: {error}", directory.display());
+		std::process::exit(1);
+	}
+
+	let publication_time = std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.map_or(0, |time| time.as_secs());
+	for (number, &material) in materials.iter().enumerate() {
+		let path = directory.join(material.file_name());
+		if path.exists() {
+			let loaded = Tablebase::load(&path)
+				.map_err(|error| error.to_string())
+				.and_then(|tablebase| {
+					generator
+						.insert(material, &tablebase)
+						.map_err(|error| error.to_string())
+				});
+			if let Err(error) = loaded {
+				eprintln!("{}: {error}", path.display());
+				std::process::exit(1);
+			}
+			if !json {
+				println!("{material}: already generated");
+			}
+			continue;
+		}
+
+		let started = Instant::now();
+		let mut progress_bar = ProgressBar::new(0, json);
+		let outcomes = generator.generate(material, |progress| {
+			progress_bar.total = progress.positions;
+			let label = format!(
+				"{material} ({}/{}), {} moves",
+				number + 1,
+				materials.len(),
+				progress.pass
+			);
+			progress_bar.update(&label, progress.resolved);
+		});
+		progress_bar.clear();
+
+		let (wins, losses, draws, longest) =
+			tablebase_summary(outcomes.iter().map(|(_, outcome)| *outcome));
+		let tablebase = Tablebase::new(
+			&format!("{ENGINE_NAME} {material}"),
+			ENGINE_AUTHOR,
+			publication_time,
+			outcomes,
+		);
+		if let Err(error) = tablebase.save(&path) {
+			eprintln!("{}: {error}", path.display());
+			std::process::exit(1);
+		}
+
+		let seconds = started.elapsed().as_secs_f64();
+		if json {
+			let output = json!({
+				"material": material.to_string(),
+				"file": path.display().to_string(),
+				"positions": tablebase.len(),
+				"wins": wins,
+				"losses": losses,
+				"draws": draws,
+				"longest": longest,
+				"seconds": seconds,
+			});
+			println!("{output}");
+		} else {
+			println!(
+				"{material}: {} positions, {wins} wins, {losses} losses, {draws} draws, longest {longest} moves ({seconds:.1}s)",
+				tablebase.len()
+			);
+		}
+	}
+}
+
+/// Summarizes every tablebase file in a directory
+fn tablebase_stats(directory: &Path, json: bool) {
+	let entries = match std::fs::read_dir(directory) {
+		Ok(entries) => entries,
+		Err(error) => {
+			eprintln!("{}: {error}", directory.display());
+			return;
+		}
+	};
+	let mut paths: Vec<_> = entries
+		.filter_map(|entry| Some(entry.ok()?.path()))
+		.filter(|path| path.extension().is_some_and(|extension| extension == "atb"))
+		.collect();
+	paths.sort();
+
+	let mut totals = (0, 0, 0, 0);
+	if !json {
+		println!(
+			"{:<20} {:>10} {:>10} {:>10} {:>10} {:>8}",
+			"name", "positions", "wins", "losses", "draws", "longest"
+		);
+	}
+	for path in &paths {
+		let tablebase = match Tablebase::load(path) {
+			Ok(tablebase) => tablebase,
+			Err(error) => {
+				tracing::warn!(path = %path.display(), %error, "skipped a tablebase");
+				continue;
+			}
+		};
+
+		let (wins, losses, draws, longest) =
+			tablebase_summary(tablebase.outcomes().map(|(_, outcome)| outcome));
+		totals = (
+			totals.0 + wins,
+			totals.1 + losses,
+			totals.2 + draws,
+			totals.3.max(longest),
+		);
+		if json {
+			let output = json!({
+				"file": path.display().to_string(),
+				"name": tablebase.name(),
+				"author": tablebase.author(),
+				"published": tablebase.publication_time(),
+				"positions": tablebase.len(),
+				"wins": wins,
+				"losses": losses,
+				"draws": draws,
+				"longest": longest,
+			});
+			println!("{output}");
+		} else {
+			println!(
+				"{:<20} {:>10} {wins:>10} {losses:>10} {draws:>10} {longest:>8}",
+				tablebase.name(),
+				tablebase.len()
+			);
+		}
+	}
+
+	let (wins, losses, draws, longest) = totals;
+	if !json {
+		println!(
+			"{:<20} {:>10} {wins:>10} {losses:>10} {draws:>10} {longest:>8}",
+			format!("{} files", paths.len()),
+			wins + losses + draws
+		);
+	}
+}
@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+use engine::test_suite;
+use engine::{Config, FullMove};
+use serde_json::json;
+
+pub const USAGE: &str = "usage: ampere testsuite <file> [--time <seconds>] [--hash <megabytes>]";
+
+/// Searches every position in a test suite for a fixed time, and reports
+/// which ones the engine solved
+pub fn run_testsuite(args: &[String], config: &Config, json: bool) {
+	let mut path = None;
+	let mut time = config.time();
+	let mut hash_megabytes = config.hash_megabytes();
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--time" => match args
+				.next()
+				.and_then(|seconds| Duration::try_from_secs_f64(seconds.parse().ok()?).ok())
+			{
+				Some(seconds) => {
+					time = seconds;
+					true
+				}
+				None => false,
+			},
+			"--hash" => match args.next().and_then(|mb| mb.parse().ok()) {
+				Some(mb) => {
+					hash_megabytes = mb;
+					true
+				}
+				None => false,
+			},
+			_ if path.is_none() && !arg.starts_with("--") => {
+				path = Some(arg);
+				true
+			}
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			return;
+		}
+	}
+
+	let Some(path) = path else {
+		eprintln!("{USAGE}");
+		return;
+	};
+	let tests = match std::fs::read_to_string(path) {
+		Ok(text) => match test_suite::parse(&text) {
+			Ok(tests) => tests,
+			Err(error) => {
+				eprintln!("{error}");
+				std::process::exit(1);
+			}
+		},
+		Err(error) => {
+			eprintln!("{path}: {error}");
+			std::process::exit(1);
+		}
+	};
+
+	let mut solved = 0;
+	let mut total_time = Duration::ZERO;
+	test_suite::run(&tests, time, hash_megabytes, |test, result| {
+		let found = result.found.as_ref().map(FullMove::long_notation);
+		let expected: Vec<String> = test
+			.best_moves
+			.iter()
+			.map(FullMove::long_notation)
+			.collect();
+		if let Some(time) = result.time_to_solution {
+			solved += 1;
+			total_time += time;
+		}
+
+		if json {
+			let output = json!({
+				"id": test.id,
+				"found": found,
+				"expected": expected,
+				"solved": result.solved,
+				"time_to_solution": result.time_to_solution.map(|time| time.as_secs_f64()),
+				"nodes": result.nodes,
+			});
+			println!("{output}");
+		} else if let Some(time) = result.time_to_solution {
+			println!("{}: solved in {:.3}s", test.id, time.as_secs_f64());
+		} else {
+			println!(
+				"{}: FAILED, found {}, expected {}",
+				test.id,
+				found.as_deref().unwrap_or("nothing"),
+				expected.join(" or ")
+			);
+		}
+	});
+
+	if json {
+		let output = json!({
+			"solved": solved,
+			"total": tests.len(),
+			"time_to_solution": total_time.as_secs_f64(),
+		});
+		println!("{output}");
+	} else {
+		println!(
+			"solved {solved} of {}, in {:.3}s total",
+			tests.len(),
+			total_time.as_secs_f64()
+		);
+	}
+}
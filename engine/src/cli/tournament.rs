@@ -0,0 +1,317 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+
+use engine::tournament::{
+	self, Checkpoint, GameRecord, Hypothesis, Participant, Score, SprtSettings, Standings,
+	TournamentError, TournamentSettings,
+};
+use pdn::GameResult;
+use serde_json::{json, Value};
+
+/// A score as JSON, with its Elo estimate. Infinite estimates are `null`.
+pub fn score_json(score: Score) -> Value {
+	let estimate = score.elo();
+	json!({
+		"wins": score.wins,
+		"draws": score.draws,
+		"losses": score.losses,
+		"points": score.points(),
+		"elo": estimate.map(|estimate| estimate.elo).filter(|elo| elo.is_finite()),
+		"error": estimate.map(|estimate| estimate.error).filter(|error| error.is_finite()),
+	})
+}
+
+/// Each player's score against the field, from first place to last
+pub fn standings_json(standings: &Standings) -> Vec<Value> {
+	standings
+		.ranking()
+		.into_iter()
+		.map(|player| {
+			let mut output = score_json(standings.total(player));
+			output["name"] = json!(standings.names[player]);
+			output
+		})
+		.collect()
+}
+
+/// Writes the checkpoint to a temporary file first, so that the last
+/// checkpoint survives if the program is killed while it's being saved
+fn save_checkpoint(path: &str, checkpoint: &Checkpoint) -> std::io::Result<()> {
+	let temporary = format!("{path}.tmp");
+	std::fs::write(&temporary, checkpoint.to_json())?;
+	std::fs::rename(temporary, path)
+}
+
+pub const USAGE: &str = "usage: ampere tournament --engine <options> --engine <options>... [--rounds <n>] [--openings <file>] [--opening-moves <n>] [--max-moves <n>] [--eleven-man] [--seed <n>] [--pdn <file>] [--state <file> [--resume]] [--sprt [--elo0 <elo>] [--elo1 <elo>] [--alpha <p>] [--beta <p>]]\n       ampere gauntlet --candidate <options> --engine <options>... [--rounds <n>] [--openings <file>] [--opening-moves <n>] [--max-moves <n>] [--eleven-man] [--seed <n>] [--pdn <file>] [--state <file> [--resume]] [--sprt [--elo0 <elo>] [--elo1 <elo>] [--alpha <p>] [--beta <p>]]";
+
+/// Plays a round-robin between engine configurations, and prints the
+/// crosstable. Each `--engine` is a list of options, such as
+/// `name=weak,time=0.1,Skill Level=5`. External engines are given a `cmd`,
+/// and a `protocol` of hub, json or dxp. With `--sprt`, two engines play
+/// until the test accepts a hypothesis, and `--rounds` is the limit on the
+/// length of the match. With `--eleven-man`, random openings are eleven-man
+/// ballots. `--pdn` appends each game to a PDN file, which can be
+/// read by `ampere rate`. `--state` saves the progress after every game, and
+/// with `--resume`, the same command continues from the saved state. In a
+/// gauntlet, the `--candidate` only
+/// plays the other engines, which don't play each other.
+pub fn run_tournament(args: &[String], json: bool, gauntlet: bool) {
+	let mut candidate = None;
+	let mut participants = Vec::new();
+	let mut settings = TournamentSettings::default();
+	let mut rounds = None;
+	let mut use_sprt = false;
+	let mut pdn_file = None;
+	let mut state_path = None;
+	let mut resume = false;
+	let mut sprt = SprtSettings::default();
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--engine" | "--candidate" => match args.next().map(|spec| Participant::parse(spec)) {
+				Some(Ok(participant)) if arg == "--engine" => {
+					participants.push(participant);
+					true
+				}
+				Some(Ok(participant)) => {
+					candidate = Some(participant);
+					gauntlet
+				}
+				Some(Err(error)) => {
+					eprintln!("{error}");
+					return;
+				}
+				None => false,
+			},
+			"--openings" => match args.next() {
+				Some(path) => {
+					let openings = std::fs::read_to_string(path)
+						.map_err(|error| error.to_string())
+						.and_then(|text| {
+							tournament::parse_openings(&text).map_err(|error| error.to_string())
+						});
+					match openings {
+						Ok(openings) => settings.openings = openings,
+						Err(error) => {
+							eprintln!("{path}: {error}");
+							return;
+						}
+					}
+					true
+				}
+				None => false,
+			},
+			"--rounds" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					rounds = Some(n);
+					true
+				}
+				None => false,
+			},
+			"--sprt" => {
+				use_sprt = true;
+				true
+			}
+			"--pdn" => match args.next() {
+				Some(path) => {
+					let file = OpenOptions::new().create(true).append(true).open(path);
+					match file {
+						Ok(file) => pdn_file = Some(file),
+						Err(error) => {
+							eprintln!("{path}: {error}");
+							return;
+						}
+					}
+					true
+				}
+				None => false,
+			},
+			"--state" => match args.next() {
+				Some(path) => {
+					state_path = Some(path);
+					true
+				}
+				None => false,
+			},
+			"--resume" => {
+				resume = true;
+				true
+			}
+			"--eleven-man" => {
+				settings.eleven_man = true;
+				true
+			}
+			"--seed" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					settings.seed = n;
+					true
+				}
+				None => false,
+			},
+			"--elo0" | "--elo1" | "--alpha" | "--beta" => {
+				match args.next().and_then(|value| value.parse().ok()) {
+					Some(value) => {
+						*match arg.as_str() {
+							"--elo0" => &mut sprt.elo0,
+							"--elo1" => &mut sprt.elo1,
+							"--alpha" => &mut sprt.alpha,
+							_ => &mut sprt.beta,
+						} = value;
+						true
+					}
+					None => false,
+				}
+			}
+			"--opening-moves" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					settings.random_opening_moves = n;
+					true
+				}
+				None => false,
+			},
+			"--max-moves" => match args.next().and_then(|n| n.parse().ok()) {
+				Some(n) => {
+					settings.max_moves = n;
+					true
+				}
+				None => false,
+			},
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			return;
+		}
+	}
+
+	if (gauntlet && candidate.is_none()) || (resume && state_path.is_none()) {
+		eprintln!("{USAGE}");
+		return;
+	}
+
+	// in a gauntlet, the candidate is the first player
+	let participants: Vec<Participant> = candidate.into_iter().chain(participants).collect();
+	if use_sprt && participants.len() != 2 {
+		eprintln!("an SPRT needs exactly two engines");
+		return;
+	}
+
+	// an SPRT keeps going until it's finished, unless it's given a limit
+	settings.rounds = rounds.unwrap_or(if use_sprt { usize::MAX } else { 1 });
+	let names = participants.iter().map(|p| p.name.clone()).collect();
+	let mut checkpoint = Checkpoint::new(settings.seed, names);
+	if let Some(path) = state_path.filter(|_| resume) {
+		let saved = std::fs::read_to_string(path)
+			.map_err(|error| error.to_string())
+			.and_then(|text| Checkpoint::parse(&text).map_err(|error| error.to_string()));
+		match saved {
+			Ok(saved) if saved.names == checkpoint.names => checkpoint = saved,
+			Ok(_) => {
+				eprintln!("{path}: {}", TournamentError::CheckpointMismatch);
+				return;
+			}
+			Err(error) => {
+				eprintln!("{path}: {error}");
+				return;
+			}
+		}
+		settings.seed = checkpoint.seed;
+		settings.completed.clone_from(&checkpoint.games);
+	}
+
+	let mut played = checkpoint.games.len();
+	let on_game = |game: &GameRecord, standings: &Standings| {
+		played += 1;
+		if let Some(path) = state_path {
+			checkpoint.record(game);
+			if let Err(error) = save_checkpoint(path, &checkpoint) {
+				tracing::error!(%error, "failed to save the checkpoint");
+			}
+		}
+
+		let (dark, light) = (
+			&participants[game.dark].name,
+			&participants[game.light].name,
+		);
+		let winner = match game.result {
+			GameResult::BlackWin => Some(dark),
+			GameResult::WhiteWin => Some(light),
+			GameResult::Draw | GameResult::Unfinished => None,
+		};
+
+		let llr = use_sprt.then(|| sprt.llr(&standings.total(0)));
+		if let Some(file) = &mut pdn_file {
+			if let Err(error) = writeln!(file, "{}", game.to_pdn(dark, light)) {
+				tracing::error!(%error, "failed to write the game");
+			}
+		}
+
+		if json {
+			let output = json!({
+				"game": played,
+				"black": dark,
+				"white": light,
+				"winner": winner,
+				"moves": game.moves.len(),
+				"llr": llr,
+			});
+			println!("{output}");
+		} else {
+			let outcome =
+				winner.map_or_else(|| "draw".to_string(), |winner| format!("{winner} wins"));
+			match llr {
+				Some(llr) => println!("game {played}: {dark} vs {light}, {outcome}, llr {llr:.2}"),
+				None => println!("game {played}: {dark} vs {light}, {outcome}"),
+			}
+		}
+	};
+	let result = match participants.as_slice() {
+		[candidate, baseline] if use_sprt => {
+			tournament::sprt(candidate, baseline, &settings, &sprt, on_game)
+		}
+		[candidate, baselines @ ..] if gauntlet => {
+			tournament::gauntlet(candidate, baselines, &settings, on_game)
+				.map(|standings| (standings, None))
+		}
+		_ => tournament::round_robin(&participants, &settings, on_game)
+			.map(|standings| (standings, None)),
+	};
+
+	let (standings, hypothesis) = match result {
+		Ok(standings) => standings,
+		Err(error) => {
+			eprintln!("{error}");
+			std::process::exit(1);
+		}
+	};
+
+	if json {
+		let mut output = json!({ "standings": standings_json(&standings) });
+		if use_sprt {
+			output["sprt"] = json!({
+				"llr": sprt.llr(&standings.total(0)),
+				"accepted": hypothesis.map(|hypothesis| format!("{hypothesis:?}")),
+			});
+		}
+		println!("{output}");
+	} else {
+		println!();
+		println!("{standings}");
+		if use_sprt {
+			let (candidate, baseline) = (&standings.names[0], &standings.names[1]);
+			let llr = sprt.llr(&standings.total(0));
+			match hypothesis {
+				Some(Hypothesis::H0) => println!(
+					"H0 accepted (llr {llr:.2}): {candidate} is at most {} elo stronger than {baseline}",
+					sprt.elo0
+				),
+				Some(Hypothesis::H1) => println!(
+					"H1 accepted (llr {llr:.2}): {candidate} is at least {} elo stronger than {baseline}",
+					sprt.elo1
+				),
+				None => println!("neither hypothesis was accepted (llr {llr:.2})"),
+			}
+		}
+	}
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use engine::{Config, MEGABYTE};
+use model::CheckersBitBoard;
+
+use crate::cli::analyze::search_settings;
+
+pub const USAGE: &str = "usage: ampere tui [--fen <fen>] [--time <seconds>] [--depth <n>] \
+	[--hash <megabytes>]";
+
+/// Plays a game in a full-screen view of the board. The engine plays a move
+/// with the given limits when it's asked to.
+pub fn run_tui(args: &[String], config: &Config) {
+	let mut position = Some(CheckersBitBoard::starting_position());
+	let (mut depth, mut time) = (None, Some(config.time()));
+	let mut hash_megabytes = config.hash_megabytes();
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let valid = match arg.as_str() {
+			"--fen" => {
+				position = args.next().and_then(|fen| engine::parse_fen(fen));
+				position.is_some()
+			}
+			"--depth" => {
+				depth = args.next().and_then(|n| n.parse().ok());
+				depth.is_some()
+			}
+			"--time" => {
+				time = args
+					.next()
+					.and_then(|seconds| seconds.parse().ok())
+					.and_then(|seconds| Duration::try_from_secs_f64(seconds).ok());
+				time.is_some()
+			}
+			"--hash" => match args.next().and_then(|mb| mb.parse().ok()) {
+				Some(mb) => {
+					hash_megabytes = mb;
+					true
+				}
+				None => false,
+			},
+			_ => false,
+		};
+
+		if !valid {
+			eprintln!("{USAGE}");
+			return;
+		}
+	}
+
+	let Some(position) = position else {
+		eprintln!("{USAGE}");
+		return;
+	};
+
+	let settings = search_settings(depth, None, time);
+	if let Err(error) = engine::tui::run(position, hash_megabytes * MEGABYTE, settings) {
+		eprintln!("{error}");
+		std::process::exit(1);
+	}
+}
@@ -0,0 +1,402 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// A command of the command line, for completing its arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandSpec<'a> {
+	pub name: &'a str,
+	/// The words that may come right after the command, such as `find`
+	pub subcommands: Vec<&'a str>,
+	/// The flags of the command, including the dashes
+	pub flags: Vec<&'a str>,
+}
+
+/// Whether a word of a usage message is a subcommand, rather than a
+/// placeholder such as `<fen>`
+fn is_subcommand(word: &str) -> bool {
+	!word.is_empty() && word.chars().all(|c| c.is_ascii_lowercase())
+}
+
+/// The flags in a usage message, such as `--fen` in `[--fen <fen>]`, in the
+/// order they're written
+pub fn usage_flags(usage: &str) -> Vec<&str> {
+	let mut flags = Vec::new();
+	for word in usage.split_whitespace() {
+		let word = word.trim_matches(|c| matches!(c, '[' | ']' | '<' | '>' | '|' | '.'));
+		let is_flag = word.len() > 1
+			&& word.starts_with('-')
+			&& word[1..].starts_with(|c: char| c == '-' || c.is_ascii_alphabetic());
+		if is_flag {
+			extend_unique(&mut flags, [word]);
+		}
+	}
+
+	flags
+}
+
+/// Adds the words which aren't in the list yet
+fn extend_unique<'a>(list: &mut Vec<&'a str>, words: impl IntoIterator<Item = &'a str>) {
+	for word in words {
+		if !list.contains(&word) {
+			list.push(word);
+		}
+	}
+}
+
+/// The subcommands at the start of a command's arguments. This is either a
+/// single word, or a choice of words such as `<bash | zsh>`.
+fn usage_subcommands(arguments: &str) -> Vec<&str> {
+	let Some(choices) = arguments.strip_prefix('<') else {
+		let word = arguments.split_whitespace().next();
+		return word
+			.filter(|word| is_subcommand(word))
+			.into_iter()
+			.collect();
+	};
+
+	let choices: Vec<&str> = choices
+		.split('>')
+		.next()
+		.unwrap_or_default()
+		.split('|')
+		.map(str::trim)
+		.collect();
+	if choices.len() > 1 && choices.iter().all(|choice| is_subcommand(choice)) {
+		choices
+	} else {
+		Vec::new()
+	}
+}
+
+/// Reads the commands of `program` from their usage messages, such as
+/// `usage: ampere book probe [--fen <fen>]`. The word after a command, or a
+/// choice of words such as `<bash | zsh>`, is a subcommand. A line that
+/// doesn't start with the program continues the line before it.
+pub fn commands_from_usage<'a>(program: &str, usages: &[&'a str]) -> Vec<CommandSpec<'a>> {
+	let mut commands: Vec<CommandSpec<'a>> = Vec::new();
+	for usage in usages {
+		let mut current: Option<usize> = None;
+		for line in usage.lines() {
+			let line = line.trim_start();
+			let line = line.strip_prefix("usage:").unwrap_or(line).trim_start();
+			let Some(rest) = line
+				.strip_prefix(program)
+				.filter(|rest| rest.starts_with(char::is_whitespace))
+			else {
+				if let Some(index) = current {
+					extend_unique(&mut commands[index].flags, usage_flags(line));
+				}
+				continue;
+			};
+
+			let rest = rest.trim_start();
+			let (name, arguments) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+			let arguments = arguments.trim_start();
+			let index = match commands.iter().position(|command| command.name == name) {
+				Some(index) => index,
+				None => {
+					commands.push(CommandSpec {
+						name,
+						subcommands: Vec::new(),
+						flags: Vec::new(),
+					});
+					commands.len() - 1
+				}
+			};
+			current = Some(index);
+
+			let command = &mut commands[index];
+			extend_unique(&mut command.subcommands, usage_subcommands(arguments));
+			extend_unique(&mut command.flags, usage_flags(arguments));
+		}
+	}
+
+	commands
+}
+
+/// A shell that completions can be generated for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Shell {
+	Bash,
+	Zsh,
+	Fish,
+	PowerShell,
+}
+
+impl Shell {
+	pub const ALL: [Self; 4] = [Self::Bash, Self::Zsh, Self::Fish, Self::PowerShell];
+
+	pub const fn name(self) -> &'static str {
+		match self {
+			Self::Bash => "bash",
+			Self::Zsh => "zsh",
+			Self::Fish => "fish",
+			Self::PowerShell => "powershell",
+		}
+	}
+}
+
+impl FromStr for Shell {
+	type Err = ();
+
+	fn from_str(name: &str) -> Result<Self, Self::Err> {
+		Self::ALL
+			.into_iter()
+			.find(|shell| shell.name().eq_ignore_ascii_case(name))
+			.ok_or(())
+	}
+}
+
+/// Writes a script which completes the commands of `program` in `shell`.
+/// The global flags may be given with any command.
+pub fn generate(
+	shell: Shell,
+	program: &str,
+	commands: &[CommandSpec],
+	global_flags: &[&str],
+) -> String {
+	match shell {
+		Shell::Bash => bash(program, commands, global_flags),
+		Shell::Zsh => zsh(program, commands, global_flags),
+		Shell::Fish => fish(program, commands, global_flags),
+		Shell::PowerShell => powershell(program, commands, global_flags),
+	}
+}
+
+fn command_names(commands: &[CommandSpec]) -> String {
+	let names: Vec<&str> = commands.iter().map(|command| command.name).collect();
+	names.join(" ")
+}
+
+/// The function name used for a program, which can't contain dashes
+fn function_name(program: &str) -> String {
+	format!("_{}", program.replace(['-', '.'], "_"))
+}
+
+fn bash(program: &str, commands: &[CommandSpec], global_flags: &[&str]) -> String {
+	let function = function_name(program);
+	let mut script = format!(
+		"{function}() {{\n\
+		 \tlocal cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n\
+		 \tif [ \"$COMP_CWORD\" -eq 1 ]; then\n\
+		 \t\tCOMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n\
+		 \t\treturn\n\
+		 \tfi\n\n\
+		 \tlocal subcommands=\"\"\n\
+		 \tlocal flags=\"{}\"\n\
+		 \tcase \"${{COMP_WORDS[1]}}\" in\n",
+		command_names(commands),
+		global_flags.join(" ")
+	);
+	for command in commands {
+		let _ = writeln!(
+			script,
+			"\t\t{}) subcommands=\"{}\"; flags=\"$flags {}\" ;;",
+			command.name,
+			command.subcommands.join(" "),
+			command.flags.join(" ")
+		);
+	}
+	script.push_str(
+		"\tesac\n\n\
+		 \tif [[ \"$cur\" == -* ]]; then\n\
+		 \t\tCOMPREPLY=($(compgen -W \"$flags\" -- \"$cur\"))\n\
+		 \telif [ \"$COMP_CWORD\" -eq 2 ] && [ -n \"$subcommands\" ]; then\n\
+		 \t\tCOMPREPLY=($(compgen -W \"$subcommands\" -- \"$cur\"))\n\
+		 \telse\n\
+		 \t\tCOMPREPLY=($(compgen -f -- \"$cur\"))\n\
+		 \tfi\n\
+		 }\n\n",
+	);
+	let _ = writeln!(script, "complete -o filenames -F {function} {program}");
+	script
+}
+
+fn zsh(program: &str, commands: &[CommandSpec], global_flags: &[&str]) -> String {
+	let function = function_name(program);
+	let mut script = format!(
+		"#compdef {program}\n\n\
+		 {function}() {{\n\
+		 \tif (( CURRENT == 2 )); then\n\
+		 \t\tcompadd -- {}\n\
+		 \t\treturn\n\
+		 \tfi\n\n\
+		 \tlocal -a subcommands flags\n\
+		 \tflags=({})\n\
+		 \tcase $words[2] in\n",
+		command_names(commands),
+		global_flags.join(" ")
+	);
+	for command in commands {
+		let _ = writeln!(
+			script,
+			"\t\t{}) subcommands=({}); flags+=({}) ;;",
+			command.name,
+			command.subcommands.join(" "),
+			command.flags.join(" ")
+		);
+	}
+	let _ = write!(
+		script,
+		"\tesac\n\n\
+		 \tif [[ $PREFIX == -* ]]; then\n\
+		 \t\tcompadd -- $flags\n\
+		 \telif (( CURRENT == 3 && $#subcommands )); then\n\
+		 \t\tcompadd -- $subcommands\n\
+		 \telse\n\
+		 \t\t_files\n\
+		 \tfi\n\
+		 }}\n\n\
+		 compdef {function} {program}\n"
+	);
+	script
+}
+
+/// Writes a fish completion for a flag, which is `-l` for long flags, and
+/// `-s` for short ones
+fn fish_flag(flag: &str) -> String {
+	match flag.strip_prefix("--") {
+		Some(long) => format!("-l {long}"),
+		None => format!("-s {}", flag.trim_start_matches('-')),
+	}
+}
+
+fn fish(program: &str, commands: &[CommandSpec], global_flags: &[&str]) -> String {
+	let mut script = format!(
+		"complete -c {program} -n \"__fish_use_subcommand\" -f -a \"{}\"\n",
+		command_names(commands)
+	);
+	for flag in global_flags {
+		let _ = writeln!(script, "complete -c {program} {}", fish_flag(flag));
+	}
+
+	for command in commands {
+		let condition = format!("__fish_seen_subcommand_from {}", command.name);
+		if !command.subcommands.is_empty() {
+			let _ = writeln!(
+				script,
+				"complete -c {program} -n \"{condition}\" -a \"{}\"",
+				command.subcommands.join(" ")
+			);
+		}
+		for flag in &command.flags {
+			let _ = writeln!(
+				script,
+				"complete -c {program} -n \"{condition}\" {}",
+				fish_flag(flag)
+			);
+		}
+	}
+
+	script
+}
+
+/// Quotes a list of words as a PowerShell array
+fn powershell_array(words: &[&str]) -> String {
+	let quoted: Vec<String> = words.iter().map(|word| format!("'{word}'")).collect();
+	format!("@({})", quoted.join(", "))
+}
+
+fn powershell(program: &str, commands: &[CommandSpec], global_flags: &[&str]) -> String {
+	let names: Vec<&str> = commands.iter().map(|command| command.name).collect();
+	let mut script = format!(
+		"Register-ArgumentCompleter -Native -CommandName '{program}' -ScriptBlock {{\n\
+		 \tparam($wordToComplete, $commandAst, $cursorPosition)\n\n\
+		 \t$commands = {}\n\
+		 \t$globalFlags = {}\n\
+		 \t$subcommands = @{{\n",
+		powershell_array(&names),
+		powershell_array(global_flags)
+	);
+	for command in commands
+		.iter()
+		.filter(|command| !command.subcommands.is_empty())
+	{
+		let _ = writeln!(
+			script,
+			"\t\t'{}' = {}",
+			command.name,
+			powershell_array(&command.subcommands)
+		);
+	}
+	script.push_str("\t}\n\t$flags = @{\n");
+	for command in commands {
+		let _ = writeln!(
+			script,
+			"\t\t'{}' = {}",
+			command.name,
+			powershell_array(&command.flags)
+		);
+	}
+	script.push_str(
+		"\t}\n\n\
+		 \t$words = @($commandAst.CommandElements | ForEach-Object { $_.ToString() })\n\
+		 \tif ($wordToComplete) { $position = $words.Count - 1 } else { $position = $words.Count }\n\
+		 \tif ($position -le 1) {\n\
+		 \t\t$candidates = $commands\n\
+		 \t} elseif ($wordToComplete -like '-*') {\n\
+		 \t\t$candidates = $globalFlags + $flags[$words[1]]\n\
+		 \t} elseif ($position -eq 2 -and $subcommands.ContainsKey($words[1])) {\n\
+		 \t\t$candidates = $subcommands[$words[1]]\n\
+		 \t} else {\n\
+		 \t\treturn\n\
+		 \t}\n\n\
+		 \t$candidates | Where-Object { $_ -like \"$wordToComplete*\" } | ForEach-Object {\n\
+		 \t\t[System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)\n\
+		 \t}\n\
+		 }\n",
+	);
+	script
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const USAGES: &[&str] = &[
+		"usage: ampere perft <depth> [--fen <fen>] [--divide]
+       ampere perft --check [max depth]",
+		"usage: ampere book probe [<book>] [--moves <moves>]
+       ampere book build <book> <pdn file>... [--plies <n>]",
+		"usage: ampere completions <bash | zsh | fish | powershell>",
+		"usage: ampere analyze <fen | pdn file> [--depth <n>]
+                       [--report <html file>]",
+	];
+
+	#[test]
+	fn reads_usage_messages() {
+		let commands = commands_from_usage("ampere", USAGES);
+		let names: Vec<&str> = commands.iter().map(|command| command.name).collect();
+		assert_eq!(names, ["perft", "book", "completions", "analyze"]);
+
+		assert!(commands[0].subcommands.is_empty());
+		assert_eq!(commands[0].flags, ["--fen", "--divide", "--check"]);
+		assert_eq!(commands[1].subcommands, ["probe", "build"]);
+		assert_eq!(commands[1].flags, ["--moves", "--plies"]);
+		assert_eq!(
+			commands[2].subcommands,
+			["bash", "zsh", "fish", "powershell"]
+		);
+		assert!(commands[3].subcommands.is_empty());
+		assert_eq!(commands[3].flags, ["--depth", "--report"]);
+
+		let global = usage_flags("usage: ampere [-v | -q] [--log <filter>] <command>");
+		assert_eq!(global, ["-v", "-q", "--log"]);
+	}
+
+	#[test]
+	fn every_shell_completes_every_command() {
+		let commands = commands_from_usage("ampere", USAGES);
+		for shell in Shell::ALL {
+			assert_eq!(shell.name().parse(), Ok(shell));
+			let script = generate(shell, "ampere", &commands, &["--json", "-v"]);
+			for word in ["perft", "book", "probe", "divide", "plies", "json"] {
+				assert!(script.contains(word), "{} is missing {word}", shell.name());
+			}
+		}
+
+		let fish = generate(Shell::Fish, "ampere", &commands, &["-v"]);
+		assert!(fish.contains("complete -c ampere -s v\n"));
+		assert!(fish.contains("-n \"__fish_seen_subcommand_from perft\" -l divide\n"));
+	}
+}
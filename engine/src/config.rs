@@ -0,0 +1,205 @@
+use std::io;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use thiserror::Error;
+
+/// The size of the transposition table, if it isn't configured
+const DEFAULT_HASH_MEGABYTES: usize = 16;
+
+/// The time to search each position, if it isn't configured
+const DEFAULT_TIME: Duration = Duration::from_secs(1);
+
+/// The defaults for the command line, which are read from a config file.
+/// Anything given on the command line overrides these.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Config {
+	/// The size of the transposition table, in megabytes
+	pub hash_megabytes: Option<usize>,
+	pub threads: Option<NonZeroUsize>,
+	/// The opening book that is used when no book is given
+	pub book: Option<PathBuf>,
+	/// The directory of the tablebase files
+	pub tablebases: Option<PathBuf>,
+	/// The game database that is used when no database is given
+	pub database: Option<PathBuf>,
+	/// The time to search each position for
+	pub time: Option<Duration>,
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+	#[error("Line {line}: expected a key and a value, such as `hash = 64`")]
+	InvalidLine { line: usize },
+	#[error("Line {line}: {key:?} is not a setting")]
+	UnknownKey { line: usize, key: String },
+	#[error("Line {line}: {value:?} is not a valid value for {key:?}")]
+	InvalidValue {
+		line: usize,
+		key: String,
+		value: String,
+	},
+	#[error(transparent)]
+	IoError(#[from] io::Error),
+}
+
+/// Reads a TOML value. Strings are quoted, and everything after a `#` is a
+/// comment.
+fn parse_value(value: &str) -> Option<String> {
+	let value = value.trim();
+	if let Some(literal) = value.strip_prefix('\'') {
+		let (string, rest) = literal.split_once('\'')?;
+		return is_comment(rest).then(|| string.to_string());
+	}
+
+	if let Some(basic) = value.strip_prefix('"') {
+		let mut string = String::new();
+		let mut chars = basic.chars();
+		while let Some(c) = chars.next() {
+			match c {
+				'"' => return is_comment(chars.as_str()).then_some(string),
+				'\\' => match chars.next()? {
+					'n' => string.push('\n'),
+					't' => string.push('\t'),
+					c @ ('"' | '\\') => string.push(c),
+					_ => return None,
+				},
+				c => string.push(c),
+			}
+		}
+		return None;
+	}
+
+	let value = value.split('#').next()?.trim();
+	(!value.is_empty()).then(|| value.to_string())
+}
+
+/// Checks that the rest of a line is only a comment
+fn is_comment(rest: &str) -> bool {
+	let rest = rest.trim();
+	rest.is_empty() || rest.starts_with('#')
+}
+
+impl Config {
+	/// Reads a config file, which is a subset of TOML. Each line sets one
+	/// setting, such as `hash = 64` or `book = "openings.amb"`. Section
+	/// headers, empty lines and comments are skipped.
+	pub fn parse(text: &str) -> Result<Self, ConfigError> {
+		let mut config = Self::default();
+		for (index, line) in text.lines().enumerate() {
+			let line_number = index + 1;
+			let line = line.trim();
+			if is_comment(line) || line.starts_with('[') {
+				continue;
+			}
+
+			let (key, value) = line
+				.split_once('=')
+				.ok_or(ConfigError::InvalidLine { line: line_number })?;
+			let key = key.trim();
+			let invalid_value = || ConfigError::InvalidValue {
+				line: line_number,
+				key: key.to_string(),
+				value: value.trim().to_string(),
+			};
+			let value = parse_value(value).ok_or_else(invalid_value)?;
+
+			match key {
+				"hash" => config.hash_megabytes = Some(value.parse().map_err(|_| invalid_value())?),
+				"threads" => config.threads = Some(value.parse().map_err(|_| invalid_value())?),
+				"book" => config.book = Some(PathBuf::from(value)),
+				"tablebases" => config.tablebases = Some(PathBuf::from(value)),
+				"database" => config.database = Some(PathBuf::from(value)),
+				"time" => {
+					let seconds = value.parse().map_err(|_| invalid_value())?;
+					config.time =
+						Some(Duration::try_from_secs_f64(seconds).map_err(|_| invalid_value())?);
+				}
+				_ => {
+					return Err(ConfigError::UnknownKey {
+						line: line_number,
+						key: key.to_string(),
+					})
+				}
+			}
+		}
+
+		Ok(config)
+	}
+
+	/// The config file, which is `ampere/config.toml` in the user's config
+	/// directory. Returns `None` if the user doesn't have a home directory.
+	pub fn path() -> Option<PathBuf> {
+		let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+			.filter(|dir| !dir.is_empty())
+			.map(PathBuf::from)
+			.or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+		Some(config_dir.join("ampere").join("config.toml"))
+	}
+
+	/// Reads a config file. If the file doesn't exist, then nothing is
+	/// configured.
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+		match std::fs::read_to_string(path) {
+			Ok(text) => Self::parse(&text),
+			Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+			Err(error) => Err(error.into()),
+		}
+	}
+
+	pub fn hash_megabytes(&self) -> usize {
+		self.hash_megabytes.unwrap_or(DEFAULT_HASH_MEGABYTES)
+	}
+
+	pub fn threads(&self) -> NonZeroUsize {
+		self.threads.unwrap_or(NonZeroUsize::MIN)
+	}
+
+	pub fn time(&self) -> Duration {
+		self.time.unwrap_or(DEFAULT_TIME)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_settings() {
+		let text = "# defaults\n[engine]\nhash = 64 # megabytes\nthreads = 4\n\n\
+		            book = \"C:\\\\books\\\\main.amb\"\ntablebases = '/tb#1'\ntime = 0.5\n\
+		            database = \"games.adb\"\n";
+		let config = Config::parse(text).unwrap();
+		assert_eq!(config.hash_megabytes(), 64);
+		assert_eq!(config.threads().get(), 4);
+		assert_eq!(config.book, Some(PathBuf::from("C:\\books\\main.amb")));
+		assert_eq!(config.tablebases, Some(PathBuf::from("/tb#1")));
+		assert_eq!(config.database, Some(PathBuf::from("games.adb")));
+		assert_eq!(config.time(), Duration::from_millis(500));
+
+		let config = Config::parse("").unwrap();
+		assert_eq!(config.hash_megabytes(), DEFAULT_HASH_MEGABYTES);
+		assert_eq!(config.time(), DEFAULT_TIME);
+	}
+
+	#[test]
+	fn rejects_invalid_settings() {
+		assert!(matches!(
+			Config::parse("hash"),
+			Err(ConfigError::InvalidLine { line: 1 })
+		));
+		assert!(matches!(
+			Config::parse("\nhashes = 4"),
+			Err(ConfigError::UnknownKey { line: 2, .. })
+		));
+		assert!(matches!(
+			Config::parse("threads = 0"),
+			Err(ConfigError::InvalidValue { line: 1, .. })
+		));
+		assert!(matches!(
+			Config::parse("book = \"unterminated"),
+			Err(ConfigError::InvalidValue { line: 1, .. })
+		));
+	}
+}
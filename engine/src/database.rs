@@ -0,0 +1,535 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use model::CheckersBitBoard;
+use pdn::index::{IndexFileError, NextMove, PositionIndex};
+use pdn::semantic::replay;
+use pdn::statistics::Statistics;
+use pdn::GameResult;
+use thiserror::Error;
+
+use crate::notation::{
+	first_pdn_game, full_moves, parse_pdn, pdn_tag, play_pdn_game, FullMove, PdnError,
+};
+
+const MAGIC: u32 = u32::from_be_bytes(*b".adb");
+const SUPPORTED_VERSION: u16 = 1;
+
+/// A search for games in a PDN file. A game matches if it matches every
+/// condition which is set.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GameQuery {
+	/// Part of the name of either player, ignoring case
+	pub player: Option<String>,
+	/// The result, as it's written in PDN, such as `1-0`. Unfinished games
+	/// are found with `*`.
+	pub result: Option<String>,
+	/// A position which is reached at any point in the game
+	pub position: Option<CheckersBitBoard>,
+	/// The moves the game starts with. Captures which take the same pieces
+	/// are the same move.
+	pub opening: Vec<FullMove>,
+}
+
+impl GameQuery {
+	/// Checks if a game matches the query. Games which can't be read don't
+	/// match, unless the query only looks at the tags.
+	pub fn matches(&self, pdn: &str) -> bool {
+		if let Some(player) = &self.player {
+			let player = player.to_lowercase();
+			let has_player = ["Black", "White"].iter().any(|tag| {
+				pdn_tag(pdn, tag).is_some_and(|name| name.to_lowercase().contains(&player))
+			});
+			if !has_player {
+				return false;
+			}
+		}
+
+		if let Some(result) = &self.result {
+			// without a tag, the result is at the end of the moves
+			if result.parse() != Ok(game_result(pdn)) {
+				return false;
+			}
+		}
+
+		if self.position.is_none() && self.opening.is_empty() {
+			return true;
+		}
+
+		let Ok(game) = parse_pdn(pdn) else {
+			return false;
+		};
+
+		if !self.opening.is_empty() {
+			if game.start != CheckersBitBoard::starting_position()
+				|| game.moves.len() < self.opening.len()
+			{
+				return false;
+			}
+
+			let same_opening = self
+				.opening
+				.iter()
+				.zip(&game.moves)
+				.all(|(expected, played)| expected.result == played.result);
+			if !same_opening {
+				return false;
+			}
+		}
+
+		if let Some(position) = self.position {
+			let reached = game.start == position
+				|| game
+					.moves
+					.iter()
+					.any(|full_move| full_move.result == position);
+			if !reached {
+				return false;
+			}
+		}
+
+		true
+	}
+}
+
+/// The result of a game. Without a tag, the result is at the end of the
+/// moves.
+fn game_result(pdn: &str) -> GameResult {
+	let result = pdn_tag(pdn, "Result")
+		.or_else(|| pdn.split_whitespace().last().map(str::to_string))
+		.unwrap_or_default();
+	result.parse().unwrap_or(GameResult::Unfinished)
+}
+
+/// How many games ended with each result
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResultCounts {
+	pub black_wins: u32,
+	pub white_wins: u32,
+	pub draws: u32,
+	pub unfinished: u32,
+}
+
+impl ResultCounts {
+	pub fn add(&mut self, result: GameResult) {
+		let count = match result {
+			GameResult::BlackWin => &mut self.black_wins,
+			GameResult::WhiteWin => &mut self.white_wins,
+			GameResult::Draw => &mut self.draws,
+			GameResult::Unfinished => &mut self.unfinished,
+		};
+		*count = count.saturating_add(1);
+	}
+
+	/// The number of games, including the unfinished ones
+	pub fn games(&self) -> u32 {
+		self.black_wins
+			.saturating_add(self.white_wins)
+			.saturating_add(self.draws)
+			.saturating_add(self.unfinished)
+	}
+}
+
+impl From<&NextMove> for ResultCounts {
+	fn from(next: &NextMove) -> Self {
+		Self {
+			black_wins: next.black_wins,
+			white_wins: next.white_wins,
+			draws: next.draws,
+			unfinished: next.unfinished,
+		}
+	}
+}
+
+/// A game in a database, and where it was imported from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameInfo {
+	pub file: String,
+	/// The number of the game in its file, starting from zero
+	pub index: u32,
+	pub black: String,
+	pub white: String,
+	pub result: GameResult,
+}
+
+/// The games which reached a position
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PositionStats {
+	/// The IDs of the games, in the order they were imported
+	pub games: Vec<u32>,
+	pub results: ResultCounts,
+}
+
+/// The games in a collection, with an index of their positions, so that the
+/// games which reached a position can be found without reading every game
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GameDatabase {
+	games: Vec<GameInfo>,
+	index: PositionIndex,
+}
+
+#[derive(Debug, Error)]
+pub enum DatabaseFileError {
+	#[error("Invalid database file: the magic header field was incorrect")]
+	MagicError,
+	#[error("This version of the database file format is unsupported. Only {SUPPORTED_VERSION} is supported")]
+	UnsupportedVersion(u16),
+	#[error("Invalid database file: the position index could not be read")]
+	IndexError,
+	#[error(transparent)]
+	IoError(#[from] io::Error),
+}
+
+impl From<IndexFileError> for DatabaseFileError {
+	fn from(error: IndexFileError) -> Self {
+		match error {
+			IndexFileError::IoError(error) => Self::IoError(error),
+			IndexFileError::MagicError | IndexFileError::UnsupportedVersion(_) => Self::IndexError,
+		}
+	}
+}
+
+fn read_string(reader: &mut impl Read) -> io::Result<String> {
+	let len = reader.read_u16::<BigEndian>()?;
+	let mut bytes = vec![0; len as usize];
+	reader.read_exact(&mut bytes)?;
+	Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn write_string(writer: &mut impl Write, string: &str) -> io::Result<()> {
+	// long strings are cut off at a character boundary
+	let mut len = string.len().min(u16::MAX as usize);
+	while !string.is_char_boundary(len) {
+		len -= 1;
+	}
+	writer.write_u16::<BigEndian>(len as u16)?;
+	writer.write_all(&string.as_bytes()[..len])
+}
+
+impl GameDatabase {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// The number of games
+	pub fn len(&self) -> usize {
+		self.games.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.games.is_empty()
+	}
+
+	/// The number of positions which are indexed
+	pub fn positions(&self) -> usize {
+		self.index.len()
+	}
+
+	pub fn game(&self, id: u32) -> Option<&GameInfo> {
+		self.games.get(id as usize)
+	}
+
+	/// The games which reached `position`, and how they ended
+	pub fn get(&self, position: CheckersBitBoard) -> Option<PositionStats> {
+		let entry = self.index.get(position)?;
+		let mut games: Vec<u32> = entry
+			.occurrences()
+			.iter()
+			.map(|occurrence| occurrence.game as u32)
+			.collect();
+		// the occurrences in one game are next to each other
+		games.dedup();
+
+		let mut results = ResultCounts::default();
+		for info in games.iter().filter_map(|&id| self.game(id)) {
+			results.add(info.result);
+		}
+
+		Some(PositionStats { games, results })
+	}
+
+	/// The moves that were played in a position, from the most played to the
+	/// least played
+	pub fn moves(&self, position: CheckersBitBoard) -> Vec<(FullMove, ResultCounts)> {
+		let Some(entry) = self.index.get(position) else {
+			return Vec::new();
+		};
+		let legal_moves = full_moves(position);
+		let mut moves: Vec<_> = entry
+			.next_moves()
+			.iter()
+			.filter_map(|next| {
+				let full_move = legal_moves
+					.iter()
+					.find(|full_move| full_move.from() == next.from && full_move.to() == next.to)?;
+				Some((full_move.clone(), ResultCounts::from(next)))
+			})
+			.collect();
+		moves.sort_by_key(|(_, results)| std::cmp::Reverse(results.games()));
+		moves
+	}
+
+	/// Adds a game, which is game `index` of `file`. A position that the
+	/// game reaches more than once is only counted once for the game, but its
+	/// moves are counted each time.
+	pub fn add_game(&mut self, file: &str, index: usize, pdn: &str) -> Result<u32, PdnError> {
+		let game = first_pdn_game(pdn)?.ok_or(PdnError::InvalidPdn)?;
+		// the index gives an ID to games it can't play, so they're skipped here
+		replay(&game)?;
+		self.index
+			.add_game(&game)
+			.expect("the moves of the game can be played");
+
+		let id = self.games.len() as u32;
+		let tag = |name| pdn_tag(pdn, name).unwrap_or_else(|| "?".to_string());
+		self.games.push(GameInfo {
+			file: file.to_string(),
+			index: index as u32,
+			black: tag("Black"),
+			white: tag("White"),
+			result: game_result(pdn),
+		});
+
+		Ok(id)
+	}
+
+	pub fn read(reader: &mut impl Read) -> Result<Self, DatabaseFileError> {
+		let magic = reader.read_u32::<BigEndian>()?;
+		if magic != MAGIC {
+			return Err(DatabaseFileError::MagicError);
+		}
+
+		let version = reader.read_u16::<BigEndian>()?;
+		if version != SUPPORTED_VERSION {
+			return Err(DatabaseFileError::UnsupportedVersion(version));
+		}
+
+		let len = reader.read_u64::<BigEndian>()?;
+		let mut games = Vec::new();
+		for _ in 0..len {
+			let file = read_string(reader)?;
+			let index = reader.read_u32::<BigEndian>()?;
+			let black = read_string(reader)?;
+			let white = read_string(reader)?;
+			let result = match reader.read_u8()? {
+				1 => GameResult::BlackWin,
+				2 => GameResult::WhiteWin,
+				3 => GameResult::Draw,
+				_ => GameResult::Unfinished,
+			};
+			games.push(GameInfo {
+				file,
+				index,
+				black,
+				white,
+				result,
+			});
+		}
+
+		let index = PositionIndex::read(reader)?;
+		Ok(Self { games, index })
+	}
+
+	pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+		writer.write_u32::<BigEndian>(MAGIC)?;
+		writer.write_u16::<BigEndian>(SUPPORTED_VERSION)?;
+
+		writer.write_u64::<BigEndian>(self.games.len() as u64)?;
+		for game in &self.games {
+			write_string(writer, &game.file)?;
+			writer.write_u32::<BigEndian>(game.index)?;
+			write_string(writer, &game.black)?;
+			write_string(writer, &game.white)?;
+			writer.write_u8(match game.result {
+				GameResult::BlackWin => 1,
+				GameResult::WhiteWin => 2,
+				GameResult::Draw => 3,
+				GameResult::Unfinished => 0,
+			})?;
+		}
+
+		self.index.write(writer)?;
+
+		Ok(())
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, DatabaseFileError> {
+		Self::read(&mut BufReader::new(File::open(path)?))
+	}
+
+	pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		self.write(&mut writer)?;
+		writer.flush()
+	}
+}
+
+/// The games which started from the same position with the same moves
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpeningStats {
+	pub start: CheckersBitBoard,
+	pub moves: Vec<FullMove>,
+	/// The results and lengths of the games
+	pub statistics: Statistics,
+}
+
+impl OpeningStats {
+	/// The average number of moves in each game
+	pub fn average_length(&self) -> f64 {
+		self.statistics.average_length().unwrap_or_default()
+	}
+}
+
+/// Groups the games by their starting position and first `plies` moves, and
+/// adds up their results, from the most played opening to the least played.
+/// Games which are shorter than `plies` are grouped by all of their moves.
+/// Games which can't be read are skipped.
+pub fn opening_stats<'a>(
+	games: impl IntoIterator<Item = &'a str>,
+	plies: usize,
+) -> Vec<OpeningStats> {
+	let mut openings: HashMap<(CheckersBitBoard, Vec<CheckersBitBoard>), OpeningStats> =
+		HashMap::new();
+	for pdn in games {
+		let Ok(Some(game)) = first_pdn_game(pdn) else {
+			continue;
+		};
+		let Ok(played) = play_pdn_game(&game) else {
+			continue;
+		};
+
+		let moves = &played.moves[..plies.min(played.moves.len())];
+		let positions = moves.iter().map(|full_move| full_move.result).collect();
+		openings
+			.entry((played.start, positions))
+			.or_insert_with(|| OpeningStats {
+				start: played.start,
+				moves: moves.to_vec(),
+				statistics: Statistics::new(),
+			})
+			.statistics
+			.add_game(&game);
+	}
+
+	let mut openings: Vec<_> = openings.into_values().collect();
+	// openings with the same number of games are sorted by their moves
+	openings.sort_by(|a, b| {
+		let moves = |opening: &OpeningStats| {
+			opening
+				.moves
+				.iter()
+				.map(ToString::to_string)
+				.collect::<Vec<_>>()
+		};
+		b.statistics
+			.games()
+			.cmp(&a.statistics.games())
+			.then_with(|| moves(a).cmp(&moves(b)))
+	});
+	openings
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::notation::parse_fen;
+
+	const GAME: &str = "[Black \"Ampere\"]\n[White \"Kingsrow\"]\n[Result \"1-0\"]\n\n\
+	                    1. 11-15 23-19 2. 8-11 22-17 1-0\n";
+
+	#[test]
+	fn matches_tags() {
+		let query = |player: &str, result: &str| GameQuery {
+			player: Some(player.to_string()),
+			result: Some(result.to_string()),
+			..GameQuery::default()
+		};
+		assert!(query("kings", "1-0").matches(GAME));
+		assert!(query("AMPERE", "2-0").matches(GAME));
+		assert!(!query("chinook", "1-0").matches(GAME));
+		assert!(!query("ampere", "0-1").matches(GAME));
+		assert!(!query("ampere", "*").matches(GAME));
+	}
+
+	#[test]
+	fn matches_moves() {
+		let opening = |moves: &str| GameQuery {
+			opening: parse_pdn(moves).unwrap().moves,
+			..GameQuery::default()
+		};
+		assert!(opening("11-15 23-19").matches(GAME));
+		assert!(!opening("11-15 22-17").matches(GAME));
+		assert!(!opening("11-15 23-19 8-11 22-17 9-13").matches(GAME));
+		let untagged = GameQuery {
+			result: Some("1-0".to_string()),
+			..opening("11-15")
+		};
+		assert!(untagged.matches("1. 11-15 23-19 1-0"));
+
+		let after_two = parse_pdn("11-15 23-19").unwrap().moves[1].result;
+		let query = GameQuery {
+			position: Some(after_two),
+			..GameQuery::default()
+		};
+		assert!(query.matches(GAME));
+
+		let query = GameQuery {
+			position: parse_fen("B:W18:B14"),
+			..GameQuery::default()
+		};
+		assert!(!query.matches(GAME));
+	}
+
+	#[test]
+	fn indexes_positions() {
+		let mut database = GameDatabase::new();
+		database.add_game("games.pdn", 0, GAME).unwrap();
+		database
+			.add_game("games.pdn", 1, "1. 11-15 22-18 0-1")
+			.unwrap();
+		assert!(database.add_game("games.pdn", 2, "1. 11-18").is_err());
+
+		let start = database.get(CheckersBitBoard::starting_position()).unwrap();
+		assert_eq!(start.games, [0, 1]);
+		assert_eq!(start.results.black_wins, 1);
+		assert_eq!(start.results.white_wins, 1);
+
+		let after_one = parse_pdn("11-15").unwrap().moves[0].result;
+		let moves = database.moves(after_one);
+		assert_eq!(moves.len(), 2);
+		assert_eq!(moves[0].1.games(), 1);
+
+		let kings = "[FEN \"W:WK32:BK1\"]\n1... 32-27 2. 1-5 27-32 3. 5-1 *";
+		assert_eq!(database.add_game("kings.pdn", 0, kings), Ok(2));
+		let repeated = database.get(parse_fen("W:WK32:BK1").unwrap()).unwrap();
+		assert_eq!(repeated.games, [2]);
+		assert_eq!(repeated.results.unfinished, 1);
+
+		let mut file = Vec::new();
+		database.write(&mut file).unwrap();
+		let read = GameDatabase::read(&mut file.as_slice()).unwrap();
+		assert_eq!(read, database);
+		assert_eq!(read.game(0).unwrap().white, "Kingsrow");
+	}
+
+	#[test]
+	fn groups_openings() {
+		let games = [
+			GAME,
+			"1. 11-15 23-19 9-13 0-1",
+			"1. 11-15 22-18 1-1",
+			"1. 11-18",
+		];
+		let openings = opening_stats(games, 2);
+		assert_eq!(openings.len(), 2);
+		assert_eq!(openings[0].moves.len(), 2);
+		assert_eq!(openings[0].moves[1].to_string(), "23-19");
+		assert_eq!(openings[0].statistics.black_wins(), 1);
+		assert_eq!(openings[0].statistics.white_wins(), 1);
+		assert_eq!(openings[0].average_length(), 3.5);
+		assert_eq!(openings[1].statistics.draws(), 1);
+	}
+}
@@ -0,0 +1,166 @@
+use model::{CheckersBitBoard, PieceColor};
+
+use crate::notation::square_from_number;
+
+/// The width of each square of an SVG diagram
+const SQUARE_SIZE: usize = 60;
+
+/// The width of the border around an SVG diagram
+const BORDER: usize = 12;
+
+const LIGHT_SQUARE_COLOR: &str = "#f3e3c3";
+const DARK_SQUARE_COLOR: &str = "#5b8a5a";
+const BORDER_COLOR: &str = "#3b2a1a";
+
+/// What is on a square
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Square {
+	Empty,
+	Man(PieceColor),
+	King(PieceColor),
+}
+
+/// The piece on a square, from its number in standard notation
+pub(crate) fn square(board: CheckersBitBoard, number: usize) -> Square {
+	let Some(value) = square_from_number(number) else {
+		return Square::Empty;
+	};
+
+	match (board.color_at(value), board.king_at(value)) {
+		(Some(color), Some(true)) if board.piece_at(value) => Square::King(color),
+		(Some(color), Some(false)) if board.piece_at(value) => Square::Man(color),
+		_ => Square::Empty,
+	}
+}
+
+/// The row and column of a square, from its number in standard notation.
+/// Black starts at the top of the board, with square 1 in the top row, and
+/// the single corner of each player is on their left.
+pub(crate) const fn row_and_column(number: usize) -> (usize, usize) {
+	let row = (number - 1) / 4;
+	let column = (number - 1) % 4 * 2 + if row.is_multiple_of(2) { 1 } else { 0 };
+	(row, column)
+}
+
+/// Draws the board with Unicode pieces, with black at the top. The square
+/// numbers of each row are written next to it.
+pub fn text_diagram(board: CheckersBitBoard) -> String {
+	let mut diagram = String::new();
+	for row in 0..8 {
+		let mut cells = [" "; 8];
+		for number in row * 4 + 1..=row * 4 + 4 {
+			let (_, column) = row_and_column(number);
+			cells[column] = match square(board, number) {
+				Square::Empty => "·",
+				Square::Man(PieceColor::Dark) => "⛂",
+				Square::King(PieceColor::Dark) => "⛃",
+				Square::Man(PieceColor::Light) => "⛀",
+				Square::King(PieceColor::Light) => "⛁",
+			};
+		}
+
+		diagram.push_str(&cells.join(" "));
+		diagram.push_str(&format!(
+			"    {:>2} {:>2} {:>2} {:>2}\n",
+			row * 4 + 1,
+			row * 4 + 2,
+			row * 4 + 3,
+			row * 4 + 4
+		));
+	}
+
+	let turn = match board.turn() {
+		PieceColor::Dark => "black",
+		PieceColor::Light => "white",
+	};
+	diagram.push_str(&format!("{turn} to move\n"));
+	diagram
+}
+
+/// Draws the board as an SVG image, with black at the top, and the number of
+/// each playable square in its corner
+pub fn svg_diagram(board: CheckersBitBoard) -> String {
+	let size = SQUARE_SIZE * 8 + BORDER * 2;
+	let mut svg = format!(
+		"<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{size}\" height=\"{size}\" \
+		 viewBox=\"0 0 {size} {size}\">\n\
+		 <rect width=\"{size}\" height=\"{size}\" fill=\"{BORDER_COLOR}\"/>\n\
+		 <rect x=\"{BORDER}\" y=\"{BORDER}\" width=\"{}\" height=\"{}\" fill=\"{LIGHT_SQUARE_COLOR}\"/>\n",
+		SQUARE_SIZE * 8,
+		SQUARE_SIZE * 8
+	);
+
+	for number in 1..=32 {
+		let (row, column) = row_and_column(number);
+		let x = BORDER + column * SQUARE_SIZE;
+		let y = BORDER + row * SQUARE_SIZE;
+		svg.push_str(&format!(
+			"<rect x=\"{x}\" y=\"{y}\" width=\"{SQUARE_SIZE}\" height=\"{SQUARE_SIZE}\" \
+			 fill=\"{DARK_SQUARE_COLOR}\"/>\n\
+			 <text x=\"{}\" y=\"{}\" font-family=\"sans-serif\" font-size=\"11\" \
+			 fill=\"#ffffff\">{number}</text>\n",
+			x + 3,
+			y + 12
+		));
+
+		let (color, king) = match square(board, number) {
+			Square::Empty => continue,
+			Square::Man(color) => (color, false),
+			Square::King(color) => (color, true),
+		};
+		let (fill, stroke) = match color {
+			PieceColor::Dark => ("#202020", "#000000"),
+			PieceColor::Light => ("#fafafa", "#404040"),
+		};
+		let (cx, cy) = (x + SQUARE_SIZE / 2, y + SQUARE_SIZE / 2);
+		let radius = SQUARE_SIZE * 2 / 5;
+		svg.push_str(&format!(
+			"<circle cx=\"{cx}\" cy=\"{cy}\" r=\"{radius}\" fill=\"{fill}\" stroke=\"{stroke}\" \
+			 stroke-width=\"2\"/>\n\
+			 <circle cx=\"{cx}\" cy=\"{cy}\" r=\"{}\" fill=\"none\" stroke=\"{stroke}\" \
+			 stroke-width=\"1\"/>\n",
+			radius * 3 / 4
+		));
+		if king {
+			svg.push_str(&format!(
+				"<text x=\"{cx}\" y=\"{}\" font-family=\"serif\" font-size=\"{}\" \
+				 text-anchor=\"middle\" fill=\"#d4a017\">K</text>\n",
+				cy + radius / 2,
+				radius * 3 / 2
+			));
+		}
+	}
+
+	svg.push_str("</svg>\n");
+	svg
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::notation::parse_fen;
+
+	#[test]
+	fn squares_are_in_standard_places() {
+		assert_eq!(row_and_column(1), (0, 1));
+		assert_eq!(row_and_column(4), (0, 7));
+		assert_eq!(row_and_column(5), (1, 0));
+		assert_eq!(row_and_column(29), (7, 0));
+		assert_eq!(row_and_column(32), (7, 6));
+	}
+
+	#[test]
+	fn draws_every_piece() {
+		let diagram = text_diagram(CheckersBitBoard::starting_position());
+		assert_eq!(diagram.matches('⛂').count(), 12);
+		assert_eq!(diagram.matches('⛀').count(), 12);
+		assert!(diagram.starts_with("  ⛂   ⛂   ⛂   ⛂"));
+		assert!(diagram.ends_with("black to move\n"));
+
+		let board = parse_fen("W:WK18:B14").unwrap();
+		let svg = svg_diagram(board);
+		assert_eq!(svg.matches("<circle").count(), 4);
+		assert_eq!(svg.matches(">K</text>").count(), 1);
+		assert_eq!(text_diagram(board).matches('⛁').count(), 1);
+	}
+}
@@ -0,0 +1,775 @@
+use std::fmt::{self, Display};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+use model::{CheckersBitBoard, PieceColor};
+use pdn::GameResult;
+use thiserror::Error;
+
+use crate::notation::{
+	full_moves, parse_position, position_string, square_from_number, square_number, FullMove,
+};
+use crate::{Clock, Engine, EvaluationSettings, ENGINE_NAME};
+
+/// The port DXP connections are usually made on
+pub const DEFAULT_PORT: u16 = 27531;
+
+/// The version of the protocol that is sent in a game request
+const PROTOCOL_VERSION: u8 = 1;
+
+/// The width of the name field in game requests and acceptances
+const NAME_LENGTH: usize = 32;
+
+/// Why a game request was answered the way it was
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AcceptCode {
+	Accepted,
+	RefusedColor,
+	RefusedTime,
+	RefusedMoves,
+	RefusedPosition,
+	Refused,
+}
+
+impl AcceptCode {
+	const fn to_char(self) -> char {
+		match self {
+			Self::Accepted => '0',
+			Self::RefusedColor => '1',
+			Self::RefusedTime => '2',
+			Self::RefusedMoves => '3',
+			Self::RefusedPosition => '4',
+			Self::Refused => '9',
+		}
+	}
+
+	const fn from_char(code: char) -> Option<Self> {
+		match code {
+			'0' => Some(Self::Accepted),
+			'1' => Some(Self::RefusedColor),
+			'2' => Some(Self::RefusedTime),
+			'3' => Some(Self::RefusedMoves),
+			'4' => Some(Self::RefusedPosition),
+			'9' => Some(Self::Refused),
+			_ => None,
+		}
+	}
+}
+
+/// How a game ended, from the perspective of the program that sent the
+/// message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndReason {
+	Unknown,
+	Loss,
+	Draw,
+	Win,
+}
+
+impl EndReason {
+	const fn to_char(self) -> char {
+		match self {
+			Self::Unknown => '0',
+			Self::Loss => '1',
+			Self::Draw => '2',
+			Self::Win => '3',
+		}
+	}
+
+	const fn from_char(reason: char) -> Option<Self> {
+		match reason {
+			'0' => Some(Self::Unknown),
+			'1' => Some(Self::Loss),
+			'2' => Some(Self::Draw),
+			'3' => Some(Self::Win),
+			_ => None,
+		}
+	}
+
+	/// The same reason, from the perspective of the other program
+	const fn opposite(self) -> Self {
+		match self {
+			Self::Loss => Self::Win,
+			Self::Win => Self::Loss,
+			reason => reason,
+		}
+	}
+
+	/// The result of the game, if `color` gave this reason
+	const fn result(self, color: PieceColor) -> Option<GameResult> {
+		match self {
+			Self::Unknown => None,
+			Self::Loss => Some(GameResult::loss_for(color)),
+			Self::Draw => Some(GameResult::Draw),
+			Self::Win => Some(GameResult::loss_for(color.flip())),
+		}
+	}
+}
+
+/// A DXP message. Squares are numbered from 1 to 32, as in standard
+/// notation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+	GameRequest {
+		name: String,
+		/// The color played by the program that receives the request
+		follower_color: PieceColor,
+		/// The thinking time of each player, in minutes
+		minutes: u16,
+		/// The number of moves which must be played in that time
+		moves: u16,
+		/// The starting position, or `None` for the standard one
+		position: Option<CheckersBitBoard>,
+	},
+	GameAccept {
+		name: String,
+		code: AcceptCode,
+	},
+	Move {
+		/// The time used for the move, in seconds
+		seconds: u16,
+		from: usize,
+		to: usize,
+		captured: Vec<usize>,
+	},
+	GameEnd {
+		reason: EndReason,
+		/// Whether the other program shouldn't start another game
+		stop: bool,
+	},
+	Chat(String),
+	BackRequest {
+		move_number: u16,
+		color: PieceColor,
+	},
+	BackAccept {
+		accepted: bool,
+	},
+}
+
+#[derive(Debug, Error)]
+pub enum DxpError {
+	#[error("Invalid DXP message: {0:?}")]
+	InvalidMessage(String),
+	#[error("The opponent played an illegal move: {0}")]
+	IllegalMove(String),
+	#[error("The game request was refused: {0:?}")]
+	Refused(AcceptCode),
+	#[error("Expected {expected}, but received {received:?}")]
+	UnexpectedMessage {
+		expected: &'static str,
+		received: Message,
+	},
+	#[error("The opponent disconnected")]
+	Disconnected,
+	#[error(transparent)]
+	IoError(#[from] io::Error),
+}
+
+const fn color_char(color: PieceColor) -> char {
+	match color {
+		PieceColor::Dark => 'Z',
+		PieceColor::Light => 'W',
+	}
+}
+
+const fn parse_color(color: char) -> Option<PieceColor> {
+	match color {
+		'Z' => Some(PieceColor::Dark),
+		'W' => Some(PieceColor::Light),
+		_ => None,
+	}
+}
+
+/// Pads or truncates a name to the width of the name field
+fn name_field(name: &str) -> String {
+	let name: String = name.chars().take(NAME_LENGTH).collect();
+	format!("{name:<NAME_LENGTH$}")
+}
+
+impl Display for Message {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::GameRequest {
+				name,
+				follower_color,
+				minutes,
+				moves,
+				position,
+			} => {
+				write!(
+					f,
+					"R{PROTOCOL_VERSION:02}{}{}{minutes:03}{moves:03}",
+					name_field(name),
+					color_char(*follower_color)
+				)?;
+				match position {
+					Some(position) => {
+						write!(f, "B{}", position_string(*position, 'Z'))
+					}
+					None => write!(f, "A"),
+				}
+			}
+			Self::GameAccept { name, code } => {
+				write!(f, "A{}{}", name_field(name), code.to_char())
+			}
+			Self::Move {
+				seconds,
+				from,
+				to,
+				captured,
+			} => {
+				write!(f, "M{seconds:04}{from:02}{to:02}{:02}", captured.len())?;
+				for square in captured {
+					write!(f, "{square:02}")?;
+				}
+
+				Ok(())
+			}
+			Self::GameEnd { reason, stop } => {
+				write!(f, "E{}{}", reason.to_char(), *stop as u8)
+			}
+			Self::Chat(text) => write!(f, "C{text}"),
+			Self::BackRequest { move_number, color } => {
+				write!(f, "B{move_number:03}{}", color_char(*color))
+			}
+			Self::BackAccept { accepted } => {
+				write!(f, "K{}", if *accepted { '0' } else { '1' })
+			}
+		}
+	}
+}
+
+/// Takes the next `len` characters of a message, and parses them
+fn field<T: std::str::FromStr>(rest: &mut &str, len: usize) -> Option<T> {
+	let value = rest.get(..len)?;
+	*rest = &rest[len..];
+	value.trim().parse().ok()
+}
+
+impl Message {
+	pub fn parse(message: &str) -> Result<Self, DxpError> {
+		Self::try_parse(message).ok_or_else(|| DxpError::InvalidMessage(message.to_string()))
+	}
+
+	fn try_parse(message: &str) -> Option<Self> {
+		let mut chars = message.chars();
+		let kind = chars.next()?;
+		let mut rest = chars.as_str();
+
+		let message = match kind {
+			'R' => {
+				let _version: u8 = field(&mut rest, 2)?;
+				let name: String = field(&mut rest, NAME_LENGTH)?;
+				let follower_color = parse_color(field(&mut rest, 1)?)?;
+				let minutes = field(&mut rest, 3)?;
+				let moves = field(&mut rest, 3)?;
+				let position = match field(&mut rest, 1)? {
+					'A' => None,
+					'B' => Some(parse_position(std::mem::take(&mut rest), 'Z')?),
+					_ => return None,
+				};
+
+				Self::GameRequest {
+					name,
+					follower_color,
+					minutes,
+					moves,
+					position,
+				}
+			}
+			'A' => Self::GameAccept {
+				name: field(&mut rest, NAME_LENGTH)?,
+				code: AcceptCode::from_char(field(&mut rest, 1)?)?,
+			},
+			'M' => {
+				let seconds = field(&mut rest, 4)?;
+				let from = field(&mut rest, 2)?;
+				let to = field(&mut rest, 2)?;
+				let len: usize = field(&mut rest, 2)?;
+				let captured = (0..len)
+					.map(|_| field(&mut rest, 2))
+					.collect::<Option<_>>()?;
+
+				Self::Move {
+					seconds,
+					from,
+					to,
+					captured,
+				}
+			}
+			'E' => Self::GameEnd {
+				reason: EndReason::from_char(field(&mut rest, 1)?)?,
+				stop: field::<u8>(&mut rest, 1)? != 0,
+			},
+			'C' => Self::Chat(std::mem::take(&mut rest).to_string()),
+			'B' => Self::BackRequest {
+				move_number: field(&mut rest, 3)?,
+				color: parse_color(field(&mut rest, 1)?)?,
+			},
+			'K' => Self::BackAccept {
+				accepted: field::<char>(&mut rest, 1)? == '0',
+			},
+			_ => return None,
+		};
+
+		rest.is_empty().then_some(message)
+	}
+}
+
+/// Reads the next message, which ends with a null byte. Returns `Ok(None)`
+/// if the connection was closed.
+pub fn read_message(reader: &mut impl BufRead) -> Result<Option<Message>, DxpError> {
+	let mut bytes = Vec::new();
+	if reader.read_until(0, &mut bytes)? == 0 {
+		return Ok(None);
+	}
+
+	if bytes.last() == Some(&0) {
+		bytes.pop();
+	}
+
+	Message::parse(&String::from_utf8_lossy(&bytes)).map(Some)
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Message) -> io::Result<()> {
+	write!(writer, "{message}\0")?;
+	writer.flush()
+}
+
+pub(crate) fn expect_message(reader: &mut impl BufRead) -> Result<Message, DxpError> {
+	read_message(reader)?.ok_or(DxpError::Disconnected)
+}
+
+/// The settings for a game that Ampere asks for
+#[derive(Debug, Clone)]
+pub struct GameSettings {
+	pub name: String,
+	/// The color Ampere plays
+	pub color: PieceColor,
+	/// The thinking time of each player, in minutes
+	pub minutes: u16,
+	/// The number of moves which must be played in that time
+	pub moves: u16,
+	/// The starting position, or `None` for the standard one
+	pub position: Option<CheckersBitBoard>,
+}
+
+impl Default for GameSettings {
+	fn default() -> Self {
+		Self {
+			name: ENGINE_NAME.to_string(),
+			color: PieceColor::Dark,
+			minutes: 10,
+			moves: 50,
+			position: None,
+		}
+	}
+}
+
+/// Keeps track of Ampere's thinking time. The time is given again after
+/// every `moves` moves.
+struct GameClock {
+	period: Duration,
+	moves: u16,
+	remaining: Duration,
+	moves_played: u16,
+}
+
+impl GameClock {
+	fn new(minutes: u16, moves: u16) -> Self {
+		let period = Duration::from_secs(minutes as u64 * 60);
+		Self {
+			period,
+			moves,
+			remaining: period,
+			moves_played: 0,
+		}
+	}
+
+	fn clock(&self) -> Clock {
+		let moves_to_go = (self.moves != 0).then(|| {
+			let moves = (self.moves - self.moves_played % self.moves) as u32;
+			(moves, self.period)
+		});
+
+		Clock::Standard {
+			white_time_remaining: self.remaining,
+			black_time_remaining: self.remaining,
+			white_increment: Duration::ZERO,
+			black_increment: Duration::ZERO,
+			moves_until_next_time_control: moves_to_go,
+		}
+	}
+
+	fn record_move(&mut self, time: Duration) {
+		self.remaining = self.remaining.saturating_sub(time);
+		self.moves_played += 1;
+		if self.moves != 0 && self.moves_played.is_multiple_of(self.moves) {
+			self.remaining += self.period;
+		}
+	}
+}
+
+pub(crate) fn move_message(full_move: &FullMove, time: Duration) -> Message {
+	Message::Move {
+		seconds: time.as_secs().min(9999) as u16,
+		from: square_number(full_move.from()),
+		to: square_number(full_move.to()),
+		captured: full_move.captured_numbers(),
+	}
+}
+
+/// Finds the legal move described by a move message. The captured squares
+/// only need to be checked if they're needed to tell moves apart.
+pub(crate) fn find_move(
+	position: CheckersBitBoard,
+	from: usize,
+	to: usize,
+	captured: &[usize],
+) -> Option<FullMove> {
+	let from = square_from_number(from)?;
+	let to = square_from_number(to)?;
+	let captured = captured
+		.iter()
+		.map(|number| square_from_number(*number))
+		.try_fold(0u32, |bits, square| Some(bits | 1 << square?))?;
+
+	full_moves(position).into_iter().find(|full_move| {
+		full_move.from() == from
+			&& full_move.to() == to
+			&& (captured == 0 || full_move.captured == captured)
+	})
+}
+
+/// Searches for Ampere's move, and plays it. Returns `None` if there are no
+/// legal moves.
+fn search_move(engine: &Engine, position: CheckersBitBoard, clock: &GameClock) -> Option<FullMove> {
+	engine.set_position(position);
+	let (_, best_move) = engine.evaluate(
+		None,
+		EvaluationSettings {
+			clock: clock.clock(),
+			..EvaluationSettings::default()
+		},
+	);
+	engine.best_full_move(position, best_move?)
+}
+
+/// Plays a game which has already been agreed to, until either side sends
+/// `GAMEEND`. Returns the result of the game, if it's known.
+fn play(
+	engine: &Engine,
+	reader: &mut impl BufRead,
+	writer: &mut impl Write,
+	color: PieceColor,
+	mut clock: GameClock,
+	mut position: CheckersBitBoard,
+	output: &impl Fn(&str),
+) -> Result<Option<GameResult>, DxpError> {
+	let end_game = |writer: &mut _, reader: &mut _, reason| -> Result<_, DxpError> {
+		write_message(
+			writer,
+			&Message::GameEnd {
+				reason,
+				stop: false,
+			},
+		)?;
+		// wait for the opponent to confirm that the game is over
+		loop {
+			match expect_message(reader)? {
+				Message::GameEnd { .. } => return Ok(reason.result(color)),
+				Message::Chat(text) => output(&text),
+				_ => {}
+			}
+		}
+	};
+
+	loop {
+		if position.turn() == color {
+			let start = Instant::now();
+			let Some(full_move) = search_move(engine, position, &clock) else {
+				return end_game(writer, reader, EndReason::Loss);
+			};
+
+			let time = start.elapsed();
+			clock.record_move(time);
+			write_message(writer, &move_message(&full_move, time))?;
+			output(&full_move.to_string());
+			position = full_move.result;
+			continue;
+		}
+
+		match expect_message(reader)? {
+			Message::Move {
+				from, to, captured, ..
+			} => {
+				let Some(full_move) = find_move(position, from, to, &captured) else {
+					write_message(
+						writer,
+						&Message::GameEnd {
+							reason: EndReason::Unknown,
+							stop: true,
+						},
+					)?;
+					return Err(DxpError::IllegalMove(format!("{from}-{to}")));
+				};
+
+				output(&full_move.to_string());
+				position = full_move.result;
+			}
+			Message::GameEnd { reason, .. } => {
+				let reason = reason.opposite();
+				write_message(
+					writer,
+					&Message::GameEnd {
+						reason,
+						stop: false,
+					},
+				)?;
+				return Ok(reason.result(color));
+			}
+			Message::Chat(text) => output(&text),
+			// taking back moves isn't supported
+			Message::BackRequest { .. } => {
+				write_message(writer, &Message::BackAccept { accepted: false })?
+			}
+			message => {
+				return Err(DxpError::UnexpectedMessage {
+					expected: "a move",
+					received: message,
+				})
+			}
+		}
+	}
+}
+
+/// Asks the opponent for a game with the given settings, and plays it if
+/// they accept. Each move, and each chat message, is passed to `output`.
+pub fn initiate(
+	engine: &Engine,
+	reader: &mut impl BufRead,
+	writer: &mut impl Write,
+	settings: &GameSettings,
+	output: impl Fn(&str),
+) -> Result<Option<GameResult>, DxpError> {
+	write_message(
+		writer,
+		&Message::GameRequest {
+			name: settings.name.clone(),
+			follower_color: settings.color.flip(),
+			minutes: settings.minutes,
+			moves: settings.moves,
+			position: settings.position,
+		},
+	)?;
+
+	match expect_message(reader)? {
+		Message::GameAccept {
+			code: AcceptCode::Accepted,
+			..
+		} => {}
+		Message::GameAccept { code, .. } => return Err(DxpError::Refused(code)),
+		message => {
+			return Err(DxpError::UnexpectedMessage {
+				expected: "a game acceptance",
+				received: message,
+			})
+		}
+	}
+
+	let result = play(
+		engine,
+		reader,
+		writer,
+		settings.color,
+		GameClock::new(settings.minutes, settings.moves),
+		settings
+			.position
+			.unwrap_or_else(CheckersBitBoard::starting_position),
+		&output,
+	)?;
+
+	if let Some(result) = result {
+		engine.finish_game(result)?;
+	}
+
+	Ok(result)
+}
+
+/// Waits for the opponent to ask for a game, accepts it, and plays it. Each
+/// move, and each chat message, is passed to `output`.
+pub fn follow(
+	engine: &Engine,
+	reader: &mut impl BufRead,
+	writer: &mut impl Write,
+	name: &str,
+	output: impl Fn(&str),
+) -> Result<Option<GameResult>, DxpError> {
+	let (color, minutes, moves, position) = match expect_message(reader)? {
+		Message::GameRequest {
+			follower_color,
+			minutes,
+			moves,
+			position,
+			..
+		} => (follower_color, minutes, moves, position),
+		message => {
+			return Err(DxpError::UnexpectedMessage {
+				expected: "a game request",
+				received: message,
+			})
+		}
+	};
+
+	write_message(
+		writer,
+		&Message::GameAccept {
+			name: name.to_string(),
+			code: AcceptCode::Accepted,
+		},
+	)?;
+
+	let result = play(
+		engine,
+		reader,
+		writer,
+		color,
+		GameClock::new(minutes, moves),
+		position.unwrap_or_else(CheckersBitBoard::starting_position),
+		&output,
+	)?;
+
+	if let Some(result) = result {
+		engine.finish_game(result)?;
+	}
+
+	Ok(result)
+}
+
+/// Connects to a DXP server, and plays one game with the given settings
+pub fn connect(
+	engine: &Engine,
+	address: impl ToSocketAddrs,
+	settings: &GameSettings,
+	output: impl Fn(&str),
+) -> Result<Option<GameResult>, DxpError> {
+	let stream = TcpStream::connect(address)?;
+	let mut reader = BufReader::new(&stream);
+	let mut writer = &stream;
+	initiate(engine, &mut reader, &mut writer, settings, output)
+}
+
+/// Waits for a DXP client to connect, and plays one game that it asks for
+pub fn listen(
+	engine: &Engine,
+	address: impl ToSocketAddrs,
+	name: &str,
+	output: impl Fn(&str),
+) -> Result<Option<GameResult>, DxpError> {
+	let (stream, _) = TcpListener::bind(address)?.accept()?;
+	let mut reader = BufReader::new(&stream);
+	let mut writer = &stream;
+	follow(engine, &mut reader, &mut writer, name, output)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::TextFrontend;
+
+	#[test]
+	fn messages_round_trip() {
+		let messages = [
+			Message::GameRequest {
+				name: "Opponent".to_string(),
+				follower_color: PieceColor::Light,
+				minutes: 10,
+				moves: 50,
+				position: Some(CheckersBitBoard::starting_position()),
+			},
+			Message::GameAccept {
+				name: "Ampere".to_string(),
+				code: AcceptCode::RefusedTime,
+			},
+			Message::Move {
+				seconds: 12,
+				from: 15,
+				to: 24,
+				captured: vec![19, 20],
+			},
+			Message::GameEnd {
+				reason: EndReason::Draw,
+				stop: true,
+			},
+			Message::Chat("good game".to_string()),
+			Message::BackRequest {
+				move_number: 7,
+				color: PieceColor::Dark,
+			},
+			Message::BackAccept { accepted: false },
+		];
+
+		for message in messages {
+			assert_eq!(Message::parse(&message.to_string()).unwrap(), message);
+		}
+	}
+
+	#[test]
+	fn move_format() {
+		let message = Message::Move {
+			seconds: 3,
+			from: 11,
+			to: 15,
+			captured: Vec::new(),
+		};
+		assert_eq!(message.to_string(), "M0003111500");
+	}
+
+	#[test]
+	fn follows_a_game_until_it_ends() {
+		let engine = Engine::new(1 << 12, Arc::new(TextFrontend::new(|_| {})));
+		let request = Message::GameRequest {
+			name: "Opponent".to_string(),
+			follower_color: PieceColor::Light,
+			minutes: 1,
+			moves: 10,
+			position: None,
+		};
+		let end = Message::GameEnd {
+			reason: EndReason::Loss,
+			stop: false,
+		};
+		let input = format!("{request}\0{end}\0");
+
+		let mut output = Vec::new();
+		let result = follow(
+			&engine,
+			&mut input.as_bytes(),
+			&mut output,
+			"Ampere",
+			|_| {},
+		)
+		.unwrap();
+		assert_eq!(result, Some(GameResult::WhiteWin));
+
+		let mut output = output.as_slice();
+		assert!(matches!(
+			read_message(&mut output).unwrap(),
+			Some(Message::GameAccept {
+				code: AcceptCode::Accepted,
+				..
+			})
+		));
+		assert_eq!(
+			read_message(&mut output).unwrap(),
+			Some(Message::GameEnd {
+				reason: EndReason::Win,
+				stop: false
+			})
+		);
+	}
+}
@@ -0,0 +1,217 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use model::{CheckersBitBoard, PossibleMoves};
+
+use crate::notation::{complete_line, full_moves, FullMove};
+use crate::pns::{proven_line, ProofResult};
+use crate::retrograde::Material;
+use crate::tablebase::{Outcome, Tablebase, TablebaseFileError};
+
+/// The longest line that is followed through the tablebases
+const MAX_LINE_LENGTH: usize = 256;
+
+/// Loads the tablebase files in a directory as they're needed, and remembers
+/// which files were consulted, and which ones were missing
+#[derive(Debug, Default)]
+pub struct TablebaseSet {
+	directory: Option<PathBuf>,
+	tables: BTreeMap<Material, Option<Tablebase>>,
+}
+
+impl TablebaseSet {
+	/// Tablebases are loaded from `directory`. If there isn't a directory,
+	/// then every tablebase is missing.
+	pub fn new(directory: Option<PathBuf>) -> Self {
+		Self {
+			directory,
+			tables: BTreeMap::new(),
+		}
+	}
+
+	fn table(&mut self, material: Material) -> Option<&Tablebase> {
+		let directory = self.directory.as_ref();
+		self.tables
+			.entry(material)
+			.or_insert_with(|| {
+				let path = directory?.join(material.file_name());
+				match Tablebase::load(&path) {
+					Ok(tablebase) => Some(tablebase),
+					Err(TablebaseFileError::IoError(error))
+						if error.kind() == std::io::ErrorKind::NotFound =>
+					{
+						None
+					}
+					Err(error) => {
+						tracing::warn!(path = %path.display(), %error, "couldn't load a tablebase");
+						None
+					}
+				}
+			})
+			.as_ref()
+	}
+
+	/// The outcome of a position, or `None` if its tablebase is missing.
+	/// Positions without any moves are lost, so they don't need a tablebase.
+	pub fn probe(&mut self, board: CheckersBitBoard) -> Option<Outcome> {
+		if PossibleMoves::moves(board).is_empty() {
+			return Some(Outcome::Loss(0));
+		}
+
+		self.table(Material::of(board))?.probe(board)
+	}
+
+	/// The materials whose tablebases were loaded
+	pub fn consulted(&self) -> Vec<Material> {
+		self.tables
+			.iter()
+			.filter(|(_, table)| table.is_some())
+			.map(|(material, _)| *material)
+			.collect()
+	}
+
+	/// The materials that were needed, but whose tablebases couldn't be loaded
+	pub fn missing(&self) -> Vec<Material> {
+		self.tables
+			.iter()
+			.filter(|(_, table)| table.is_none())
+			.map(|(material, _)| *material)
+			.collect()
+	}
+}
+
+/// The game-theoretic value of an endgame, for the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndgameValue {
+	/// The exact outcome, from the tablebases
+	Exact(Outcome),
+	/// A win or loss that was proven by a proof-number search. The distance
+	/// isn't known, because the proven line may not be the shortest.
+	Proven(ProofResult),
+	/// Neither the tablebases nor the proof search could solve the position
+	Unknown,
+}
+
+/// The value of an endgame, and the line of best play that shows it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndgameSolution {
+	pub value: EndgameValue,
+	/// The moves of both players. Drawn positions have no line.
+	pub line: Vec<FullMove>,
+}
+
+/// The move which keeps the outcome of the position. Wins are made as fast as
+/// possible, and losses are dragged out as long as possible.
+fn best_move(
+	tables: &mut TablebaseSet,
+	board: CheckersBitBoard,
+	outcome: Outcome,
+) -> Option<FullMove> {
+	let mut best: Option<(FullMove, Outcome)> = None;
+	for full_move in full_moves(board) {
+		let Some(reply) = tables.probe(full_move.result) else {
+			continue;
+		};
+
+		let better = match (outcome, reply, best.as_ref().map(|(_, reply)| *reply)) {
+			(Outcome::Win(_), Outcome::Loss(moves), Some(Outcome::Loss(best))) => moves < best,
+			(Outcome::Loss(_), Outcome::Win(moves), Some(Outcome::Win(best))) => moves > best,
+			(Outcome::Win(_), Outcome::Loss(_), None)
+			| (Outcome::Loss(_), Outcome::Win(_), None)
+			| (Outcome::Draw, Outcome::Draw, None) => true,
+			_ => false,
+		};
+		if better {
+			best = Some((full_move, reply));
+		}
+	}
+
+	best.map(|(full_move, _)| full_move)
+}
+
+/// Follows the best moves through the tablebases, until the game ends
+fn tablebase_line(
+	tables: &mut TablebaseSet,
+	board: CheckersBitBoard,
+	outcome: Outcome,
+) -> Vec<FullMove> {
+	let mut line = Vec::new();
+	let (mut position, mut outcome) = (board, outcome);
+	while line.len() < MAX_LINE_LENGTH && outcome != Outcome::Draw {
+		let Some(full_move) = best_move(tables, position, outcome) else {
+			break;
+		};
+
+		position = full_move.result;
+		line.push(full_move);
+		let Some(next) = tables.probe(position) else {
+			break;
+		};
+		outcome = next;
+	}
+
+	line
+}
+
+/// Finds the value of an endgame. The tablebases are used when the position's
+/// tablebase is available, and otherwise a proof-number search with at most
+/// `max_nodes` nodes is used.
+pub fn solve_endgame(
+	tables: &mut TablebaseSet,
+	board: CheckersBitBoard,
+	max_nodes: usize,
+) -> EndgameSolution {
+	if let Some(outcome) = tables.probe(board) {
+		return EndgameSolution {
+			value: EndgameValue::Exact(outcome),
+			line: tablebase_line(tables, board, outcome),
+		};
+	}
+
+	match proven_line(board, max_nodes) {
+		Some((result, line)) => EndgameSolution {
+			value: EndgameValue::Proven(result),
+			line: complete_line(board, &line),
+		},
+		None => EndgameSolution {
+			value: EndgameValue::Unknown,
+			line: Vec::new(),
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::notation::parse_fen;
+	use crate::retrograde::Generator;
+
+	#[test]
+	fn follows_the_tablebases() {
+		let directory = std::env::temp_dir().join(format!("ampere-endgame-{}", std::process::id()));
+		std::fs::create_dir_all(&directory).unwrap();
+		let mut generator = Generator::new();
+		for material in Material::up_to(2) {
+			let outcomes = generator.generate(material, |_| {});
+			Tablebase::new("test", "test", 0, outcomes)
+				.save(directory.join(material.file_name()))
+				.unwrap();
+		}
+
+		// a man that can capture the last king
+		let board = parse_fen("B:WK14:B9").unwrap();
+		let mut tables = TablebaseSet::new(Some(directory.clone()));
+		let solution = solve_endgame(&mut tables, board, 1000);
+		assert_eq!(solution.value, EndgameValue::Exact(Outcome::Win(1)));
+		assert_eq!(solution.line.len(), 1);
+		assert!(tables.consulted().contains(&Material::of(board)));
+		assert!(tables.missing().is_empty());
+		std::fs::remove_dir_all(directory).unwrap();
+
+		// without tablebases, the proof search is used
+		let mut tables = TablebaseSet::new(None);
+		let solution = solve_endgame(&mut tables, board, 100_000);
+		assert!(matches!(solution.value, EndgameValue::Proven(_)));
+		assert_eq!(tables.missing(), [Material::of(board)]);
+	}
+}
@@ -1,44 +1,164 @@
+use std::any::Any;
 use std::num::{NonZeroU8, NonZeroUsize};
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread::JoinHandle;
 use std::time::Duration;
 
+use arrayvec::ArrayVec;
 use model::{CheckersBitBoard, Move, PieceColor, PossibleMoves};
 use parking_lot::Mutex;
+use pdn::GameResult;
+use thiserror::Error;
 
-use crate::eval::Evaluation;
-use crate::search::search;
-use crate::{TranspositionTable, TranspositionTableRef};
+use crate::analysis::Analysis;
+use crate::bench::{self, BenchResult};
+use crate::book::{BookFileError, OpeningBook};
+use crate::eval::{EvalParams, Evaluation};
+use crate::frontend::{Frontend, SilentFrontend};
+use crate::learning::{Learning, LearningFileError, LearningTable};
+use crate::notation::{complete_line, find_move, FullMove};
+use crate::options::{
+	EngineOption, OptionError, OptionKind, OptionValue, AGGRESSIVENESS_OPTION, ALPHA_BETA,
+	BOOK_OPTION, CONTEMPT_OPTION, DEBUG_OPTION, DEFAULT_HASH_SIZE, HASH_OPTION,
+	LEARNING_FILE_OPTION, MAX_CONTEMPT, MAX_HASH_SIZE, MAX_THREADS, MEGABYTE, MONTE_CARLO,
+	PONDER_OPTION, SEARCH_ALGORITHM_OPTION, SKILL_LEVEL_OPTION, THREADS_OPTION,
+};
+use crate::pns::{self, Proof};
+use crate::search::{self, search, SearchPath};
+use crate::skill::{self, MAX_SKILL_LEVEL};
+use crate::stats::{SearchCounters, SearchStats};
+use crate::tactics::{self, Combination};
+use crate::tree::SearchTree;
+use crate::TranspositionTable;
 
 pub const ENGINE_NAME: &str = "Ampere";
 pub const ENGINE_AUTHOR: &str = "Mica White";
 pub const ENGINE_ABOUT: &str = "Ampere Checkers Bot v1.0\nCopyright Mica White";
 
-type EvalThread = JoinHandle<(Evaluation, Option<Move>)>;
+type EvalThread = JoinHandle<Option<(Evaluation, Option<Move>)>>;
 
-pub struct Engine<'a> {
+/// The moves at the root of a search
+pub type RootMoves = ArrayVec<Move, { PossibleMoves::MAX_POSSIBLE_MOVES }>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PlayMovesError {
+	#[error("Move {index} ({checker_move}) is not legal in this position")]
+	IllegalMove { index: usize, checker_move: Move },
+	#[error("Move {index} ({notation:?}) is not a legal move in this position")]
+	InvalidNotation { index: usize, notation: String },
+}
+
+pub struct Engine {
 	position: Mutex<CheckersBitBoard>,
-	transposition_table: TranspositionTable,
+	transposition_table: Mutex<Arc<TranspositionTable>>,
 
 	debug: AtomicBool,
-	frontend: &'a dyn Frontend,
+	threads: AtomicUsize,
+	ponder: AtomicBool,
+	contempt: Mutex<f32>,
+	eval_params: Mutex<EvalParams>,
+	skill_level: AtomicU8,
+	search_algorithm: Mutex<SearchAlgorithm>,
+	learning: Mutex<Option<Learning>>,
+	book: Mutex<Option<(PathBuf, Arc<OpeningBook>)>>,
+	resumed_analysis: Mutex<Option<Analysis>>,
+	frontend: Arc<dyn Frontend>,
 
 	current_thread: Mutex<Option<EvalThread>>,
-	current_task: Mutex<Option<Arc<EvaluationTask<'a>>>>,
-	pondering_task: Mutex<Option<Arc<EvaluationTask<'a>>>>,
+	current_task: Mutex<Option<Arc<EvaluationTask>>>,
+	pondering_task: Mutex<Option<Arc<EvaluationTask>>>,
+	last_task: Mutex<Option<Arc<EvaluationTask>>>,
 }
 
-pub struct EvaluationTask<'a> {
+pub struct EvaluationTask {
 	pub position: CheckersBitBoard,
-	pub transposition_table: TranspositionTableRef<'a>,
+	pub transposition_table: Arc<TranspositionTable>,
 	pub allowed_moves: Option<Arc<[Move]>>,
 	pub limits: ActualLimit,
+	/// The number of threads that search the position at once, sharing the
+	/// transposition table
+	pub threads: usize,
 	pub ponder: bool,
+	pub contempt: Evaluation,
+	pub eval_params: EvalParams,
+	pub skill_level: u8,
+	pub algorithm: SearchAlgorithm,
+	/// If this is set, then only a forced win within this many moves is
+	/// searched for
+	pub mate_in: Option<u8>,
+	pub learning: Option<Arc<LearningTable>>,
+	/// If this is set, then a book move is played instead of searching, when
+	/// the position is in the book
+	pub book: Option<Arc<OpeningBook>>,
 	pub cancel_flag: AtomicBool,
 	pub end_ponder_flag: AtomicBool,
 
 	pub nodes_explored: AtomicUsize,
+	pub counters: SearchCounters,
+	/// The positions on the way to the node being searched, used to find
+	/// repetitions
+	pub path: SearchPath,
+	/// The depth of the first iteration, which is only above zero when a
+	/// saved analysis is resumed
+	pub start_depth: u8,
+	/// The root moves and their scores from the last completed iteration,
+	/// from best to worst
+	pub root_moves: Mutex<ArrayVec<(Move, Evaluation), { PossibleMoves::MAX_POSSIBLE_MOVES }>>,
+	pub tree: Option<Mutex<SearchTree>>,
+	pub frontend: Arc<dyn Frontend>,
+}
+
+impl EvaluationTask {
+	/// A full-strength alpha-beta search of `position`, with no limits and the
+	/// default evaluation. Searches change the fields they need to.
+	pub fn new(
+		position: CheckersBitBoard,
+		transposition_table: Arc<TranspositionTable>,
+		frontend: Arc<dyn Frontend>,
+	) -> Self {
+		Self {
+			position,
+			transposition_table,
+			allowed_moves: None,
+			limits: ActualLimit::default(),
+			threads: 1,
+			ponder: false,
+			contempt: Evaluation::DRAW,
+			eval_params: EvalParams::default(),
+			skill_level: MAX_SKILL_LEVEL,
+			algorithm: SearchAlgorithm::AlphaBeta,
+			mate_in: None,
+			learning: None,
+			book: None,
+			cancel_flag: AtomicBool::new(false),
+			end_ponder_flag: AtomicBool::new(false),
+
+			nodes_explored: AtomicUsize::new(0),
+			counters: SearchCounters::default(),
+			path: SearchPath::default(),
+			start_depth: 0,
+			root_moves: Mutex::new(ArrayVec::new()),
+			tree: None,
+			frontend,
+		}
+	}
+
+	/// The evaluation of a drawn position, from the perspective of `turn`
+	pub fn draw_score(&self, turn: PieceColor) -> Evaluation {
+		if turn == self.position.turn() {
+			-self.contempt
+		} else {
+			self.contempt
+		}
+	}
+
+	pub fn stats(&self) -> SearchStats {
+		self.counters
+			.stats(self.nodes_explored.load(Ordering::Acquire))
+	}
 }
 
 #[derive(Debug, Default, Clone)]
@@ -47,18 +167,35 @@ pub struct EvaluationSettings {
 	pub ponder: bool,
 	pub clock: Clock,
 	pub search_until: SearchLimit,
+	/// How much worse than an even position a draw is for the engine. A
+	/// positive value makes the engine avoid draws, and a negative value makes
+	/// it seek them out. It's clamped to [`MAX_CONTEMPT`] in either direction,
+	/// so that a draw never looks like a forced result. If this is `None`,
+	/// then the engine's contempt is used.
+	pub contempt: Option<f32>,
+	/// The evaluation weights to use for this search. If this is `None`, then
+	/// the engine's evaluation weights are used.
+	pub eval_params: Option<EvalParams>,
+	/// If this is set, then the nodes explored by the search are recorded,
+	/// up to the given number of nodes. The tree can be retrieved using
+	/// [`Engine::search_tree`].
+	pub record_tree: Option<usize>,
 }
 
 impl EvaluationSettings {
 	fn get_limits(&self, this_color: PieceColor) -> ActualLimit {
 		match &self.search_until {
-			SearchLimit::Infinite => ActualLimit::default(),
+			SearchLimit::Infinite | SearchLimit::MateIn(_) => ActualLimit::default(),
 			SearchLimit::Limited(limit) => *limit,
-			SearchLimit::Auto => ActualLimit {
-				nodes: None,
-				depth: NonZeroU8::new(30),
-				time: Some(self.clock.recommended_time(this_color)),
-			},
+			SearchLimit::Auto => {
+				let time = self.clock.recommended_time(this_color);
+				tracing::debug!(?time, "allotted time for the search");
+				ActualLimit {
+					nodes: None,
+					depth: NonZeroU8::new(30),
+					time: Some(time),
+				}
+			}
 		}
 	}
 }
@@ -118,6 +255,19 @@ pub enum SearchLimit {
 	Auto,
 	Infinite,
 	Limited(ActualLimit),
+	/// Only look for a forced win, where the engine completes at most this
+	/// many moves. Lines that don't win are pruned.
+	MateIn(u8),
+}
+
+/// The algorithm used to search for the best move
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SearchAlgorithm {
+	/// An iterative deepening alpha-beta search
+	#[default]
+	AlphaBeta,
+	/// A Monte-Carlo tree search, using the static evaluation at the leaves
+	MonteCarlo,
 }
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -128,31 +278,342 @@ pub struct ActualLimit {
 	pub time: Option<Duration>,
 }
 
-pub trait Frontend: Sync {
-	fn debug(&self, msg: &str);
-
-	fn report_best_move(&self, best_move: Move);
-}
+impl Engine {
+	/// Creates an engine with a transposition table that uses at most
+	/// `transposition_table_size` bytes
+	pub fn new(transposition_table_size: usize, frontend: Arc<dyn Frontend>) -> Self {
+		Self::with_transposition_table(TranspositionTable::new(transposition_table_size), frontend)
+	}
 
-impl<'a> Engine<'a> {
-	pub fn new(transposition_table_size: usize, frontend: &'a dyn Frontend) -> Self {
+	/// Creates an engine that uses the given transposition table. Use
+	/// [`TranspositionTable::with_megabytes`] or
+	/// [`TranspositionTable::with_memory_fraction`] to size it.
+	pub fn with_transposition_table(
+		transposition_table: TranspositionTable,
+		frontend: Arc<dyn Frontend>,
+	) -> Self {
 		Self {
 			position: Mutex::new(CheckersBitBoard::starting_position()),
-			transposition_table: TranspositionTable::new(transposition_table_size),
+			transposition_table: Mutex::new(Arc::new(transposition_table)),
 
 			debug: AtomicBool::new(false),
+			threads: AtomicUsize::new(1),
+			ponder: AtomicBool::new(false),
+			contempt: Mutex::new(0.0),
+			eval_params: Mutex::new(EvalParams::default()),
+			skill_level: AtomicU8::new(MAX_SKILL_LEVEL),
+			search_algorithm: Mutex::new(SearchAlgorithm::default()),
+			learning: Mutex::new(None),
+			book: Mutex::new(None),
+			resumed_analysis: Mutex::new(None),
 			frontend,
 
 			current_thread: Mutex::new(None),
 			current_task: Mutex::new(None),
 			pondering_task: Mutex::new(None),
+			last_task: Mutex::new(None),
 		}
 	}
 
+	/// Searches a fixed set of positions to a fixed depth, and returns the
+	/// total number of nodes searched and how long it took
+	pub fn bench() -> BenchResult {
+		bench::run()
+	}
+
 	pub fn set_debug(&self, debug: bool) {
 		self.debug.store(debug, Ordering::Release);
 	}
 
+	/// The number of threads that each alpha-beta search uses
+	pub fn threads(&self) -> usize {
+		self.threads.load(Ordering::Acquire)
+	}
+
+	/// Sets the number of threads that each alpha-beta search uses. The extra
+	/// threads search the same position, filling the shared transposition
+	/// table, so that the main thread reaches its depth sooner.
+	pub fn set_threads(&self, threads: usize) {
+		self.threads
+			.store(threads.clamp(1, MAX_THREADS as usize), Ordering::Release);
+	}
+
+	/// Whether background searches ponder after reporting their best move
+	pub fn ponder(&self) -> bool {
+		self.ponder.load(Ordering::Acquire)
+	}
+
+	/// Sets whether searches started with [`Engine::start_evaluation`] keep
+	/// searching the expected reply after they report their best move, even
+	/// if their settings don't ask them to ponder. Pondering stops when the
+	/// next search starts.
+	pub fn set_ponder(&self, ponder: bool) {
+		self.ponder.store(ponder, Ordering::Release);
+	}
+
+	pub fn contempt(&self) -> f32 {
+		*self.contempt.lock()
+	}
+
+	/// Sets the contempt used by searches that don't specify their own. It's
+	/// clamped to [`MAX_CONTEMPT`] in either direction.
+	pub fn set_contempt(&self, contempt: f32) {
+		*self.contempt.lock() = contempt.clamp(-MAX_CONTEMPT, MAX_CONTEMPT);
+	}
+
+	/// The size of the transposition table, in bytes
+	pub fn transposition_table_size(&self) -> usize {
+		self.transposition_table.lock().size()
+	}
+
+	/// Replaces the transposition table with an empty one of the given size,
+	/// in bytes. A search that is already running keeps using the old table
+	/// until it finishes.
+	pub fn resize_transposition_table(&self, size: usize) {
+		*self.transposition_table.lock() = Arc::new(TranspositionTable::new(size));
+	}
+
+	pub fn eval_params(&self) -> EvalParams {
+		*self.eval_params.lock()
+	}
+
+	pub fn set_eval_params(&self, params: EvalParams) {
+		*self.eval_params.lock() = params;
+	}
+
+	pub fn skill_level(&self) -> u8 {
+		self.skill_level.load(Ordering::Acquire)
+	}
+
+	/// Sets how strongly the engine plays, from zero to [`MAX_SKILL_LEVEL`].
+	/// Below the maximum, the engine searches less deeply, and sometimes
+	/// chooses weaker moves.
+	pub fn set_skill_level(&self, skill_level: u8) {
+		self.skill_level
+			.store(skill_level.min(MAX_SKILL_LEVEL), Ordering::Release);
+	}
+
+	pub fn search_algorithm(&self) -> SearchAlgorithm {
+		*self.search_algorithm.lock()
+	}
+
+	pub fn set_search_algorithm(&self, algorithm: SearchAlgorithm) {
+		*self.search_algorithm.lock() = algorithm;
+	}
+
+	/// Loads a learning file, where the engine records the results of its
+	/// games. Lines that the engine has repeatedly lost are avoided in future
+	/// games. If the file doesn't exist, it is created at the end of the game.
+	pub fn enable_learning(&self, path: impl Into<PathBuf>) -> Result<(), LearningFileError> {
+		*self.learning.lock() = Some(Learning::load(path.into())?);
+		Ok(())
+	}
+
+	pub fn disable_learning(&self) {
+		*self.learning.lock() = None;
+	}
+
+	/// Loads an opening book. Searches which aren't infinite play a book move
+	/// instead of searching, when the position is in the book.
+	pub fn enable_book(&self, path: impl Into<PathBuf>) -> Result<(), BookFileError> {
+		let path = path.into();
+		let book = OpeningBook::load(&path)?;
+		*self.book.lock() = Some((path, Arc::new(book)));
+		Ok(())
+	}
+
+	pub fn disable_book(&self) {
+		*self.book.lock() = None;
+	}
+
+	/// The book used by a search which runs until `search_until`
+	fn search_book(&self, search_until: &SearchLimit) -> Option<Arc<OpeningBook>> {
+		if matches!(search_until, SearchLimit::Infinite) {
+			return None;
+		}
+
+		self.book.lock().as_ref().map(|(_, book)| book.clone())
+	}
+
+	/// Records the result of the current game in the learning file, if
+	/// learning is enabled
+	pub fn finish_game(&self, result: GameResult) -> std::io::Result<()> {
+		match self.learning.lock().as_mut() {
+			Some(learning) => learning.finish_game(result),
+			None => Ok(()),
+		}
+	}
+
+	/// Lists every option that can be changed with [`Engine::set_option`],
+	/// along with its current value
+	pub fn options(&self) -> Vec<EngineOption> {
+		let learning_file = self
+			.learning
+			.lock()
+			.as_ref()
+			.map(|learning| learning.path.display().to_string())
+			.unwrap_or_default();
+		let book_file = self
+			.book
+			.lock()
+			.as_ref()
+			.map(|(path, _)| path.display().to_string())
+			.unwrap_or_default();
+
+		vec![
+			EngineOption {
+				name: HASH_OPTION,
+				kind: OptionKind::Spin {
+					min: 1,
+					max: MAX_HASH_SIZE,
+				},
+				default: OptionValue::Spin(DEFAULT_HASH_SIZE),
+				value: OptionValue::Spin(self.transposition_table_size().div_ceil(MEGABYTE) as i64),
+			},
+			EngineOption {
+				name: THREADS_OPTION,
+				kind: OptionKind::Spin {
+					min: 1,
+					max: MAX_THREADS,
+				},
+				default: OptionValue::Spin(1),
+				value: OptionValue::Spin(self.threads() as i64),
+			},
+			EngineOption {
+				name: PONDER_OPTION,
+				kind: OptionKind::Check,
+				default: OptionValue::Check(false),
+				value: OptionValue::Check(self.ponder()),
+			},
+			EngineOption {
+				name: CONTEMPT_OPTION,
+				kind: OptionKind::Float {
+					min: -MAX_CONTEMPT,
+					max: MAX_CONTEMPT,
+				},
+				default: OptionValue::Float(0.0),
+				value: OptionValue::Float(self.contempt()),
+			},
+			EngineOption {
+				name: SKILL_LEVEL_OPTION,
+				kind: OptionKind::Spin {
+					min: 0,
+					max: MAX_SKILL_LEVEL as i64,
+				},
+				default: OptionValue::Spin(MAX_SKILL_LEVEL as i64),
+				value: OptionValue::Spin(self.skill_level() as i64),
+			},
+			EngineOption {
+				name: AGGRESSIVENESS_OPTION,
+				kind: OptionKind::Float {
+					min: -1.0,
+					max: 1.0,
+				},
+				default: OptionValue::Float(EvalParams::default().aggressiveness),
+				value: OptionValue::Float(self.eval_params().aggressiveness),
+			},
+			EngineOption {
+				name: LEARNING_FILE_OPTION,
+				kind: OptionKind::String,
+				default: OptionValue::String(String::new()),
+				value: OptionValue::String(learning_file),
+			},
+			EngineOption {
+				name: BOOK_OPTION,
+				kind: OptionKind::String,
+				default: OptionValue::String(String::new()),
+				value: OptionValue::String(book_file),
+			},
+			EngineOption {
+				name: SEARCH_ALGORITHM_OPTION,
+				kind: OptionKind::Combo {
+					values: &[ALPHA_BETA, MONTE_CARLO],
+				},
+				default: OptionValue::String(ALPHA_BETA.to_string()),
+				value: OptionValue::String(
+					match self.search_algorithm() {
+						SearchAlgorithm::AlphaBeta => ALPHA_BETA,
+						SearchAlgorithm::MonteCarlo => MONTE_CARLO,
+					}
+					.to_string(),
+				),
+			},
+			EngineOption {
+				name: DEBUG_OPTION,
+				kind: OptionKind::Check,
+				default: OptionValue::Check(false),
+				value: OptionValue::Check(self.debug.load(Ordering::Acquire)),
+			},
+		]
+	}
+
+	/// Sets an option by name. The name is case-insensitive, and the value is
+	/// parsed according to the option's type.
+	pub fn set_option(&self, name: &str, value: &str) -> Result<(), OptionError> {
+		let option = self
+			.options()
+			.into_iter()
+			.find(|option| option.name.eq_ignore_ascii_case(name.trim()))
+			.ok_or_else(|| OptionError::UnknownOption(name.to_string()))?;
+
+		match (option.name, option.parse(value)?) {
+			(HASH_OPTION, OptionValue::Spin(size)) => {
+				self.resize_transposition_table(size as usize * MEGABYTE)
+			}
+			(THREADS_OPTION, OptionValue::Spin(threads)) => self.set_threads(threads as usize),
+			(PONDER_OPTION, OptionValue::Check(ponder)) => self.set_ponder(ponder),
+			(CONTEMPT_OPTION, OptionValue::Float(contempt)) => self.set_contempt(contempt),
+			(SKILL_LEVEL_OPTION, OptionValue::Spin(level)) => self.set_skill_level(level as u8),
+			(AGGRESSIVENESS_OPTION, OptionValue::Float(aggressiveness)) => {
+				self.eval_params.lock().aggressiveness = aggressiveness
+			}
+			(LEARNING_FILE_OPTION, OptionValue::String(path)) if path.is_empty() => {
+				self.disable_learning()
+			}
+			(LEARNING_FILE_OPTION, OptionValue::String(path)) => self.enable_learning(path)?,
+			(BOOK_OPTION, OptionValue::String(path)) if path.is_empty() => self.disable_book(),
+			(BOOK_OPTION, OptionValue::String(path)) => self.enable_book(path)?,
+			(SEARCH_ALGORITHM_OPTION, OptionValue::String(algorithm)) => {
+				self.set_search_algorithm(if algorithm == MONTE_CARLO {
+					SearchAlgorithm::MonteCarlo
+				} else {
+					SearchAlgorithm::AlphaBeta
+				})
+			}
+			(DEBUG_OPTION, OptionValue::Check(debug)) => self.set_debug(debug),
+			_ => unreachable!("the value is parsed using the option's kind"),
+		}
+
+		Ok(())
+	}
+
+	fn remember_move(&self, position: CheckersBitBoard, best_move: Move) {
+		if let Some(learning) = self.learning.lock().as_mut() {
+			let child = unsafe { best_move.apply_to(position) };
+			learning.game.push((child, position.turn()));
+		}
+	}
+
+	fn learning_table(&self) -> Option<Arc<LearningTable>> {
+		self.learning
+			.lock()
+			.as_ref()
+			.map(|learning| learning.table.clone())
+	}
+
+	/// Tries to prove whether the current position is a win or a loss for the
+	/// player to move, using a proof-number search of about `max_nodes`
+	/// nodes
+	pub fn solve(&self, max_nodes: usize) -> Proof {
+		pns::prove(self.current_position(), max_nodes)
+	}
+
+	/// Looks for a combination in the current position, searching only
+	/// forcing moves up to `max_plies` deep
+	pub fn find_combination(&self, max_plies: u8) -> Option<Combination> {
+		tactics::find_combination(self.current_position(), max_plies)
+	}
+
 	pub fn is_legal_move(&self, checker_move: Move) -> bool {
 		let position = self.position.lock();
 		PossibleMoves::moves(*position).contains(checker_move)
@@ -183,72 +644,119 @@ impl<'a> Engine<'a> {
 		}
 	}
 
-	pub fn evaluate(
-		&self,
-		cancel: Option<&AtomicBool>,
-		settings: EvaluationSettings,
-	) -> (Evaluation, Option<Move>) {
-		// finish the pondering thread
-		let mut pondering_task = self.pondering_task.lock();
-		if let Some(task) = pondering_task.take() {
-			task.end_ponder_flag.store(true, Ordering::Release);
+	/// Sets the position to `base`, and then plays each of the moves in order.
+	/// If any move is illegal, then the position isn't changed at all.
+	pub fn play_moves(&self, base: CheckersBitBoard, moves: &[Move]) -> Result<(), PlayMovesError> {
+		let mut position = base;
+		for (index, &checker_move) in moves.iter().enumerate() {
+			if !PossibleMoves::moves(position).contains(checker_move) {
+				return Err(PlayMovesError::IllegalMove {
+					index,
+					checker_move,
+				});
+			}
+
+			position = unsafe { checker_move.apply_to(position) };
 		}
 
-		let position = *self.position.lock();
-		let transposition_table = self.transposition_table.get_ref();
-		let limits = settings.get_limits(position.turn());
-		let allowed_moves = settings.restrict_moves;
-		let cancel_flag = AtomicBool::new(false);
-		let end_ponder_flag = AtomicBool::new(false);
+		self.set_position(position);
+		Ok(())
+	}
 
-		let nodes_explored = AtomicUsize::new(0);
+	/// The same as [`Engine::play_moves`], but the moves are written out,
+	/// separated by whitespace. A multi-jump may be written with every square,
+	/// such as `9x18x27`, or with only its start and end, such as `9x27`.
+	pub fn play_notation(&self, base: CheckersBitBoard, moves: &str) -> Result<(), PlayMovesError> {
+		let mut position = base;
+		for (index, notation) in moves.split_whitespace().enumerate() {
+			let full_move =
+				find_move(position, notation).ok_or_else(|| PlayMovesError::InvalidNotation {
+					index,
+					notation: notation.to_string(),
+				})?;
+			position = full_move.result;
+		}
 
-		let task = EvaluationTask {
-			position,
-			transposition_table,
-			allowed_moves,
-			limits,
-			ponder: false,
-			cancel_flag,
-			end_ponder_flag,
+		self.set_position(position);
+		Ok(())
+	}
 
-			nodes_explored,
+	/// Builds the task for searching the current position with `settings`.
+	/// If a saved analysis of the position was resumed, then the search
+	/// continues from it.
+	fn search_task(&self, settings: EvaluationSettings) -> EvaluationTask {
+		let position = *self.position.lock();
+		let skill_level = self.skill_level();
+		let mate_in = match settings.search_until {
+			SearchLimit::MateIn(moves) => Some(moves),
+			_ => None,
 		};
+		let (start_depth, root_moves) =
+			self.take_resumed_analysis(position, settings.restrict_moves.as_deref());
 
-		search(Arc::new(task), self.frontend, cancel)
+		EvaluationTask {
+			allowed_moves: settings.restrict_moves.clone(),
+			limits: skill::limit_search(settings.get_limits(position.turn()), skill_level),
+			threads: self.threads(),
+			ponder: settings.ponder || self.ponder(),
+			contempt: contempt_score(settings.contempt.unwrap_or_else(|| self.contempt())),
+			eval_params: settings.eval_params.unwrap_or_else(|| self.eval_params()),
+			skill_level,
+			algorithm: self.search_algorithm(),
+			mate_in,
+			learning: self.learning_table(),
+			book: self.search_book(&settings.search_until),
+			start_depth,
+			root_moves: Mutex::new(root_moves),
+			tree: settings
+				.record_tree
+				.map(|budget| Mutex::new(SearchTree::new(budget))),
+			..EvaluationTask::new(
+				position,
+				self.transposition_table.lock().clone(),
+				self.frontend.clone(),
+			)
+		}
 	}
 
-	pub fn start_evaluation(&'static self, settings: EvaluationSettings) {
-		// finish the pondering thread
-		let mut pondering_task = self.pondering_task.lock();
-		if let Some(task) = pondering_task.take() {
-			task.end_ponder_flag.store(true, Ordering::Release);
-		}
+	pub fn evaluate(
+		&self,
+		cancel: Option<&AtomicBool>,
+		settings: EvaluationSettings,
+	) -> (Evaluation, Option<Move>) {
+		self.end_pondering();
 
-		let position = *self.position.lock();
-		let transposition_table = self.transposition_table.get_ref();
-		let limits = settings.get_limits(position.turn());
-		let allowed_moves = settings.restrict_moves;
-		let ponder = settings.ponder;
-		let cancel_flag = AtomicBool::new(false);
-		let end_ponder_flag = AtomicBool::new(false);
+		// the search would never finish if it pondered
+		let task = EvaluationTask {
+			ponder: false,
+			..self.search_task(settings)
+		};
+		let position = task.position;
+		let task = Arc::new(task);
+		*self.last_task.lock() = Some(task.clone());
 
-		let nodes_explored = AtomicUsize::new(0);
+		let result = search(task, cancel);
+		if let Some(best_move) = result.1 {
+			self.remember_move(position, best_move);
+		}
 
-		let task = EvaluationTask {
-			position,
-			transposition_table,
-			allowed_moves,
-			limits,
-			ponder,
-			cancel_flag,
-			end_ponder_flag,
+		result
+	}
 
-			nodes_explored,
-		};
+	/// Starts searching the current position in a background thread. The
+	/// best move is reported to the frontend when the search finishes. A
+	/// search that is already running is stopped first, so that two searches
+	/// never report to the frontend at once.
+	pub fn start_evaluation(self: &Arc<Self>, settings: EvaluationSettings) {
+		self.end_pondering();
+		self.stop_evaluation();
 
+		let task = self.search_task(settings);
+		let position = task.position;
+		let ponder = task.ponder;
 		let task = Arc::new(task);
 		let task_ref = task.clone();
+		*self.last_task.lock() = Some(task.clone());
 		let mut task_ptr = self.current_task.lock();
 		*task_ptr = Some(task);
 
@@ -257,17 +765,535 @@ impl<'a> Engine<'a> {
 			*pondering_task = Some(task_ref.clone());
 		}
 
-		let thread = std::thread::spawn(move || search(task_ref, self.frontend, None));
+		let engine = Arc::clone(self);
+		let thread = std::thread::spawn(move || {
+			let task = task_ref.clone();
+			match panic::catch_unwind(AssertUnwindSafe(|| search(task_ref, None))) {
+				Ok(result) => {
+					if let Some(best_move) = result.1 {
+						engine.remember_move(position, best_move);
+					}
+
+					Some(result)
+				}
+				Err(payload) => {
+					engine.forget_task(&task);
+					engine.frontend.on_error(&format!(
+						"The search panicked: {}",
+						panic_message(&*payload)
+					));
+					None
+				}
+			}
+		});
 		let mut thread_ptr = self.current_thread.lock();
 		*thread_ptr = Some(thread);
 	}
 
-	pub fn stop_evaluation(&self) -> Option<()> {
+	/// Fills the transposition table by searching the positions along an
+	/// expected line from the current position, to a shallow `depth`. This
+	/// makes the next search reach its depth sooner when there was no
+	/// pondering. The line stops at the first illegal move, and the number of
+	/// positions searched is returned.
+	pub fn warm_start(&self, line: &[Move], depth: u8) -> usize {
+		let task = EvaluationTask {
+			contempt: contempt_score(self.contempt()),
+			eval_params: self.eval_params(),
+			..EvaluationTask::new(
+				self.current_position(),
+				self.transposition_table.lock().clone(),
+				Arc::new(SilentFrontend),
+			)
+		};
+
+		search::warm_up(&task, line, depth)
+	}
+
+	/// The expected line from the current position, following the best moves
+	/// in the transposition table. The line stops after `max_len` moves, or
+	/// when a position isn't in the table.
+	pub fn principal_variation(&self, max_len: usize) -> Vec<Move> {
+		let table = self.transposition_table.lock().clone();
+		let table = table.get_ref();
+		let mut position = self.current_position();
+		let mut line = Vec::new();
+		while line.len() < max_len {
+			let Some((_, best_move, _)) = table.get_entry(position) else {
+				break;
+			};
+
+			if !PossibleMoves::moves(position).contains(best_move) {
+				break;
+			}
+
+			line.push(best_move);
+			position = unsafe { best_move.apply_to(position) };
+		}
+
+		line
+	}
+
+	/// The expected line from the current position, starting with
+	/// `best_move`. If the principal variation starts with a different move,
+	/// then the line is only the best move.
+	pub fn expected_line(&self, best_move: Option<Move>) -> Vec<Move> {
+		/// The longest principal variation that is followed
+		const MAX_PV_LENGTH: usize = 32;
+
+		let pv = self.principal_variation(MAX_PV_LENGTH);
+		match best_move {
+			Some(best_move) if pv.first() != Some(&best_move) => vec![best_move],
+			_ => pv,
+		}
+	}
+
+	/// The full move that starts with `best_move` from `position`, with the
+	/// rest of a multi-jump taken from the principal variation.
+	pub fn best_full_move(&self, position: CheckersBitBoard, best_move: Move) -> Option<FullMove> {
+		complete_line(position, &self.expected_line(Some(best_move)))
+			.into_iter()
+			.next()
+	}
+
+	/// The state of the most recent search, which can be saved and resumed
+	/// later with [`Engine::resume_analysis`]. If `include_table` is true,
+	/// then the transposition table is included, which makes resuming faster
+	/// but the saved file much larger. Returns `None` if no iteration of the
+	/// alpha-beta search has finished.
+	pub fn analysis(&self, include_table: bool) -> Option<Analysis> {
+		let task = self.last_task.lock().clone()?;
+		let iterations = task.counters.stats(0).iteration_nodes.len();
+		let depth = (task.start_depth as usize + iterations).checked_sub(1)?;
+		let root_moves = task.root_moves.lock().to_vec();
+		if root_moves.is_empty() {
+			return None;
+		}
+
+		Some(Analysis {
+			position: task.position,
+			depth: depth as u8,
+			root_moves,
+			transposition_table: include_table.then(|| task.transposition_table.clone()),
+		})
+	}
+
+	/// Sets the position to the one in a saved analysis. If the analysis
+	/// included a transposition table, then it replaces the current table.
+	/// The next search of this position continues from the next depth, with
+	/// the root moves searched in the saved order.
+	pub fn resume_analysis(&self, mut analysis: Analysis) {
+		self.set_position(analysis.position);
+		if let Some(table) = analysis.transposition_table.take() {
+			*self.transposition_table.lock() = table;
+		}
+
+		*self.resumed_analysis.lock() = Some(analysis);
+	}
+
+	/// Takes the resumed analysis, if it's for this position. Returns the
+	/// depth to start searching from, and the root moves in the order they
+	/// should be searched.
+	fn take_resumed_analysis(
+		&self,
+		position: CheckersBitBoard,
+		allowed_moves: Option<&[Move]>,
+	) -> (
+		u8,
+		ArrayVec<(Move, Evaluation), { PossibleMoves::MAX_POSSIBLE_MOVES }>,
+	) {
+		let Some(analysis) = self
+			.resumed_analysis
+			.lock()
+			.take()
+			.filter(|analysis| analysis.position == position)
+		else {
+			return (0, ArrayVec::new());
+		};
+
+		let root_moves = analysis
+			.root_moves
+			.into_iter()
+			.filter(|(checker_move, _)| {
+				allowed_moves.is_none_or(|moves| moves.contains(checker_move))
+			})
+			.take(PossibleMoves::MAX_POSSIBLE_MOVES)
+			.collect();
+
+		(analysis.depth.saturating_add(1), root_moves)
+	}
+
+	/// Statistics about the most recent search. If the search is still
+	/// running, then these are the statistics so far.
+	pub fn search_stats(&self) -> Option<SearchStats> {
+		self.last_task.lock().as_ref().map(|task| task.stats())
+	}
+
+	/// The nodes explored by the most recent search, if it was asked to
+	/// record them
+	pub fn search_tree(&self) -> Option<SearchTree> {
+		let last_task = self.last_task.lock();
+		let tree = last_task.as_ref()?.tree.as_ref()?.lock().clone();
+		Some(tree)
+	}
+
+	/// Tells the pondering thread, if there is one, to finish
+	fn end_pondering(&self) {
+		if let Some(task) = self.pondering_task.lock().take() {
+			task.end_ponder_flag.store(true, Ordering::Release);
+		}
+	}
+
+	/// Clears the state of a search thread that panicked, so that the engine
+	/// can start a new search
+	fn forget_task(&self, task: &Arc<EvaluationTask>) {
+		for slot in [&self.current_task, &self.pondering_task] {
+			let mut slot = slot.lock();
+			if slot
+				.as_ref()
+				.is_some_and(|current| Arc::ptr_eq(current, task))
+			{
+				*slot = None;
+			}
+		}
+
+		let mut thread = self.current_thread.lock();
+		if thread
+			.as_ref()
+			.is_some_and(|thread| thread.thread().id() == std::thread::current().id())
+		{
+			*thread = None;
+		}
+	}
+
+	/// Stops the current evaluation, and returns the best evaluation and move
+	/// that it found. Returns `None` if there was no evaluation running, or if
+	/// the search thread panicked.
+	pub fn stop_evaluation(&self) -> Option<(Evaluation, Option<Move>)> {
 		let current_task = self.current_task.lock().take()?;
+		tracing::debug!("stopping the search");
 		current_task.cancel_flag.store(true, Ordering::Release);
+		current_task.end_ponder_flag.store(true, Ordering::Release);
+
+		// the lock is released first, since a panicking search locks it again
+		let thread = self.current_thread.lock().take()?;
+		thread.join().ok().flatten()
+	}
+}
+
+/// The score of a draw for the engine, with `contempt` clamped so that it's
+/// never a forced result
+fn contempt_score(contempt: f32) -> Evaluation {
+	Evaluation::new(contempt.clamp(-MAX_CONTEMPT, MAX_CONTEMPT))
+}
+
+/// Gets the message from a panic's payload
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+	if let Some(message) = payload.downcast_ref::<&str>() {
+		message
+	} else if let Some(message) = payload.downcast_ref::<String>() {
+		message
+	} else {
+		"unknown error"
+	}
+}
 
-		let _ = self.current_thread.lock().take()?.join();
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{parse_fen, MoveDirection, TextFrontend};
 
-		Some(())
+	#[derive(Default)]
+	struct PanickingFrontend {
+		errors: AtomicUsize,
+		panicking: AtomicBool,
+	}
+
+	impl Frontend for PanickingFrontend {
+		fn on_current_move(&self, _current_move: Move, _index: usize) {
+			self.panicking.store(true, Ordering::Release);
+			std::thread::sleep(Duration::from_millis(50));
+			panic!("the frontend failed");
+		}
+
+		fn on_string(&self, _msg: &str) {}
+
+		fn on_error(&self, _msg: &str) {
+			self.errors.fetch_add(1, Ordering::AcqRel);
+		}
+
+		fn report_best_move(&self, _best_move: Move) {}
+	}
+
+	#[test]
+	fn search_panic_is_reported() {
+		let frontend = Arc::new(PanickingFrontend::default());
+		let engine = Arc::new(Engine::new(1 << 16, frontend.clone()));
+		engine.start_evaluation(EvaluationSettings::default());
+
+		for _ in 0..500 {
+			if frontend.errors.load(Ordering::Acquire) != 0 {
+				break;
+			}
+			std::thread::sleep(Duration::from_millis(10));
+		}
+
+		assert_eq!(frontend.errors.load(Ordering::Acquire), 1);
+		assert!(engine.stop_evaluation().is_none());
+	}
+
+	#[test]
+	fn stopping_during_a_panic() {
+		let frontend = Arc::new(PanickingFrontend::default());
+		let engine = Arc::new(Engine::new(1 << 16, frontend.clone()));
+		engine.start_evaluation(EvaluationSettings::default());
+		while !frontend.panicking.load(Ordering::Acquire) {
+			std::thread::yield_now();
+		}
+
+		assert!(engine.stop_evaluation().is_none());
+		assert_eq!(frontend.errors.load(Ordering::Acquire), 1);
+	}
+
+	#[derive(Default)]
+	struct CountingFrontend {
+		best_moves: AtomicUsize,
+	}
+
+	impl Frontend for CountingFrontend {
+		fn on_string(&self, _msg: &str) {}
+
+		fn report_best_move(&self, _best_move: Move) {
+			self.best_moves.fetch_add(1, Ordering::AcqRel);
+		}
+	}
+
+	#[test]
+	fn starting_a_search_stops_the_last_one() {
+		let frontend = Arc::new(CountingFrontend::default());
+		let engine = Arc::new(Engine::new(1 << 16, frontend.clone()));
+		let settings = || EvaluationSettings {
+			search_until: SearchLimit::Infinite,
+			..EvaluationSettings::default()
+		};
+
+		engine.start_evaluation(settings());
+		engine.start_evaluation(settings());
+		assert_eq!(frontend.best_moves.load(Ordering::Acquire), 1);
+		engine.stop_evaluation();
+		assert_eq!(frontend.best_moves.load(Ordering::Acquire), 2);
+	}
+
+	#[test]
+	fn illegal_moves_leave_the_position_alone() {
+		let engine = Engine::new(1 << 16, Arc::new(PanickingFrontend::default()));
+		let start = CheckersBitBoard::starting_position();
+		engine.play_notation(start, "11-15").unwrap();
+		let position = engine.current_position();
+		assert_ne!(position, start);
+
+		let error = engine.play_notation(start, "11-15 1-2").unwrap_err();
+		assert!(matches!(
+			error,
+			PlayMovesError::InvalidNotation { index: 1, .. }
+		));
+		assert_eq!(engine.current_position(), position);
+	}
+
+	#[test]
+	fn multi_jumps_are_written_completely() {
+		let engine = Engine::new(1 << 16, Arc::new(PanickingFrontend::default()));
+		let start = parse_fen("W:W27:B23,14,1").unwrap();
+		let after = parse_fen("B:W9:B1").unwrap();
+		engine.play_notation(start, "27x18x9").unwrap();
+		assert_eq!(engine.current_position(), after);
+		engine.play_notation(start, "27x9").unwrap();
+		assert_eq!(engine.current_position(), after);
+		assert!(engine.play_notation(start, "27x18").is_err());
+
+		// the king can go around the loop either way
+		let start = parse_fen("W:WK10:B14,15,22,23").unwrap();
+		assert!(engine.play_notation(start, "10x10").is_err());
+		engine.play_notation(start, "10x17x26x19x10").unwrap();
+		assert_eq!(engine.current_position(), parse_fen("B:WK10:B").unwrap());
+	}
+
+	#[test]
+	fn resumed_analysis_starts_deeper() {
+		let settings = |depth| EvaluationSettings {
+			search_until: SearchLimit::Limited(ActualLimit {
+				nodes: None,
+				depth: NonZeroU8::new(depth),
+				time: None,
+			}),
+			..EvaluationSettings::default()
+		};
+
+		let engine = Engine::new(1 << 16, Arc::new(TextFrontend::new(|_| {})));
+		engine.set_skill_level(MAX_SKILL_LEVEL);
+		engine.evaluate(None, settings(3));
+		let analysis = engine.analysis(false).unwrap();
+		assert_eq!(analysis.depth, 3);
+
+		let engine = Engine::new(1 << 16, Arc::new(TextFrontend::new(|_| {})));
+		engine.resume_analysis(analysis);
+		engine.evaluate(None, settings(4));
+		assert_eq!(engine.search_stats().unwrap().iteration_nodes.len(), 1);
+	}
+
+	#[test]
+	fn warm_start_fills_the_table() {
+		let engine = Engine::new(1 << 16, Arc::new(PanickingFrontend::default()));
+		let start = engine.current_position();
+		let first_move = PossibleMoves::moves(start).into_iter().next().unwrap();
+		let illegal_move = Move::new(31, MoveDirection::ForwardLeft, true);
+
+		assert_eq!(engine.warm_start(&[first_move, illegal_move], 3), 2);
+		let table = engine.transposition_table.lock().clone();
+		let after_first = unsafe { first_move.apply_to(start) };
+		for position in [start, after_first] {
+			let (_, best_move, _) = table.get_ref().get_entry(position).unwrap();
+			assert!(PossibleMoves::moves(position).contains(best_move));
+		}
+		assert!(table.get_ref().get(start, 3).is_some());
+	}
+
+	#[test]
+	fn stopped_resumed_search_plays_the_saved_move() {
+		let engine = Engine::new(1 << 16, Arc::new(TextFrontend::new(|_| {})));
+		engine.set_skill_level(MAX_SKILL_LEVEL);
+		engine.evaluate(
+			None,
+			EvaluationSettings {
+				search_until: SearchLimit::Limited(ActualLimit {
+					nodes: None,
+					depth: NonZeroU8::new(3),
+					time: None,
+				}),
+				..EvaluationSettings::default()
+			},
+		);
+		let analysis = engine.analysis(false).unwrap();
+		let saved_move = analysis.root_moves[0].0;
+
+		let engine = Engine::new(1 << 16, Arc::new(TextFrontend::new(|_| {})));
+		engine.set_skill_level(MAX_SKILL_LEVEL);
+		engine.resume_analysis(analysis);
+		let stopped = AtomicBool::new(true);
+		let settings = EvaluationSettings {
+			search_until: SearchLimit::Infinite,
+			..EvaluationSettings::default()
+		};
+
+		let (_, best_move) = engine.evaluate(Some(&stopped), settings);
+		assert_eq!(best_move, Some(saved_move));
+	}
+
+	#[test]
+	fn stopped_search_still_finds_a_move() {
+		let engine = Engine::new(1 << 16, Arc::new(TextFrontend::new(|_| {})));
+		engine.set_skill_level(MAX_SKILL_LEVEL);
+		let stopped = AtomicBool::new(true);
+		let settings = EvaluationSettings {
+			search_until: SearchLimit::Infinite,
+			..EvaluationSettings::default()
+		};
+
+		let (_, best_move) = engine.evaluate(Some(&stopped), settings);
+		let best_move = best_move.unwrap();
+		assert!(PossibleMoves::moves(engine.current_position()).contains(best_move));
+	}
+
+	#[test]
+	fn contempt_is_never_a_forced_result() {
+		let engine = Engine::new(1 << 16, Arc::new(TextFrontend::new(|_| {})));
+		assert!(engine.set_option("Contempt", "1").is_err());
+		engine.set_contempt(1.0);
+		assert_eq!(engine.contempt(), MAX_CONTEMPT);
+		assert!(!contempt_score(1.0).is_force_sequence());
+		assert!(!contempt_score(-1.0).is_force_sequence());
+	}
+
+	#[test]
+	fn helper_threads_search_with_the_main_thread() {
+		let engine = Engine::new(1 << 20, Arc::new(TextFrontend::new(|_| {})));
+		assert!(matches!(
+			engine.set_option("Threads", "0"),
+			Err(OptionError::OutOfRange { .. })
+		));
+		engine.set_option("Threads", "4").unwrap();
+		assert_eq!(engine.threads(), 4);
+
+		let settings = EvaluationSettings {
+			search_until: SearchLimit::Limited(ActualLimit {
+				nodes: None,
+				depth: NonZeroU8::new(6),
+				time: None,
+			}),
+			..EvaluationSettings::default()
+		};
+		let (_, best_move) = engine.evaluate(None, settings);
+		assert!(PossibleMoves::moves(engine.current_position()).contains(best_move.unwrap()));
+	}
+
+	#[test]
+	fn ponder_option_ponders_background_searches() {
+		let frontend = Arc::new(CountingFrontend::default());
+		let engine = Arc::new(Engine::new(1 << 16, frontend.clone()));
+		assert!(matches!(
+			engine.set_option("Ponder", "maybe"),
+			Err(OptionError::InvalidValue { .. })
+		));
+		engine.set_option("Ponder", "on").unwrap();
+		assert!(engine.ponder());
+
+		let settings = EvaluationSettings {
+			search_until: SearchLimit::Limited(ActualLimit {
+				nodes: None,
+				depth: NonZeroU8::new(2),
+				time: None,
+			}),
+			..EvaluationSettings::default()
+		};
+		// a search that isn't in the background still finishes
+		assert!(engine.evaluate(None, settings.clone()).1.is_some());
+
+		engine.start_evaluation(settings);
+		for _ in 0..500 {
+			if frontend.best_moves.load(Ordering::Acquire) != 0 {
+				break;
+			}
+			std::thread::sleep(Duration::from_millis(10));
+		}
+
+		// the move was reported, but the search is still pondering
+		assert_eq!(frontend.best_moves.load(Ordering::Acquire), 1);
+		assert!(engine.pondering_task.lock().is_some());
+		assert!(engine.stop_evaluation().is_some());
+	}
+
+	#[test]
+	fn book_moves_are_played() {
+		let start = CheckersBitBoard::starting_position();
+		let book_move = crate::full_moves(start).into_iter().last().unwrap();
+		let mut book = OpeningBook::new();
+		book.add(book_move.result, 1);
+		let path = std::env::temp_dir().join(format!("ampere-book-{}.amb", std::process::id()));
+		book.save(&path).unwrap();
+
+		let engine = Engine::new(1 << 16, Arc::new(TextFrontend::new(|_| {})));
+		let result = engine.set_option("book", &path.display().to_string());
+		std::fs::remove_file(&path).unwrap();
+		result.unwrap();
+
+		let settings = EvaluationSettings {
+			search_until: SearchLimit::Limited(ActualLimit {
+				nodes: None,
+				depth: NonZeroU8::new(1),
+				time: None,
+			}),
+			..EvaluationSettings::default()
+		};
+		assert_eq!(engine.evaluate(None, settings).1, Some(book_move.hops[0]));
+		assert!(engine.set_option("Book", "no such book").is_err());
 	}
 }
@@ -1,9 +1,141 @@
 use std::fmt::{self, Display};
 use std::ops::Neg;
 
-use model::CheckersBitBoard;
+use model::{CheckersBitBoard, PossibleMoves};
 
-const KING_WORTH: u32 = 2;
+/// The weights given to each term of the evaluation function. Each weight is
+/// measured in men, so a weight of `1.0` is worth as much as a single man.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EvalParams {
+	/// The worth of a king
+	pub king_worth: f32,
+	/// The bonus for each man guarding its back rank
+	pub back_rank: f32,
+	/// The bonus for each piece on one of the eight center squares
+	pub center: f32,
+	/// The bonus for each man on the opponent's half of the board
+	pub advanced_man: f32,
+	/// The bonus for each man that can't be stopped from promoting
+	pub runaway: f32,
+	/// The bonus for each move available to a king
+	pub king_mobility: f32,
+	/// The bonus for each enemy king that has no moves available
+	pub trapped_king: f32,
+	/// The bonus for each piece that has been traded off the board, given to
+	/// the player that is ahead in material. This is scaled by the
+	/// aggressiveness.
+	pub exchange: f32,
+	/// How aggressively to play, from -1 (defensive) to 1 (aggressive). An
+	/// aggressive style pushes men forward when it isn't ahead in material,
+	/// and trades pieces when it is. A defensive style does the opposite.
+	pub aggressiveness: f32,
+}
+
+impl Default for EvalParams {
+	fn default() -> Self {
+		Self {
+			king_worth: 2.0,
+			back_rank: 0.1,
+			center: 0.05,
+			advanced_man: 0.03,
+			runaway: 0.5,
+			king_mobility: 0.02,
+			trapped_king: 0.25,
+			exchange: 0.05,
+			aggressiveness: 0.0,
+		}
+	}
+}
+
+// the rows that each player's men start from
+const DARK_BACK_RANK: u32 = 0b00000000000001000001000001000001;
+const LIGHT_BACK_RANK: u32 = 0b10000010000000000000100000100000;
+
+// the rows on the opponent's half of the board, excluding the promotion row
+const DARK_ADVANCED_RANKS: u32 = 0b01110001110000110000010000011100;
+const LIGHT_ADVANCED_RANKS: u32 = 0b00001100001110001110001110000010;
+
+// the number of pieces on the board at the start of the game
+const STARTING_PIECES: u32 = 24;
+
+// the eight squares in the middle of the board
+const CENTER_SQUARES: u32 = 0b00110000111100001100000000000000;
+
+/// For each square, the squares in front of a dark man that an enemy piece
+/// would need to be on in order to stop it from promoting
+#[rustfmt::skip]
+const DARK_PROMOTION_CONES: [u32; 32] = [
+	0b11110011111000111100111110111010,
+	0b11100011110000111000101100110000,
+	0b00000000000000000000110000111000,
+	0b00000000000000000000110000110000,
+	0b00000000000000000000100000100000,
+	0b00000000000000000000000000000000,
+	0b11111011111100111110111110111100,
+	0b11110011111000111100111100111000,
+	0b11100011110000111000101000110000,
+	0b11000011100000110000000000100000,
+	0b00000000000000000000100000000000,
+	0b00000000000000000000000000000000,
+	0b11111111111110111110110000111100,
+	0b11111011111100111100110000111100,
+	0b11110011111000111000110000111000,
+	0b11100011110000110000100000110000,
+	0b11000011100000100000000000100000,
+	0b10000011000000000000000000000000,
+	0b11111111111110000000110000111100,
+	0b11111111111100000000110000111100,
+	0b11111011111000000000110000111100,
+	0b11110011110000000000110000111000,
+	0b11100011100000000000100000110000,
+	0b11000011000000000000000000100000,
+	0b10000010000000000000000000000000,
+	0b00000000000000000000000000000000,
+	0b11111000000000000000110000111100,
+	0b11110000000000000000110000111100,
+	0b11100000000000000000110000111000,
+	0b11000000000000000000100000110000,
+	0b10000000000000000000000000100000,
+	0b00000000000000000000000000000000,
+];
+
+/// For each square, the squares in front of a light man that an enemy piece
+/// would need to be on in order to stop it from promoting
+#[rustfmt::skip]
+const LIGHT_PROMOTION_CONES: [u32; 32] = [
+	0b00000000000000000000000000000000,
+	0b00000000000000000000000000000001,
+	0b00001100000111000011000001000000,
+	0b00011100001111000111000011000101,
+	0b00111100011111001111000111001111,
+	0b01111100111111011111001111011111,
+	0b00000000000000000000000000000000,
+	0b00000000000000000000000001000001,
+	0b00000000000000000000000011000011,
+	0b00000000000000000000000111000011,
+	0b00011100001111000111000011001101,
+	0b00111100011111001111010111011111,
+	0b00000000000000000000000000000000,
+	0b00000000000000000001000001000000,
+	0b00000000000000000011000011000001,
+	0b00000000000000000111000111000011,
+	0b00000000000000001111001111000011,
+	0b00000000000000011111001111000011,
+	0b00000000000000000000000000000000,
+	0b00000000000001000001000000000000,
+	0b00000000000011000011000001000000,
+	0b00000000000111000111000011000001,
+	0b00000000001111001111000111000011,
+	0b00000000011111011111001111000011,
+	0b00000000111111111111001111000011,
+	0b00000001111111111111001111000011,
+	0b00000000000011000001000000000000,
+	0b00000100000111000011000001000000,
+	0b00001100001111000111000011000001,
+	0b00011100011111001111000111000011,
+	0b00111100111111011111001111000011,
+	0b01111101111111111111001111000011,
+];
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct Evaluation(i16);
@@ -49,6 +181,15 @@ impl Evaluation {
 		Self((eval * 16384.0) as i16)
 	}
 
+	/// The underlying representation of the evaluation
+	pub(crate) const fn to_bits(self) -> i16 {
+		self.0
+	}
+
+	pub(crate) const fn from_bits(bits: i16) -> Self {
+		Self(bits)
+	}
+
 	pub fn to_f32(self) -> Option<f32> {
 		if self.is_force_sequence() {
 			return None;
@@ -102,21 +243,82 @@ impl Evaluation {
 	}
 }
 
-pub fn eval_position(board: CheckersBitBoard) -> Evaluation {
-	let light_pieces = board.pieces_bits() & !board.color_bits();
-	let dark_pieces = board.pieces_bits() & board.color_bits();
+fn weigh(count: u32, weight: f32) -> f32 {
+	count as f32 * weight
+}
 
-	let light_peasants = light_pieces & !board.king_bits();
-	let dark_peasants = dark_pieces & !board.king_bits();
+/// Counts the men that no enemy piece is in a position to stop from promoting
+fn count_runaways(men: u32, enemy_pieces: u32, cones: &[u32; 32]) -> u32 {
+	let mut runaways = 0;
+	let mut remaining = men;
+	while remaining != 0 {
+		let square = remaining.trailing_zeros() as usize;
+		if cones[square] & enemy_pieces == 0 {
+			runaways += 1;
+		}
 
-	let light_kings = light_pieces & board.king_bits();
-	let dark_kings = dark_pieces & board.king_bits();
+		remaining &= remaining - 1;
+	}
+
+	runaways
+}
+
+/// Returns the kings that are able to slide, and the number of slides
+/// available to all of the kings
+fn king_slides(kings: u32, not_occupied: u32) -> (u32, u32) {
+	let forward_left = not_occupied.rotate_right(7) & kings & PossibleMoves::FORWARD_LEFT_SLIDES;
+	let forward_right = not_occupied.rotate_right(1) & kings & PossibleMoves::FORWARD_RIGHT_SLIDES;
+	let backward_left = not_occupied.rotate_left(1) & kings & PossibleMoves::BACKWARD_LEFT_SLIDES;
+	let backward_right = not_occupied.rotate_left(7) & kings & PossibleMoves::BACKWARD_RIGHT_SLIDES;
+
+	let mobile_kings = forward_left | forward_right | backward_left | backward_right;
+	let slides = forward_left.count_ones()
+		+ forward_right.count_ones()
+		+ backward_left.count_ones()
+		+ backward_right.count_ones();
+
+	(mobile_kings, slides)
+}
+
+pub fn eval_position(board: CheckersBitBoard, params: &EvalParams) -> Evaluation {
+	let (dark, light) = eval_features(board);
+	let light_material = light.material(params);
+	let dark_material = dark.material(params);
+
+	// the playing style only affects the player that is ahead, or the one
+	// that is behind, so it doesn't cancel out
+	let aggressiveness = params.aggressiveness.clamp(-1.0, 1.0);
+	let traded_pieces = STARTING_PIECES.saturating_sub(board.pieces_bits().count_ones());
+	let exchange_bonus = traded_pieces as f32 * params.exchange * aggressiveness;
+	let attack_weight = params.advanced_man * (1.0 + aggressiveness);
+	let (light_advanced_weight, light_exchange_bonus) = if light_material > dark_material {
+		(params.advanced_man, exchange_bonus)
+	} else {
+		(attack_weight, 0.0)
+	};
+	let (dark_advanced_weight, dark_exchange_bonus) = if dark_material > light_material {
+		(params.advanced_man, exchange_bonus)
+	} else {
+		(attack_weight, 0.0)
+	};
 
 	// if we assume the black player doesn't exist, how good is this for white?
-	let light_eval =
-		(light_peasants.count_ones() as f32) + ((light_kings.count_ones() * KING_WORTH) as f32);
-	let dark_eval =
-		(dark_peasants.count_ones() as f32) + ((dark_kings.count_ones() * KING_WORTH) as f32);
+	let light_eval = light_material
+		+ weigh(light.back_rank, params.back_rank)
+		+ weigh(light.center, params.center)
+		+ weigh(light.advanced_men, light_advanced_weight)
+		+ weigh(light.runaways, params.runaway)
+		+ weigh(light.king_mobility, params.king_mobility)
+		+ weigh(dark.trapped_kings, params.trapped_king)
+		+ light_exchange_bonus;
+	let dark_eval = dark_material
+		+ weigh(dark.back_rank, params.back_rank)
+		+ weigh(dark.center, params.center)
+		+ weigh(dark.advanced_men, dark_advanced_weight)
+		+ weigh(dark.runaways, params.runaway)
+		+ weigh(dark.king_mobility, params.king_mobility)
+		+ weigh(light.trapped_kings, params.trapped_king)
+		+ dark_exchange_bonus;
 
 	// avoiding a divide by zero error
 	if dark_eval + light_eval != 0.0 {
@@ -126,8 +328,109 @@ pub fn eval_position(board: CheckersBitBoard) -> Evaluation {
 	}
 }
 
+/// The terms of the evaluation function, counted for one player
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EvalFeatures {
+	pub men: u32,
+	pub kings: u32,
+	/// The men guarding their back rank
+	pub back_rank: u32,
+	/// The pieces on one of the eight center squares
+	pub center: u32,
+	/// The men on the opponent's half of the board
+	pub advanced_men: u32,
+	/// The men that can't be stopped from promoting
+	pub runaways: u32,
+	/// The moves available to the kings
+	pub king_mobility: u32,
+	/// The kings that have no moves available
+	pub trapped_kings: u32,
+}
+
+impl EvalFeatures {
+	/// The names of the features, in the order of [`EvalFeatures::to_array`]
+	pub const NAMES: [&'static str; 8] = [
+		"men",
+		"kings",
+		"back_rank",
+		"center",
+		"advanced_men",
+		"runaways",
+		"king_mobility",
+		"trapped_kings",
+	];
+
+	/// The value of the player's pieces, where a man is worth one
+	fn material(self, params: &EvalParams) -> f32 {
+		self.men as f32 + weigh(self.kings, params.king_worth)
+	}
+
+	pub const fn to_array(self) -> [u32; 8] {
+		[
+			self.men,
+			self.kings,
+			self.back_rank,
+			self.center,
+			self.advanced_men,
+			self.runaways,
+			self.king_mobility,
+			self.trapped_kings,
+		]
+	}
+}
+
+/// Counts the terms of the evaluation function for black, and then for
+/// white. These are the same terms that [`eval_position`] weighs.
+pub fn eval_features(board: CheckersBitBoard) -> (EvalFeatures, EvalFeatures) {
+	let not_occupied = !board.pieces_bits();
+	let light_pieces = board.pieces_bits() & !board.color_bits();
+	let dark_pieces = board.pieces_bits() & board.color_bits();
+
+	let light_peasants = light_pieces & !board.king_bits();
+	let dark_peasants = dark_pieces & !board.king_bits();
+
+	let light_kings = light_pieces & board.king_bits();
+	let dark_kings = dark_pieces & board.king_bits();
+
+	let (light_mobile_kings, light_king_slides) = king_slides(light_kings, not_occupied);
+	let (dark_mobile_kings, dark_king_slides) = king_slides(dark_kings, not_occupied);
+
+	let dark = EvalFeatures {
+		men: dark_peasants.count_ones(),
+		kings: dark_kings.count_ones(),
+		back_rank: (dark_peasants & DARK_BACK_RANK).count_ones(),
+		center: (dark_pieces & CENTER_SQUARES).count_ones(),
+		advanced_men: (dark_peasants & DARK_ADVANCED_RANKS).count_ones(),
+		runaways: if light_kings == 0 {
+			count_runaways(dark_peasants, light_pieces, &DARK_PROMOTION_CONES)
+		} else {
+			0
+		},
+		king_mobility: dark_king_slides,
+		trapped_kings: (dark_kings & !dark_mobile_kings).count_ones(),
+	};
+	let light = EvalFeatures {
+		men: light_peasants.count_ones(),
+		kings: light_kings.count_ones(),
+		back_rank: (light_peasants & LIGHT_BACK_RANK).count_ones(),
+		center: (light_pieces & CENTER_SQUARES).count_ones(),
+		advanced_men: (light_peasants & LIGHT_ADVANCED_RANKS).count_ones(),
+		runaways: if dark_kings == 0 {
+			count_runaways(light_peasants, dark_pieces, &LIGHT_PROMOTION_CONES)
+		} else {
+			0
+		},
+		king_mobility: light_king_slides,
+		trapped_kings: (light_kings & !light_mobile_kings).count_ones(),
+	};
+
+	(dark, light)
+}
+
 #[cfg(test)]
 mod tests {
+	use model::PieceColor;
+
 	use super::*;
 
 	#[test]
@@ -157,4 +460,66 @@ mod tests {
 		assert_eq!(-Evaluation::DRAW, Evaluation::DRAW);
 		assert_eq!(-Evaluation::new(0.5), Evaluation::new(-0.5));
 	}
+
+	#[test]
+	fn starting_position_is_even() {
+		let board = CheckersBitBoard::starting_position();
+		assert_eq!(
+			eval_position(board, &EvalParams::default()),
+			Evaluation::DRAW
+		);
+	}
+
+	#[test]
+	fn aggressive_player_trades_when_ahead() {
+		// dark is up a man, with most of the pieces traded off
+		let board = CheckersBitBoard::new(
+			0b1000011 | (1 << 25) | (1 << 31),
+			0b1000011,
+			0,
+			PieceColor::Dark,
+		);
+		let aggressive = EvalParams {
+			aggressiveness: 1.0,
+			..EvalParams::default()
+		};
+
+		let normal_eval = eval_position(board, &EvalParams::default());
+		let aggressive_eval = eval_position(board, &aggressive);
+		assert!(aggressive_eval > normal_eval);
+	}
+
+	#[test]
+	fn runaways() {
+		// a dark man on 2 is stopped by a light man on 4, but not on 25
+		assert_eq!(count_runaways(1 << 2, 1 << 4, &DARK_PROMOTION_CONES), 0);
+		assert_eq!(count_runaways(1 << 2, 1 << 25, &DARK_PROMOTION_CONES), 1);
+	}
+
+	#[test]
+	fn trapped_king() {
+		// a dark king in the corner on 18, blocked by a light man on 19
+		let board =
+			CheckersBitBoard::new((1 << 18) | (1 << 19), 1 << 18, 1 << 18, PieceColor::Dark);
+		let (mobile_kings, slides) = king_slides(board.king_bits(), !board.pieces_bits());
+		assert_eq!(mobile_kings, 0);
+		assert_eq!(slides, 0);
+	}
+
+	#[test]
+	fn king_slides_backward_right_from_26() {
+		// the only empty square is the one behind and to the right of 26
+		assert_eq!(king_slides(1 << 26, 1 << 19), (1 << 26, 1));
+	}
+
+	#[test]
+	fn king_slides_match_move_generation() {
+		for square in 0..32 {
+			let board =
+				CheckersBitBoard::new(1 << square, 1 << square, 1 << square, PieceColor::Dark);
+			let (_, slides) = king_slides(board.king_bits(), !board.pieces_bits());
+			let moves = PossibleMoves::moves(board).into_iter().count();
+			assert_eq!(slides as usize, moves, "a king on {square}");
+		}
+	}
 }
@@ -0,0 +1,350 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use model::{CheckersBitBoard, PieceColor};
+use pdn::GameResult;
+use thiserror::Error;
+
+use crate::annotate::score;
+use crate::eval::{eval_features, EvalFeatures};
+use crate::notation::{fen, move_comments, parse_pdn, pdn_tag, split_pdn_games};
+use crate::selfplay::{read_header, TrainingDataError, TrainingSample};
+use crate::tournament::parse_result;
+
+/// A position to be exported, along with how it was evaluated, and how the
+/// game ended
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportRow {
+	pub position: CheckersBitBoard,
+	/// The evaluation from black's perspective, from negative one to one
+	pub score: Option<f32>,
+	pub result: Option<GameResult>,
+}
+
+impl ExportRow {
+	/// The features of the position, for black, and then for white
+	fn features(&self) -> [u32; 16] {
+		let (black, white) = eval_features(self.position);
+		let mut features = [0; 16];
+		features[..8].copy_from_slice(&black.to_array());
+		features[8..].copy_from_slice(&white.to_array());
+		features
+	}
+}
+
+/// The file format that the rows are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExportFormat {
+	Csv,
+	Parquet,
+}
+
+impl ExportFormat {
+	/// Guesses the format from the extension of a file. Files without the
+	/// `parquet` extension are written as CSV.
+	pub fn from_path(path: impl AsRef<Path>) -> Self {
+		match path.as_ref().extension() {
+			Some(extension) if extension.eq_ignore_ascii_case("parquet") => Self::Parquet,
+			_ => Self::Csv,
+		}
+	}
+}
+
+impl FromStr for ExportFormat {
+	type Err = ();
+
+	fn from_str(format: &str) -> Result<Self, Self::Err> {
+		match format.to_ascii_lowercase().as_str() {
+			"csv" => Ok(Self::Csv),
+			"parquet" => Ok(Self::Parquet),
+			_ => Err(()),
+		}
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+	#[error("This build can't write Parquet files. It must be built with the `parquet` feature")]
+	ParquetUnsupported,
+	#[cfg(feature = "parquet")]
+	#[error(transparent)]
+	ParquetError(#[from] parquet::errors::ParquetError),
+	#[error(transparent)]
+	TrainingDataError(#[from] TrainingDataError),
+	#[error(transparent)]
+	IoError(#[from] io::Error),
+}
+
+/// The names of the columns, in the order they're written
+pub fn columns() -> Vec<String> {
+	let features = ["black", "white"].into_iter().flat_map(|player| {
+		EvalFeatures::NAMES
+			.iter()
+			.map(move |name| format!("{player}_{name}"))
+	});
+	std::iter::once("fen".to_string())
+		.chain(features)
+		.chain(["score".to_string(), "result".to_string()])
+		.collect()
+}
+
+/// The result of a game as a number, from black's perspective, or `None` if
+/// the game is unfinished
+const fn result_number(result: GameResult) -> Option<i32> {
+	match result {
+		GameResult::BlackWin => Some(1),
+		GameResult::WhiteWin => Some(-1),
+		GameResult::Draw => Some(0),
+		GameResult::Unfinished => None,
+	}
+}
+
+/// Reads the samples of a self-play training data file, including its
+/// header
+pub fn training_rows(reader: &mut impl Read) -> Result<Vec<ExportRow>, TrainingDataError> {
+	read_header(reader)?;
+
+	let mut rows = Vec::new();
+	while let Some(sample) = TrainingSample::read(reader)? {
+		let black_score = match sample.position.turn() {
+			PieceColor::Dark => sample.score,
+			PieceColor::Light => -sample.score,
+		};
+		rows.push(ExportRow {
+			position: sample.position,
+			score: Some(score(black_score)),
+			result: Some(sample.result),
+		});
+	}
+
+	Ok(rows)
+}
+
+/// Reads the evaluation at the start of a comment, such as `{+0.125}`, which
+/// is how annotated games store them
+fn comment_score(comment: &str) -> Option<f32> {
+	let token = comment.split([',', ' ']).next()?.trim();
+	match token {
+		_ if token.starts_with("+M") => Some(1.0),
+		_ if token.starts_with("-M") => Some(-1.0),
+		_ => token.parse().ok(),
+	}
+}
+
+/// Reads the position after each move of each game in a PDN file. The
+/// evaluations are read from the comments, as they're written by
+/// [`annotate`](crate::annotate), and the results are read from the `Result`
+/// tags. Games that can't be read are skipped.
+pub fn pdn_rows(pdn: &str) -> Vec<ExportRow> {
+	let mut rows = Vec::new();
+	for (number, text) in split_pdn_games(pdn).into_iter().enumerate() {
+		let game = match parse_pdn(text) {
+			Ok(game) => game,
+			Err(error) => {
+				tracing::warn!(game = number + 1, %error, "skipped a game");
+				continue;
+			}
+		};
+
+		let result = pdn_tag(text, "Result").and_then(|result| parse_result(&result));
+		let comments = move_comments(text);
+		for (index, played) in game.moves.iter().enumerate() {
+			rows.push(ExportRow {
+				position: played.result,
+				score: comments
+					.get(index)
+					.and_then(|comment| comment_score(comment)),
+				result,
+			});
+		}
+	}
+
+	rows
+}
+
+/// Writes the rows as CSV, with a header of the column names. Missing
+/// evaluations and results are left empty.
+pub fn write_csv(rows: &[ExportRow], writer: &mut impl Write) -> io::Result<()> {
+	writeln!(writer, "{}", columns().join(","))?;
+	for row in rows {
+		// FENs have commas in them
+		write!(writer, "\"{}\"", fen(row.position))?;
+		for feature in row.features() {
+			write!(writer, ",{feature}")?;
+		}
+
+		match row.score {
+			Some(score) => write!(writer, ",{score}")?,
+			None => write!(writer, ",")?,
+		}
+		match row.result.and_then(result_number) {
+			Some(result) => writeln!(writer, ",{result}")?,
+			None => writeln!(writer, ",")?,
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(feature = "parquet")]
+fn write_column<T: parquet::data_type::DataType, W: Write + Send>(
+	row_group: &mut parquet::file::writer::SerializedRowGroupWriter<'_, W>,
+	values: &[T::T],
+	definition_levels: Option<&[i16]>,
+) -> Result<(), parquet::errors::ParquetError> {
+	let Some(mut column) = row_group.next_column()? else {
+		return Err(parquet::errors::ParquetError::General(
+			"there are more columns than the schema has".to_string(),
+		));
+	};
+	column
+		.typed::<T>()
+		.write_batch(values, definition_levels, None)?;
+	column.close()
+}
+
+/// Writes the rows as a Parquet file, in a single row group. The evaluations
+/// and results are optional columns.
+#[cfg(feature = "parquet")]
+fn write_parquet(rows: &[ExportRow], writer: impl Write + Send) -> Result<(), ExportError> {
+	use std::sync::Arc;
+
+	use parquet::data_type::{ByteArray, ByteArrayType, FloatType, Int32Type};
+	use parquet::file::properties::WriterProperties;
+	use parquet::file::writer::SerializedFileWriter;
+	use parquet::schema::parser::parse_message_type;
+
+	let columns = columns();
+	let mut schema = String::from("message training {\n  REQUIRED BYTE_ARRAY fen (UTF8);\n");
+	for name in &columns[1..columns.len() - 2] {
+		schema.push_str(&format!("  REQUIRED INT32 {name};\n"));
+	}
+	schema.push_str("  OPTIONAL FLOAT score;\n  OPTIONAL INT32 result;\n}\n");
+
+	let schema = Arc::new(parse_message_type(&schema)?);
+	let properties = Arc::new(WriterProperties::builder().build());
+	let mut file = SerializedFileWriter::new(writer, schema, properties)?;
+	let mut row_group = file.next_row_group()?;
+
+	let fens: Vec<ByteArray> = rows
+		.iter()
+		.map(|row| ByteArray::from(fen(row.position).into_bytes()))
+		.collect();
+	write_column::<ByteArrayType, _>(&mut row_group, &fens, None)?;
+
+	let features: Vec<[u32; 16]> = rows.iter().map(ExportRow::features).collect();
+	for index in 0..16 {
+		let values: Vec<i32> = features
+			.iter()
+			.map(|features| features[index] as i32)
+			.collect();
+		write_column::<Int32Type, _>(&mut row_group, &values, None)?;
+	}
+
+	let defined: Vec<i16> = rows.iter().map(|row| row.score.is_some().into()).collect();
+	let scores: Vec<f32> = rows.iter().filter_map(|row| row.score).collect();
+	write_column::<FloatType, _>(&mut row_group, &scores, Some(&defined))?;
+
+	let results: Vec<Option<i32>> = rows
+		.iter()
+		.map(|row| row.result.and_then(result_number))
+		.collect();
+	let defined: Vec<i16> = results
+		.iter()
+		.map(|result| result.is_some().into())
+		.collect();
+	let results: Vec<i32> = results.into_iter().flatten().collect();
+	write_column::<Int32Type, _>(&mut row_group, &results, Some(&defined))?;
+
+	row_group.close()?;
+	file.close()?;
+	Ok(())
+}
+
+/// Writes the rows in the given format
+pub fn write_rows(
+	rows: &[ExportRow],
+	format: ExportFormat,
+	mut writer: impl Write + Send,
+) -> Result<(), ExportError> {
+	match format {
+		ExportFormat::Csv => {
+			write_csv(rows, &mut writer)?;
+			writer.flush()?;
+			Ok(())
+		}
+		#[cfg(feature = "parquet")]
+		ExportFormat::Parquet => write_parquet(rows, writer),
+		#[cfg(not(feature = "parquet"))]
+		ExportFormat::Parquet => Err(ExportError::ParquetUnsupported),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::selfplay::write_header;
+	use crate::Evaluation;
+
+	#[test]
+	fn reads_annotated_games() {
+		let pdn =
+			"[Result \"2-0\"]\n\n1. 11-15 {+0.010} 23-19 {-0.020, 22-18 was better (+0.000)} \
+		           2. 8-11 2-0\n";
+		let rows = pdn_rows(pdn);
+		assert_eq!(rows.len(), 3);
+		assert_eq!(rows[0].score, Some(0.01));
+		assert_eq!(rows[1].score, Some(-0.02));
+		assert_eq!(rows[2].score, None);
+		assert!(rows
+			.iter()
+			.all(|row| row.result == Some(GameResult::BlackWin)));
+
+		let mut csv = Vec::new();
+		write_csv(&rows, &mut csv).unwrap();
+		let csv = String::from_utf8(csv).unwrap();
+		let lines: Vec<&str> = csv.lines().collect();
+		assert_eq!(lines.len(), 4);
+		assert_eq!(lines[0].split(',').count(), 19);
+		assert!(lines[1].starts_with("\"W:W21,22,23"));
+		assert!(lines[1].ends_with(",0.01,1"));
+		assert!(lines[3].ends_with(",,1"));
+	}
+
+	#[test]
+	fn reads_training_data() {
+		let sample = TrainingSample {
+			position: CheckersBitBoard::starting_position(),
+			score: Evaluation::new(0.25),
+			result: GameResult::Draw,
+		};
+		let mut data = Vec::new();
+		write_header(&mut data).unwrap();
+		sample.write(&mut data).unwrap();
+
+		let rows = training_rows(&mut data.as_slice()).unwrap();
+		assert_eq!(rows.len(), 1);
+		assert_eq!(rows[0].score, Some(0.25));
+		assert_eq!(rows[0].features()[0], 12);
+		assert_eq!(rows[0].features()[8], 12);
+	}
+
+	#[cfg(feature = "parquet")]
+	#[test]
+	fn writes_parquet() {
+		use parquet::file::reader::{FileReader, SerializedFileReader};
+
+		let rows = pdn_rows("1. 11-15 {+0.010} 23-19 *");
+		let path =
+			std::env::temp_dir().join(format!("ampere-export-{}.parquet", std::process::id()));
+		let file = std::fs::File::create(&path).unwrap();
+		write_rows(&rows, ExportFormat::Parquet, file).unwrap();
+
+		let reader = SerializedFileReader::new(std::fs::File::open(&path).unwrap()).unwrap();
+		let metadata = reader.metadata().file_metadata();
+		assert_eq!(metadata.num_rows(), 2);
+		assert_eq!(metadata.schema_descr().num_columns(), columns().len());
+		std::fs::remove_file(path).unwrap();
+	}
+}
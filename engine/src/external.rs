@@ -0,0 +1,444 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use model::{CheckersBitBoard, PieceColor, PossibleMoves};
+use pdn::GameResult;
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::dxp::{self, DxpError, EndReason, Message};
+use crate::hub::{argument, parse_hub_move, parse_message};
+use crate::notation::{full_moves, position_string, FullMove};
+use crate::{ActualLimit, ENGINE_NAME};
+
+/// The number of moves in a DXP time control
+const DXP_MOVES: u16 = 50;
+
+/// How long an engine has to exit after it's asked to quit
+const QUIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The protocol an external engine speaks
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+	/// The Hub protocol, over the engine's standard input and output
+	#[default]
+	Hub,
+	/// Ampere's JSON-RPC protocol, over the engine's standard input and
+	/// output
+	JsonRpc,
+	/// DamExchange, over a TCP connection. The engine must already be
+	/// listening for games.
+	Dxp,
+}
+
+impl FromStr for Protocol {
+	type Err = ExternalEngineError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.to_ascii_lowercase().as_str() {
+			"hub" => Ok(Self::Hub),
+			"json" | "json-rpc" => Ok(Self::JsonRpc),
+			"dxp" => Ok(Self::Dxp),
+			_ => Err(ExternalEngineError::UnknownProtocol(s.to_string())),
+		}
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum ExternalEngineError {
+	#[error("{0:?} is not a protocol. The protocols are hub, json and dxp")]
+	UnknownProtocol(String),
+	#[error("The engine's command line is empty")]
+	EmptyCommand,
+	#[error("The engine closed the connection")]
+	Closed,
+	#[error("The engine sent an error: {0}")]
+	EngineError(String),
+	#[error("The engine played an illegal move: {0}")]
+	IllegalMove(String),
+	#[error("Options can't be set over DXP")]
+	OptionsUnsupported,
+	#[error(transparent)]
+	DxpError(#[from] DxpError),
+	#[error("Invalid JSON from the engine: {0}")]
+	JsonError(#[from] serde_json::Error),
+	#[error(transparent)]
+	IoError(#[from] io::Error),
+}
+
+/// An engine in another process, or on the other end of a DXP connection
+pub struct ExternalEngine {
+	protocol: Protocol,
+	reader: Box<dyn BufRead + Send>,
+	writer: Box<dyn Write + Send>,
+	child: Option<Child>,
+	/// The id of the next JSON-RPC request
+	next_id: u64,
+	/// The color the engine plays in the current game
+	color: PieceColor,
+}
+
+impl ExternalEngine {
+	/// Starts an engine. For Hub and JSON-RPC, `command` is the command line
+	/// of the engine, where the arguments are separated by whitespace. For
+	/// DXP, it's the address of the engine.
+	pub fn start(protocol: Protocol, command: &str) -> Result<Self, ExternalEngineError> {
+		let (reader, writer, child): (Box<dyn BufRead + Send>, Box<dyn Write + Send>, _) =
+			match protocol {
+				Protocol::Hub | Protocol::JsonRpc => {
+					let mut words = command.split_whitespace();
+					let program = words.next().ok_or(ExternalEngineError::EmptyCommand)?;
+					let mut child = Command::new(program)
+						.args(words)
+						.stdin(Stdio::piped())
+						.stdout(Stdio::piped())
+						.spawn()?;
+					let stdin = child.stdin.take().ok_or(ExternalEngineError::Closed)?;
+					let stdout = child.stdout.take().ok_or(ExternalEngineError::Closed)?;
+					(
+						Box::new(BufReader::new(stdout)),
+						Box::new(stdin),
+						Some(child),
+					)
+				}
+				Protocol::Dxp => {
+					let stream = TcpStream::connect(command.trim())?;
+					let writer = stream.try_clone()?;
+					(Box::new(BufReader::new(stream)), Box::new(writer), None)
+				}
+			};
+
+		let mut engine = Self {
+			protocol,
+			reader,
+			writer,
+			child,
+			next_id: 0,
+			color: PieceColor::Dark,
+		};
+
+		if protocol == Protocol::Hub {
+			engine.send("hub")?;
+			engine.wait_for("wait")?;
+			engine.send("init")?;
+			engine.wait_for("ready")?;
+		}
+
+		Ok(engine)
+	}
+
+	fn send(&mut self, line: &str) -> io::Result<()> {
+		writeln!(self.writer, "{line}")?;
+		self.writer.flush()
+	}
+
+	fn read_line(&mut self) -> Result<String, ExternalEngineError> {
+		let mut line = String::new();
+		if self.reader.read_line(&mut line)? == 0 {
+			return Err(ExternalEngineError::Closed);
+		}
+
+		Ok(line)
+	}
+
+	/// Reads Hub messages until one has the given command, and returns its
+	/// line
+	fn wait_for(&mut self, command: &str) -> Result<String, ExternalEngineError> {
+		loop {
+			let line = self.read_line()?;
+			match parse_message(&line) {
+				Some((received, _)) if received == command => return Ok(line),
+				Some(("error", arguments)) => {
+					let message = argument(&arguments, "message").unwrap_or_default();
+					tracing::warn!(message, "the engine sent an error");
+				}
+				_ => {}
+			}
+		}
+	}
+
+	/// Sends a JSON-RPC request, and waits for its response. Notifications
+	/// which are received first are skipped.
+	fn request(&mut self, method: &str, params: Value) -> Result<Value, ExternalEngineError> {
+		self.next_id += 1;
+		let id = self.next_id;
+		let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+		self.send(&request.to_string())?;
+
+		loop {
+			let response: Value = serde_json::from_str(&self.read_line()?)?;
+			if response["id"] != id {
+				continue;
+			}
+
+			if let Some(error) = response.get("error") {
+				let message = error["message"].as_str().unwrap_or_default();
+				return Err(ExternalEngineError::EngineError(message.to_string()));
+			}
+
+			return Ok(response["result"].clone());
+		}
+	}
+
+	/// Waits for a JSON-RPC notification with the given method, and returns
+	/// its parameters
+	fn notification(&mut self, method: &str) -> Result<Value, ExternalEngineError> {
+		loop {
+			let notification: Value = serde_json::from_str(&self.read_line()?)?;
+			if notification["method"] == method {
+				return Ok(notification["params"].clone());
+			}
+		}
+	}
+
+	pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), ExternalEngineError> {
+		match self.protocol {
+			Protocol::Hub => {
+				self.send(&format!("set-param name=\"{name}\" value=\"{value}\""))?;
+				Ok(())
+			}
+			Protocol::JsonRpc => {
+				self.request("set_option", json!({ "name": name, "value": value }))?;
+				Ok(())
+			}
+			Protocol::Dxp => Err(ExternalEngineError::OptionsUnsupported),
+		}
+	}
+
+	/// Starts a new game, where the engine plays `color`. Over DXP, the
+	/// engine is asked for a game, with enough time to use `limit` on each
+	/// move.
+	pub fn new_game(
+		&mut self,
+		start: CheckersBitBoard,
+		color: PieceColor,
+		limit: &ActualLimit,
+	) -> Result<(), ExternalEngineError> {
+		self.color = color;
+		match self.protocol {
+			Protocol::Hub => self.send("new-game")?,
+			Protocol::JsonRpc => {}
+			Protocol::Dxp => {
+				let seconds = limit.time.unwrap_or(Duration::from_secs(1)).as_secs_f64();
+				let minutes = (seconds * DXP_MOVES as f64 / 60.0).ceil().max(1.0);
+				dxp::write_message(
+					&mut self.writer,
+					&Message::GameRequest {
+						name: ENGINE_NAME.to_string(),
+						follower_color: color,
+						minutes: minutes.min(u16::MAX as f64) as u16,
+						moves: DXP_MOVES,
+						position: Some(start),
+					},
+				)?;
+
+				match dxp::expect_message(&mut self.reader)? {
+					Message::GameAccept {
+						code: dxp::AcceptCode::Accepted,
+						..
+					} => {}
+					Message::GameAccept { code, .. } => return Err(DxpError::Refused(code).into()),
+					message => {
+						return Err(DxpError::UnexpectedMessage {
+							expected: "a game acceptance",
+							received: message,
+						}
+						.into())
+					}
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Tells the engine about its opponent's move. This is only needed for
+	/// DXP, because the other protocols are sent the whole position.
+	pub fn opponent_moved(
+		&mut self,
+		full_move: &FullMove,
+		time: Duration,
+	) -> Result<(), ExternalEngineError> {
+		if self.protocol == Protocol::Dxp {
+			dxp::write_message(&mut self.writer, &dxp::move_message(full_move, time))?;
+		}
+
+		Ok(())
+	}
+
+	/// Asks the engine for its move in `position`
+	pub fn best_move(
+		&mut self,
+		position: CheckersBitBoard,
+		limit: &ActualLimit,
+	) -> Result<FullMove, ExternalEngineError> {
+		match self.protocol {
+			Protocol::Hub => {
+				self.send(&format!("pos pos={}", position_string(position, 'B')))?;
+				let mut level = String::from("level");
+				if let Some(time) = limit.time {
+					level.push_str(&format!(" move-time={:.3}", time.as_secs_f64()));
+				}
+				if let Some(depth) = limit.depth {
+					level.push_str(&format!(" depth={depth}"));
+				}
+				if let Some(nodes) = limit.nodes {
+					level.push_str(&format!(" nodes={nodes}"));
+				}
+				self.send(&level)?;
+				self.send("go think")?;
+
+				let line = self.wait_for("done")?;
+				let notation = parse_message(&line)
+					.and_then(|(_, arguments)| argument(&arguments, "move"))
+					.unwrap_or_default()
+					.to_string();
+				parse_hub_move(position, &notation)
+					.ok_or(ExternalEngineError::IllegalMove(notation))
+			}
+			Protocol::JsonRpc => {
+				// each search only returns one jump of a multi-jump
+				let mut board = position;
+				let mut hops = Vec::new();
+				loop {
+					self.request(
+						"set_position",
+						json!({ "position": position_string(position, 'B'), "moves": hops }),
+					)?;
+					self.request(
+						"go",
+						json!({
+							"depth": limit.depth,
+							"nodes": limit.nodes,
+							"move_time": limit.time.map(|time| time.as_secs_f64()),
+						}),
+					)?;
+
+					let params = self.notification("best_move")?;
+					let notation = params["move"].as_str().unwrap_or_default();
+					let Some(hop) = PossibleMoves::moves(board)
+						.into_iter()
+						.find(|hop| hop.to_string() == notation)
+					else {
+						return Err(ExternalEngineError::IllegalMove(notation.to_string()));
+					};
+
+					// safety: the move was generated from this position
+					board = unsafe { hop.apply_to(board) };
+					hops.push(hop.to_string());
+					if board.turn() != position.turn() {
+						break;
+					}
+				}
+
+				full_moves(position)
+					.into_iter()
+					.find(|full_move| full_move.result == board)
+					.ok_or_else(|| ExternalEngineError::IllegalMove(hops.join(" ")))
+			}
+			Protocol::Dxp => loop {
+				match dxp::expect_message(&mut self.reader)? {
+					Message::Move {
+						from, to, captured, ..
+					} => {
+						return dxp::find_move(position, from, to, &captured).ok_or_else(|| {
+							ExternalEngineError::IllegalMove(format!("{from}-{to}"))
+						})
+					}
+					Message::GameEnd { .. } => return Err(ExternalEngineError::Closed),
+					_ => {}
+				}
+			},
+		}
+	}
+
+	/// Tells the engine how the game ended. Over DXP, this waits for the
+	/// engine to confirm it.
+	pub fn finish_game(&mut self, result: GameResult) -> Result<(), ExternalEngineError> {
+		if self.protocol != Protocol::Dxp {
+			return Ok(());
+		}
+
+		let reason = match result {
+			GameResult::Draw => EndReason::Draw,
+			GameResult::Unfinished => EndReason::Unknown,
+			result if result == GameResult::loss_for(self.color) => EndReason::Loss,
+			_ => EndReason::Win,
+		};
+		dxp::write_message(
+			&mut self.writer,
+			&Message::GameEnd {
+				reason,
+				stop: false,
+			},
+		)?;
+
+		loop {
+			if let Message::GameEnd { .. } = dxp::expect_message(&mut self.reader)? {
+				return Ok(());
+			}
+		}
+	}
+}
+
+impl Drop for ExternalEngine {
+	fn drop(&mut self) {
+		let _ = match self.protocol {
+			Protocol::Hub => self.send("quit"),
+			Protocol::JsonRpc => self.send(r#"{"jsonrpc": "2.0", "method": "quit"}"#),
+			Protocol::Dxp => dxp::write_message(
+				&mut self.writer,
+				&Message::GameEnd {
+					reason: EndReason::Unknown,
+					stop: true,
+				},
+			),
+		};
+
+		let Some(child) = &mut self.child else {
+			return;
+		};
+
+		let started = Instant::now();
+		while started.elapsed() < QUIT_TIMEOUT {
+			if let Ok(Some(_)) = child.try_wait() {
+				return;
+			}
+			std::thread::sleep(Duration::from_millis(10));
+		}
+
+		let _ = child.kill();
+		let _ = child.wait();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::notation::parse_position;
+
+	#[test]
+	fn position_strings() {
+		let start = CheckersBitBoard::starting_position();
+		assert_eq!(
+			position_string(start, 'B'),
+			"Bbbbbbbbbbbbbeeeeeeeewwwwwwwwwwww"
+		);
+
+		let position = crate::notation::parse_fen("W:WK5,18:B14,K30").unwrap();
+		assert_eq!(
+			parse_position(&position_string(position, 'B'), 'B'),
+			Some(position)
+		);
+	}
+
+	#[test]
+	fn parses_protocols() {
+		assert_eq!("hub".parse::<Protocol>().unwrap(), Protocol::Hub);
+		assert_eq!("JSON".parse::<Protocol>().unwrap(), Protocol::JsonRpc);
+		assert!("uci".parse::<Protocol>().is_err());
+	}
+}
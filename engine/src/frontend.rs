@@ -0,0 +1,174 @@
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+use model::Move;
+use parking_lot::Mutex;
+
+use crate::eval::Evaluation;
+
+/// Receives information from the engine while it searches
+pub trait Frontend: Send + Sync {
+	/// Called each time the search finishes searching to a new depth
+	fn on_depth_complete(&self, _info: &SearchInfo) {}
+
+	/// Called when the search starts searching a root move. The index is the
+	/// position of the move in the search order, starting at zero.
+	fn on_current_move(&self, _current_move: Move, _index: usize) {}
+
+	/// A message for the user, which doesn't fit any other callback
+	fn on_string(&self, msg: &str);
+
+	/// Called when a background search fails. By default, the message is
+	/// passed to [`Frontend::on_string`].
+	fn on_error(&self, msg: &str) {
+		self.on_string(msg);
+	}
+
+	fn report_best_move(&self, best_move: Move);
+}
+
+/// The state of the search after an iteration is completed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchInfo {
+	pub depth: u8,
+	/// The deepest ply that the search has reached so far
+	pub selective_depth: u8,
+	/// The evaluation of the position, for the player whose turn it is
+	pub eval: Evaluation,
+	pub best_move: Option<Move>,
+	/// The number of nodes searched so far
+	pub nodes: usize,
+	/// How long the search has been running
+	pub time: Duration,
+}
+
+impl SearchInfo {
+	pub fn nodes_per_second(&self) -> u64 {
+		(self.nodes as f64 / self.time.as_secs_f64().max(f64::EPSILON)) as u64
+	}
+}
+
+impl Display for SearchInfo {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"depth {} seldepth {} score {} nodes {} time {} nps {}",
+			self.depth,
+			self.selective_depth,
+			self.eval,
+			self.nodes,
+			self.time.as_millis(),
+			self.nodes_per_second()
+		)?;
+
+		if let Some(best_move) = self.best_move {
+			write!(f, " pv {best_move}")?;
+		}
+
+		Ok(())
+	}
+}
+
+/// Adapts the structured callbacks into lines of text, which are passed to
+/// the given function
+pub struct TextFrontend<F: Fn(&str) + Send + Sync> {
+	output: F,
+}
+
+impl<F: Fn(&str) + Send + Sync> TextFrontend<F> {
+	pub fn new(output: F) -> Self {
+		Self { output }
+	}
+}
+
+impl<F: Fn(&str) + Send + Sync> Frontend for TextFrontend<F> {
+	fn on_depth_complete(&self, info: &SearchInfo) {
+		(self.output)(&format!("info {info}"));
+	}
+
+	fn on_current_move(&self, current_move: Move, index: usize) {
+		(self.output)(&format!(
+			"info currmove {current_move} currmovenumber {}",
+			index + 1
+		));
+	}
+
+	fn on_string(&self, msg: &str) {
+		(self.output)(&format!("info string {msg}"));
+	}
+
+	fn report_best_move(&self, best_move: Move) {
+		(self.output)(&format!("bestmove {best_move}"));
+	}
+}
+
+/// Keeps the information from the latest completed depth, and ignores
+/// everything else
+#[derive(Debug, Default)]
+pub struct InfoFrontend {
+	info: Mutex<Option<SearchInfo>>,
+}
+
+impl InfoFrontend {
+	/// The information from the latest completed depth
+	pub fn latest(&self) -> Option<SearchInfo> {
+		*self.info.lock()
+	}
+
+	/// Forgets the latest information, so the next search starts fresh
+	pub fn clear(&self) {
+		*self.info.lock() = None;
+	}
+}
+
+impl Frontend for InfoFrontend {
+	fn on_depth_complete(&self, info: &SearchInfo) {
+		*self.info.lock() = Some(*info);
+	}
+
+	fn on_string(&self, _msg: &str) {}
+
+	fn report_best_move(&self, _best_move: Move) {}
+}
+
+/// A frontend that ignores everything it's given
+pub(crate) struct SilentFrontend;
+
+impl Frontend for SilentFrontend {
+	fn on_string(&self, _msg: &str) {}
+
+	fn report_best_move(&self, _best_move: Move) {}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use model::PossibleMoves;
+
+	use super::*;
+	use crate::parse_fen;
+
+	#[test]
+	fn text_moves_use_standard_notation() {
+		let lines = Arc::new(Mutex::new(Vec::new()));
+		let output = Arc::clone(&lines);
+		let frontend = TextFrontend::new(move |line: &str| output.lock().push(line.to_string()));
+
+		let board = parse_fen("W:W18:B14").unwrap();
+		let jump = PossibleMoves::moves(board).into_iter().next().unwrap();
+		frontend.on_depth_complete(&SearchInfo {
+			depth: 1,
+			selective_depth: 1,
+			eval: Evaluation::DRAW,
+			best_move: Some(jump),
+			nodes: 1,
+			time: Duration::from_millis(1),
+		});
+		frontend.report_best_move(jump);
+
+		let lines = lines.lock();
+		assert!(lines[0].ends_with(" pv 18x9"), "{}", lines[0]);
+		assert_eq!(lines[1], "bestmove 18x9");
+	}
+}
@@ -0,0 +1,337 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, ToSocketAddrs};
+use std::sync::Arc;
+
+use model::CheckersBitBoard;
+use serde_json::{json, Value};
+
+use crate::eval::eval_position;
+use crate::frontend::InfoFrontend;
+use crate::json_rpc::{evaluation_settings, move_strings};
+use crate::notation::{complete_line, full_moves, parse_fen, FullMove};
+use crate::options::MEGABYTE;
+use crate::Engine;
+
+/// The default address for the server
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+
+/// The largest request body that is accepted
+const MAX_BODY_LENGTH: usize = 1 << 16;
+
+/// A request from a client. Only the parts of HTTP the server needs are kept.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Request {
+	method: String,
+	path: String,
+	query: Vec<(String, String)>,
+	body: Vec<u8>,
+}
+
+impl Request {
+	fn query(&self, key: &str) -> Option<&str> {
+		self.query
+			.iter()
+			.find(|(name, _)| name == key)
+			.map(|(_, value)| value.as_str())
+	}
+}
+
+/// Decodes a URL component, where `+` is a space and `%3A` is a byte
+fn percent_decode(value: &str) -> String {
+	let mut bytes = Vec::with_capacity(value.len());
+	let mut rest = value.as_bytes();
+	while let Some((&byte, tail)) = rest.split_first() {
+		rest = tail;
+		match byte {
+			b'+' => bytes.push(b' '),
+			b'%' => {
+				let hex = rest
+					.get(..2)
+					.and_then(|hex| std::str::from_utf8(hex).ok())
+					.and_then(|hex| u8::from_str_radix(hex, 16).ok());
+				match hex {
+					Some(decoded) => {
+						bytes.push(decoded);
+						rest = &rest[2..];
+					}
+					None => bytes.push(b'%'),
+				}
+			}
+			_ => bytes.push(byte),
+		}
+	}
+
+	String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Reads a request. Returns `Ok(None)` if the request isn't valid HTTP.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+	let mut line = String::new();
+	reader.read_line(&mut line)?;
+	let mut parts = line.split_whitespace();
+	let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+		return Ok(None);
+	};
+
+	let mut content_length = 0;
+	loop {
+		let mut header = String::new();
+		if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+			break;
+		}
+
+		if let Some((name, value)) = header.split_once(':') {
+			if name.trim().eq_ignore_ascii_case("content-length") {
+				content_length = value.trim().parse().unwrap_or(0);
+			}
+		}
+	}
+
+	if content_length > MAX_BODY_LENGTH {
+		return Ok(None);
+	}
+
+	let mut body = vec![0; content_length];
+	reader.read_exact(&mut body)?;
+
+	let (path, query) = target.split_once('?').unwrap_or((target, ""));
+	let query = query
+		.split('&')
+		.filter(|pair| !pair.is_empty())
+		.map(|pair| {
+			let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+			(percent_decode(key), percent_decode(value))
+		})
+		.collect();
+
+	Ok(Some(Request {
+		method: method.to_string(),
+		path: path.to_string(),
+		query,
+		body,
+	}))
+}
+
+fn write_response(writer: &mut impl Write, status: u16, body: &Value) -> io::Result<()> {
+	let reason = match status {
+		200 => "OK",
+		400 => "Bad Request",
+		404 => "Not Found",
+		405 => "Method Not Allowed",
+		_ => "Internal Server Error",
+	};
+	let body = body.to_string();
+
+	write!(
+		writer,
+		"HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+		body.len()
+	)?;
+	writer.flush()
+}
+
+/// An error, which is sent to the client with its status code
+struct HttpError {
+	status: u16,
+	message: String,
+}
+
+impl HttpError {
+	fn bad_request(message: impl Into<String>) -> Self {
+		Self {
+			status: 400,
+			message: message.into(),
+		}
+	}
+}
+
+impl From<String> for HttpError {
+	fn from(message: String) -> Self {
+		Self::bad_request(message)
+	}
+}
+
+struct Server {
+	engine: Engine,
+	frontend: Arc<InfoFrontend>,
+}
+
+impl Server {
+	fn new(transposition_table_size: usize) -> Self {
+		let frontend = Arc::new(InfoFrontend::default());
+		Self {
+			engine: Engine::new(transposition_table_size, frontend.clone()),
+			frontend,
+		}
+	}
+
+	/// Finds the position from a FEN, or the starting position, and then
+	/// plays the moves. The engine's position is set to the result.
+	fn set_position(&self, fen: Option<&str>, moves: &str) -> Result<CheckersBitBoard, HttpError> {
+		let position = match fen {
+			Some(fen) => parse_fen(fen).ok_or_else(|| HttpError::bad_request("invalid FEN"))?,
+			None => CheckersBitBoard::starting_position(),
+		};
+
+		self.engine
+			.play_notation(position, moves)
+			.map_err(|error| HttpError::bad_request(error.to_string()))?;
+		Ok(self.engine.current_position())
+	}
+
+	/// Searches the position in the body, and returns the best move, score,
+	/// and expected line
+	fn analyse(&self, body: &[u8]) -> Result<Value, HttpError> {
+		let params: Value = serde_json::from_slice(body)
+			.map_err(|error| HttpError::bad_request(error.to_string()))?;
+		let fen = params.get("fen").and_then(Value::as_str);
+		let moves = move_strings(&params, "moves")?;
+		let settings = evaluation_settings(&params)?;
+
+		let position = self.set_position(fen, &moves.join(" "))?;
+		self.frontend.clear();
+		let (eval, best_move) = self.engine.evaluate(None, settings);
+
+		let pv: Vec<String> = complete_line(position, &self.engine.expected_line(best_move))
+			.iter()
+			.map(FullMove::long_notation)
+			.collect();
+
+		let info = self.frontend.latest();
+		Ok(json!({
+			"best_move": pv.first(),
+			"score": eval.to_string(),
+			"depth": info.map_or(0, |info| info.depth),
+			"nodes": info.map_or(0, |info| info.nodes),
+			"pv": pv,
+		}))
+	}
+
+	fn handle(&self, request: &Request) -> Result<Value, HttpError> {
+		let query_position = || {
+			self.set_position(
+				request.query("fen"),
+				request.query("moves").unwrap_or_default(),
+			)
+		};
+
+		match (request.method.as_str(), request.path.as_str()) {
+			("POST", "/analyse") => self.analyse(&request.body),
+			("GET", "/legal-moves") => {
+				let moves: Vec<String> = full_moves(query_position()?)
+					.iter()
+					.map(FullMove::long_notation)
+					.collect();
+				Ok(json!({ "moves": moves }))
+			}
+			("GET", "/eval") => {
+				let eval = eval_position(query_position()?, &self.engine.eval_params());
+				Ok(json!({ "score": eval.to_string() }))
+			}
+			(_, "/analyse" | "/legal-moves" | "/eval") => Err(HttpError {
+				status: 405,
+				message: format!("{} isn't allowed here", request.method),
+			}),
+			_ => Err(HttpError {
+				status: 404,
+				message: format!("there is nothing at {}", request.path),
+			}),
+		}
+	}
+}
+
+/// Serves analysis requests over HTTP, one at a time, until the listener
+/// fails. The endpoints are:
+///
+/// - `POST /analyse`, with a JSON body containing a `fen`, `moves`, and the
+///   limits `depth`, `nodes` and `move_time`, in seconds. The best move,
+///   score, and expected line are returned.
+/// - `GET /legal-moves?fen=...&moves=...`
+/// - `GET /eval?fen=...&moves=...`, which returns the static evaluation.
+///
+/// Moves are written with every square the piece lands on, such as
+/// `9x18x27`, and the starting position is used if there's no FEN.
+pub fn serve(address: impl ToSocketAddrs, hash_megabytes: usize) -> io::Result<()> {
+	let listener = TcpListener::bind(address)?;
+	let server = Server::new(hash_megabytes.max(1) * MEGABYTE);
+
+	for stream in listener.incoming() {
+		let stream = stream?;
+		let mut reader = BufReader::new(&stream);
+		let mut writer = &stream;
+
+		let result = match read_request(&mut reader) {
+			Ok(Some(request)) => server.handle(&request),
+			Ok(None) => Err(HttpError::bad_request("invalid request")),
+			Err(error) => {
+				tracing::debug!(%error, "failed to read a request");
+				continue;
+			}
+		};
+
+		let (status, body) = match result {
+			Ok(body) => (200, body),
+			Err(error) => (error.status, json!({ "error": error.message })),
+		};
+
+		if let Err(error) = write_response(&mut writer, status, &body) {
+			tracing::debug!(%error, "failed to send a response");
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn request(method: &str, target: &str, body: &str) -> Request {
+		let raw = format!(
+			"{method} {target} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+			body.len()
+		);
+		read_request(&mut raw.as_bytes()).unwrap().unwrap()
+	}
+
+	#[test]
+	fn parses_requests() {
+		let request = request("GET", "/eval?fen=B%3AW21-32%3AB1-12&moves=11-15+23-19", "");
+		assert_eq!(request.method, "GET");
+		assert_eq!(request.path, "/eval");
+		assert_eq!(request.query("fen"), Some("B:W21-32:B1-12"));
+		assert_eq!(request.query("moves"), Some("11-15 23-19"));
+	}
+
+	#[test]
+	fn legal_moves() {
+		let server = Server::new(1 << 12);
+		let response = server
+			.handle(&request("GET", "/legal-moves?moves=11-15", ""))
+			.ok()
+			.unwrap();
+		assert_eq!(response["moves"].as_array().unwrap().len(), 7);
+	}
+
+	#[test]
+	fn analyse() {
+		let server = Server::new(1 << 12);
+		let body = r#"{"fen": "B:W21-32:B1-12", "depth": 3}"#;
+		let response = server
+			.handle(&request("POST", "/analyse", body))
+			.ok()
+			.unwrap();
+		assert!(response["best_move"].is_string());
+		assert_eq!(response["depth"], 3);
+	}
+
+	#[test]
+	fn unknown_path() {
+		let server = Server::new(1 << 12);
+		let error = server
+			.handle(&request("GET", "/nothing", ""))
+			.err()
+			.unwrap();
+		assert_eq!(error.status, 404);
+	}
+}
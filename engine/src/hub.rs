@@ -0,0 +1,465 @@
+use std::io::{self, BufRead};
+use std::num::{NonZeroU8, NonZeroUsize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, Weak};
+use std::time::Duration;
+
+use model::{CheckersBitBoard, Move};
+
+use crate::notation::{
+	complete_line, full_moves, parse_position, square_from_number, square_number, FullMove,
+};
+use crate::options::{DEFAULT_HASH_SIZE, MEGABYTE};
+use crate::{
+	ActualLimit, Clock, Engine, EvaluationSettings, Frontend, OptionKind, SearchInfo, SearchLimit,
+	ENGINE_AUTHOR, ENGINE_NAME,
+};
+
+type Output = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// How long the engine may think, as set by the `level` command
+#[derive(Debug, Default, Clone)]
+struct Level {
+	depth: Option<NonZeroU8>,
+	nodes: Option<NonZeroUsize>,
+	move_time: Option<Duration>,
+	time: Option<Duration>,
+	increment: Duration,
+	moves: Option<u32>,
+	infinite: bool,
+}
+
+impl Level {
+	fn settings(&self) -> EvaluationSettings {
+		let search_until = if self.infinite {
+			SearchLimit::Infinite
+		} else if self.depth.is_some() || self.nodes.is_some() {
+			SearchLimit::Limited(ActualLimit {
+				nodes: self.nodes,
+				depth: self.depth,
+				time: self.move_time,
+			})
+		} else {
+			SearchLimit::Auto
+		};
+
+		let clock = match (self.move_time, self.time) {
+			(Some(move_time), _) => Clock::TimePerMove(move_time),
+			// the GUI only sends the engine's own time, so both sides get it
+			(None, Some(time)) => Clock::Standard {
+				white_time_remaining: time,
+				black_time_remaining: time,
+				white_increment: self.increment,
+				black_increment: self.increment,
+				moves_until_next_time_control: self.moves.map(|moves| (moves, time)),
+			},
+			(None, None) => Clock::default(),
+		};
+
+		EvaluationSettings {
+			clock,
+			search_until,
+			..EvaluationSettings::default()
+		}
+	}
+}
+
+/// Sends the engine's search results to the GUI as Hub messages
+struct HubFrontend {
+	output: Output,
+	engine: OnceLock<Weak<Engine>>,
+	/// Set when the result of a search shouldn't be sent, such as when
+	/// pondering is stopped by `ponder-hit`
+	discard_result: AtomicBool,
+}
+
+impl HubFrontend {
+	fn engine(&self) -> Option<Arc<Engine>> {
+		self.engine.get()?.upgrade()
+	}
+
+	/// Converts the engine's expected line into complete moves, starting
+	/// with `best_move`
+	fn line(&self, best_move: Move) -> (CheckersBitBoard, Vec<FullMove>) {
+		let Some(engine) = self.engine() else {
+			return (CheckersBitBoard::starting_position(), Vec::new());
+		};
+
+		let root = engine.current_position();
+		(
+			root,
+			complete_line(root, &engine.expected_line(Some(best_move))),
+		)
+	}
+}
+
+impl Frontend for HubFrontend {
+	fn on_depth_complete(&self, info: &SearchInfo) {
+		let pv = match info.best_move {
+			Some(best_move) => {
+				let (_, line) = self.line(best_move);
+				line.iter().map(hub_move).collect::<Vec<_>>().join(" ")
+			}
+			None => String::new(),
+		};
+
+		(self.output)(&format!(
+			"info depth={} mean-depth={} score={} nodes={} time={:.3} nps={} pv=\"{pv}\"",
+			info.depth,
+			info.selective_depth,
+			info.eval,
+			info.nodes,
+			info.time.as_secs_f64(),
+			info.nodes_per_second(),
+		));
+	}
+
+	fn on_string(&self, msg: &str) {
+		(self.output)(&format!("info message={}", quote(msg)));
+	}
+
+	fn on_error(&self, msg: &str) {
+		(self.output)(&format!("error message={}", quote(msg)));
+	}
+
+	fn report_best_move(&self, best_move: Move) {
+		if self.discard_result.swap(false, Ordering::AcqRel) {
+			return;
+		}
+
+		let (_, line) = self.line(best_move);
+		let Some(full_move) = line.first() else {
+			return;
+		};
+
+		match line.get(1) {
+			Some(ponder) => (self.output)(&format!(
+				"done move={} ponder={}",
+				hub_move(full_move),
+				hub_move(ponder)
+			)),
+			None => (self.output)(&format!("done move={}", hub_move(full_move))),
+		}
+	}
+}
+
+/// Writes a move in Hub notation. Captures list the starting square, the
+/// ending square, and then the captured squares.
+fn hub_move(full_move: &FullMove) -> String {
+	let from = square_number(full_move.from());
+	let to = square_number(full_move.to());
+	if !full_move.is_capture() {
+		return format!("{from}-{to}");
+	}
+
+	let mut notation = format!("{from}x{to}");
+	for captured in full_move.captured_numbers() {
+		notation.push_str(&format!("x{captured}"));
+	}
+
+	notation
+}
+
+/// Finds the legal move written in Hub notation. The captured squares may be
+/// left out, as long as the move isn't ambiguous.
+pub(crate) fn parse_hub_move(position: CheckersBitBoard, notation: &str) -> Option<FullMove> {
+	let numbers = notation
+		.split(['-', 'x'])
+		.map(|number| number.parse().ok().and_then(square_from_number))
+		.collect::<Option<Vec<usize>>>()?;
+	let [from, to, captured @ ..] = numbers.as_slice() else {
+		return None;
+	};
+
+	let captured = captured
+		.iter()
+		.fold(0u32, |bits, square| bits | 1 << square);
+	full_moves(position).into_iter().find(|full_move| {
+		full_move.from() == *from
+			&& full_move.to() == *to
+			&& (captured == 0 || full_move.captured == captured)
+	})
+}
+
+/// Quotes a value if it contains spaces, so that it's read as one value
+fn quote(value: &str) -> String {
+	if value.is_empty() || value.contains(char::is_whitespace) {
+		format!("\"{}\"", value.replace('"', "'"))
+	} else {
+		value.to_string()
+	}
+}
+
+/// Splits a Hub message into its command, and its `key=value` arguments.
+/// Values with spaces are written in double quotes. An argument without a
+/// value, such as `infinite`, has an empty value.
+pub(crate) fn parse_message(line: &str) -> Option<(&str, Vec<(&str, &str)>)> {
+	let line = line.trim();
+	let (command, mut rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+	if command.is_empty() {
+		return None;
+	}
+
+	let mut arguments = Vec::new();
+	loop {
+		rest = rest.trim_start();
+		if rest.is_empty() {
+			break;
+		}
+
+		let key_end = rest
+			.find(|c: char| c == '=' || c.is_whitespace())
+			.unwrap_or(rest.len());
+		let key = &rest[..key_end];
+		rest = &rest[key_end..];
+
+		let value = if let Some(value) = rest.strip_prefix('=') {
+			if let Some(quoted) = value.strip_prefix('"') {
+				let end = quoted.find('"')?;
+				rest = &quoted[end + 1..];
+				&quoted[..end]
+			} else {
+				let end = value.find(char::is_whitespace).unwrap_or(value.len());
+				rest = &value[end..];
+				&value[..end]
+			}
+		} else {
+			""
+		};
+
+		arguments.push((key, value));
+	}
+
+	Some((command, arguments))
+}
+
+pub(crate) fn argument<'a>(arguments: &[(&str, &'a str)], key: &str) -> Option<&'a str> {
+	arguments
+		.iter()
+		.find(|(name, _)| *name == key)
+		.map(|(_, value)| *value)
+}
+
+fn seconds(value: &str) -> Option<Duration> {
+	value
+		.parse::<f64>()
+		.ok()
+		.and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+}
+
+/// Runs the Hub protocol, reading messages from `input` until it is closed or
+/// `quit` is received. Each message for the GUI is passed to `output`, without
+/// a newline.
+pub fn run(input: impl BufRead, output: impl Fn(&str) + Send + Sync + 'static) -> io::Result<()> {
+	let output: Output = Arc::new(output);
+	let frontend = Arc::new(HubFrontend {
+		output: output.clone(),
+		engine: OnceLock::new(),
+		discard_result: AtomicBool::new(false),
+	});
+	let engine = Arc::new(Engine::new(
+		DEFAULT_HASH_SIZE as usize * MEGABYTE,
+		frontend.clone(),
+	));
+	let _ = frontend.engine.set(Arc::downgrade(&engine));
+
+	let mut level = Level::default();
+	let mut pondering = false;
+	for line in input.lines() {
+		let line = line?;
+		let Some((command, arguments)) = parse_message(&line) else {
+			continue;
+		};
+
+		match command {
+			"hub" => {
+				output(&format!(
+					"id name={} version={} author={}",
+					quote(ENGINE_NAME),
+					env!("CARGO_PKG_VERSION"),
+					quote(ENGINE_AUTHOR)
+				));
+				for option in engine.options() {
+					let kind = match option.kind {
+						OptionKind::Check => "bool".to_string(),
+						OptionKind::Spin { min, max } => format!("int min={min} max={max}"),
+						OptionKind::Float { min, max } => format!("real min={min} max={max}"),
+						OptionKind::String => "string".to_string(),
+						OptionKind::Combo { values } => {
+							format!("enum values={}", quote(&values.join(" ")))
+						}
+					};
+					output(&format!(
+						"param name={} value={} type={kind}",
+						quote(option.name),
+						quote(&option.value.to_string())
+					));
+				}
+				output("wait");
+			}
+			"init" => output("ready"),
+			"ping" => output("pong"),
+			"new-game" => {
+				engine.stop_evaluation();
+				engine.reset_position();
+			}
+			"set-param" => {
+				let (Some(name), Some(value)) =
+					(argument(&arguments, "name"), argument(&arguments, "value"))
+				else {
+					output("error message=\"set-param needs a name and a value\"");
+					continue;
+				};
+
+				if let Err(error) = engine.set_option(name, value) {
+					output(&format!("error message={}", quote(&error.to_string())));
+				}
+			}
+			"pos" => {
+				let position = match argument(&arguments, "pos") {
+					Some(position) => parse_position(position, 'B'),
+					None => Some(CheckersBitBoard::starting_position()),
+				};
+				let Some(mut position) = position else {
+					output("error message=\"invalid position\"");
+					continue;
+				};
+
+				let moves = argument(&arguments, "moves").unwrap_or_default();
+				let mut legal = true;
+				for notation in moves.split_whitespace() {
+					match parse_hub_move(position, notation) {
+						Some(full_move) => position = full_move.result,
+						None => {
+							output(&format!(
+								"error message={}",
+								quote(&format!("illegal move {notation}"))
+							));
+							legal = false;
+							break;
+						}
+					}
+				}
+
+				if legal {
+					engine.set_position(position);
+				}
+			}
+			"level" => {
+				level = Level::default();
+				for (key, value) in arguments {
+					match key {
+						"depth" => level.depth = value.parse().ok(),
+						"nodes" => level.nodes = value.parse().ok(),
+						"move-time" => level.move_time = seconds(value),
+						"time" => level.time = seconds(value),
+						"inc" => level.increment = seconds(value).unwrap_or_default(),
+						"moves" => level.moves = value.parse().ok(),
+						"infinite" => level.infinite = true,
+						_ => {}
+					}
+				}
+			}
+			"go" => {
+				let mode = arguments.first().map_or("think", |(mode, _)| *mode);
+				pondering = mode == "ponder";
+				let mut settings = level.settings();
+				if mode != "think" {
+					settings.search_until = SearchLimit::Infinite;
+				}
+
+				engine.start_evaluation(settings);
+			}
+			"ponder-hit" => {
+				if pondering {
+					pondering = false;
+					frontend.discard_result.store(true, Ordering::Release);
+					if engine.stop_evaluation().is_none() {
+						frontend.discard_result.store(false, Ordering::Release);
+					}
+					engine.start_evaluation(level.settings());
+				}
+			}
+			"stop" => {
+				pondering = false;
+				engine.stop_evaluation();
+			}
+			"quit" => break,
+			_ => output(&format!(
+				"error message={}",
+				quote(&format!("unknown command {command}"))
+			)),
+		}
+	}
+
+	engine.stop_evaluation();
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Write;
+	use std::sync::{mpsc, Mutex};
+
+	use super::*;
+
+	#[test]
+	fn parses_messages() {
+		let (command, arguments) =
+			parse_message("pos pos=Wbbbbbbbbbbbbeeeeeeeewwwwwwwwwwww moves=\"11-15 23-19\"")
+				.unwrap();
+		assert_eq!(command, "pos");
+		assert_eq!(
+			argument(&arguments, "pos"),
+			Some("Wbbbbbbbbbbbbeeeeeeeewwwwwwwwwwww")
+		);
+		assert_eq!(argument(&arguments, "moves"), Some("11-15 23-19"));
+	}
+
+	#[test]
+	fn starting_position() {
+		let position = parse_position("Bbbbbbbbbbbbbeeeeeeeewwwwwwwwwwww", 'B').unwrap();
+		assert_eq!(position, CheckersBitBoard::starting_position());
+	}
+
+	#[test]
+	fn plays_a_move() {
+		let lines = Arc::new(Mutex::new(Vec::new()));
+		let output = lines.clone();
+		let input = "hub\ninit\npos moves=\"11-15 23-19\"\nlevel depth=3\ngo think\n";
+		run(input.as_bytes(), move |line| {
+			output.lock().unwrap().push(line.to_string())
+		})
+		.unwrap();
+
+		let lines = lines.lock().unwrap();
+		assert!(lines.iter().any(|line| line == "wait"));
+		assert!(lines.iter().any(|line| line == "ready"));
+		assert!(lines.iter().any(|line| line.starts_with("done move=")));
+	}
+
+	#[test]
+	fn plays_a_forced_win() {
+		let (reader, mut writer) = io::pipe().unwrap();
+		let (sender, receiver) = mpsc::channel();
+		let hub = std::thread::spawn(move || {
+			run(io::BufReader::new(reader), move |line| {
+				let _ = sender.send(line.to_string());
+			})
+		});
+
+		// white captures the last black piece with 18x9, and the GUI waits
+		// for the result instead of stopping the search
+		writer
+			.write_all(b"pos pos=Weeeeeeeeeeeeebeeeweeeeeeeeeeeeee\nlevel depth=5\ngo think\n")
+			.unwrap();
+		let done = receiver
+			.iter()
+			.find(|line| line.starts_with("done"))
+			.unwrap();
+		assert_eq!(done, "done move=18x9x14");
+
+		drop(writer);
+		hub.join().unwrap().unwrap();
+	}
+}
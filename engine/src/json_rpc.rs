@@ -0,0 +1,429 @@
+use std::io::{self, BufRead};
+use std::num::{NonZeroU8, NonZeroUsize};
+use std::sync::{Arc, OnceLock, Weak};
+use std::time::Duration;
+
+use model::{CheckersBitBoard, Move};
+use serde_json::{json, Value};
+
+use crate::notation::parse_position;
+use crate::options::{DEFAULT_HASH_SIZE, MEGABYTE};
+use crate::{
+	ActualLimit, Clock, Engine, EvaluationSettings, Frontend, OptionKind, SearchInfo, SearchLimit,
+};
+
+// error codes defined by JSON-RPC 2.0
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+type Output = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// An error which is sent in response to a request
+struct RpcError {
+	code: i64,
+	message: String,
+}
+
+impl RpcError {
+	fn invalid_params(message: impl Into<String>) -> Self {
+		Self {
+			code: INVALID_PARAMS,
+			message: message.into(),
+		}
+	}
+}
+
+fn notification(method: &str, params: Value) -> String {
+	json!({ "jsonrpc": "2.0", "method": method, "params": params }).to_string()
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> String {
+	match result {
+		Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+		Err(error) => json!({
+			"jsonrpc": "2.0",
+			"id": id,
+			"error": { "code": error.code, "message": error.message },
+		}),
+	}
+	.to_string()
+}
+
+/// Sends the engine's search results to the client as JSON-RPC notifications
+struct JsonFrontend {
+	output: Output,
+	engine: OnceLock<Weak<Engine>>,
+}
+
+impl JsonFrontend {
+	/// The engine's expected line, starting with `best_move`
+	fn line(&self, best_move: Move) -> Vec<Move> {
+		match self.engine.get().and_then(Weak::upgrade) {
+			Some(engine) => engine.expected_line(Some(best_move)),
+			None => vec![best_move],
+		}
+	}
+}
+
+fn move_list(moves: &[Move]) -> Vec<String> {
+	moves.iter().map(Move::to_string).collect()
+}
+
+impl Frontend for JsonFrontend {
+	fn on_depth_complete(&self, info: &SearchInfo) {
+		let pv = info
+			.best_move
+			.map(|best_move| move_list(&self.line(best_move)))
+			.unwrap_or_default();
+
+		(self.output)(&notification(
+			"info",
+			json!({
+				"depth": info.depth,
+				"selective_depth": info.selective_depth,
+				"score": info.eval.to_string(),
+				"nodes": info.nodes,
+				"time": info.time.as_secs_f64(),
+				"nps": info.nodes_per_second(),
+				"pv": pv,
+			}),
+		));
+	}
+
+	fn on_string(&self, msg: &str) {
+		(self.output)(&notification("message", json!({ "text": msg })));
+	}
+
+	fn on_error(&self, msg: &str) {
+		(self.output)(&notification("error", json!({ "text": msg })));
+	}
+
+	fn report_best_move(&self, best_move: Move) {
+		let line = self.line(best_move);
+		(self.output)(&notification(
+			"best_move",
+			json!({
+				"move": best_move.to_string(),
+				"ponder": line.get(1).map(Move::to_string),
+			}),
+		));
+	}
+}
+
+impl From<String> for RpcError {
+	fn from(message: String) -> Self {
+		Self::invalid_params(message)
+	}
+}
+
+/// Reads an optional parameter, failing if it can't be converted
+pub(crate) fn optional<'a, T>(
+	params: &'a Value,
+	key: &str,
+	convert: impl FnOnce(&'a Value) -> Option<T>,
+) -> Result<Option<T>, String> {
+	params
+		.get(key)
+		.filter(|value| !value.is_null())
+		.map(|value| convert(value).ok_or_else(|| format!("invalid value for {key}")))
+		.transpose()
+}
+
+/// Reads a list of moves, written as strings
+pub(crate) fn move_strings<'a>(params: &'a Value, key: &str) -> Result<Vec<&'a str>, String> {
+	let moves = optional(params, key, |value| {
+		value
+			.as_array()?
+			.iter()
+			.map(|notation| notation.as_str())
+			.collect::<Option<Vec<_>>>()
+	})?;
+
+	Ok(moves.unwrap_or_default())
+}
+
+/// Reads the `depth`, `nodes`, `move_time` and `infinite` limits of a search.
+/// The move time is in seconds.
+pub(crate) fn evaluation_settings(params: &Value) -> Result<EvaluationSettings, String> {
+	let depth = optional(params, "depth", |value| {
+		NonZeroU8::new(value.as_u64()?.try_into().ok()?)
+	})?;
+	let nodes = optional(params, "nodes", |value| {
+		NonZeroUsize::new(value.as_u64()?.try_into().ok()?)
+	})?;
+	let move_time = optional(params, "move_time", |value| {
+		Duration::try_from_secs_f64(value.as_f64()?).ok()
+	})?;
+	let infinite = optional(params, "infinite", Value::as_bool)?.unwrap_or(false);
+
+	let search_until = if infinite {
+		SearchLimit::Infinite
+	} else if depth.is_some() || nodes.is_some() {
+		SearchLimit::Limited(ActualLimit {
+			nodes,
+			depth,
+			time: move_time,
+		})
+	} else {
+		SearchLimit::Auto
+	};
+
+	Ok(EvaluationSettings {
+		clock: move_time.map_or_else(Clock::default, Clock::TimePerMove),
+		search_until,
+		..EvaluationSettings::default()
+	})
+}
+
+/// Sets the position to `position`, or the starting position, and then plays
+/// each of the `moves`
+fn set_position(engine: &Engine, params: &Value) -> Result<Value, RpcError> {
+	let position = optional(params, "position", |value| {
+		value
+			.as_str()
+			.and_then(|position| parse_position(position, 'B'))
+	})?
+	.unwrap_or_else(CheckersBitBoard::starting_position);
+	let moves = move_strings(params, "moves")?;
+
+	engine
+		.play_notation(position, &moves.join(" "))
+		.map_err(|error| RpcError::invalid_params(error.to_string()))?;
+	Ok(Value::Null)
+}
+
+/// Starts a search in the background. The best move is sent as a
+/// notification when the search finishes.
+fn go(engine: &Arc<Engine>, params: &Value) -> Result<Value, RpcError> {
+	engine.start_evaluation(evaluation_settings(params)?);
+	Ok(Value::Null)
+}
+
+/// Stops the search, and returns the best move it found
+fn stop(engine: &Engine) -> Value {
+	match engine.stop_evaluation() {
+		Some((eval, best_move)) => json!({
+			"score": eval.to_string(),
+			"move": best_move.map(|best_move| best_move.to_string()),
+		}),
+		None => Value::Null,
+	}
+}
+
+fn options(engine: &Engine) -> Value {
+	let options: Vec<Value> = engine
+		.options()
+		.into_iter()
+		.map(|option| {
+			let mut value = json!({
+				"name": option.name,
+				"value": option.value.to_string(),
+				"default": option.default.to_string(),
+			});
+			let kind = match option.kind {
+				OptionKind::Check => json!({ "type": "check" }),
+				OptionKind::Spin { min, max } => json!({ "type": "spin", "min": min, "max": max }),
+				OptionKind::Float { min, max } => {
+					json!({ "type": "float", "min": min, "max": max })
+				}
+				OptionKind::String => json!({ "type": "string" }),
+				OptionKind::Combo { values } => json!({ "type": "combo", "values": values }),
+			};
+			if let (Some(value), Value::Object(kind)) = (value.as_object_mut(), kind) {
+				value.extend(kind);
+			}
+
+			value
+		})
+		.collect();
+
+	Value::Array(options)
+}
+
+fn set_option(engine: &Engine, params: &Value) -> Result<Value, RpcError> {
+	let name = optional(params, "name", Value::as_str)?
+		.ok_or_else(|| RpcError::invalid_params("set_option needs a name"))?;
+	// numbers and booleans are accepted, as well as strings
+	let value = match params.get("value") {
+		Some(Value::String(value)) => value.clone(),
+		Some(value) if !value.is_null() => value.to_string(),
+		_ => return Err(RpcError::invalid_params("set_option needs a value")),
+	};
+
+	engine
+		.set_option(name, &value)
+		.map_err(|error| RpcError::invalid_params(error.to_string()))?;
+	Ok(Value::Null)
+}
+
+fn handle(engine: &Arc<Engine>, method: &str, params: &Value) -> Result<Value, RpcError> {
+	match method {
+		"set_position" => set_position(engine, params),
+		"go" => go(engine, params),
+		"stop" => Ok(stop(engine)),
+		"options" => Ok(options(engine)),
+		"set_option" => set_option(engine, params),
+		_ => Err(RpcError {
+			code: METHOD_NOT_FOUND,
+			message: format!("unknown method {method}"),
+		}),
+	}
+}
+
+/// Runs the JSON-RPC protocol, reading one request from each line of `input`
+/// until it is closed or `quit` is received. Each response and notification
+/// is passed to `output` as a single line of JSON, without a newline.
+///
+/// The methods are `set_position`, `go`, `stop`, `options`, `set_option` and
+/// `quit`. Moves are written the same way as [`Move`]'s `Display`
+/// implementation, such as `11-15` or `15x24`.
+pub fn run(input: impl BufRead, output: impl Fn(&str) + Send + Sync + 'static) -> io::Result<()> {
+	let output: Output = Arc::new(output);
+	let frontend = Arc::new(JsonFrontend {
+		output: output.clone(),
+		engine: OnceLock::new(),
+	});
+	let engine = Arc::new(Engine::new(
+		DEFAULT_HASH_SIZE as usize * MEGABYTE,
+		frontend.clone(),
+	));
+	let _ = frontend.engine.set(Arc::downgrade(&engine));
+
+	for line in input.lines() {
+		let line = line?;
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		let request: Value = match serde_json::from_str(&line) {
+			Ok(request) => request,
+			Err(error) => {
+				output(&response(
+					Value::Null,
+					Err(RpcError {
+						code: PARSE_ERROR,
+						message: error.to_string(),
+					}),
+				));
+				continue;
+			}
+		};
+
+		// requests without an id are notifications, which aren't answered
+		let id = request.get("id").cloned();
+		let Some(method) = request.get("method").and_then(Value::as_str) else {
+			output(&response(
+				id.unwrap_or(Value::Null),
+				Err(RpcError {
+					code: INVALID_REQUEST,
+					message: "the request has no method".to_string(),
+				}),
+			));
+			continue;
+		};
+
+		if method == "quit" {
+			if let Some(id) = id {
+				output(&response(id, Ok(Value::Null)));
+			}
+			break;
+		}
+
+		let params = request.get("params").unwrap_or(&Value::Null);
+		let result = handle(&engine, method, params);
+		if let Some(id) = id {
+			output(&response(id, result));
+		}
+	}
+
+	engine.stop_evaluation();
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Write;
+	use std::sync::{mpsc, Mutex};
+
+	use super::*;
+
+	fn run_lines(input: &str) -> Vec<Value> {
+		let lines = Arc::new(Mutex::new(Vec::new()));
+		let output = lines.clone();
+		run(input.as_bytes(), move |line| {
+			output.lock().unwrap().push(line.to_string())
+		})
+		.unwrap();
+
+		let lines = lines.lock().unwrap();
+		lines
+			.iter()
+			.map(|line| serde_json::from_str(line).unwrap())
+			.collect()
+	}
+
+	#[test]
+	fn plays_a_move() {
+		let messages = run_lines(concat!(
+			r#"{"jsonrpc": "2.0", "id": 1, "method": "set_position", "params": {"moves": ["11-15", "23-19"]}}"#,
+			"\n",
+			r#"{"jsonrpc": "2.0", "id": 2, "method": "go", "params": {"depth": 3}}"#,
+			"\n",
+		));
+
+		assert!(messages
+			.iter()
+			.any(|message| message["id"] == 1 && message["result"].is_null()));
+		assert!(messages.iter().any(|message| message["id"] == 2));
+		assert!(messages.iter().any(
+			|message| message["method"] == "best_move" && message["params"]["move"].is_string()
+		));
+	}
+
+	#[test]
+	fn plays_a_forced_win() {
+		let (reader, mut writer) = io::pipe().unwrap();
+		let (sender, receiver) = mpsc::channel();
+		let server = std::thread::spawn(move || {
+			run(io::BufReader::new(reader), move |line| {
+				let _ = sender.send(serde_json::from_str::<Value>(line).unwrap());
+			})
+		});
+
+		// white captures the last black piece with 18x9, and the client waits
+		// for the result instead of stopping the search
+		writer
+			.write_all(concat!(
+				r#"{"jsonrpc": "2.0", "id": 1, "method": "set_position", "params": {"position": "Weeeeeeeeeeeeebeeeweeeeeeeeeeeeee"}}"#,
+				"\n",
+				r#"{"jsonrpc": "2.0", "id": 2, "method": "go", "params": {"depth": 5}}"#,
+				"\n",
+			).as_bytes())
+			.unwrap();
+		let best_move = receiver
+			.iter()
+			.find(|message| message["method"] == "best_move")
+			.unwrap();
+		assert_eq!(best_move["params"]["move"], "18x9");
+
+		drop(writer);
+		server.join().unwrap().unwrap();
+	}
+
+	#[test]
+	fn reports_errors() {
+		let messages = run_lines(concat!(
+			"not json\n",
+			r#"{"jsonrpc": "2.0", "id": 1, "method": "fly"}"#,
+			"\n",
+			r#"{"jsonrpc": "2.0", "id": 2, "method": "set_position", "params": {"moves": ["1-2"]}}"#,
+			"\n",
+		));
+
+		assert_eq!(messages[0]["error"]["code"], PARSE_ERROR);
+		assert_eq!(messages[1]["error"]["code"], METHOD_NOT_FOUND);
+		assert_eq!(messages[2]["error"]["code"], INVALID_PARAMS);
+	}
+}
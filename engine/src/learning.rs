@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use model::{CheckersBitBoard, Move, PieceColor, PossibleMoves};
+use pdn::GameResult;
+use thiserror::Error;
+
+use crate::engine::EvaluationTask;
+use crate::eval::Evaluation;
+use crate::notation::FullMove;
+use crate::search::root_move_score;
+
+const MAGIC: u32 = u32::from_be_bytes(*b".aml");
+const SUPPORTED_VERSION: u16 = 0;
+
+/// The number of games a line must be played before it affects move choice
+const MIN_GAMES: u32 = 2;
+
+/// The penalty given to a line that has always been lost
+const MAX_PENALTY: f32 = 0.2;
+
+/// The results of the games where the engine moved into a position
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LearningEntry {
+	pub wins: u32,
+	pub draws: u32,
+	pub losses: u32,
+}
+
+impl LearningEntry {
+	pub const fn games(self) -> u32 {
+		self.wins + self.draws + self.losses
+	}
+
+	/// How strongly the line should be avoided, from 0 to 1
+	fn penalty(self) -> f32 {
+		let games = self.games();
+		if games < MIN_GAMES {
+			return 0.0;
+		}
+
+		(self.losses as f32 - self.wins as f32).max(0.0) / games as f32
+	}
+}
+
+/// The positions the engine has chosen to move into, and how those games
+/// ended
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LearningTable {
+	entries: HashMap<CheckersBitBoard, LearningEntry>,
+}
+
+#[derive(Debug, Error)]
+pub enum LearningFileError {
+	#[error("Invalid learning file: the magic header field was incorrect")]
+	MagicError,
+	#[error("This version of the learning file format is unsupported. Only {SUPPORTED_VERSION} is supported")]
+	UnsupportedVersion(u16),
+	#[error(transparent)]
+	IoError(#[from] io::Error),
+}
+
+impl LearningTable {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	pub fn get(&self, position: CheckersBitBoard) -> Option<LearningEntry> {
+		self.entries.get(&position).copied()
+	}
+
+	/// Records the result of a game. Each position is one that the engine
+	/// chose to move into, along with the color the engine was playing.
+	pub fn record(&mut self, positions: &[(CheckersBitBoard, PieceColor)], result: GameResult) {
+		// an unfinished game says nothing about its moves
+		if result == GameResult::Unfinished {
+			return;
+		}
+
+		for (position, color) in positions {
+			let entry = self.entries.entry(*position).or_default();
+			if result == GameResult::Draw {
+				entry.draws += 1;
+			} else if result == GameResult::loss_for(*color) {
+				entry.losses += 1;
+			} else {
+				entry.wins += 1;
+			}
+		}
+	}
+
+	pub fn read(reader: &mut impl Read) -> Result<Self, LearningFileError> {
+		let magic = reader.read_u32::<BigEndian>()?;
+		if magic != MAGIC {
+			return Err(LearningFileError::MagicError);
+		}
+
+		let version = reader.read_u16::<BigEndian>()?;
+		if version != SUPPORTED_VERSION {
+			return Err(LearningFileError::UnsupportedVersion(version));
+		}
+
+		let len = reader.read_u64::<BigEndian>()?;
+		let mut entries = HashMap::new();
+		for _ in 0..len {
+			let pieces = reader.read_u32::<BigEndian>()?;
+			let color = reader.read_u32::<BigEndian>()?;
+			let kings = reader.read_u32::<BigEndian>()?;
+			let turn = if reader.read_u8()? == PieceColor::Dark as u8 {
+				PieceColor::Dark
+			} else {
+				PieceColor::Light
+			};
+
+			let entry = LearningEntry {
+				wins: reader.read_u32::<BigEndian>()?,
+				draws: reader.read_u32::<BigEndian>()?,
+				losses: reader.read_u32::<BigEndian>()?,
+			};
+
+			entries.insert(CheckersBitBoard::new(pieces, color, kings, turn), entry);
+		}
+
+		Ok(Self { entries })
+	}
+
+	pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+		writer.write_u32::<BigEndian>(MAGIC)?;
+		writer.write_u16::<BigEndian>(SUPPORTED_VERSION)?;
+		writer.write_u64::<BigEndian>(self.entries.len() as u64)?;
+
+		for (board, entry) in &self.entries {
+			let pieces = board.pieces_bits();
+			writer.write_u32::<BigEndian>(pieces)?;
+			writer.write_u32::<BigEndian>(board.color_bits() & pieces)?;
+			writer.write_u32::<BigEndian>(board.king_bits() & pieces)?;
+			writer.write_u8(board.turn() as u8)?;
+			writer.write_u32::<BigEndian>(entry.wins)?;
+			writer.write_u32::<BigEndian>(entry.draws)?;
+			writer.write_u32::<BigEndian>(entry.losses)?;
+		}
+
+		Ok(())
+	}
+
+	/// Loads the learning file. If the file doesn't exist yet, then an empty
+	/// table is returned.
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, LearningFileError> {
+		match File::open(path) {
+			Ok(file) => Self::read(&mut BufReader::new(file)),
+			Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::new()),
+			Err(error) => Err(error.into()),
+		}
+	}
+
+	pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		self.write(&mut writer)?;
+		writer.flush()
+	}
+}
+
+/// The learning file in use by an engine, and the positions it has moved into
+/// during the current game
+#[derive(Debug)]
+pub(crate) struct Learning {
+	pub path: PathBuf,
+	pub table: Arc<LearningTable>,
+	pub game: Vec<(CheckersBitBoard, PieceColor)>,
+}
+
+impl Learning {
+	pub fn load(path: PathBuf) -> Result<Self, LearningFileError> {
+		let table = Arc::new(LearningTable::load(&path)?);
+		Ok(Self {
+			path,
+			table,
+			game: Vec::new(),
+		})
+	}
+
+	/// Records the result of the current game, and saves the learning file
+	pub fn finish_game(&mut self, result: GameResult) -> io::Result<()> {
+		Arc::make_mut(&mut self.table).record(&self.game, result);
+		self.game.clear();
+		self.table.save(&self.path)
+	}
+}
+
+/// How strongly the engine should avoid playing a move, from 0 to 1
+fn move_penalty(table: &LearningTable, board: CheckersBitBoard, m: Move) -> f32 {
+	let child = unsafe { m.apply_to(board) };
+	table.get(child).map_or(0.0, LearningEntry::penalty)
+}
+
+/// Avoids the best move if the engine has repeatedly lost after playing it.
+/// Each root move's score from the last finished iteration of the search is
+/// penalized by how often it was lost, and the highest scoring move is chosen.
+pub fn choose_move(task: &EvaluationTask, best_move: Move) -> Move {
+	let Some(table) = &task.learning else {
+		return best_move;
+	};
+
+	let board = task.position;
+	let penalty = |m: Move| move_penalty(table, board, m) * MAX_PENALTY;
+	if penalty(best_move) == 0.0 {
+		return best_move;
+	}
+
+	// if no iteration finished, the moves are estimated from the table instead
+	let searched = task.root_moves.lock().clone();
+	let estimate = |m: Move| (m, root_move_score(board, m, task));
+	let score = |(m, score): (Move, Evaluation)| (score.add_f32(-penalty(m)), m);
+	let best = if !searched.is_empty() {
+		searched
+			.into_iter()
+			.map(score)
+			.max_by_key(|(score, _)| *score)
+	} else if let Some(moves) = &task.allowed_moves {
+		moves
+			.iter()
+			.copied()
+			.map(estimate)
+			.map(score)
+			.max_by_key(|(score, _)| *score)
+	} else {
+		PossibleMoves::moves(board)
+			.into_iter()
+			.map(estimate)
+			.map(score)
+			.max_by_key(|(score, _)| *score)
+	};
+
+	best.map_or(best_move, |(_, m)| m)
+}
+
+/// The weight of a book move, after the lines the engine has lost are
+/// discounted. A line which has always been lost is never picked.
+pub fn book_weight(task: &EvaluationTask, full_move: &FullMove, weight: u32) -> f32 {
+	let Some(table) = &task.learning else {
+		return weight as f32;
+	};
+
+	weight as f32 * (1.0 - move_penalty(table, task.position, full_move.hops[0]))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::frontend::SilentFrontend;
+	use crate::notation::full_moves;
+	use crate::TranspositionTable;
+
+	fn lost_task(lost_move: Move) -> EvaluationTask {
+		let position = CheckersBitBoard::starting_position();
+		let child = unsafe { lost_move.apply_to(position) };
+		let mut table = LearningTable::new();
+		for _ in 0..MIN_GAMES {
+			table.record(
+				&[(child, position.turn())],
+				GameResult::loss_for(position.turn()),
+			);
+		}
+
+		EvaluationTask {
+			learning: Some(Arc::new(table)),
+			..EvaluationTask::new(
+				position,
+				Arc::new(TranspositionTable::new(1 << 16)),
+				Arc::new(SilentFrontend),
+			)
+		}
+	}
+
+	#[test]
+	fn record_results() {
+		let position = CheckersBitBoard::starting_position();
+		let mut table = LearningTable::new();
+		table.record(&[(position, PieceColor::Dark)], GameResult::WhiteWin);
+		table.record(&[(position, PieceColor::Dark)], GameResult::WhiteWin);
+		table.record(&[(position, PieceColor::Light)], GameResult::WhiteWin);
+
+		let entry = table.get(position).unwrap();
+		assert_eq!(entry.losses, 2);
+		assert_eq!(entry.wins, 1);
+		assert!(entry.penalty() > 0.0);
+	}
+
+	#[test]
+	fn file_round_trip() {
+		let mut table = LearningTable::new();
+		table.record(
+			&[(CheckersBitBoard::starting_position(), PieceColor::Dark)],
+			GameResult::Draw,
+		);
+
+		let mut buffer = Vec::new();
+		table.write(&mut buffer).unwrap();
+		let read = LearningTable::read(&mut buffer.as_slice()).unwrap();
+		assert_eq!(read, table);
+	}
+
+	#[test]
+	fn lost_move_uses_searched_scores() {
+		let moves = PossibleMoves::moves(CheckersBitBoard::starting_position());
+		let mut moves = moves.into_iter();
+		let (best, second) = (moves.next().unwrap(), moves.next().unwrap());
+		let task = lost_task(best);
+		task.root_moves.lock().push((best, Evaluation::new(0.1)));
+		task.root_moves.lock().push((second, Evaluation::new(0.05)));
+
+		// only the searched moves are considered, so the second is picked
+		assert_eq!(choose_move(&task, best), second);
+	}
+
+	#[test]
+	fn lost_book_line_is_never_picked() {
+		let full_move = full_moves(CheckersBitBoard::starting_position()).remove(0);
+		let task = lost_task(full_move.hops[0]);
+
+		assert_eq!(book_weight(&task, &full_move, 10), 0.0);
+	}
+}
@@ -2,17 +2,92 @@
 #![feature(maybe_uninit_uninit_array)]
 #![feature(maybe_uninit_slice)]
 
+pub use analysis::{Analysis, AnalysisFileError};
+pub use ballot::{
+	eleven_man_ballots, eleven_man_position, parse_eleven_man_ballot, ElevenManBallot,
+};
+pub use bench::{BenchResult, BENCH_DEPTH};
+pub use book::{BookFileError, OpeningBook};
+pub use config::{Config, ConfigError};
+pub use database::{
+	opening_stats, DatabaseFileError, GameDatabase, GameInfo, GameQuery, OpeningStats,
+	PositionStats, ResultCounts,
+};
+pub use diagram::{svg_diagram, text_diagram};
+pub use endgame::{solve_endgame, EndgameSolution, EndgameValue, TablebaseSet};
 pub use engine::{
-	ActualLimit, Clock, Engine, EvaluationSettings, Frontend, SearchLimit, ENGINE_ABOUT,
-	ENGINE_AUTHOR, ENGINE_NAME,
+	ActualLimit, Clock, Engine, EvaluationSettings, PlayMovesError, SearchAlgorithm, SearchLimit,
+	ENGINE_ABOUT, ENGINE_AUTHOR, ENGINE_NAME,
 };
-pub use eval::Evaluation;
+pub use eval::{eval_features, EvalFeatures, EvalParams, Evaluation};
+pub use frontend::{Frontend, InfoFrontend, SearchInfo, TextFrontend};
+pub use learning::{LearningEntry, LearningFileError, LearningTable};
+#[cfg(feature = "logging")]
+pub use logging::{FrontendLayer, LogError, LogSettings};
+pub use mate::forced_win;
 pub use model::{CheckersBitBoard, Move, MoveDirection, Piece, PieceColor, PossibleMoves};
+pub use notation::{
+	complete_line, fen, find_move, full_moves, parse_fen, parse_pdn, parse_position, pdn_tag,
+	split_pdn_games, square_from_number, square_number, FullMove, PdnError, PdnGame,
+};
+pub use options::{EngineOption, OptionError, OptionKind, OptionValue, MAX_CONTEMPT, MEGABYTE};
+pub use perft::{
+	perft, perft_complete_moves, perft_divide, perft_hashed, perft_parallel, PerftPosition,
+	PerftTable, PERFT_SUITE,
+};
+pub use pns::{prove, proven_line, Proof, ProofResult};
+pub use report::{html_report, GameReport};
+pub use skill::MAX_SKILL_LEVEL;
+pub use stats::SearchStats;
+pub use tactics::{find_combination, Combination};
 pub use transposition_table::{TranspositionTable, TranspositionTableRef};
+pub use tree::{SearchTree, TableEvent, TreeNode};
 
-pub mod c_abi;
+mod analysis;
+pub mod annotate;
+mod ballot;
+mod bench;
+mod book;
+#[cfg(feature = "checkerboard")]
+pub mod checkerboard;
+pub mod completions;
+mod config;
+mod database;
+mod diagram;
+pub mod dxp;
+mod endgame;
 mod engine;
 mod eval;
+pub mod export;
+pub mod external;
+mod frontend;
+pub mod http;
+pub mod hub;
+pub mod json_rpc;
 mod lazysort;
+mod learning;
+#[cfg(all(feature = "lidraughts", not(target_arch = "wasm32")))]
+pub mod lidraughts;
+#[cfg(feature = "logging")]
+mod logging;
+mod mate;
+mod mcts;
+mod notation;
+mod options;
+mod perft;
+mod pns;
+mod report;
+pub mod retrograde;
 mod search;
+pub mod selfplay;
+mod skill;
+mod stats;
+pub mod tablebase;
+mod tactics;
+pub mod test_suite;
+mod time;
+pub mod tournament;
 mod transposition_table;
+mod tree;
+#[cfg(all(feature = "tui", not(target_arch = "wasm32")))]
+pub mod tui;
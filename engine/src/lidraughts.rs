@@ -0,0 +1,345 @@
+use std::io::{self, BufRead, BufReader};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use model::{CheckersBitBoard, PieceColor};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::frontend::SilentFrontend;
+use crate::notation::{full_moves, parse_fen, square_number, FullMove};
+use crate::options::MEGABYTE;
+use crate::{Clock, Engine, EvaluationSettings};
+
+/// The server which is used by default
+pub const DEFAULT_SERVER: &str = "https://lidraughts.org";
+
+/// How the bot connects to lidraughts, and which games it plays
+#[derive(Debug, Clone)]
+pub struct BotSettings {
+	pub server: String,
+	/// The API token of the bot account
+	pub token: String,
+	/// The variants which are accepted. The engine only knows the rules of
+	/// American checkers, so only variants with those rules should be listed.
+	pub variants: Vec<String>,
+	/// The most games that are played at once. Challenges are declined while
+	/// this many games are running.
+	pub max_games: usize,
+	/// The size of each game's transposition table, in megabytes
+	pub hash_megabytes: usize,
+}
+
+impl BotSettings {
+	pub fn new(token: impl Into<String>) -> Self {
+		Self {
+			server: DEFAULT_SERVER.to_string(),
+			token: token.into(),
+			variants: Vec::new(),
+			max_games: 1,
+			hash_megabytes: 16,
+		}
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum BotError {
+	#[error(transparent)]
+	HttpError(#[from] Box<ureq::Error>),
+	#[error("Invalid response from the server: {0}")]
+	JsonError(#[from] serde_json::Error),
+	#[error("The server sent an invalid position: {0:?}")]
+	InvalidFen(String),
+	#[error("The server sent an illegal move: {0:?}")]
+	IllegalMove(String),
+	#[error(transparent)]
+	IoError(#[from] io::Error),
+}
+
+struct Client {
+	agent: ureq::Agent,
+	server: String,
+	token: String,
+}
+
+impl Client {
+	fn new(settings: &BotSettings) -> Self {
+		Self {
+			agent: ureq::AgentBuilder::new().build(),
+			server: settings.server.trim_end_matches('/').to_string(),
+			token: settings.token.clone(),
+		}
+	}
+
+	fn get(&self, path: &str) -> Result<ureq::Response, BotError> {
+		self.agent
+			.get(&format!("{}{path}", self.server))
+			.set("Authorization", &format!("Bearer {}", self.token))
+			.call()
+			.map_err(|error| BotError::HttpError(Box::new(error)))
+	}
+
+	fn post(&self, path: &str) -> Result<ureq::Response, BotError> {
+		self.agent
+			.post(&format!("{}{path}", self.server))
+			.set("Authorization", &format!("Bearer {}", self.token))
+			.call()
+			.map_err(|error| BotError::HttpError(Box::new(error)))
+	}
+
+	/// Reads a stream of newline-delimited JSON, calling `on_event` with each
+	/// event. Empty lines are sent to keep the connection alive, so they're
+	/// skipped.
+	fn stream(
+		&self,
+		path: &str,
+		mut on_event: impl FnMut(Value) -> Result<bool, BotError>,
+	) -> Result<(), BotError> {
+		let reader = BufReader::new(self.get(path)?.into_reader());
+		for line in reader.lines() {
+			let line = line?;
+			if line.trim().is_empty() {
+				continue;
+			}
+
+			if !on_event(serde_json::from_str(&line)?)? {
+				break;
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Writes a move as two digits for each square the piece lands on, such as
+/// `091827`
+fn lidraughts_move(full_move: &FullMove) -> String {
+	std::iter::once(full_move.from())
+		.chain(full_move.hops.iter().map(|hop| hop.end_position()))
+		.map(|square| format!("{:02}", square_number(square)))
+		.collect()
+}
+
+/// Finds the legal move written in lidraughts notation. The squares may also
+/// be separated, such as `9x18x27`.
+fn parse_move(position: CheckersBitBoard, notation: &str) -> Option<FullMove> {
+	let squares: Vec<usize> = if notation.contains(['-', 'x']) {
+		notation
+			.split(['-', 'x'])
+			.map(|square| square.parse().ok())
+			.collect::<Option<_>>()?
+	} else {
+		if !notation.len().is_multiple_of(2) || !notation.is_ascii() {
+			return None;
+		}
+
+		(0..notation.len())
+			.step_by(2)
+			.map(|i| notation[i..i + 2].parse().ok())
+			.collect::<Option<_>>()?
+	};
+	let (&from, &to) = (squares.first()?, squares.last()?);
+
+	full_moves(position).into_iter().find(|full_move| {
+		let landings = full_move
+			.hops
+			.iter()
+			.map(|hop| square_number(hop.end_position()));
+		square_number(full_move.from()) == from
+			&& square_number(full_move.to()) == to
+			&& (squares.len() == 2 || landings.eq(squares[1..].iter().copied()))
+	})
+}
+
+/// The position after the moves in a game state have been played
+fn game_position(initial: CheckersBitBoard, moves: &str) -> Result<CheckersBitBoard, BotError> {
+	moves
+		.split_whitespace()
+		.try_fold(initial, |position, notation| {
+			parse_move(position, notation)
+				.map(|full_move| full_move.result)
+				.ok_or_else(|| BotError::IllegalMove(notation.to_string()))
+		})
+}
+
+fn milliseconds(value: &Value) -> Duration {
+	Duration::from_millis(value.as_u64().unwrap_or_default())
+}
+
+/// The clock in a game state. Games without a clock get the default time
+/// per move.
+fn game_clock(state: &Value) -> Clock {
+	if state.get("wtime").is_none_or(Value::is_null) {
+		return Clock::default();
+	}
+
+	Clock::Standard {
+		white_time_remaining: milliseconds(&state["wtime"]),
+		black_time_remaining: milliseconds(&state["btime"]),
+		white_increment: milliseconds(&state["winc"]),
+		black_increment: milliseconds(&state["binc"]),
+		moves_until_next_time_control: None,
+	}
+}
+
+fn think(engine: &Engine, position: CheckersBitBoard, clock: Clock) -> Option<FullMove> {
+	engine.set_position(position);
+	let (_, best_move) = engine.evaluate(
+		None,
+		EvaluationSettings {
+			clock,
+			..EvaluationSettings::default()
+		},
+	);
+	engine.best_full_move(position, best_move?)
+}
+
+/// Plays a game until it's over
+fn play_game(
+	client: &Client,
+	account: &str,
+	game_id: &str,
+	hash_megabytes: usize,
+) -> Result<(), BotError> {
+	let engine = Engine::new(hash_megabytes.max(1) * MEGABYTE, Arc::new(SilentFrontend));
+	let mut color = PieceColor::Light;
+	let mut initial = CheckersBitBoard::starting_position();
+
+	client.stream(&format!("/api/bot/game/stream/{game_id}"), |event| {
+		let state = match event["type"].as_str() {
+			Some("gameFull") => {
+				let white = event["white"]["id"].as_str().unwrap_or_default();
+				color = if white.eq_ignore_ascii_case(account) {
+					PieceColor::Light
+				} else {
+					PieceColor::Dark
+				};
+
+				initial = match event["initialFen"].as_str() {
+					None | Some("startpos") => CheckersBitBoard::starting_position(),
+					Some(fen) => {
+						parse_fen(fen).ok_or_else(|| BotError::InvalidFen(fen.to_string()))?
+					}
+				};
+
+				&event["state"]
+			}
+			Some("gameState") => &event,
+			_ => return Ok(true),
+		};
+
+		if state["status"]
+			.as_str()
+			.is_some_and(|status| status != "started")
+		{
+			return Ok(false);
+		}
+
+		let position = game_position(initial, state["moves"].as_str().unwrap_or_default())?;
+		if position.turn() != color {
+			return Ok(true);
+		}
+
+		if let Some(full_move) = think(&engine, position, game_clock(state)) {
+			let notation = lidraughts_move(&full_move);
+			client.post(&format!("/api/bot/game/{game_id}/move/{notation}"))?;
+		}
+
+		Ok(true)
+	})
+}
+
+/// Turns the account into a bot account. This can't be undone, and only
+/// works for accounts which haven't played any games.
+pub fn upgrade_account(settings: &BotSettings) -> Result<(), BotError> {
+	Client::new(settings).post("/api/bot/account/upgrade")?;
+	Ok(())
+}
+
+/// Listens for challenges, and plays each game that starts, until the event
+/// stream is closed. Challenges for other variants are declined. Each game is
+/// played on its own thread, with its own engine.
+pub fn run(settings: &BotSettings) -> Result<(), BotError> {
+	let client = Arc::new(Client::new(settings));
+	let account: Value = serde_json::from_reader(client.get("/api/account")?.into_reader())?;
+	let account: Arc<str> = account["id"].as_str().unwrap_or_default().into();
+	let active_games = Arc::new(AtomicUsize::new(0));
+
+	client.stream("/api/stream/event", |event| {
+		match event["type"].as_str() {
+			Some("challenge") => {
+				let challenge = &event["challenge"];
+				let id = challenge["id"].as_str().unwrap_or_default();
+				let variant = challenge["variant"]["key"].as_str().unwrap_or_default();
+				let accept = settings.variants.iter().any(|key| key == variant)
+					&& active_games.load(Ordering::Acquire) < settings.max_games;
+
+				let action = if accept { "accept" } else { "decline" };
+				if let Err(error) = client.post(&format!("/api/challenge/{id}/{action}")) {
+					tracing::warn!(%error, id, "failed to answer a challenge");
+				}
+			}
+			Some("gameStart") => {
+				let game_id = event["game"]["id"].as_str().unwrap_or_default().to_string();
+				let (client, account, active_games) =
+					(client.clone(), account.clone(), active_games.clone());
+				let hash_megabytes = settings.hash_megabytes;
+
+				active_games.fetch_add(1, Ordering::AcqRel);
+				std::thread::spawn(move || {
+					if let Err(error) = play_game(&client, &account, &game_id, hash_megabytes) {
+						tracing::warn!(%error, game_id, "the game failed");
+					}
+					active_games.fetch_sub(1, Ordering::AcqRel);
+				});
+			}
+			_ => {}
+		}
+
+		Ok(true)
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::*;
+
+	#[test]
+	fn move_notation() {
+		let start = CheckersBitBoard::starting_position();
+		let full_move = parse_move(start, "1115").unwrap();
+		assert_eq!(full_move.to_string(), "11-15");
+		assert_eq!(lidraughts_move(&full_move), "1115");
+		assert_eq!(parse_move(start, "11-15"), Some(full_move));
+		assert_eq!(parse_move(start, "1114"), None);
+	}
+
+	#[test]
+	fn position_from_moves() {
+		let start = CheckersBitBoard::starting_position();
+		let position = game_position(start, "1115 2319 0914").unwrap();
+		assert_eq!(position.turn(), PieceColor::Light);
+		assert!(game_position(start, "1115 1115").is_err());
+	}
+
+	#[test]
+	fn clock_from_state() {
+		let state = json!({ "wtime": 60000, "btime": 30000, "winc": 2000, "binc": 2000 });
+		let Clock::Standard {
+			white_time_remaining,
+			black_time_remaining,
+			black_increment,
+			..
+		} = game_clock(&state)
+		else {
+			panic!("the clock should be standard");
+		};
+
+		assert_eq!(white_time_remaining, Duration::from_secs(60));
+		assert_eq!(black_time_remaining, Duration::from_secs(30));
+		assert_eq!(black_increment, Duration::from_secs(2));
+	}
+}
@@ -0,0 +1,127 @@
+use std::fmt::{self, Debug, Write};
+use std::sync::Arc;
+
+use thiserror::Error;
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::{EnvFilter, ParseError};
+use tracing_subscriber::layer::{Context, Layer};
+
+use crate::Frontend;
+
+/// How much the command line logs to stderr
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LogSettings {
+	/// Zero logs warnings and errors. Each level above zero logs more detail,
+	/// and below zero only errors are logged.
+	pub verbosity: i8,
+	/// Writes each event as a line of JSON
+	pub json: bool,
+	/// Directives such as `engine::search=trace`, which override the
+	/// verbosity for the modules that they name
+	pub filter: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum LogError {
+	#[error("Invalid log filter: {0}")]
+	InvalidFilter(#[from] ParseError),
+	#[error("A logger has already been installed")]
+	AlreadyInstalled,
+}
+
+impl LogSettings {
+	fn level(&self) -> LevelFilter {
+		match self.verbosity {
+			..=-1 => LevelFilter::ERROR,
+			0 => LevelFilter::WARN,
+			1 => LevelFilter::INFO,
+			2 => LevelFilter::DEBUG,
+			_ => LevelFilter::TRACE,
+		}
+	}
+
+	/// Sends log events to stderr, for the rest of the program
+	pub fn install(&self) -> Result<(), LogError> {
+		let filter = EnvFilter::builder()
+			.with_default_directive(self.level().into())
+			.parse(self.filter.as_deref().unwrap_or_default())?;
+		let builder = tracing_subscriber::fmt()
+			.with_writer(std::io::stderr)
+			.with_env_filter(filter);
+
+		let installed = if self.json {
+			builder.json().try_init()
+		} else {
+			builder.without_time().try_init()
+		};
+		installed.map_err(|_| LogError::AlreadyInstalled)
+	}
+}
+
+/// A tracing layer which sends the engine's log events to a frontend. Errors
+/// are sent to [`Frontend::on_error`], and everything else is sent to
+/// [`Frontend::on_string`].
+pub struct FrontendLayer {
+	frontend: Arc<dyn Frontend>,
+}
+
+/// Collects an event's message and fields into a single line
+#[derive(Default)]
+struct LineVisitor {
+	message: String,
+	fields: String,
+}
+
+impl Visit for LineVisitor {
+	fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+		if field.name() == "message" {
+			let _ = write!(self.message, "{value:?}");
+		} else {
+			let _ = write!(self.fields, " {}={value:?}", field.name());
+		}
+	}
+
+	fn record_str(&mut self, field: &Field, value: &str) {
+		if field.name() == "message" {
+			self.message.push_str(value);
+		} else {
+			let _ = write!(self.fields, " {}={value}", field.name());
+		}
+	}
+}
+
+impl FrontendLayer {
+	pub fn new(frontend: Arc<dyn Frontend>) -> Self {
+		Self { frontend }
+	}
+}
+
+impl Debug for FrontendLayer {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("FrontendLayer").finish_non_exhaustive()
+	}
+}
+
+impl<S: Subscriber> Layer<S> for FrontendLayer {
+	fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+		let mut visitor = LineVisitor::default();
+		event.record(&mut visitor);
+
+		let metadata = event.metadata();
+		let line = format!(
+			"{} {}: {}{}",
+			metadata.level(),
+			metadata.target(),
+			visitor.message,
+			visitor.fields
+		);
+
+		if *metadata.level() == Level::ERROR {
+			self.frontend.on_error(&line);
+		} else {
+			self.frontend.on_string(&line);
+		}
+	}
+}
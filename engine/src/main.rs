@@ -1,29 +1,155 @@
 use std::num::NonZeroU8;
+use std::sync::Arc;
 
-use engine::{ActualLimit, Engine, EvaluationSettings, Frontend};
+#[cfg(feature = "logging")]
+use engine::LogSettings;
+use engine::{ActualLimit, Config, Engine, EvaluationSettings, TextFrontend, MEGABYTE};
+#[cfg(not(target_arch = "wasm32"))]
 use mimalloc::MiMalloc;
 use model::CheckersBitBoard;
 
+mod cli;
+
+#[cfg(not(target_arch = "wasm32"))]
 #[global_allocator]
 static ALLOCATOR: MiMalloc = MiMalloc;
 
-const DEPTH: u8 = 19;
+const USAGE: &str =
+	"usage: ampere [--json] [-v | --verbose | -q | --quiet] [--log-json] [--log <filter>] <command>";
 
-struct BasicFrontend;
+/// Removes the logging flags from the arguments, which may be given anywhere.
+/// Each `-v` logs more detail, `-q` only logs errors, `--log-json` writes the
+/// log as JSON, and `--log` filters it by module, such as
+/// `engine::search=trace`. The filter can also be set with the `AMPERE_LOG`
+/// environment variable. Returns `None` if `--log` doesn't have a filter.
+#[cfg(feature = "logging")]
+fn take_log_settings(args: &mut Vec<String>) -> Option<LogSettings> {
+	let mut settings = LogSettings {
+		filter: std::env::var("AMPERE_LOG").ok(),
+		..LogSettings::default()
+	};
 
-impl Frontend for BasicFrontend {
-	fn debug(&self, msg: &str) {
-		println!("{msg}");
+	let mut remaining = Vec::with_capacity(args.len());
+	let mut all_args = std::mem::take(args).into_iter();
+	while let Some(arg) = all_args.next() {
+		match arg.as_str() {
+			"-q" | "--quiet" => settings.verbosity = -1,
+			"--verbose" => settings.verbosity = settings.verbosity.saturating_add(1),
+			"--log-json" => settings.json = true,
+			"--log" => settings.filter = Some(all_args.next()?),
+			// -vv is the same as -v -v
+			_ if arg.len() > 1 && arg.starts_with('-') && arg[1..].chars().all(|c| c == 'v') => {
+				let count = i8::try_from(arg.len() - 1).unwrap_or(i8::MAX);
+				settings.verbosity = settings.verbosity.saturating_add(count);
+			}
+			_ => remaining.push(arg),
+		}
 	}
 
-	fn report_best_move(&self, best_move: model::Move) {
-		println!("{best_move}");
-	}
+	*args = remaining;
+	Some(settings)
 }
 
 fn main() {
-	let engine = Box::leak(Box::new(Engine::new(1_000_000, &BasicFrontend)));
-	let (_, best) = engine.evaluate(
+	// --json may be given anywhere, and switches the output of perft, eval,
+	// and analyze to JSON
+	let mut args: Vec<String> = std::env::args().skip(1).collect();
+	let json = args.iter().any(|arg| arg == "--json");
+	args.retain(|arg| arg != "--json");
+	#[cfg(feature = "logging")]
+	{
+		let Some(log_settings) = take_log_settings(&mut args) else {
+			eprintln!("{USAGE}");
+			std::process::exit(1);
+		};
+		if let Err(error) = log_settings.install() {
+			eprintln!("{error}");
+			std::process::exit(1);
+		}
+	}
+	let (command, args) = match args.split_first() {
+		Some((command, args)) => (Some(command.as_str()), args),
+		None => (None, &[][..]),
+	};
+
+	let config_path = Config::path();
+	let config = match config_path.as_ref().map(Config::load) {
+		Some(Ok(config)) => {
+			tracing::debug!(path = ?config_path, ?config, "loaded the config");
+			config
+		}
+		Some(Err(error)) => {
+			eprintln!("{}: {error}", config_path.unwrap_or_default().display());
+			std::process::exit(1);
+		}
+		None => Config::default(),
+	};
+
+	match command {
+		Some("hub") => {
+			let stdin = std::io::stdin();
+			if let Err(error) = engine::hub::run(stdin.lock(), |line| println!("{line}")) {
+				eprintln!("{error}");
+			}
+			return;
+		}
+		Some("json") => {
+			let stdin = std::io::stdin();
+			if let Err(error) = engine::json_rpc::run(stdin.lock(), |line| println!("{line}")) {
+				eprintln!("{error}");
+			}
+			return;
+		}
+		Some("serve") => {
+			let address = args
+				.first()
+				.map_or(engine::http::DEFAULT_ADDRESS, String::as_str);
+			if let Err(error) = engine::http::serve(address, 16) {
+				eprintln!("{error}");
+			}
+			return;
+		}
+		#[cfg(all(feature = "lidraughts", not(target_arch = "wasm32")))]
+		Some("lidraughts") => return cli::lidraughts::run_lidraughts(args),
+		Some("perft") => return cli::perft::run_perft(args, &config, json),
+		Some("analyze") => return cli::analyze::run_analyze(args, &config, json),
+		Some("eval") => return cli::analyze::run_eval(args, &config, json),
+		Some("testsuite") => return cli::testsuite::run_testsuite(args, &config, json),
+		Some("tournament") => return cli::tournament::run_tournament(args, json, false),
+		Some("gauntlet") => return cli::tournament::run_tournament(args, json, true),
+		Some("annotate") => return cli::annotate::run_annotate(args, &config, json),
+		Some("pdn") => return cli::pdn::run_pdn(args, json),
+		Some("db") => return cli::db::run_db(args, &config, json),
+		Some("openings") => return cli::openings::run_openings(args, json),
+		Some("convert") => return cli::convert::run_convert(args, json),
+		Some("solve") => return cli::solve::run_solve(args, json),
+		Some("endgame") => return cli::endgame::run_endgame(args, &config, json),
+		Some("diagram") => return cli::diagram::run_diagram(args, json),
+		Some("rate") => return cli::rate::run_rate(args, json),
+		Some("selfplay") => return cli::selfplay::run_selfplay(args, &config, json),
+		Some("ballots") => return cli::ballots::run_ballots(args, json),
+		Some("book") => return cli::book::run_book(args, &config, json),
+		Some("tablebase") => return cli::tablebase::run_tablebase(args, &config, json),
+		Some("config") => return cli::config::run_config(&config, json),
+		Some("completions") => return cli::completions::run_completions(args),
+		#[cfg(all(feature = "tui", not(target_arch = "wasm32")))]
+		Some("tui") => return cli::tui::run_tui(args, &config),
+		Some("export") => return cli::export::run_export(args),
+		Some("dxp") => return cli::dxp::play_dxp(args, &config),
+		Some(command) => {
+			eprintln!("unknown command {command}");
+			eprintln!("{USAGE}");
+			std::process::exit(1);
+		}
+		// with no command, the engine searches a few positions as a benchmark
+		None => {}
+	}
+
+	let engine = Engine::new(
+		config.hash_megabytes() * MEGABYTE,
+		Arc::new(TextFrontend::new(|line| println!("{line}"))),
+	);
+	let (_, _best) = engine.evaluate(
 		None,
 		EvaluationSettings {
 			restrict_moves: None,
@@ -31,9 +157,12 @@ fn main() {
 			clock: engine::Clock::Unlimited,
 			search_until: engine::SearchLimit::Limited(ActualLimit {
 				nodes: None,
-				depth: Some(NonZeroU8::new(DEPTH).unwrap()),
+				depth: Some(NonZeroU8::new(cli::DEPTH).unwrap()),
 				time: None,
 			}),
+			contempt: None,
+			eval_params: None,
+			record_tree: None,
 		},
 	);
 	engine.set_position(CheckersBitBoard::new(
@@ -50,9 +179,12 @@ fn main() {
 			clock: engine::Clock::Unlimited,
 			search_until: engine::SearchLimit::Limited(ActualLimit {
 				nodes: None,
-				depth: Some(NonZeroU8::new(DEPTH).unwrap()),
+				depth: Some(NonZeroU8::new(cli::DEPTH).unwrap()),
 				time: None,
 			}),
+			contempt: None,
+			eval_params: None,
+			record_tree: None,
 		},
 	);
 }
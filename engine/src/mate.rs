@@ -0,0 +1,141 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use model::{CheckersBitBoard, Move, PieceColor, PossibleMoves};
+
+use crate::engine::EvaluationTask;
+use crate::eval::Evaluation;
+use crate::frontend::SearchInfo;
+use crate::time::Instant;
+
+/// Searches for a forced win, where only the winning side's lines are
+/// considered
+struct MateSearch<'a> {
+	attacker: PieceColor,
+	cancel_flag: &'a AtomicBool,
+	nodes: &'a AtomicUsize,
+}
+
+impl MateSearch<'_> {
+	/// Finds a sequence of moves that wins for the attacker, with the
+	/// attacker completing at most `moves_left` more moves. Against each
+	/// attacker move, every defense is tried, and the longest defense is
+	/// returned. Returns `None` if there is no forced win, or if the search
+	/// was cancelled.
+	fn win_within(&self, board: CheckersBitBoard, moves_left: u8) -> Option<Vec<Move>> {
+		self.nodes.fetch_add(1, Ordering::Relaxed);
+		if self.cancel_flag.load(Ordering::Acquire) {
+			return None;
+		}
+
+		let moves = PossibleMoves::moves(board);
+		if board.turn() == self.attacker {
+			if moves_left == 0 {
+				return None;
+			}
+
+			moves.into_iter().find_map(|current_move| {
+				let child = unsafe { current_move.apply_to(board) };
+				// a multi-jump continues the same move
+				let moves_left = if child.turn() == self.attacker {
+					moves_left
+				} else {
+					moves_left - 1
+				};
+
+				let mut line = self.win_within(child, moves_left)?;
+				line.insert(0, current_move);
+				Some(line)
+			})
+		} else {
+			// the defender loses if they can't move
+			let mut longest_defense = Vec::new();
+			for current_move in moves {
+				let child = unsafe { current_move.apply_to(board) };
+				let mut line = self.win_within(child, moves_left)?;
+				if line.len() + 1 > longest_defense.len() {
+					line.insert(0, current_move);
+					longest_defense = line;
+				}
+			}
+
+			Some(longest_defense)
+		}
+	}
+}
+
+/// Looks for a forced win for the player to move, where they complete at
+/// most `max_moves` moves. The shortest win is found, and the moves of both
+/// players are returned, starting with the winning move. Returns `None` if
+/// there is no forced win within that many moves.
+pub fn forced_win(board: CheckersBitBoard, max_moves: u8) -> Option<Vec<Move>> {
+	let cancel_flag = AtomicBool::new(false);
+	let nodes = AtomicUsize::new(0);
+	let search = MateSearch {
+		attacker: board.turn(),
+		cancel_flag: &cancel_flag,
+		nodes: &nodes,
+	};
+
+	(1..=max_moves).find_map(|moves| search.win_within(board, moves))
+}
+
+/// Runs the forced-win search for a task. If a win is found, then the
+/// evaluation is a forced win, and the winning move is returned. Otherwise,
+/// no move is returned.
+pub fn search(
+	task: &EvaluationTask,
+	max_moves: u8,
+	cancel_flag: &AtomicBool,
+) -> (Evaluation, Option<Move>) {
+	let start = Instant::now();
+	let search = MateSearch {
+		attacker: task.position.turn(),
+		cancel_flag,
+		nodes: &task.nodes_explored,
+	};
+
+	for moves in 1..=max_moves {
+		let Some(line) = search.win_within(task.position, moves) else {
+			continue;
+		};
+
+		let eval = Evaluation::from_bits(Evaluation::WIN.to_bits() - line.len() as i16);
+		let best_move = line.first().copied();
+		task.frontend.on_depth_complete(&SearchInfo {
+			depth: moves,
+			selective_depth: line.len() as u8,
+			eval,
+			best_move,
+			nodes: task.nodes_explored.load(Ordering::Acquire),
+			time: start.elapsed(),
+		});
+		task.frontend.on_string(&format!(
+			"forced win: {}",
+			line.iter()
+				.map(Move::to_string)
+				.collect::<Vec<_>>()
+				.join(" ")
+		));
+
+		return (eval, best_move);
+	}
+
+	(Evaluation::DRAW, None)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn finds_a_win_in_one() {
+		let board = CheckersBitBoard::new(1 << 20 | 1 << 21, 1 << 20, 0, PieceColor::Dark);
+		let line = forced_win(board, 1).unwrap();
+		assert_eq!(line.len(), 1);
+	}
+
+	#[test]
+	fn no_win_from_the_start() {
+		assert_eq!(forced_win(CheckersBitBoard::starting_position(), 2), None);
+	}
+}
@@ -0,0 +1,276 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use model::{CheckersBitBoard, Move, PieceColor, PossibleMoves};
+
+use crate::engine::EvaluationTask;
+use crate::eval::{eval_position, Evaluation};
+use crate::frontend::SearchInfo;
+use crate::time::Instant;
+
+/// How strongly the search favors moves which haven't been tried much
+const EXPLORATION: f32 = 1.4;
+
+/// The number of playouts when the search is only limited by depth, which
+/// doesn't mean anything to the tree search
+const DEFAULT_PLAYOUTS: usize = 100_000;
+
+/// How often the clock and cancel flag are checked, in playouts
+const CHECK_INTERVAL: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+	board: CheckersBitBoard,
+	last_move: Option<Move>,
+	parent: Option<usize>,
+	first_child: usize,
+	child_count: usize,
+	expanded: bool,
+	visits: u32,
+	/// The total value of the playouts through this node, for the player who
+	/// made the move into it
+	value: f32,
+}
+
+impl Node {
+	fn new(board: CheckersBitBoard, last_move: Option<Move>, parent: Option<usize>) -> Self {
+		Self {
+			board,
+			last_move,
+			parent,
+			first_child: 0,
+			child_count: 0,
+			expanded: false,
+			visits: 0,
+			value: 0.0,
+		}
+	}
+
+	fn children(&self) -> std::ops::Range<usize> {
+		self.first_child..self.first_child + self.child_count
+	}
+
+	fn mean_value(&self) -> f32 {
+		if self.visits == 0 {
+			0.5
+		} else {
+			self.value / self.visits as f32
+		}
+	}
+}
+
+/// The static evaluation of a leaf, as the chance of winning for the player
+/// to move, from 0 to 1
+fn leaf_value(board: CheckersBitBoard, task: &EvaluationTask) -> f32 {
+	let eval = eval_position(board, &task.eval_params);
+	let eval = if board.turn() == PieceColor::Dark {
+		eval
+	} else {
+		-eval
+	};
+
+	match eval.to_f32() {
+		Some(eval) => (eval + 1.0) / 2.0,
+		None if eval.is_force_win() => 1.0,
+		None => 0.0,
+	}
+}
+
+struct Tree {
+	nodes: Vec<Node>,
+}
+
+impl Tree {
+	/// Picks the child with the highest upper confidence bound
+	fn select_child(&self, index: usize) -> usize {
+		let parent = &self.nodes[index];
+		let log_visits = (parent.visits.max(1) as f32).ln();
+		parent
+			.children()
+			.max_by(|&a, &b| {
+				let score = |child: usize| {
+					let child = &self.nodes[child];
+					if child.visits == 0 {
+						return f32::INFINITY;
+					}
+
+					child.mean_value() + EXPLORATION * (log_visits / child.visits as f32).sqrt()
+				};
+				score(a).total_cmp(&score(b))
+			})
+			.expect("expanded nodes have children")
+	}
+
+	fn expand(&mut self, index: usize, allowed_moves: Option<&[Move]>) {
+		let board = self.nodes[index].board;
+		let first_child = self.nodes.len();
+		let mut add_child = |current_move: Move| {
+			let child = unsafe { current_move.apply_to(board) };
+			self.nodes
+				.push(Node::new(child, Some(current_move), Some(index)));
+		};
+
+		match allowed_moves {
+			Some(moves) => moves.iter().copied().for_each(&mut add_child),
+			None => PossibleMoves::moves(board).into_iter().for_each(add_child),
+		}
+
+		let child_count = self.nodes.len() - first_child;
+		let node = &mut self.nodes[index];
+		node.first_child = first_child;
+		node.child_count = child_count;
+		node.expanded = true;
+	}
+
+	/// Adds the value of a playout, for the player to move at `leaf`, to
+	/// every node between the leaf and the root
+	fn backpropagate(&mut self, leaf: usize, value: f32) {
+		let leaf_turn = self.nodes[leaf].board.turn();
+		let mut current = Some(leaf);
+		while let Some(index) = current {
+			let parent = self.nodes[index].parent;
+			let mover = parent.map_or(leaf_turn, |parent| self.nodes[parent].board.turn());
+			let node = &mut self.nodes[index];
+			node.visits += 1;
+			node.value += if mover == leaf_turn {
+				value
+			} else {
+				1.0 - value
+			};
+			current = parent;
+		}
+	}
+
+	/// Runs one playout, and returns the depth of the leaf it reached
+	fn playout(&mut self, task: &EvaluationTask) -> u8 {
+		let mut current = 0;
+		let mut depth = 0;
+		while self.nodes[current].expanded && self.nodes[current].child_count != 0 {
+			current = self.select_child(current);
+			depth += 1;
+		}
+
+		let allowed_moves = if current == 0 {
+			task.allowed_moves.as_deref()
+		} else {
+			None
+		};
+		if !self.nodes[current].expanded {
+			self.expand(current, allowed_moves);
+		}
+
+		// the player to move at the leaf has lost if they have no moves
+		let value = if self.nodes[current].child_count == 0 {
+			0.0
+		} else {
+			leaf_value(self.nodes[current].board, task)
+		};
+		self.backpropagate(current, value);
+
+		depth.min(u8::MAX as usize) as u8
+	}
+
+	fn best_child(&self) -> Option<&Node> {
+		self.nodes[0]
+			.children()
+			.map(|child| &self.nodes[child])
+			.max_by_key(|child| child.visits)
+	}
+}
+
+/// Searches the task's position with Monte-Carlo tree search, using the
+/// static evaluation of each new leaf instead of a random rollout. The search
+/// runs until the node limit or time limit is reached, or until it is
+/// cancelled.
+pub fn search(task: &EvaluationTask, cancel_flag: &AtomicBool) -> (Evaluation, Option<Move>) {
+	let start = Instant::now();
+	let limits = task.limits;
+	let max_time = limits.time.map(|time| start + time.div_f32(2.0));
+	let max_playouts = match (limits.nodes, limits.time) {
+		(Some(nodes), _) => Some(nodes.get()),
+		(None, None) if limits.depth.is_some() => Some(DEFAULT_PLAYOUTS),
+		(None, _) => None,
+	};
+
+	let mut tree = Tree {
+		nodes: vec![Node::new(task.position, None, None)],
+	};
+	let mut playouts = 0;
+	let mut max_depth = 0;
+	loop {
+		if max_playouts.is_some_and(|max| playouts >= max) {
+			break;
+		}
+
+		if playouts % CHECK_INTERVAL == 0 {
+			if cancel_flag.load(Ordering::Acquire) {
+				break;
+			}
+
+			if max_time.is_some_and(|max_time| Instant::now() > max_time) {
+				break;
+			}
+		}
+
+		max_depth = max_depth.max(tree.playout(task));
+		playouts += 1;
+		task.nodes_explored.fetch_add(1, Ordering::Release);
+
+		if tree.nodes[0].child_count == 0 {
+			break;
+		}
+	}
+
+	let Some(best) = tree.best_child() else {
+		return (Evaluation::LOSS, None);
+	};
+
+	// the value is for the player who moved, which is the player at the root
+	let eval = Evaluation::new(best.mean_value() * 2.0 - 1.0);
+	let best_move = best.last_move;
+
+	task.frontend.on_depth_complete(&SearchInfo {
+		depth: max_depth,
+		selective_depth: max_depth,
+		eval,
+		best_move,
+		nodes: playouts,
+		time: start.elapsed(),
+	});
+
+	(eval, best_move)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::num::NonZeroUsize;
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::{
+		ActualLimit, Engine, EvaluationSettings, SearchAlgorithm, SearchLimit, TextFrontend,
+	};
+
+	#[test]
+	fn captures_the_last_piece() {
+		let engine = Engine::new(1 << 16, Arc::new(TextFrontend::new(|_| {})));
+		engine.set_search_algorithm(SearchAlgorithm::MonteCarlo);
+		engine.set_position(CheckersBitBoard::new(
+			1 << 20 | 1 << 21,
+			1 << 20,
+			0,
+			PieceColor::Dark,
+		));
+
+		let settings = EvaluationSettings {
+			search_until: SearchLimit::Limited(ActualLimit {
+				nodes: NonZeroUsize::new(100),
+				depth: None,
+				time: None,
+			}),
+			..EvaluationSettings::default()
+		};
+		let (eval, best_move) = engine.evaluate(None, settings);
+		assert!(best_move.is_some());
+		assert!(eval.is_force_win());
+	}
+}
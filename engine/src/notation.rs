@@ -0,0 +1,578 @@
+use std::fmt::{self, Display};
+
+use model::{CheckersBitBoard, Move, PieceColor, SquareCoordinate};
+pub use pdn::semantic::parse_fen;
+use pdn::semantic::{captured_square, replay_from, PlayedMove, SemanticError};
+use pdn::{scan_headers, BodyPart, Game, GameResult, ParseError, VariationError};
+use thiserror::Error;
+
+/// The number of a square in standard notation, from 1 to 32
+pub fn square_number(square: usize) -> usize {
+	SquareCoordinate::from_ampere_value(square)
+		.to_normal_value()
+		.expect("every ampere square can be played on")
+}
+
+/// The ampere square for a square number in standard notation. Returns `None`
+/// if the number isn't between 1 and 32.
+pub fn square_from_number(number: usize) -> Option<usize> {
+	if !(1..=32).contains(&number) {
+		return None;
+	}
+
+	SquareCoordinate::from_normal_value(number - 1).to_ampere_value()
+}
+
+/// Parses a position written as the side to move, followed by each of the 32
+/// squares in order, such as `Bbbbbbbbbbbbbeeeeeeeewwwwwwwwwwww`. `dark` is
+/// the uppercase letter of the dark side, such as `B` for black or `Z` for
+/// DXP's zwart. White is light, and kings are written in uppercase.
+pub fn parse_position(position: &str, dark: char) -> Option<CheckersBitBoard> {
+	let dark_man = dark.to_ascii_lowercase();
+	let mut chars = position.chars();
+	let turn = match chars.next()? {
+		'W' => PieceColor::Light,
+		turn if turn == dark => PieceColor::Dark,
+		_ => return None,
+	};
+
+	let (mut pieces, mut dark_pieces, mut kings) = (0u32, 0u32, 0u32);
+	for number in 1..=32 {
+		let bit = 1 << square_from_number(number)?;
+		match chars.next()? {
+			'e' => continue,
+			'w' => {}
+			'W' => kings |= bit,
+			piece if piece == dark_man => dark_pieces |= bit,
+			piece if piece == dark => {
+				dark_pieces |= bit;
+				kings |= bit;
+			}
+			_ => return None,
+		}
+
+		pieces |= bit;
+	}
+
+	if chars.next().is_some() {
+		return None;
+	}
+
+	Some(CheckersBitBoard::new(pieces, dark_pieces, kings, turn))
+}
+
+/// Writes a position in the same format as [`parse_position`]
+pub(crate) fn position_string(board: CheckersBitBoard, dark: char) -> String {
+	let turn = match board.turn() {
+		PieceColor::Dark => dark,
+		PieceColor::Light => 'W',
+	};
+
+	let squares = (1..=32).map(|number| {
+		let square = square_from_number(number).expect("every number from 1 to 32 is a square");
+		match (board.color_at(square), board.king_at(square)) {
+			(Some(PieceColor::Dark), Some(false)) => dark.to_ascii_lowercase(),
+			(Some(PieceColor::Dark), Some(true)) => dark,
+			(Some(PieceColor::Light), Some(false)) => 'w',
+			(Some(PieceColor::Light), Some(true)) => 'W',
+			_ => 'e',
+		}
+	});
+
+	std::iter::once(turn).chain(squares).collect()
+}
+
+/// Writes a position in FEN, listing white's pieces and then black's
+pub fn fen(board: CheckersBitBoard) -> String {
+	let squares = |color: PieceColor| {
+		(1..=32)
+			.filter_map(|number| {
+				let square = square_from_number(number)?;
+				(board.color_at(square) == Some(color)).then(|| {
+					if board.king_at(square) == Some(true) {
+						format!("K{number}")
+					} else {
+						number.to_string()
+					}
+				})
+			})
+			.collect::<Vec<_>>()
+			.join(",")
+	};
+
+	let turn = match board.turn() {
+		PieceColor::Dark => 'B',
+		PieceColor::Light => 'W',
+	};
+
+	format!(
+		"{turn}:W{}:B{}",
+		squares(PieceColor::Light),
+		squares(PieceColor::Dark)
+	)
+}
+
+/// A complete move, including every jump of a multi-jump
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FullMove {
+	/// The moves that make up the full move. There is only more than one if
+	/// this is a multi-jump.
+	pub hops: Vec<Move>,
+	/// The squares of the pieces which are captured, as a bitboard
+	pub captured: u32,
+	/// The position after the move is played
+	pub result: CheckersBitBoard,
+}
+
+impl FullMove {
+	/// The square the moving piece starts on
+	pub fn from(&self) -> usize {
+		self.hops[0].start() as usize
+	}
+
+	/// The square the moving piece ends on
+	pub fn to(&self) -> usize {
+		self.hops[self.hops.len() - 1].end_position()
+	}
+
+	pub fn is_capture(&self) -> bool {
+		self.captured != 0
+	}
+
+	/// Writes every square the piece lands on, such as `9x18x27`. Unlike the
+	/// `Display` implementation, this is never ambiguous, and it can be read
+	/// by [`Engine::play_notation`](crate::Engine::play_notation).
+	pub fn long_notation(&self) -> String {
+		let separator = if self.is_capture() { "x" } else { "-" };
+		std::iter::once(self.from())
+			.chain(self.hops.iter().map(|hop| hop.end_position()))
+			.map(|square| square_number(square).to_string())
+			.collect::<Vec<_>>()
+			.join(separator)
+	}
+
+	/// The captured squares, in standard notation, in the order they were
+	/// captured
+	pub fn captured_numbers(&self) -> Vec<usize> {
+		self.hops
+			.iter()
+			.filter_map(|hop| captured_square(*hop))
+			.map(square_number)
+			.collect()
+	}
+}
+
+impl From<PlayedMove> for FullMove {
+	fn from(played: PlayedMove) -> Self {
+		let captured =
+			played.before.pieces_bits() & !played.after.pieces_bits() & !(1 << played.from());
+		Self {
+			hops: played.hops,
+			captured,
+			result: played.after,
+		}
+	}
+}
+
+/// Writes the move as the starting and ending squares, such as `11-15` or
+/// `9x25`
+impl Display for FullMove {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let separator = if self.is_capture() { "x" } else { "-" };
+		write!(
+			f,
+			"{}{separator}{}",
+			square_number(self.from()),
+			square_number(self.to())
+		)
+	}
+}
+
+/// Lists every legal complete move in a position. Multi-jumps are followed
+/// until the turn changes.
+pub fn full_moves(board: CheckersBitBoard) -> Vec<FullMove> {
+	pdn::semantic::full_moves(board)
+		.into_iter()
+		.map(Into::into)
+		.collect()
+}
+
+/// Finds the legal move with the given notation. Every square may be
+/// written, such as `9x18x27`, or only the start and end, such as `9x27`.
+/// Returns `None` if only part of a multi-jump is written, or if more than
+/// one move has the same start and end.
+pub fn find_move(board: CheckersBitBoard, notation: &str) -> Option<FullMove> {
+	let moves = full_moves(board);
+	if let Some(full_move) = moves
+		.iter()
+		.find(|full_move| full_move.long_notation() == notation)
+	{
+		return Some(full_move.clone());
+	}
+
+	let mut matches = moves
+		.into_iter()
+		.filter(|full_move| full_move.to_string() == notation);
+	let full_move = matches.next()?;
+	matches.next().is_none().then_some(full_move)
+}
+
+/// The moves of a game, and the position they start from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdnGame {
+	/// The position in the `FEN` tag, or the starting position
+	pub start: CheckersBitBoard,
+	pub moves: Vec<FullMove>,
+}
+
+impl PdnGame {
+	/// Writes the game in PDN, without a result. If the game doesn't start
+	/// from the starting position, then the `SetUp` and `FEN` tags are
+	/// written.
+	pub fn to_pdn(&self) -> String {
+		let mut pdn = String::new();
+		if self.start != CheckersBitBoard::starting_position() {
+			pdn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n\n", fen(self.start)));
+		}
+
+		write_movetext(&mut pdn, self.start, &self.moves, &[], "*");
+		pdn
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PdnError {
+	#[error("The text is not a valid PDN game")]
+	InvalidPdn,
+	#[error("The FEN tag ({0:?}) is not a valid position")]
+	InvalidFen(String),
+	#[error("Move {index} ({notation:?}) is not a legal move in this position")]
+	IllegalMove { index: usize, notation: String },
+}
+
+/// Writes a move as it was written in the PDN, such as `11-15` or `c3xe5`
+fn pdn_move_notation(game_move: &pdn::Move) -> String {
+	let separator = match game_move {
+		pdn::Move::Normal(..) => "-",
+		pdn::Move::Capture(..) => "x",
+	};
+	game_move
+		.squares()
+		.into_iter()
+		.map(pdn_square_notation)
+		.collect::<Vec<_>>()
+		.join(separator)
+}
+
+fn pdn_square_notation(square: &pdn::Square) -> String {
+	match square {
+		pdn::Square::Num(_, number) => number.to_string(),
+		pdn::Square::Alpha(_, file, rank) => format!("{file}{rank}"),
+	}
+}
+
+impl From<SemanticError> for PdnError {
+	fn from(error: SemanticError) -> Self {
+		match error {
+			SemanticError::UnsupportedGameType(_) => Self::InvalidPdn,
+			SemanticError::InvalidFen(fen) => Self::InvalidFen(fen),
+			SemanticError::InvalidSquare { ply, square } => Self::IllegalMove {
+				index: ply,
+				notation: pdn_square_notation(&square),
+			},
+			SemanticError::IllegalMove { ply, game_move }
+			| SemanticError::AmbiguousMove { ply, game_move } => Self::IllegalMove {
+				index: ply,
+				notation: pdn_move_notation(&game_move),
+			},
+		}
+	}
+}
+
+/// The first game of a PDN file. A game without a result is read as an
+/// unfinished game.
+pub(crate) fn first_pdn_game(pdn: &str) -> Result<Option<Game>, PdnError> {
+	let games = match pdn::parse(pdn) {
+		Ok(file) => return Ok(file.into_games().into_iter().next()),
+		Err(ParseError::Games(games)) => games,
+		Err(ParseError::Tokens(_)) => return Err(PdnError::InvalidPdn),
+	};
+
+	let error = match games.into_iter().next() {
+		Some(Ok(game)) => return Ok(Some(game)),
+		Some(Err(error)) => error,
+		None => return Ok(None),
+	};
+	let body = match error.body() {
+		// the file ended without a result
+		Err(VariationError::UnexpectedEnd(parts)) => parts.iter().cloned().collect(),
+		// the next game started without a result
+		Ok(body) if error.result().is_err() => Ok(body.clone()),
+		_ => return Err(PdnError::InvalidPdn),
+	};
+	match (error.header(), body) {
+		(Ok(header), Ok(body)) => Ok(Some(Game::new(
+			header.clone(),
+			body,
+			GameResult::Unfinished,
+		))),
+		_ => Err(PdnError::InvalidPdn),
+	}
+}
+
+/// Reads the first game of a PDN file. Comments, variations, NAGs, and tags
+/// other than `FEN` are skipped.
+pub fn parse_pdn(pdn: &str) -> Result<PdnGame, PdnError> {
+	match first_pdn_game(pdn)? {
+		Some(game) => play_pdn_game(&game),
+		None => Ok(PdnGame {
+			start: CheckersBitBoard::starting_position(),
+			moves: Vec::new(),
+		}),
+	}
+}
+
+/// Plays the main line of a game which was read by the pdn crate
+pub(crate) fn play_pdn_game(game: &Game) -> Result<PdnGame, PdnError> {
+	let start = match game.fen() {
+		Some(fen) => parse_fen(fen).ok_or_else(|| PdnError::InvalidFen(fen.to_string()))?,
+		None => CheckersBitBoard::starting_position(),
+	};
+	let moves = replay_from(start, game)?
+		.into_iter()
+		.map(Into::into)
+		.collect();
+
+	Ok(PdnGame { start, moves })
+}
+
+/// Reads the comments after each move of the first game of a PDN file, in
+/// the same order as the moves of [`parse_pdn`]. Moves without a comment get
+/// an empty one, and comments in variations are skipped.
+pub(crate) fn move_comments(pdn: &str) -> Vec<String> {
+	let Ok(Some(game)) = first_pdn_game(pdn) else {
+		return Vec::new();
+	};
+
+	let mut comments: Vec<String> = Vec::new();
+	for part in game.body() {
+		match part {
+			BodyPart::Move(_) => comments.push(String::new()),
+			BodyPart::Comment(_, comment) if !comment.trim().is_empty() => {
+				if let Some(last) = comments.last_mut() {
+					if !last.is_empty() {
+						last.push(' ');
+					}
+					last.push_str(comment.trim());
+				}
+			}
+			_ => (),
+		}
+	}
+
+	comments
+}
+
+/// The longest line of moves in a PDN game
+const PDN_LINE_LENGTH: usize = 79;
+
+/// Writes the moves of a game, followed by its result. Each move is followed
+/// by its annotation, if it has one, such as a NAG or a comment.
+pub(crate) fn write_movetext(
+	pdn: &mut String,
+	start: CheckersBitBoard,
+	moves: &[FullMove],
+	annotations: &[String],
+	result: &str,
+) {
+	let mut tokens = Vec::new();
+	let mut position = start;
+	let mut move_number = 1;
+	for (index, full_move) in moves.iter().enumerate() {
+		if position.turn() == PieceColor::Dark {
+			tokens.push(format!("{move_number}."));
+		} else if index == 0 {
+			tokens.push(format!("{move_number}..."));
+		}
+		tokens.push(full_move.to_string());
+		if let Some(annotation) = annotations.get(index).filter(|a| !a.is_empty()) {
+			tokens.push(annotation.clone());
+		}
+
+		if position.turn() == PieceColor::Light {
+			move_number += 1;
+		}
+		position = full_move.result;
+	}
+	tokens.push(result.to_string());
+
+	let mut line_length = 0;
+	for token in tokens {
+		if line_length > 0 && line_length + token.len() + 1 > PDN_LINE_LENGTH {
+			pdn.push('\n');
+			line_length = 0;
+		} else if line_length > 0 {
+			pdn.push(' ');
+			line_length += 1;
+		}
+
+		pdn.push_str(&token);
+		line_length += token.len();
+	}
+	pdn.push('\n');
+}
+
+/// The value of a tag in the first game of a PDN file, such as `Black`.
+/// Tag names are compared ignoring case.
+pub fn pdn_tag(pdn: &str, name: &str) -> Option<String> {
+	scan_headers(pdn).first()?.tag(name).map(str::to_string)
+}
+
+/// Splits a PDN file into its games. A new game starts after the result of
+/// the previous game, or at the first tag after its moves.
+pub fn split_pdn_games(pdn: &str) -> Vec<&str> {
+	scan_headers(pdn)
+		.iter()
+		.map(|headers| &pdn[headers.range()])
+		.collect()
+}
+
+/// Converts a line of moves, where each jump is a separate move, into
+/// complete moves. If the line ends in the middle of a multi-jump, then the
+/// first way of finishing it is used.
+pub fn complete_line(mut position: CheckersBitBoard, line: &[Move]) -> Vec<FullMove> {
+	let mut full_line = Vec::new();
+	let mut remaining = line;
+	while !remaining.is_empty() {
+		let candidates: Vec<FullMove> = full_moves(position)
+			.into_iter()
+			.filter(|full_move| {
+				let shared = full_move.hops.len().min(remaining.len());
+				full_move.hops[..shared] == remaining[..shared]
+			})
+			.collect();
+
+		// prefer a move which is completely in the line
+		let Some(full_move) = candidates
+			.iter()
+			.find(|full_move| full_move.hops.len() <= remaining.len())
+			.or(candidates.first())
+			.cloned()
+		else {
+			break;
+		};
+
+		remaining = &remaining[full_move.hops.len().min(remaining.len())..];
+		position = full_move.result;
+		full_line.push(full_move);
+	}
+
+	full_line
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn square_numbers_round_trip() {
+		for square in 0..32 {
+			let number = square_number(square);
+			assert!((1..=32).contains(&number));
+			assert_eq!(square_from_number(number), Some(square));
+		}
+	}
+
+	#[test]
+	fn fen_round_trip() {
+		let start = CheckersBitBoard::starting_position();
+		assert_eq!(parse_fen("B:W21-32:B1-12"), Some(start));
+		assert_eq!(parse_fen(&fen(start)), Some(start));
+
+		let board = parse_fen("W:WK3,22:B10,K30.").unwrap();
+		assert_eq!(board.turn(), PieceColor::Light);
+		assert_eq!(parse_fen(&fen(board)), Some(board));
+		assert_eq!(fen(board), "W:WK3,22:B10,K30");
+	}
+
+	#[test]
+	fn position_strings_use_the_dark_letter() {
+		let board = parse_fen("B:WK3,22:B10,K30").unwrap();
+		let dxp = position_string(board, 'Z');
+		assert_eq!(dxp, "ZeeWeeeeeezeeeeeeeeeeeweeeeeeeZee");
+		assert_eq!(parse_position(&dxp, 'Z'), Some(board));
+		assert_eq!(parse_position(&dxp, 'B'), None);
+		assert_eq!(
+			parse_position(&position_string(board, 'B'), 'B'),
+			Some(board)
+		);
+	}
+
+	#[test]
+	fn opening_moves() {
+		let moves = full_moves(CheckersBitBoard::starting_position());
+		let mut notation: Vec<String> = moves.iter().map(FullMove::to_string).collect();
+		notation.sort();
+		assert_eq!(
+			notation,
+			["10-14", "10-15", "11-15", "11-16", "12-16", "9-13", "9-14"]
+		);
+	}
+
+	#[test]
+	fn only_the_jumping_piece_continues() {
+		let board =
+			parse_fen("W:W13,22,23,24,25,26,27,28,29,30,31,32:B1,2,3,4,5,7,8,9,10,11,12,19")
+				.unwrap();
+		let mut notation: Vec<String> = full_moves(board)
+			.iter()
+			.map(FullMove::long_notation)
+			.collect();
+		notation.sort();
+		assert_eq!(notation, ["13x6", "23x16", "24x15x6"]);
+	}
+
+	#[test]
+	fn reads_pdn() {
+		let pdn = r#"[Event "test"]
+[FEN "B:W21-32:B1-12"]
+1. 11-15 {the old fourteenth} 23-19 (22-18) 2. 8-11 22-17 1-0"#;
+		let game = parse_pdn(pdn).unwrap();
+		assert_eq!(game.start, CheckersBitBoard::starting_position());
+		let notation: Vec<String> = game.moves.iter().map(FullMove::to_string).collect();
+		assert_eq!(notation, ["11-15", "23-19", "8-11", "22-17"]);
+		assert_eq!(
+			move_comments(pdn),
+			["the old fourteenth", "", "", ""].map(String::from)
+		);
+
+		assert_eq!(
+			parse_pdn("1. 11-15 11-15"),
+			Err(PdnError::IllegalMove {
+				index: 1,
+				notation: "11-15".to_string()
+			})
+		);
+		assert_eq!(parse_pdn("1. 11-15 {unclosed"), Err(PdnError::InvalidPdn));
+	}
+
+	#[test]
+	fn writes_games() {
+		let game = parse_pdn("[FEN \"W:W22:B14\"]\n\n22-18 14x23").unwrap();
+		let pdn = game.to_pdn();
+		assert_eq!(
+			pdn,
+			"[SetUp \"1\"]\n[FEN \"W:W22:B14\"]\n\n1... 22-18 2. 14x23 *\n"
+		);
+		assert_eq!(parse_pdn(&pdn).unwrap(), game);
+	}
+
+	#[test]
+	fn splits_games() {
+		let pdn = "[Event \"a\"]\n\n1. 11-15 *\n\n[Event \"b\"]\n[Round \"2\"]\n1. 9-13 *\n";
+		let games = split_pdn_games(pdn);
+		assert_eq!(games.len(), 2);
+		assert!(games[1].starts_with("[Event \"b\"]"));
+		assert_eq!(parse_pdn(games[1]).unwrap().moves[0].to_string(), "9-13");
+	}
+}
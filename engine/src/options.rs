@@ -0,0 +1,164 @@
+use std::fmt::{self, Display};
+
+use thiserror::Error;
+
+use crate::book::BookFileError;
+use crate::learning::LearningFileError;
+
+/// The number of bytes in a megabyte, which is the unit of the hash option
+pub const MEGABYTE: usize = 1 << 20;
+
+/// The default size of the transposition table, in megabytes
+pub const DEFAULT_HASH_SIZE: i64 = 16;
+
+/// The largest allowed size of the transposition table, in megabytes
+pub const MAX_HASH_SIZE: i64 = 1 << 16;
+
+/// The largest allowed number of search threads
+pub const MAX_THREADS: i64 = 256;
+
+/// The largest allowed contempt, in either direction. A man is worth about
+/// 0.04 at the start of the game, so this is far from a forced result.
+pub const MAX_CONTEMPT: f32 = 0.25;
+
+pub const HASH_OPTION: &str = "Hash";
+pub const THREADS_OPTION: &str = "Threads";
+pub const PONDER_OPTION: &str = "Ponder";
+pub const CONTEMPT_OPTION: &str = "Contempt";
+pub const SKILL_LEVEL_OPTION: &str = "Skill Level";
+pub const AGGRESSIVENESS_OPTION: &str = "Aggressiveness";
+pub const LEARNING_FILE_OPTION: &str = "Learning File";
+pub const BOOK_OPTION: &str = "Book";
+pub const DEBUG_OPTION: &str = "Debug";
+pub const SEARCH_ALGORITHM_OPTION: &str = "Search Algorithm";
+
+pub const ALPHA_BETA: &str = "AlphaBeta";
+pub const MONTE_CARLO: &str = "MonteCarlo";
+
+/// The type of an engine option, and the values it may be set to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptionKind {
+	/// A boolean, which is either `true` or `false`
+	Check,
+	/// An integer, between `min` and `max` inclusive
+	Spin { min: i64, max: i64 },
+	/// A real number, between `min` and `max` inclusive
+	Float { min: f32, max: f32 },
+	/// Any string. An empty string means the option is unset.
+	String,
+	/// One of a list of strings
+	Combo { values: &'static [&'static str] },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OptionValue {
+	Check(bool),
+	Spin(i64),
+	Float(f32),
+	String(String),
+}
+
+/// A configurable setting on the engine, which can be changed using
+/// [`Engine::set_option`](crate::Engine::set_option)
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineOption {
+	pub name: &'static str,
+	pub kind: OptionKind,
+	pub default: OptionValue,
+	pub value: OptionValue,
+}
+
+#[derive(Debug, Error)]
+pub enum OptionError {
+	#[error("There is no option named {0:?}")]
+	UnknownOption(String),
+	#[error("{value:?} is not a valid value for the {name} option")]
+	InvalidValue { name: &'static str, value: String },
+	#[error("{value} is out of range for the {name} option")]
+	OutOfRange { name: &'static str, value: String },
+	#[error(transparent)]
+	LearningFileError(#[from] LearningFileError),
+	#[error(transparent)]
+	BookFileError(#[from] BookFileError),
+}
+
+impl Display for OptionValue {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Check(value) => write!(f, "{value}"),
+			Self::Spin(value) => write!(f, "{value}"),
+			Self::Float(value) => write!(f, "{value}"),
+			Self::String(value) => write!(f, "{value}"),
+		}
+	}
+}
+
+impl EngineOption {
+	/// Parses a value for this option, checking that it is in range
+	pub fn parse(&self, value: &str) -> Result<OptionValue, OptionError> {
+		let value = value.trim();
+		let invalid = || OptionError::InvalidValue {
+			name: self.name,
+			value: value.to_string(),
+		};
+		let out_of_range = || OptionError::OutOfRange {
+			name: self.name,
+			value: value.to_string(),
+		};
+
+		match self.kind {
+			OptionKind::Check => match value.to_ascii_lowercase().as_str() {
+				"true" | "on" | "1" => Ok(OptionValue::Check(true)),
+				"false" | "off" | "0" => Ok(OptionValue::Check(false)),
+				_ => Err(invalid()),
+			},
+			OptionKind::Spin { min, max } => {
+				let parsed = value.parse().map_err(|_| invalid())?;
+				if (min..=max).contains(&parsed) {
+					Ok(OptionValue::Spin(parsed))
+				} else {
+					Err(out_of_range())
+				}
+			}
+			OptionKind::Float { min, max } => {
+				let parsed = value.parse().map_err(|_| invalid())?;
+				if (min..=max).contains(&parsed) {
+					Ok(OptionValue::Float(parsed))
+				} else {
+					Err(out_of_range())
+				}
+			}
+			OptionKind::String => Ok(OptionValue::String(value.to_string())),
+			OptionKind::Combo { values } => values
+				.iter()
+				.find(|v| v.eq_ignore_ascii_case(value))
+				.map(|v| OptionValue::String(v.to_string()))
+				.ok_or_else(invalid),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse_in_range() {
+		let option = EngineOption {
+			name: SKILL_LEVEL_OPTION,
+			kind: OptionKind::Spin { min: 0, max: 20 },
+			default: OptionValue::Spin(20),
+			value: OptionValue::Spin(20),
+		};
+
+		assert_eq!(option.parse(" 7 ").unwrap(), OptionValue::Spin(7));
+		assert!(matches!(
+			option.parse("21"),
+			Err(OptionError::OutOfRange { .. })
+		));
+		assert!(matches!(
+			option.parse("seven"),
+			Err(OptionError::InvalidValue { .. })
+		));
+	}
+}
@@ -0,0 +1,270 @@
+use std::num::NonZeroUsize;
+
+use model::{CheckersBitBoard, Move, PossibleMoves};
+use parking_lot::Mutex;
+use rayon::prelude::*;
+use rayon::{ThreadPoolBuildError, ThreadPoolBuilder};
+
+use crate::notation::full_moves;
+use crate::options::MEGABYTE;
+
+/// A position with known [`perft_complete_moves`] counts
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PerftPosition {
+	pub name: &'static str,
+	pub fen: &'static str,
+	/// The count at each depth, starting at a depth of one
+	pub counts: &'static [usize],
+}
+
+/// Positions with published perft results, for checking the move generator
+pub const PERFT_SUITE: &[PerftPosition] = &[PerftPosition {
+	name: "starting position",
+	fen: "B:W21-32:B1-12",
+	counts: &[
+		7, 49, 302, 1469, 7361, 36768, 179740, 845931, 3963680, 18391564, 85242128, 388617999,
+	],
+}];
+
+#[derive(Copy, Clone, Debug)]
+struct PerftEntry {
+	board: CheckersBitBoard,
+	depth: u8,
+	nodes: usize,
+}
+
+/// Remembers the perft counts of positions which have already been counted.
+/// Unlike the search's transposition table, the counts are always exact, so
+/// an entry is only used at the same depth.
+pub struct PerftTable {
+	entries: Box<[Mutex<Option<PerftEntry>>]>,
+}
+
+impl PerftTable {
+	/// The number of bytes used by each entry in the table
+	pub const ENTRY_SIZE: usize = std::mem::size_of::<Mutex<Option<PerftEntry>>>();
+
+	/// Creates a table that uses at most `table_size` bytes
+	pub fn new(table_size: usize) -> Self {
+		Self {
+			entries: (0..table_size / Self::ENTRY_SIZE)
+				.map(|_| Mutex::new(None))
+				.collect(),
+		}
+	}
+
+	/// Creates a table that uses at most the given number of megabytes
+	pub fn with_megabytes(megabytes: usize) -> Self {
+		Self::new(megabytes * MEGABYTE)
+	}
+
+	fn entry(&self, board: CheckersBitBoard) -> Option<&Mutex<Option<PerftEntry>>> {
+		if self.entries.is_empty() {
+			return None;
+		}
+
+		self.entries
+			.get(board.hash_code() as usize % self.entries.len())
+	}
+
+	fn get(&self, board: CheckersBitBoard, depth: u8) -> Option<usize> {
+		let entry = (*self.entry(board)?.lock())?;
+		(entry.board == board && entry.depth == depth).then_some(entry.nodes)
+	}
+
+	fn insert(&self, board: CheckersBitBoard, depth: u8, nodes: usize) {
+		if let Some(entry) = self.entry(board) {
+			*entry.lock() = Some(PerftEntry {
+				board,
+				depth,
+				nodes,
+			});
+		}
+	}
+}
+
+/// Counts the positions reachable from `board` in exactly `depth` plies. Each
+/// jump of a multi-jump counts as its own ply, which is how the search sees
+/// them.
+pub fn perft(board: CheckersBitBoard, depth: u8) -> usize {
+	let moves = PossibleMoves::moves(board).into_iter();
+	match depth {
+		0 => 1,
+		// the last ply doesn't need to be applied
+		1 => moves.count(),
+		_ => moves
+			.map(|current_move| perft(unsafe { current_move.apply_to(board) }, depth - 1))
+			.sum(),
+	}
+}
+
+fn perft_with_table(board: CheckersBitBoard, depth: u8, table: &PerftTable) -> usize {
+	if depth < 2 {
+		return perft(board, depth);
+	}
+
+	if let Some(nodes) = table.get(board, depth) {
+		return nodes;
+	}
+
+	let nodes = PossibleMoves::moves(board)
+		.into_iter()
+		.map(|current_move| {
+			perft_with_table(unsafe { current_move.apply_to(board) }, depth - 1, table)
+		})
+		.sum();
+	table.insert(board, depth, nodes);
+	nodes
+}
+
+/// Counts the positions reachable from `board` in exactly `depth` complete
+/// moves. Unlike [`perft`], a multi-jump is a single move, which is how other
+/// engines and published perft results count them.
+pub fn perft_complete_moves(board: CheckersBitBoard, depth: u8) -> usize {
+	if depth == 0 {
+		return 1;
+	}
+
+	// a king can capture the same pieces in a different order, but that's
+	// still the same move
+	let mut results = Vec::new();
+	for full_move in full_moves(board) {
+		if !results.contains(&full_move.result) {
+			results.push(full_move.result);
+		}
+	}
+
+	if depth == 1 {
+		return results.len();
+	}
+
+	results
+		.into_iter()
+		.map(|result| perft_complete_moves(result, depth - 1))
+		.sum()
+}
+
+/// The [`perft`] count under each root move, in move generation order. This
+/// is useful for finding which move disagrees with another move generator.
+pub fn perft_divide(board: CheckersBitBoard, depth: u8) -> Vec<(Move, usize)> {
+	if depth == 0 {
+		return Vec::new();
+	}
+
+	PossibleMoves::moves(board)
+		.into_iter()
+		.map(|current_move| {
+			let nodes = perft(unsafe { current_move.apply_to(board) }, depth - 1);
+			(current_move, nodes)
+		})
+		.collect()
+}
+
+/// The same as [`perft`], but each root move is counted on a separate thread,
+/// with at most `threads` threads running at once
+pub fn perft_parallel(
+	board: CheckersBitBoard,
+	depth: u8,
+	threads: NonZeroUsize,
+) -> Result<usize, ThreadPoolBuildError> {
+	if depth < 2 {
+		return Ok(perft(board, depth));
+	}
+
+	let pool = ThreadPoolBuilder::new()
+		.num_threads(threads.get())
+		.build()?;
+	let moves: Vec<_> = PossibleMoves::moves(board).into_iter().collect();
+	let nodes = pool.install(|| {
+		moves
+			.into_par_iter()
+			.map(|current_move| perft(unsafe { current_move.apply_to(board) }, depth - 1))
+			.sum()
+	});
+
+	Ok(nodes)
+}
+
+/// The same as [`perft_parallel`], but the count of each position is stored
+/// in `table`, so transpositions are only counted once. The table can be
+/// reused between calls.
+pub fn perft_hashed(
+	board: CheckersBitBoard,
+	depth: u8,
+	threads: NonZeroUsize,
+	table: &PerftTable,
+) -> Result<usize, ThreadPoolBuildError> {
+	if depth < 2 {
+		return Ok(perft(board, depth));
+	}
+
+	let pool = ThreadPoolBuilder::new()
+		.num_threads(threads.get())
+		.build()?;
+	let moves: Vec<_> = PossibleMoves::moves(board).into_iter().collect();
+	let nodes = pool.install(|| {
+		moves
+			.into_par_iter()
+			.map(|current_move| {
+				perft_with_table(unsafe { current_move.apply_to(board) }, depth - 1, table)
+			})
+			.sum()
+	});
+
+	Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn starting_position() {
+		let board = CheckersBitBoard::starting_position();
+		let expected = [1, 7, 49, 302, 1469, 7361];
+		for (depth, expected) in expected.into_iter().enumerate() {
+			assert_eq!(perft(board, depth as u8), expected);
+		}
+	}
+
+	#[test]
+	fn suite() {
+		for position in PERFT_SUITE {
+			let board = crate::notation::parse_fen(position.fen).unwrap();
+			for (depth, &expected) in (1..8).zip(position.counts) {
+				assert_eq!(perft_complete_moves(board, depth), expected);
+			}
+		}
+	}
+
+	#[test]
+	fn divide_adds_up() {
+		let board = CheckersBitBoard::starting_position();
+		let divided = perft_divide(board, 5);
+		assert_eq!(divided.len(), 7);
+		assert_eq!(
+			divided.iter().map(|(_, nodes)| nodes).sum::<usize>(),
+			perft(board, 5)
+		);
+	}
+
+	#[test]
+	fn parallel_matches_serial() {
+		let board = CheckersBitBoard::starting_position();
+		let threads = NonZeroUsize::new(4).unwrap();
+		assert_eq!(perft_parallel(board, 6, threads).unwrap(), perft(board, 6));
+	}
+
+	#[test]
+	fn hashed_matches_serial() {
+		let board = CheckersBitBoard::starting_position();
+		let table = PerftTable::with_megabytes(1);
+		let threads = NonZeroUsize::new(2).unwrap();
+		for depth in 0..8 {
+			assert_eq!(
+				perft_hashed(board, depth, threads, &table).unwrap(),
+				perft(board, depth)
+			);
+		}
+	}
+}
@@ -0,0 +1,300 @@
+use model::{CheckersBitBoard, Move, PieceColor, PossibleMoves};
+
+/// A proof or disproof number that can't be reached
+const INFINITY: u32 = u32::MAX;
+
+/// The outcome of a proof-number search, for the player whose turn it is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProofResult {
+	/// The player to move can force a win
+	Win,
+	/// The player to move loses against best play
+	Loss,
+	/// Neither a win nor a loss could be proven within the node budget. The
+	/// rules in the model crate don't include draws, so a drawn position is
+	/// never proven.
+	Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Proof {
+	pub result: ProofResult,
+	/// The winning move if the position is a win. Otherwise, this is the most
+	/// promising move that was found, if there is one.
+	pub best_move: Option<Move>,
+	/// The number of nodes in the proof tree
+	pub nodes: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Node {
+	board: CheckersBitBoard,
+	last_move: Option<Move>,
+	parent: Option<usize>,
+	first_child: usize,
+	child_count: usize,
+	proof: u32,
+	disproof: u32,
+}
+
+/// A proof-number search tree, trying to prove that `attacker` wins
+struct ProofTree {
+	nodes: Vec<Node>,
+	attacker: PieceColor,
+}
+
+impl Node {
+	fn new(board: CheckersBitBoard, last_move: Option<Move>, parent: Option<usize>) -> Self {
+		Self {
+			board,
+			last_move,
+			parent,
+			first_child: 0,
+			child_count: 0,
+			proof: 1,
+			disproof: 1,
+		}
+	}
+
+	const fn is_expanded(&self) -> bool {
+		self.child_count != 0
+	}
+
+	const fn is_solved(&self) -> bool {
+		self.proof == 0 || self.disproof == 0
+	}
+}
+
+impl ProofTree {
+	fn is_or_node(&self, node: &Node) -> bool {
+		node.board.turn() == self.attacker
+	}
+
+	fn children(&self, node: &Node) -> &[Node] {
+		&self.nodes[node.first_child..node.first_child + node.child_count]
+	}
+
+	/// Follows the most proving path from the root down to a leaf
+	fn most_proving_node(&self) -> usize {
+		let mut current = 0;
+		while self.nodes[current].is_expanded() {
+			let node = &self.nodes[current];
+			let children = node.first_child..node.first_child + node.child_count;
+			current = if self.is_or_node(node) {
+				children.min_by_key(|&child| self.nodes[child].proof)
+			} else {
+				children.min_by_key(|&child| self.nodes[child].disproof)
+			}
+			.expect("expanded nodes have children");
+		}
+
+		current
+	}
+
+	/// Adds the children of a leaf. Each child gets proof and disproof
+	/// numbers based on how many moves it has.
+	fn expand(&mut self, index: usize) {
+		let node = self.nodes[index];
+		let moves = PossibleMoves::moves(node.board);
+		let first_child = self.nodes.len();
+
+		for current_move in moves {
+			let board = unsafe { current_move.apply_to(node.board) };
+			let mut child = Node::new(board, Some(current_move), Some(index));
+			let replies = PossibleMoves::moves(board).into_iter().count() as u32;
+			let child_is_or = self.is_or_node(&child);
+
+			(child.proof, child.disproof) = match (replies, child_is_or) {
+				// whoever has no moves left loses
+				(0, true) => (INFINITY, 0),
+				(0, false) => (0, INFINITY),
+				(replies, true) => (1, replies),
+				(replies, false) => (replies, 1),
+			};
+			self.nodes.push(child);
+		}
+
+		let child_count = self.nodes.len() - first_child;
+		let node = &mut self.nodes[index];
+		node.first_child = first_child;
+		node.child_count = child_count;
+	}
+
+	/// Recalculates the proof and disproof numbers of a node from its
+	/// children. Returns `true` if either number changed.
+	fn update(&mut self, index: usize) -> bool {
+		let node = self.nodes[index];
+		let children = self.children(&node);
+		let (proof, disproof) = if self.is_or_node(&node) {
+			(
+				children.iter().map(|c| c.proof).min().unwrap_or(INFINITY),
+				children
+					.iter()
+					.fold(0u32, |sum, c| sum.saturating_add(c.disproof)),
+			)
+		} else {
+			(
+				children
+					.iter()
+					.fold(0u32, |sum, c| sum.saturating_add(c.proof)),
+				children
+					.iter()
+					.map(|c| c.disproof)
+					.min()
+					.unwrap_or(INFINITY),
+			)
+		};
+
+		let node = &mut self.nodes[index];
+		let changed = node.proof != proof || node.disproof != disproof;
+		node.proof = proof;
+		node.disproof = disproof;
+		changed
+	}
+
+	fn update_ancestors(&mut self, mut index: usize) {
+		loop {
+			if !self.update(index) {
+				return;
+			}
+
+			match self.nodes[index].parent {
+				Some(parent) => index = parent,
+				None => return,
+			}
+		}
+	}
+}
+
+/// Tries to prove whether the player to move wins or loses, stopping once
+/// the proof tree has `max_nodes` nodes. Unlike the alpha-beta search, this doesn't use the
+/// evaluation function, so a result other than [`ProofResult::Unknown`] is a
+/// proof.
+pub fn prove(board: CheckersBitBoard, max_nodes: usize) -> Proof {
+	let mut tree = ProofTree {
+		nodes: vec![Node::new(board, None, None)],
+		attacker: board.turn(),
+	};
+
+	if PossibleMoves::moves(board).into_iter().next().is_none() {
+		return Proof {
+			result: ProofResult::Loss,
+			best_move: None,
+			nodes: 1,
+		};
+	}
+
+	while !tree.nodes[0].is_solved() && tree.nodes.len() < max_nodes {
+		let leaf = tree.most_proving_node();
+		tree.expand(leaf);
+		tree.update_ancestors(leaf);
+	}
+
+	let root = tree.nodes[0];
+	let result = if root.proof == 0 {
+		ProofResult::Win
+	} else if root.disproof == 0 {
+		ProofResult::Loss
+	} else {
+		ProofResult::Unknown
+	};
+	let best_move = tree
+		.children(&root)
+		.iter()
+		.min_by_key(|child| child.proof)
+		.and_then(|child| child.last_move);
+
+	Proof {
+		result,
+		best_move,
+		nodes: tree.nodes.len(),
+	}
+}
+
+/// The longest line that is returned by [`proven_line`], in plies
+const MAX_LINE_LENGTH: usize = 256;
+
+/// Proves the position, and then finds the line that's expected after it,
+/// where the winner plays their proven moves, and the loser plays the defense
+/// which takes the most nodes to disprove. Each position in the line is
+/// proven with at most `max_nodes` nodes. Returns `None` if the position, or
+/// any position in the line, can't be proven.
+pub fn proven_line(board: CheckersBitBoard, max_nodes: usize) -> Option<(ProofResult, Vec<Move>)> {
+	let result = prove(board, max_nodes).result;
+	let winner = match result {
+		ProofResult::Win => board.turn(),
+		ProofResult::Loss => board.turn().flip(),
+		ProofResult::Unknown => return None,
+	};
+
+	let mut line = Vec::new();
+	let mut position = board;
+	while line.len() < MAX_LINE_LENGTH {
+		let moves = PossibleMoves::moves(position);
+		if moves.is_empty() {
+			break;
+		}
+
+		let next_move = if position.turn() == winner {
+			let proof = prove(position, max_nodes);
+			if proof.result != ProofResult::Win {
+				return None;
+			}
+			proof.best_move?
+		} else {
+			moves
+				.into_iter()
+				.filter_map(|current_move| {
+					let child = unsafe { current_move.apply_to(position) };
+					let proof = prove(child, max_nodes);
+					let expected = if child.turn() == winner {
+						ProofResult::Win
+					} else {
+						ProofResult::Loss
+					};
+					(proof.result == expected).then_some((current_move, proof.nodes))
+				})
+				.max_by_key(|(_, nodes)| *nodes)?
+				.0
+		};
+
+		// safety: the move was generated from this position
+		position = unsafe { next_move.apply_to(position) };
+		line.push(next_move);
+	}
+
+	Some((result, line))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn no_moves_is_a_loss() {
+		let board = CheckersBitBoard::new(1 << 21, 0, 0, PieceColor::Dark);
+		assert_eq!(prove(board, 100).result, ProofResult::Loss);
+	}
+
+	#[test]
+	fn capturing_the_last_piece_wins() {
+		let board = CheckersBitBoard::new(1 << 20 | 1 << 21, 1 << 20, 0, PieceColor::Dark);
+		let proof = prove(board, 100);
+		assert_eq!(proof.result, ProofResult::Win);
+		assert!(proof.best_move.is_some());
+	}
+
+	#[test]
+	fn finds_the_proven_line() {
+		let board = CheckersBitBoard::new(1 << 20 | 1 << 21, 1 << 20, 0, PieceColor::Dark);
+		let (result, line) = proven_line(board, 100).unwrap();
+		assert_eq!(result, ProofResult::Win);
+		assert_eq!(line.len(), 1);
+
+		let board = CheckersBitBoard::new(1 << 21, 0, 0, PieceColor::Dark);
+		assert_eq!(
+			proven_line(board, 100),
+			Some((ProofResult::Loss, Vec::new()))
+		);
+	}
+}
@@ -0,0 +1,256 @@
+use std::fmt::Write;
+
+use model::{CheckersBitBoard, PieceColor};
+
+use crate::annotate::{comment_score, score, AnnotatedMove, Judgement};
+use crate::diagram::svg_diagram;
+use crate::Evaluation;
+
+/// The size of the evaluation graph
+const GRAPH_WIDTH: usize = 640;
+const GRAPH_HEIGHT: usize = 160;
+
+/// The evaluation at the top of the graph. Larger evaluations are drawn at
+/// the top, so that the smaller swings can still be seen.
+const GRAPH_RANGE: f32 = 0.5;
+
+const STYLE: &str = "body { font-family: sans-serif; max-width: 960px; margin: 2em auto; \
+                     color: #222; }
+h1 { font-size: 1.5em; }
+h2 { font-size: 1.2em; }
+.graph { border: 1px solid #ccc; background: #fafafa; }
+.moments { display: flex; flex-wrap: wrap; gap: 1.5em; }
+.moment svg { width: 240px; height: 240px; }
+.moment p { max-width: 240px; margin: 0.3em 0; }
+table { border-collapse: collapse; }
+td { padding: 0.15em 0.8em; }
+td.number { color: #888; text-align: right; }
+.eval { color: #666; font-size: 0.85em; }
+.dubious { color: #b07d00; }
+.mistake { color: #d06000; }
+.blunder { color: #c00000; font-weight: bold; }
+";
+
+/// An annotated game, to be written in a report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameReport {
+	/// The heading of the game, such as the names of the players
+	pub title: String,
+	pub start: CheckersBitBoard,
+	pub moves: Vec<AnnotatedMove>,
+	/// The result, as it's written in PDN, such as `2-0`
+	pub result: String,
+}
+
+impl GameReport {
+	/// The positions before each move, and then the final position
+	fn positions(&self) -> Vec<CheckersBitBoard> {
+		std::iter::once(self.start)
+			.chain(self.moves.iter().map(|annotated| annotated.played.result))
+			.collect()
+	}
+
+	/// The evaluation after each move, from black's perspective
+	fn black_scores(&self) -> Vec<Evaluation> {
+		self.positions()
+			.iter()
+			.zip(&self.moves)
+			.map(|(position, annotated)| match position.turn() {
+				PieceColor::Dark => annotated.eval,
+				PieceColor::Light => -annotated.eval,
+			})
+			.collect()
+	}
+}
+
+/// Escapes the characters which have a meaning in HTML
+fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'&' => escaped.push_str("&amp;"),
+			'<' => escaped.push_str("&lt;"),
+			'>' => escaped.push_str("&gt;"),
+			'"' => escaped.push_str("&quot;"),
+			_ => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+/// The move number and dots before a move, such as `12.` or `12...`
+fn move_number(index: usize, position: CheckersBitBoard, start: CheckersBitBoard) -> String {
+	let ply = index + usize::from(start.turn() == PieceColor::Light);
+	let dots = if position.turn() == PieceColor::Dark {
+		"."
+	} else {
+		"..."
+	};
+	format!("{}{dots}", ply / 2 + 1)
+}
+
+/// Draws the evaluation after each move as a line, with black's advantage at
+/// the top
+fn eval_graph(scores: &[Evaluation]) -> String {
+	let mut svg = format!(
+		"<svg class=\"graph\" xmlns=\"http://www.w3.org/2000/svg\" width=\"{GRAPH_WIDTH}\" \
+		 height=\"{GRAPH_HEIGHT}\" viewBox=\"0 0 {GRAPH_WIDTH} {GRAPH_HEIGHT}\">\n\
+		 <line x1=\"0\" y1=\"{0}\" x2=\"{GRAPH_WIDTH}\" y2=\"{0}\" stroke=\"#bbb\"/>\n",
+		GRAPH_HEIGHT / 2
+	);
+
+	let step = GRAPH_WIDTH as f32 / scores.len().max(1) as f32;
+	let points: Vec<String> = std::iter::once(0.0)
+		.chain(scores.iter().map(|&eval| score(eval)))
+		.enumerate()
+		.map(|(ply, score)| {
+			let y = (1.0 - score.clamp(-GRAPH_RANGE, GRAPH_RANGE) / GRAPH_RANGE)
+				* GRAPH_HEIGHT as f32
+				/ 2.0;
+			format!("{:.1},{y:.1}", ply as f32 * step)
+		})
+		.collect();
+	let _ = writeln!(
+		svg,
+		"<polyline points=\"{}\" fill=\"none\" stroke=\"#2a5db0\" stroke-width=\"2\"/>",
+		points.join(" ")
+	);
+	svg.push_str("</svg>\n");
+	svg
+}
+
+fn write_game(html: &mut String, game: &GameReport) {
+	let positions = game.positions();
+	let scores = game.black_scores();
+	let _ = writeln!(html, "<h1>{}</h1>", escape(&game.title));
+
+	if !game.moves.is_empty() {
+		html.push_str("<h2>Evaluation</h2>\n");
+		html.push_str(&eval_graph(&scores));
+	}
+
+	// the mistakes and blunders are shown in the position they were played
+	html.push_str("<h2>Key moments</h2>\n<div class=\"moments\">\n");
+	for (index, annotated) in game.moves.iter().enumerate() {
+		let Some(judgement @ (Judgement::Mistake | Judgement::Blunder)) = annotated.judgement
+		else {
+			continue;
+		};
+
+		let position = positions[index];
+		let _ = write!(
+			html,
+			"<div class=\"moment\">\n{}<p>{} <span class=\"{judgement}\">{}{}</span> is a {judgement}",
+			svg_diagram(position),
+			move_number(index, position, game.start),
+			annotated.played,
+			judgement.symbol()
+		);
+		if let Some((best_move, _)) = &annotated.best_move {
+			let _ = write!(html, ", {best_move} was better");
+		}
+		html.push_str("</p>\n</div>\n");
+	}
+	let _ = writeln!(
+		html,
+		"<div class=\"moment\">\n{}<p>the final position, {}</p>\n</div>\n</div>",
+		svg_diagram(positions[positions.len() - 1]),
+		escape(&game.result)
+	);
+
+	html.push_str("<h2>Moves</h2>\n<table>\n");
+	let mut row_open = false;
+	for (index, annotated) in game.moves.iter().enumerate() {
+		let position = positions[index];
+		if position.turn() == PieceColor::Dark || !row_open {
+			if row_open {
+				html.push_str("</tr>\n");
+			}
+			let number = move_number(index, position, game.start);
+			let _ = write!(
+				html,
+				"<tr><td class=\"number\">{}</td>",
+				number.trim_end_matches('.')
+			);
+			if position.turn() == PieceColor::Light {
+				html.push_str("<td></td>");
+			}
+			row_open = true;
+		}
+
+		let (class, symbol) = annotated
+			.judgement
+			.map_or((String::new(), ""), |judgement| {
+				(judgement.to_string(), judgement.symbol())
+			});
+		let _ = write!(
+			html,
+			"<td class=\"{class}\">{}{symbol} <span class=\"eval\">{}</span></td>",
+			annotated.played,
+			comment_score(scores[index])
+		);
+
+		if position.turn() == PieceColor::Light {
+			html.push_str("</tr>\n");
+			row_open = false;
+		}
+	}
+	if row_open {
+		html.push_str("</tr>\n");
+	}
+	let _ = writeln!(html, "</table>\n<p>{}</p>", escape(&game.result));
+}
+
+/// Writes a standalone HTML page for the games, with an evaluation graph, a
+/// diagram of each mistake and blunder, and the annotated moves. Evaluations
+/// are written from black's perspective.
+pub fn html_report(games: &[GameReport]) -> String {
+	let title = games
+		.first()
+		.map_or_else(|| "Analysis".to_string(), |game| escape(&game.title));
+	let mut html = format!(
+		"<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+		 <title>{title}</title>\n<style>\n{STYLE}</style>\n</head>\n<body>\n"
+	);
+	for game in games {
+		write_game(&mut html, game);
+	}
+	html.push_str("</body>\n</html>\n");
+	html
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::notation::parse_pdn;
+
+	#[test]
+	fn reports_key_moments() {
+		let game = parse_pdn("11-15 23-19 8-11").unwrap();
+		let moves: Vec<AnnotatedMove> = game
+			.moves
+			.iter()
+			.enumerate()
+			.map(|(index, played)| AnnotatedMove {
+				played: played.clone(),
+				eval: Evaluation::new(0.0),
+				best_move: None,
+				judgement: (index == 1).then_some(Judgement::Blunder),
+			})
+			.collect();
+		let report = GameReport {
+			title: "Black <vs> White".to_string(),
+			start: game.start,
+			moves,
+			result: "1-1".to_string(),
+		};
+
+		let html = html_report(&[report]);
+		assert!(html.contains("<title>Black &lt;vs&gt; White</title>"));
+		assert!(html.contains("1... <span class=\"blunder\">23-19??</span>"));
+		assert_eq!(html.matches("<polyline").count(), 1);
+		// the blunder, and the final position
+		assert_eq!(html.matches("class=\"moment\"").count(), 2);
+		assert_eq!(html.matches("<tr>").count(), 2);
+	}
+}
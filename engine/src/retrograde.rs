@@ -0,0 +1,525 @@
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use model::{CheckersBitBoard, PieceColor};
+use rayon::prelude::*;
+use thiserror::Error;
+
+use crate::notation::{full_moves, square_from_number};
+use crate::tablebase::{Outcome, Tablebase};
+
+/// The most pieces a tablebase can be generated for
+pub const MAX_PIECES: u8 = 8;
+
+/// About how many bytes of memory each position needs while its tablebase is
+/// being built
+const BYTES_PER_POSITION: usize = 48;
+
+// Outcomes are stored in two bytes while the tables are generated. Wins are
+// odd, and losses are even.
+const UNKNOWN: u16 = 0;
+const DRAW: u16 = u16::MAX - 1;
+const INVALID: u16 = u16::MAX;
+
+const fn encode(outcome: Outcome) -> u16 {
+	match outcome {
+		Outcome::Win(moves) => 1 + 2 * moves,
+		Outcome::Loss(moves) => 2 + 2 * moves,
+		Outcome::Draw => DRAW,
+	}
+}
+
+const fn decode(value: u16) -> Option<Outcome> {
+	match value {
+		UNKNOWN | INVALID => None,
+		DRAW => Some(Outcome::Draw),
+		value if value % 2 == 1 => Some(Outcome::Win((value - 1) / 2)),
+		value => Some(Outcome::Loss((value - 2) / 2)),
+	}
+}
+
+/// The pieces on the board, which doesn't change unless there's a capture or
+/// a promotion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Material {
+	pub black_men: u8,
+	pub black_kings: u8,
+	pub white_men: u8,
+	pub white_kings: u8,
+}
+
+/// Writes `b` for each black man, `B` for each black king, and `w` and `W`
+/// for white, such as `bbBw`
+impl Display for Material {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for (letter, count) in [
+			("b", self.black_men),
+			("B", self.black_kings),
+			("w", self.white_men),
+			("W", self.white_kings),
+		] {
+			f.write_str(&letter.repeat(count as usize))?;
+		}
+
+		Ok(())
+	}
+}
+
+impl Material {
+	pub fn of(board: CheckersBitBoard) -> Self {
+		let mut material = Self {
+			black_men: 0,
+			black_kings: 0,
+			white_men: 0,
+			white_kings: 0,
+		};
+		for square in 0..32 {
+			let count = match (board.color_at(square), board.king_at(square)) {
+				(Some(PieceColor::Dark), Some(false)) => &mut material.black_men,
+				(Some(PieceColor::Dark), Some(true)) => &mut material.black_kings,
+				(Some(PieceColor::Light), Some(false)) => &mut material.white_men,
+				(Some(PieceColor::Light), Some(true)) => &mut material.white_kings,
+				_ => continue,
+			};
+			*count += 1;
+		}
+
+		material
+	}
+
+	/// The name of the material's tablebase file, which is the number of
+	/// black men, black kings, white men and white kings, such as `2101.atb`.
+	/// Letters aren't used, since some file systems ignore their case.
+	pub fn file_name(self) -> String {
+		let [black_men, black_kings, white_men, white_kings] = self.counts();
+		format!("{black_men}{black_kings}{white_men}{white_kings}.atb")
+	}
+
+	pub const fn pieces(self) -> u8 {
+		self.black_men + self.black_kings + self.white_men + self.white_kings
+	}
+
+	const fn men(self) -> u8 {
+		self.black_men + self.white_men
+	}
+
+	const fn counts(self) -> [u8; 4] {
+		[
+			self.black_men,
+			self.black_kings,
+			self.white_men,
+			self.white_kings,
+		]
+	}
+
+	/// Every material with at most `pieces` pieces, and at least one piece
+	/// on each side. They're in the order they need to be generated in,
+	/// since captures lead to fewer pieces, and promotions to fewer men.
+	pub fn up_to(pieces: u8) -> Vec<Self> {
+		let mut materials = Vec::new();
+		for black_men in 0..=pieces {
+			for black_kings in 0..=pieces - black_men {
+				for white_men in 0..=pieces - black_men - black_kings {
+					for white_kings in 0..=pieces - black_men - black_kings - white_men {
+						let material = Self {
+							black_men,
+							black_kings,
+							white_men,
+							white_kings,
+						};
+						if black_men + black_kings > 0 && white_men + white_kings > 0 {
+							materials.push(material);
+						}
+					}
+				}
+			}
+		}
+
+		materials.sort_by_key(|material| (material.pieces(), material.men(), *material));
+		materials
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum GeneratorError {
+	#[error("Tablebases with more than {MAX_PIECES} pieces can't be generated")]
+	TooManyPieces(u8),
+	#[error(
+		"Generating the tablebases needs about {needed} MB of memory, but the limit is {limit} MB"
+	)]
+	MemoryLimit { needed: usize, limit: usize },
+	#[error("The tablebase for {0} is missing some positions")]
+	IncompleteTablebase(Material),
+}
+
+/// How far the generation of one material has gotten
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+	/// Every position which is won or lost in this many moves has been found
+	pub pass: u16,
+	pub resolved: usize,
+	pub positions: usize,
+}
+
+/// Generates tablebases by working backwards from the positions where the
+/// side to move has no moves. Each material is solved one number of moves at
+/// a time, and the positions which are never solved are draws. The outcomes
+/// of every material are kept in memory, because the later materials capture
+/// and promote into them.
+pub struct Generator {
+	binomials: [[usize; 33]; 33],
+	/// The squares each kind of piece can stand on, in the order of
+	/// [`Material::counts`]. Men can't stand on the row they're crowned on.
+	squares: [Vec<usize>; 4],
+	/// For each kind of piece, the position of each square in `squares`
+	slots: [[Option<usize>; 32]; 4],
+	tables: HashMap<Material, Vec<u16>>,
+	/// The longest win or loss in each table
+	longest: HashMap<Material, u16>,
+}
+
+impl Default for Generator {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl Generator {
+	pub fn new() -> Self {
+		let mut binomials = [[0; 33]; 33];
+		for n in 0..33 {
+			binomials[n][0] = 1;
+			for k in 1..=n {
+				binomials[n][k] = binomials[n - 1][k - 1] + binomials[n - 1][k];
+			}
+		}
+
+		let numbers = |range: std::ops::RangeInclusive<usize>| {
+			range.filter_map(square_from_number).collect::<Vec<_>>()
+		};
+		let squares = [
+			numbers(1..=28),
+			numbers(1..=32),
+			numbers(5..=32),
+			numbers(1..=32),
+		];
+
+		let mut slots = [[None; 32]; 4];
+		for (kind, squares) in squares.iter().enumerate() {
+			for (slot, &square) in squares.iter().enumerate() {
+				slots[kind][square] = Some(slot);
+			}
+		}
+
+		Self {
+			binomials,
+			squares,
+			slots,
+			tables: HashMap::new(),
+			longest: HashMap::new(),
+		}
+	}
+
+	/// The number of indices in a material's table. Some of them are for
+	/// impossible positions, where two pieces are on the same square.
+	fn table_size(&self, material: Material) -> usize {
+		let counts = material.counts();
+		(0..4)
+			.map(|kind| self.binomials[self.squares[kind].len()][counts[kind] as usize])
+			.product::<usize>()
+			* 2
+	}
+
+	/// Makes sure that generating the materials fits within the memory limit,
+	/// in megabytes
+	pub fn check_memory(&self, materials: &[Material], limit: usize) -> Result<(), GeneratorError> {
+		if let Some(material) = materials.iter().find(|m| m.pieces() > MAX_PIECES) {
+			return Err(GeneratorError::TooManyPieces(material.pieces()));
+		}
+
+		let tables: usize = materials.iter().map(|&m| self.table_size(m) * 2).sum();
+		let largest = materials
+			.iter()
+			.map(|&m| self.table_size(m) * BYTES_PER_POSITION)
+			.max()
+			.unwrap_or(0);
+		let needed = (tables + largest).div_ceil(1 << 20);
+		if needed > limit {
+			return Err(GeneratorError::MemoryLimit { needed, limit });
+		}
+
+		Ok(())
+	}
+
+	fn board(&self, material: Material, index: usize) -> Option<CheckersBitBoard> {
+		let turn = if index.is_multiple_of(2) {
+			PieceColor::Dark
+		} else {
+			PieceColor::Light
+		};
+		let mut rest = index / 2;
+		let (mut pieces, mut color, mut kings) = (0u32, 0u32, 0u32);
+
+		let counts = material.counts();
+		for kind in (0..4).rev() {
+			let available = self.squares[kind].len();
+			let combinations = self.binomials[available][counts[kind] as usize];
+			let mut rank = rest % combinations;
+			rest /= combinations;
+
+			// the combinatorial number system, from the largest square down
+			let mut slot = available;
+			for k in (1..=counts[kind] as usize).rev() {
+				slot -= 1;
+				while self.binomials[slot][k] > rank {
+					slot -= 1;
+				}
+				rank -= self.binomials[slot][k];
+
+				let bit = 1 << self.squares[kind][slot];
+				if pieces & bit != 0 {
+					return None;
+				}
+				pieces |= bit;
+				if kind < 2 {
+					color |= bit;
+				}
+				if kind % 2 == 1 {
+					kings |= bit;
+				}
+			}
+		}
+
+		Some(CheckersBitBoard::new(pieces, color, kings, turn))
+	}
+
+	fn index(&self, material: Material, board: CheckersBitBoard) -> Option<usize> {
+		let pieces = board.pieces_bits();
+		let (dark, kings) = (board.color_bits() & pieces, board.king_bits() & pieces);
+		let masks = [
+			dark & !kings,
+			dark & kings,
+			pieces & !dark & !kings,
+			pieces & !dark & kings,
+		];
+
+		let counts = material.counts();
+		let mut index = 0;
+		for kind in 0..4 {
+			let mut slots = Vec::with_capacity(counts[kind] as usize);
+			let mut mask = masks[kind];
+			while mask != 0 {
+				// a man on the row it's crowned on can't be indexed
+				slots.push(self.slots[kind][mask.trailing_zeros() as usize]?);
+				mask &= mask - 1;
+			}
+			if slots.len() != counts[kind] as usize {
+				return None;
+			}
+
+			slots.sort_unstable();
+			let rank: usize = slots
+				.iter()
+				.enumerate()
+				.map(|(k, &slot)| self.binomials[slot][k + 1])
+				.sum();
+			index = index * self.binomials[self.squares[kind].len()][slots.len()] + rank;
+		}
+
+		let turn = match board.turn() {
+			PieceColor::Dark => 0,
+			PieceColor::Light => 1,
+		};
+		Some(index * 2 + turn)
+	}
+
+	/// The stored value of a position which is already in a table
+	fn value(&self, material: Material, table: &[u16], board: CheckersBitBoard) -> u16 {
+		let next = Material::of(board);
+
+		// only the side that was just captured can have no pieces
+		if next.black_men + next.black_kings == 0 || next.white_men + next.white_kings == 0 {
+			return encode(Outcome::Loss(0));
+		}
+
+		let table = if next == material {
+			table
+		} else {
+			&self.tables[&next]
+		};
+		self.index(next, board)
+			.map_or(INVALID, |index| table[index])
+	}
+
+	/// Uses a tablebase that was already generated, instead of generating it
+	/// again
+	pub fn insert(
+		&mut self,
+		material: Material,
+		tablebase: &Tablebase,
+	) -> Result<(), GeneratorError> {
+		let size = self.table_size(material);
+		let mut table = vec![INVALID; size];
+		let mut longest = 0;
+		for (index, value) in table.iter_mut().enumerate() {
+			let Some(board) = self.board(material, index) else {
+				continue;
+			};
+			let outcome = tablebase
+				.probe(board)
+				.ok_or(GeneratorError::IncompleteTablebase(material))?;
+			if let Outcome::Win(moves) | Outcome::Loss(moves) = outcome {
+				longest = longest.max(moves);
+			}
+			*value = encode(outcome);
+		}
+
+		self.tables.insert(material, table);
+		self.longest.insert(material, longest);
+		Ok(())
+	}
+
+	/// Solves every position of a material. Every material it can capture or
+	/// promote into must already be solved. `on_pass` is called after each
+	/// number of moves is finished.
+	pub fn generate(
+		&mut self,
+		material: Material,
+		mut on_pass: impl FnMut(Progress),
+	) -> Vec<(CheckersBitBoard, Outcome)> {
+		let size = self.table_size(material);
+		let mut table: Vec<u16> = (0..size)
+			.into_par_iter()
+			.map(|index| match self.board(material, index) {
+				Some(_) => UNKNOWN,
+				None => INVALID,
+			})
+			.collect();
+		let mut unresolved: Vec<usize> = (0..size).filter(|&i| table[i] == UNKNOWN).collect();
+		let positions = unresolved.len();
+
+		// a capture can lead to a win or loss that's longer than any here
+		let dependencies = self.longest.values().copied().max().unwrap_or(0);
+		let mut longest = 0;
+		let mut pass: u16 = 0;
+		loop {
+			let updates: Vec<(usize, u16)> = unresolved
+				.par_iter()
+				.filter_map(|&index| {
+					let board = self.board(material, index)?;
+					let mut all_lost = true;
+					for full_move in full_moves(board) {
+						match decode(self.value(material, &table, full_move.result)) {
+							Some(Outcome::Loss(moves)) if moves + 1 == pass => {
+								return Some((index, encode(Outcome::Win(pass))));
+							}
+							Some(Outcome::Win(moves)) if moves < pass => {}
+							_ => all_lost = false,
+						}
+					}
+
+					all_lost.then(|| (index, encode(Outcome::Loss(pass))))
+				})
+				.collect();
+
+			if updates.is_empty() && pass > dependencies.max(longest) {
+				break;
+			}
+
+			if !updates.is_empty() {
+				longest = pass;
+			}
+			for (index, value) in updates {
+				table[index] = value;
+			}
+			unresolved.retain(|&index| table[index] == UNKNOWN);
+			on_pass(Progress {
+				pass,
+				resolved: positions - unresolved.len(),
+				positions,
+			});
+			pass += 1;
+		}
+
+		for index in unresolved {
+			table[index] = DRAW;
+		}
+
+		let outcomes = table
+			.iter()
+			.enumerate()
+			.filter_map(|(index, &value)| Some((self.board(material, index)?, decode(value)?)))
+			.collect();
+		self.tables.insert(material, table);
+		self.longest.insert(material, longest);
+		outcomes
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::notation::parse_fen;
+
+	#[test]
+	fn indices_round_trip() {
+		let generator = Generator::new();
+		let material = Material {
+			black_men: 1,
+			black_kings: 1,
+			white_men: 0,
+			white_kings: 1,
+		};
+		assert_eq!(material.to_string(), "bBW");
+
+		let mut positions = 0;
+		for index in 0..generator.table_size(material) {
+			if let Some(board) = generator.board(material, index) {
+				assert_eq!(Material::of(board), material);
+				assert_eq!(generator.index(material, board), Some(index));
+				positions += 1;
+			}
+		}
+		assert!(positions > 0);
+	}
+
+	#[test]
+	fn solves_small_endings() {
+		let mut generator = Generator::new();
+		let mut outcomes = HashMap::new();
+		// endings with only kings never promote into ones with men
+		for material in Material::up_to(3).into_iter().filter(|m| m.men() == 0) {
+			outcomes.extend(generator.generate(material, |_| {}));
+		}
+
+		// two kings beat one
+		let board = parse_fen("B:WK32:BK1,K2").unwrap();
+		assert!(matches!(outcomes[&board], Outcome::Win(_)));
+		assert!(matches!(outcomes[&board.flip_turn()], Outcome::Loss(_)));
+		assert_eq!(outcomes[&parse_fen("B:WK32:BK1").unwrap()], Outcome::Draw);
+
+		let outcome = |board: CheckersBitBoard| {
+			let material = Material::of(board);
+			if material.black_men + material.black_kings == 0
+				|| material.white_men + material.white_kings == 0
+			{
+				Outcome::Loss(0)
+			} else {
+				outcomes[&board]
+			}
+		};
+		for (&board, &result) in &outcomes {
+			let next: Vec<_> = full_moves(board)
+				.into_iter()
+				.map(|full_move| outcome(full_move.result))
+				.collect();
+			match result {
+				Outcome::Win(moves) => assert!(next.contains(&Outcome::Loss(moves - 1))),
+				Outcome::Loss(moves) => assert!(next
+					.iter()
+					.all(|next| matches!(next, Outcome::Win(n) if *n < moves))),
+				Outcome::Draw => assert!(next.contains(&Outcome::Draw)),
+			}
+		}
+	}
+}
@@ -1,17 +1,121 @@
 use std::num::NonZeroU8;
 use std::sync::{atomic::AtomicBool, Arc};
-use std::time::Instant;
+use std::thread::JoinHandle;
 
+use arrayvec::ArrayVec;
 use model::{CheckersBitBoard, Move, PieceColor, PossibleMoves};
+use parking_lot::Mutex;
 
-use crate::engine::EvaluationTask;
-use crate::Frontend;
+use crate::engine::{EvaluationTask, RootMoves, SearchAlgorithm};
+use crate::frontend::{SearchInfo, SilentFrontend};
+use crate::time::Instant;
 use crate::{
 	eval::{eval_position, Evaluation},
 	lazysort::LazySort,
+	learning, mate, mcts, skill,
+	tree::TableEvent,
 	TranspositionTableRef,
 };
 
+/// The minimum depth where the transposition table move is checked for being
+/// singular
+const SINGULAR_MIN_DEPTH: u8 = 4;
+
+/// How much shallower than the current search the table entry may be, and
+/// still be trusted for a singular extension
+const SINGULAR_DEPTH_MARGIN: u8 = 3;
+
+/// How much worse every other move must be for the table move to be singular
+const SINGULAR_MARGIN: f32 = 0.05;
+
+/// The positions between the root of a search and the node being searched.
+/// A position which is already on the path is a repetition, and is scored as
+/// a draw. Each thread searches its own task, so the path is never shared.
+#[derive(Debug, Default)]
+pub struct SearchPath {
+	positions: Mutex<Vec<PathEntry>>,
+}
+
+#[derive(Debug)]
+struct PathEntry {
+	board: CheckersBitBoard,
+	/// Whether the position's score depends on a repetition below it
+	depends_on_repetition: bool,
+	/// Whether the position is the root, or was reached by a man move or a
+	/// capture. None of the positions before it can be repeated.
+	irreversible: bool,
+}
+
+/// The squares with a man on them
+const fn men(board: CheckersBitBoard) -> u32 {
+	board.pieces_bits() & !board.king_bits()
+}
+
+impl SearchPath {
+	/// Adds a position to the end of the path. Returns `true` if the position
+	/// repeats an earlier one. Only the positions since the last man move or
+	/// capture are checked.
+	fn enter(&self, board: CheckersBitBoard) -> bool {
+		let mut positions = self.positions.lock();
+		let irreversible = positions.last().is_none_or(|last| {
+			men(last.board) != men(board)
+				|| last.board.pieces_bits().count_ones() != board.pieces_bits().count_ones()
+		});
+
+		let mut repeated = false;
+		if !irreversible {
+			for entry in positions.iter().rev() {
+				if entry.board == board {
+					repeated = true;
+					break;
+				}
+
+				if entry.irreversible {
+					break;
+				}
+			}
+		}
+
+		if let Some(last) = positions.last_mut().filter(|_| repeated) {
+			last.depends_on_repetition = true;
+		}
+
+		positions.push(PathEntry {
+			board,
+			depends_on_repetition: false,
+			irreversible,
+		});
+		repeated
+	}
+
+	/// Removes the last position, passing on whether its score depended on a
+	/// repetition
+	fn exit(&self) {
+		let mut positions = self.positions.lock();
+		let Some(PathEntry {
+			depends_on_repetition: true,
+			..
+		}) = positions.pop()
+		else {
+			return;
+		};
+
+		if let Some(last) = positions.last_mut() {
+			last.depends_on_repetition = true;
+		}
+	}
+
+	/// Whether the score of the last position depends on a repetition. Those
+	/// scores depend on how the position was reached, so they can't be stored
+	/// in the transposition table.
+	fn depends_on_repetition(&self) -> bool {
+		self.positions
+			.lock()
+			.last()
+			.is_some_and(|last| last.depends_on_repetition)
+	}
+}
+
 unsafe fn sort_moves(
 	a: &Move,
 	board: CheckersBitBoard,
@@ -22,7 +126,129 @@ unsafe fn sort_moves(
 		.unwrap_or(Evaluation::DRAW)
 }
 
+/// Estimates the evaluation of a move, for the player making it
+pub fn root_move_score(
+	board: CheckersBitBoard,
+	root_move: Move,
+	task: &EvaluationTask,
+) -> Evaluation {
+	let child = unsafe { root_move.apply_to(board) };
+	let eval = task
+		.transposition_table
+		.get_ref()
+		.get_any_depth(child)
+		.unwrap_or_else(|| {
+			let eval = eval_position(child, &task.eval_params);
+			if child.turn() == PieceColor::Dark {
+				eval
+			} else {
+				-eval
+			}
+		});
+
+	if child.turn() == board.turn() {
+		eval
+	} else {
+		-eval
+	}
+}
+
+/// Searches a node, recording it in the task's search tree if there is one. A
+/// position which repeats one earlier in the search is a draw.
 pub fn negamax(
+	depth: u8,
+	alpha: Evaluation,
+	beta: Evaluation,
+	board: CheckersBitBoard,
+	allowed_moves: Option<Arc<[Move]>>,
+	cancel_flag: &AtomicBool,
+	task: &EvaluationTask,
+) -> (Evaluation, Option<Move>) {
+	if task.path.enter(board) {
+		task.path.exit();
+		return (task.draw_score(board.turn()), None);
+	}
+
+	let result = if let Some(tree) = &task.tree {
+		let id = tree.lock().enter(board, depth, alpha, beta);
+		let result = search_node(depth, alpha, beta, board, allowed_moves, cancel_flag, task);
+		let finished = !cancel_flag.load(std::sync::atomic::Ordering::Acquire);
+		tree.lock().exit(id, result, finished);
+		result
+	} else {
+		search_node(depth, alpha, beta, board, allowed_moves, cancel_flag, task)
+	};
+
+	task.path.exit();
+	result
+}
+
+fn record_table_event(task: &EvaluationTask, event: TableEvent) {
+	if let Some(tree) = &task.tree {
+		tree.lock().table_event(event);
+	}
+}
+
+/// Checks whether the move from the transposition table is the only good move
+/// in a position. Every other move is searched at a reduced depth with a null
+/// window just below the table's evaluation, and the move is singular if all
+/// of them fail low.
+fn is_singular(
+	depth: u8,
+	board: CheckersBitBoard,
+	table_move: Move,
+	table_eval: Evaluation,
+	cancel_flag: &AtomicBool,
+	task: &EvaluationTask,
+) -> bool {
+	let turn = board.turn();
+	let singular_beta = table_eval.add_f32(-SINGULAR_MARGIN);
+	let singular_alpha = Evaluation::from_bits(singular_beta.to_bits() - 1);
+	let depth = depth / 2;
+
+	for current_move in PossibleMoves::moves(board) {
+		if current_move == table_move {
+			continue;
+		}
+
+		let board = unsafe { current_move.apply_to(board) };
+		task.counters.enter_node();
+		let eval = if board.turn() == turn {
+			negamax(
+				depth - 1,
+				singular_alpha,
+				singular_beta,
+				board,
+				None,
+				cancel_flag,
+				task,
+			)
+			.0
+			.increment()
+		} else {
+			-negamax(
+				depth - 1,
+				-singular_beta,
+				-singular_alpha,
+				board,
+				None,
+				cancel_flag,
+				task,
+			)
+			.0
+			.increment()
+		};
+		task.counters.exit_node();
+
+		if eval >= singular_beta {
+			return false;
+		}
+	}
+
+	!cancel_flag.load(std::sync::atomic::Ordering::Acquire)
+}
+
+fn search_node(
 	depth: u8,
 	mut alpha: Evaluation,
 	beta: Evaluation,
@@ -35,14 +261,19 @@ pub fn negamax(
 		.fetch_add(1, std::sync::atomic::Ordering::Release);
 
 	if depth < 1 {
-		if board.turn() == PieceColor::Dark {
-			(eval_position(board), None)
+		let eval = if board.turn() == PieceColor::Dark {
+			eval_position(board, &task.eval_params)
 		} else {
-			(-eval_position(board), None)
-		}
+			-eval_position(board, &task.eval_params)
+		};
+
+		(eval, None)
 	} else {
-		let table = task.transposition_table;
-		if let Some((entry, best_move)) = table.get(board, depth) {
+		let table = task.transposition_table.get_ref();
+		let entry = table.get(board, depth);
+		task.counters.probe_tt(entry.is_some());
+		if let Some((entry, best_move)) = entry {
+			record_table_event(task, TableEvent::Hit);
 			return (entry, Some(best_move));
 		}
 
@@ -50,34 +281,86 @@ pub fn negamax(
 		let mut best_eval = Evaluation::NULL_MIN;
 		let mut best_move = None;
 
-		let sort_fn = |m: &Move| unsafe { sort_moves(m, board, table) };
-		let sorter: LazySort<Move, _, Evaluation, { PossibleMoves::MAX_POSSIBLE_MOVES }> =
-			if let Some(moves) = allowed_moves {
-				LazySort::new(moves.iter().cloned(), sort_fn)
-			} else {
-				let moves = PossibleMoves::moves(board);
-				LazySort::new(moves, sort_fn)
-			};
+		// the root moves are searched in the order from the previous iteration
+		let is_root = task.counters.ply() == 0;
+		let previous_order: RootMoves = if is_root {
+			task.root_moves.lock().iter().map(|(m, _)| *m).collect()
+		} else {
+			RootMoves::new()
+		};
 
-		if sorter.is_empty() {
+		let sort_fn = |m: &Move| unsafe { sort_moves(m, board, table) };
+		let sorter = previous_order.is_empty().then(|| {
+			let sorter: LazySort<Move, _, Evaluation, { PossibleMoves::MAX_POSSIBLE_MOVES }> =
+				if let Some(moves) = allowed_moves {
+					LazySort::new(moves.iter().cloned(), sort_fn)
+				} else {
+					let moves = PossibleMoves::moves(board);
+					LazySort::new(moves, sort_fn)
+				};
+			sorter
+		});
+
+		if sorter.as_ref().is_some_and(LazySort::is_empty) {
 			return (Evaluation::LOSS, None);
 		}
 
-		for current_move in sorter.into_iter() {
+		task.counters.interior_node();
+
+		// extend the table move by a ply if it's much better than the others.
+		// extensions stop once the line is twice as long as the remaining
+		// depth, so that they can't go on forever.
+		let singular_move = table
+			.get_entry(board)
+			.filter(|_| {
+				!is_root
+					&& depth >= SINGULAR_MIN_DEPTH
+					&& task.counters.ply() < depth.saturating_mul(2)
+			})
+			.filter(|(eval, _, entry_depth)| {
+				!eval.is_force_sequence() && entry_depth.get() + SINGULAR_DEPTH_MARGIN >= depth
+			})
+			.filter(|&(eval, table_move, _)| {
+				is_singular(depth, board, table_move, eval, cancel_flag, task)
+			})
+			.map(|(_, table_move, _)| table_move);
+
+		let mut root_scores =
+			ArrayVec::<(Move, Evaluation), { PossibleMoves::MAX_POSSIBLE_MOVES }>::new();
+		let moves = previous_order
+			.into_iter()
+			.chain(sorter.into_iter().flatten());
+		for (index, current_move) in moves.enumerate() {
 			if cancel_flag.load(std::sync::atomic::Ordering::Acquire) {
 				return (best_eval, best_move);
 			}
 
+			if is_root {
+				task.frontend.on_current_move(current_move, index);
+			}
+
+			let child_depth = if singular_move == Some(current_move) {
+				depth
+			} else {
+				depth - 1
+			};
+
 			let board = unsafe { current_move.apply_to(board) };
+			task.counters.enter_node();
 			let current_eval = if board.turn() == turn {
-				negamax(depth - 1, alpha, beta, board, None, cancel_flag, task)
+				negamax(child_depth, alpha, beta, board, None, cancel_flag, task)
 					.0
 					.increment()
 			} else {
-				-negamax(depth - 1, -beta, -alpha, board, None, cancel_flag, task)
+				-negamax(child_depth, -beta, -alpha, board, None, cancel_flag, task)
 					.0
 					.increment()
 			};
+			task.counters.exit_node();
+
+			if is_root {
+				root_scores.push((current_move, current_eval));
+			}
 
 			if best_eval < current_eval {
 				best_eval = current_eval;
@@ -89,51 +372,208 @@ pub fn negamax(
 			}
 
 			if alpha >= beta {
+				task.counters.cut_node();
 				return (best_eval, best_move);
 			}
 		}
 
+		if is_root {
+			// the sort is stable, so moves with equal scores keep their order
+			root_scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+			*task.root_moves.lock() = root_scores;
+		}
+
 		// safety: we already checked that the list isn't empty, so there must
 		//         be at least one move here
 		let best_move = unsafe { best_move.unwrap_unchecked() };
 		// safety: in the case of a zero depth, a different branch is taken
 		let depth = unsafe { NonZeroU8::new_unchecked(depth) };
-		table.insert(board, best_eval, best_move, depth);
+		if !task.path.depends_on_repetition() {
+			table.insert(board, best_eval, best_move, depth);
+			tracing::trace!(
+				?board,
+				?best_eval,
+				depth,
+				"stored a transposition table entry"
+			);
+			record_table_event(task, TableEvent::Store);
+		}
 
 		(best_eval, Some(best_move))
 	}
 }
 
+/// Fills the transposition table with the positions along an expected line,
+/// such as a book continuation or the previous principal variation. Each
+/// position is searched to `depth`, starting from the end of the line, so
+/// that the earlier searches can use the later ones. The line stops at the
+/// first illegal move. Returns the number of positions that were searched.
+pub fn warm_up(task: &EvaluationTask, line: &[Move], depth: u8) -> usize {
+	let mut positions = vec![task.position];
+	for &checker_move in line {
+		let position = positions[positions.len() - 1];
+		if !PossibleMoves::moves(position).contains(checker_move) {
+			break;
+		}
+
+		positions.push(unsafe { checker_move.apply_to(position) });
+	}
+
+	for &position in positions.iter().rev() {
+		// the root move order is for the previous position, so it can't be used
+		task.root_moves.lock().clear();
+		for depth in 1..=depth {
+			negamax(
+				depth,
+				Evaluation::NULL_MIN,
+				Evaluation::NULL_MAX,
+				position,
+				None,
+				&task.cancel_flag,
+				task,
+			);
+		}
+	}
+
+	positions.len()
+}
+
+/// Threads which search the same position as the main thread, filling the
+/// shared transposition table. They're stopped when this is dropped, even if
+/// the main thread panics.
+struct HelperThreads(Vec<(Arc<EvaluationTask>, JoinHandle<()>)>);
+
+impl HelperThreads {
+	/// Starts one fewer helper than the task's number of threads
+	fn start(task: &EvaluationTask) -> Self {
+		let helpers = (1..task.threads)
+			.map(|index| {
+				let helper = Arc::new(EvaluationTask {
+					allowed_moves: task.allowed_moves.clone(),
+					contempt: task.contempt,
+					eval_params: task.eval_params,
+					..EvaluationTask::new(
+						task.position,
+						task.transposition_table.clone(),
+						Arc::new(SilentFrontend),
+					)
+				});
+
+				// half of the helpers start a ply deeper, so that they don't
+				// all search the same nodes at the same time
+				let start_depth = 1 + (index % 2) as u8;
+				let thread_task = helper.clone();
+				let thread = std::thread::spawn(move || help(&thread_task, start_depth));
+				(helper, thread)
+			})
+			.collect();
+
+		Self(helpers)
+	}
+}
+
+impl Drop for HelperThreads {
+	fn drop(&mut self) {
+		for (helper, _) in &self.0 {
+			helper
+				.cancel_flag
+				.store(true, std::sync::atomic::Ordering::Release);
+		}
+
+		for (_, thread) in self.0.drain(..) {
+			let _ = thread.join();
+		}
+	}
+}
+
+/// Searches the helper's position more deeply until it's cancelled. The
+/// results are only used through the transposition table.
+fn help(task: &EvaluationTask, start_depth: u8) {
+	for depth in start_depth..=u8::MAX {
+		if task.cancel_flag.load(std::sync::atomic::Ordering::Acquire) {
+			break;
+		}
+
+		negamax(
+			depth,
+			Evaluation::NULL_MIN,
+			Evaluation::NULL_MAX,
+			task.position,
+			task.allowed_moves.clone(),
+			&task.cancel_flag,
+			task,
+		);
+	}
+}
+
 pub fn search(
 	task: Arc<EvaluationTask>,
-	frontend: &dyn Frontend,
 	cancel: Option<&AtomicBool>,
 ) -> (Evaluation, Option<Move>) {
+	let start = Instant::now();
 	let board = task.position;
 	let cancel_flag = cancel.unwrap_or(&task.cancel_flag);
 
+	if let Some(max_moves) = task.mate_in {
+		let result = mate::search(&task, max_moves, cancel_flag);
+		if let Some(best_move) = result.1 {
+			task.frontend.report_best_move(best_move);
+		}
+
+		return result;
+	}
+
+	let book_move = task.book.as_ref().and_then(|book| {
+		book.choose_move_by(board, task.allowed_moves.as_deref(), |full_move, weight| {
+			learning::book_weight(&task, full_move, weight)
+		})
+	});
+	if let Some(book_move) = book_move {
+		let best_move = book_move.hops[0];
+		tracing::debug!(?best_move, "played a book move");
+		task.frontend.report_best_move(best_move);
+		return (root_move_score(board, best_move, &task), Some(best_move));
+	}
+
+	if task.algorithm == SearchAlgorithm::MonteCarlo {
+		let result = mcts::search(&task, cancel_flag);
+		if let Some(best_move) = result.1 {
+			task.frontend.report_best_move(best_move);
+		}
+
+		return result;
+	}
+
 	let allowed_moves = task.allowed_moves.clone();
 	let limits = task.limits;
 	let max_depth = limits.depth;
 	let max_nodes = limits.nodes;
 	let max_time = limits.time.map(|d| Instant::now() + d.div_f32(2.0));
+	let _span = tracing::debug_span!("search", ?board, ?limits).entered();
 
 	let mut alpha = Evaluation::NULL_MIN;
 	let mut beta = Evaluation::NULL_MAX;
-	let mut depth = 0;
-	let mut eval = Evaluation::DRAW;
-	let mut best_move = None;
+	let mut depth = task.start_depth;
+	// a resumed search falls back to the saved best move if it's stopped
+	let (mut eval, mut best_move) = match task.root_moves.lock().first() {
+		Some(&(saved_move, saved_eval)) => (saved_eval, Some(saved_move)),
+		None => (Evaluation::DRAW, None),
+	};
+	let mut iteration_start = 0;
+	let helpers = HelperThreads::start(&task);
 	loop {
 		// don't leave search is no good moves have been found
 		if best_move.is_some() {
 			if let Some(max_depth) = max_depth {
 				if depth > max_depth.get() {
+					tracing::debug!("reached the depth limit");
 					break;
 				}
 			}
 
 			if let Some(max_time) = max_time {
 				if Instant::now() > max_time {
+					tracing::debug!(elapsed = ?start.elapsed(), "reached the time limit");
 					break;
 				}
 			}
@@ -144,11 +584,13 @@ pub fn search(
 					.load(std::sync::atomic::Ordering::Acquire)
 					> max_nodes.get()
 				{
+					tracing::debug!("reached the node limit");
 					break;
 				}
 			}
 		}
 
+		let _iteration = tracing::debug_span!("iteration", depth).entered();
 		let em = negamax(
 			depth,
 			alpha,
@@ -160,7 +602,8 @@ pub fn search(
 		);
 
 		// prevent incomplete search from overwriting evaluation
-		if best_move.is_some() && cancel_flag.load(std::sync::atomic::Ordering::Acquire) {
+		if cancel_flag.load(std::sync::atomic::Ordering::Acquire) {
+			tracing::debug!("the search was cancelled");
 			break;
 		}
 
@@ -168,6 +611,8 @@ pub fn search(
 		best_move = em.1;
 
 		while (eval <= alpha) || (eval >= beta) {
+			task.counters.re_search();
+			tracing::debug!(?alpha, ?beta, ?eval, "searching again with a wider window");
 			let em = negamax(
 				depth,
 				alpha,
@@ -179,7 +624,7 @@ pub fn search(
 			);
 
 			// prevent incomplete search from overwriting evaluation
-			if best_move.is_some() && cancel_flag.load(std::sync::atomic::Ordering::Acquire) {
+			if cancel_flag.load(std::sync::atomic::Ordering::Acquire) {
 				break;
 			}
 
@@ -205,24 +650,61 @@ pub fn search(
 			beta = eval.add_f32(0.125);
 		}
 
+		let nodes = task
+			.nodes_explored
+			.load(std::sync::atomic::Ordering::Acquire);
+		task.counters.finish_iteration(nodes - iteration_start);
+		tracing::debug!(
+			?eval,
+			?best_move,
+			nodes = nodes - iteration_start,
+			elapsed = ?start.elapsed(),
+			"finished the iteration"
+		);
+		iteration_start = nodes;
+
+		if !cancel_flag.load(std::sync::atomic::Ordering::Acquire) {
+			task.frontend.on_depth_complete(&SearchInfo {
+				depth,
+				selective_depth: task.counters.selective_depth(),
+				eval,
+				best_move,
+				nodes,
+				time: start.elapsed(),
+			});
+		}
+
 		if eval.is_force_sequence() {
 			// we don't need to search any deeper
-			return (eval, best_move);
+			break;
 		}
 
 		depth += 1;
 	}
+	drop(helpers);
+
+	// If the search was stopped before it found a move, then the first legal
+	// move is played. The GUI expects a move, but if someone stops it this
+	// quickly, they probably didn't care about the best move anyway.
+	let best_move = best_move.or_else(|| {
+		PossibleMoves::moves(board).into_iter().find(|legal_move| {
+			allowed_moves
+				.as_deref()
+				.is_none_or(|moves| moves.contains(legal_move))
+		})
+	});
+	let best_move = best_move
+		.map(|best_move| learning::choose_move(&task, best_move))
+		.map(|best_move| skill::choose_move(&task, best_move));
 
 	// ponder
 	if let Some(best_move) = best_move {
-		// If the best move has not been found yet, then no move will be
-		// reported. This should be very rare. This technically is not allowed
-		// by the UCI specification, but if someone stops it this quickly, they
-		// probably didn't care about the best move anyway.
-		frontend.report_best_move(best_move);
+		task.frontend.report_best_move(best_move);
 
 		if task.ponder {
 			let board = unsafe { best_move.apply_to(board) };
+			// the pondered position is one move below the root
+			task.counters.enter_node();
 
 			let mut depth = 0;
 			loop {
@@ -250,3 +732,42 @@ pub fn search(
 
 	(eval, best_move)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::notation::parse_fen;
+
+	#[test]
+	fn repetitions_are_not_stored() {
+		let start = parse_fen("B:WK32:BK1").unwrap();
+		let first_move = PossibleMoves::moves(start).into_iter().next().unwrap();
+		let child = unsafe { first_move.apply_to(start) };
+
+		let path = SearchPath::default();
+		assert!(!path.enter(start));
+		assert!(!path.enter(child));
+		assert!(path.enter(start));
+		path.exit();
+		assert!(path.depends_on_repetition());
+		path.exit();
+		assert!(path.depends_on_repetition());
+		path.exit();
+		assert!(!path.depends_on_repetition());
+
+		assert!(!path.enter(child));
+		assert!(!path.depends_on_repetition());
+	}
+
+	#[test]
+	fn positions_before_a_man_move_are_not_checked() {
+		let start = CheckersBitBoard::starting_position();
+		let first_move = PossibleMoves::moves(start).into_iter().next().unwrap();
+		let child = unsafe { first_move.apply_to(start) };
+
+		let path = SearchPath::default();
+		assert!(!path.enter(start));
+		assert!(!path.enter(child));
+		assert!(!path.enter(start));
+	}
+}
@@ -0,0 +1,314 @@
+use std::io::{self, Read, Write};
+
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use model::{CheckersBitBoard, Move, PieceColor, PossibleMoves};
+use pdn::GameResult;
+use rand::seq::IteratorRandom;
+use thiserror::Error;
+
+use crate::eval::Evaluation;
+use crate::{Engine, EvaluationSettings, SearchLimit, MAX_SKILL_LEVEL};
+
+const MAGIC: u32 = u32::from_be_bytes(*b".amd");
+const SUPPORTED_VERSION: u16 = 0;
+
+/// How a self-play game should be played
+#[derive(Debug, Clone)]
+pub struct SelfPlaySettings {
+	/// The limits on each search
+	pub search_until: SearchLimit,
+	/// The number of random plies to play at the start of each game
+	pub random_opening_moves: u8,
+	/// The skill level to play at. Lower levels add noise to the engine's
+	/// choice of moves.
+	pub skill_level: u8,
+	/// The number of plies after which the game is declared a draw. A
+	/// multi-jump counts as a single ply.
+	pub max_plies: u16,
+}
+
+impl Default for SelfPlaySettings {
+	fn default() -> Self {
+		Self {
+			search_until: SearchLimit::default(),
+			random_opening_moves: 4,
+			skill_level: MAX_SKILL_LEVEL,
+			max_plies: 300,
+		}
+	}
+}
+
+/// Writes a result as a number, from black's perspective. Returns `None` if
+/// the game is unfinished.
+const fn result_to_i8(result: GameResult) -> Option<i8> {
+	match result {
+		GameResult::BlackWin => Some(1),
+		GameResult::WhiteWin => Some(-1),
+		GameResult::Draw => Some(0),
+		GameResult::Unfinished => None,
+	}
+}
+
+const fn result_from_i8(value: i8) -> Option<GameResult> {
+	match value {
+		1 => Some(GameResult::BlackWin),
+		-1 => Some(GameResult::WhiteWin),
+		0 => Some(GameResult::Draw),
+		_ => None,
+	}
+}
+
+/// A position from a self-play game, along with how it was evaluated, and
+/// how the game ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrainingSample {
+	pub position: CheckersBitBoard,
+	/// The evaluation of the search, from the perspective of the player whose
+	/// turn it is
+	pub score: Evaluation,
+	pub result: GameResult,
+}
+
+/// A game the engine played against itself
+#[derive(Debug, Clone)]
+pub struct SelfPlayGame {
+	pub start: CheckersBitBoard,
+	pub moves: Vec<Move>,
+	pub samples: Vec<TrainingSample>,
+	pub result: GameResult,
+}
+
+/// Plays a game starting from `start`, with the engine playing both sides.
+/// This changes the position and skill level of the engine.
+pub fn play_game(
+	engine: &Engine,
+	start: CheckersBitBoard,
+	settings: &SelfPlaySettings,
+) -> SelfPlayGame {
+	let mut rng = rand::thread_rng();
+	let mut board = start;
+	let mut moves = Vec::new();
+	let mut plies = 0;
+	let mut scores = Vec::new();
+
+	engine.set_skill_level(settings.skill_level);
+
+	let result = loop {
+		if plies >= settings.max_plies {
+			break GameResult::Draw;
+		}
+
+		let possible_moves = PossibleMoves::moves(board);
+		if possible_moves.is_empty() {
+			break GameResult::loss_for(board.turn());
+		}
+
+		let next_move = if plies < u16::from(settings.random_opening_moves) {
+			possible_moves.into_iter().choose(&mut rng)
+		} else {
+			engine.set_position(board);
+			let (score, best_move) = engine.evaluate(
+				None,
+				EvaluationSettings {
+					search_until: settings.search_until.clone(),
+					..EvaluationSettings::default()
+				},
+			);
+			scores.push((board, score));
+			best_move
+		};
+
+		let Some(next_move) = next_move else {
+			break GameResult::loss_for(board.turn());
+		};
+
+		// safety: the move was generated from this position
+		let next_board = unsafe { next_move.apply_to(board) };
+		// each jump of a multi-jump is a separate move, but only one ply
+		if next_board.turn() != board.turn() {
+			plies += 1;
+		}
+		board = next_board;
+		moves.push(next_move);
+	};
+
+	let samples = scores
+		.into_iter()
+		.map(|(position, score)| TrainingSample {
+			position,
+			score,
+			result,
+		})
+		.collect();
+
+	SelfPlayGame {
+		start,
+		moves,
+		samples,
+		result,
+	}
+}
+
+#[derive(Debug, Error)]
+pub enum TrainingDataError {
+	#[error("Invalid training data: the magic header field was incorrect")]
+	MagicError,
+	#[error("This version of the training data format is unsupported. Only {SUPPORTED_VERSION} is supported")]
+	UnsupportedVersion(u16),
+	#[error("The game result {0} is invalid")]
+	InvalidResult(i8),
+	#[error(transparent)]
+	IoError(#[from] io::Error),
+}
+
+/// Writes the header of a training data file. This should be written once,
+/// before any of the samples.
+pub fn write_header(writer: &mut impl Write) -> io::Result<()> {
+	writer.write_u32::<BigEndian>(MAGIC)?;
+	writer.write_u16::<BigEndian>(SUPPORTED_VERSION)
+}
+
+/// Reads and validates the header of a training data file
+pub fn read_header(reader: &mut impl Read) -> Result<(), TrainingDataError> {
+	let magic = reader.read_u32::<BigEndian>()?;
+	if magic != MAGIC {
+		return Err(TrainingDataError::MagicError);
+	}
+
+	let version = reader.read_u16::<BigEndian>()?;
+	if version != SUPPORTED_VERSION {
+		return Err(TrainingDataError::UnsupportedVersion(version));
+	}
+
+	Ok(())
+}
+
+impl TrainingSample {
+	/// Writes the sample as sixteen bytes. Only samples from finished games
+	/// can be written.
+	pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+		let Some(result) = result_to_i8(self.result) else {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"the sample's game is unfinished",
+			));
+		};
+
+		let board = self.position;
+		let pieces = board.pieces_bits();
+		writer.write_u32::<BigEndian>(pieces)?;
+		// the bits for empty squares are undefined, so they're cleared
+		writer.write_u32::<BigEndian>(board.color_bits() & pieces)?;
+		writer.write_u32::<BigEndian>(board.king_bits() & pieces)?;
+		writer.write_u8(board.turn() as u8)?;
+		writer.write_i16::<BigEndian>(self.score.to_bits())?;
+		writer.write_i8(result)
+	}
+
+	/// Reads a sample. Returns `Ok(None)` if the end of the file was reached.
+	pub fn read(reader: &mut impl Read) -> Result<Option<Self>, TrainingDataError> {
+		let pieces = match reader.read_u32::<BigEndian>() {
+			Ok(pieces) => pieces,
+			Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+			Err(error) => return Err(error.into()),
+		};
+		let color = reader.read_u32::<BigEndian>()?;
+		let kings = reader.read_u32::<BigEndian>()?;
+		let turn = if reader.read_u8()? == PieceColor::Dark as u8 {
+			PieceColor::Dark
+		} else {
+			PieceColor::Light
+		};
+		let score = Evaluation::from_bits(reader.read_i16::<BigEndian>()?);
+		let result = reader.read_i8()?;
+		let Some(result) = result_from_i8(result) else {
+			return Err(TrainingDataError::InvalidResult(result));
+		};
+
+		Ok(Some(Self {
+			position: CheckersBitBoard::new(pieces, color, kings, turn),
+			score,
+			result,
+		}))
+	}
+}
+
+/// Plays `games` self-play games, writing the training samples from each one
+/// to `writer`, including the file header. The games are returned.
+pub fn generate(
+	engine: &Engine,
+	games: usize,
+	settings: &SelfPlaySettings,
+	writer: &mut impl Write,
+) -> io::Result<Vec<SelfPlayGame>> {
+	write_header(writer)?;
+
+	let mut played = Vec::with_capacity(games);
+	for _ in 0..games {
+		let game = play_game(engine, CheckersBitBoard::starting_position(), settings);
+		for sample in &game.samples {
+			sample.write(writer)?;
+		}
+
+		played.push(game);
+	}
+
+	Ok(played)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::{parse_fen, TextFrontend};
+
+	#[test]
+	fn sample_round_trip() {
+		let sample = TrainingSample {
+			position: CheckersBitBoard::starting_position(),
+			score: Evaluation::new(0.25),
+			result: GameResult::WhiteWin,
+		};
+
+		let mut buffer = Vec::new();
+		write_header(&mut buffer).unwrap();
+		sample.write(&mut buffer).unwrap();
+		assert_eq!(buffer.len(), 6 + 16);
+
+		let mut reader = buffer.as_slice();
+		read_header(&mut reader).unwrap();
+		assert_eq!(TrainingSample::read(&mut reader).unwrap(), Some(sample));
+		assert_eq!(TrainingSample::read(&mut reader).unwrap(), None);
+	}
+
+	#[test]
+	fn unfinished_samples_are_not_written() {
+		let sample = TrainingSample {
+			position: CheckersBitBoard::starting_position(),
+			score: Evaluation::new(0.25),
+			result: GameResult::Unfinished,
+		};
+
+		let mut buffer = Vec::new();
+		let error = sample.write(&mut buffer).unwrap_err();
+		assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+		assert!(buffer.is_empty());
+	}
+
+	#[test]
+	fn multi_jumps_are_one_ply() {
+		let engine = Engine::new(1 << 16, Arc::new(TextFrontend::new(|_| {})));
+		// white jumps twice, from 27 over 23 and 14
+		let start = parse_fen("W:W27:B23,14,1").unwrap();
+		let settings = SelfPlaySettings {
+			random_opening_moves: 1,
+			max_plies: 1,
+			..SelfPlaySettings::default()
+		};
+
+		let game = play_game(&engine, start, &settings);
+		assert_eq!(game.moves.len(), 2);
+		assert_eq!(game.result, GameResult::Draw);
+	}
+}
@@ -0,0 +1,121 @@
+use std::num::{NonZeroU8, NonZeroUsize};
+
+use arrayvec::ArrayVec;
+use model::{Move, PossibleMoves};
+use rand::Rng;
+
+use crate::engine::EvaluationTask;
+use crate::eval::Evaluation;
+use crate::search::root_move_score;
+use crate::ActualLimit;
+
+/// The skill level at which the engine plays at full strength
+pub const MAX_SKILL_LEVEL: u8 = 20;
+
+/// The largest amount of noise added to a root move's evaluation, at skill
+/// level zero
+const MAX_NOISE: f32 = 0.25;
+
+/// The chance of picking the second-best move, at skill level zero
+const MAX_SECOND_BEST_CHANCE: f32 = 0.3;
+
+/// How far away the skill level is from full strength, from 0 to 1
+fn skill_gap(skill_level: u8) -> f32 {
+	MAX_SKILL_LEVEL.saturating_sub(skill_level) as f32 / MAX_SKILL_LEVEL as f32
+}
+
+/// How much the engine's choice is weakened at a skill level, from 0 to 1.
+/// This falls off faster than the skill gap, so that the highest levels almost
+/// always keep the move chosen by the search.
+fn weakness(skill_level: u8) -> f32 {
+	skill_gap(skill_level).powi(2)
+}
+
+/// Restricts the depth and number of nodes that can be searched at the given
+/// skill level. At full strength, the limits are left alone.
+pub fn limit_search(limits: ActualLimit, skill_level: u8) -> ActualLimit {
+	if skill_level >= MAX_SKILL_LEVEL {
+		return limits;
+	}
+
+	// safety: the skill level is less than 20, so neither of these are zero
+	let max_depth = unsafe { NonZeroU8::new_unchecked(skill_level + 1) };
+	let max_nodes = unsafe { NonZeroUsize::new_unchecked(1 << (skill_level + 8)) };
+
+	ActualLimit {
+		nodes: Some(limits.nodes.map_or(max_nodes, |nodes| nodes.min(max_nodes))),
+		depth: Some(limits.depth.map_or(max_depth, |depth| depth.min(max_depth))),
+		time: limits.time,
+	}
+}
+
+/// Picks a root move for the task's skill level. The moves are ranked by the
+/// scores from the last finished iteration of the search, with random noise
+/// added, and sometimes the second-best move is chosen.
+pub fn choose_move(task: &EvaluationTask, best_move: Move) -> Move {
+	let weakness = weakness(task.skill_level);
+	if weakness == 0.0 {
+		return best_move;
+	}
+
+	let board = task.position;
+	let mut rng = rand::thread_rng();
+	let noise = weakness * MAX_NOISE;
+	let add_noise =
+		|(m, score): (Move, Evaluation)| (score.add_f32(rng.gen_range(-noise..=noise)), m);
+
+	// if no iteration finished, the moves are estimated from the table instead
+	let searched = task.root_moves.lock().clone();
+	let estimate = |m: Move| (m, root_move_score(board, m, task));
+	let mut moves: ArrayVec<(Evaluation, Move), { PossibleMoves::MAX_POSSIBLE_MOVES }> =
+		if !searched.is_empty() {
+			searched.into_iter().map(add_noise).collect()
+		} else if let Some(moves) = &task.allowed_moves {
+			moves.iter().copied().map(estimate).map(add_noise).collect()
+		} else {
+			PossibleMoves::moves(board)
+				.into_iter()
+				.map(estimate)
+				.map(add_noise)
+				.collect()
+		};
+	moves.sort_unstable_by_key(|(score, _)| std::cmp::Reverse(*score));
+
+	let second_best_chance = (weakness * MAX_SECOND_BEST_CHANCE) as f64;
+	match moves.as_slice() {
+		[_, (_, second_best), ..] if rng.gen_bool(second_best_chance) => *second_best,
+		[(_, best), ..] => *best,
+		[] => best_move,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn full_strength_is_unlimited() {
+		let limits = ActualLimit::default();
+		assert_eq!(limit_search(limits, MAX_SKILL_LEVEL), limits);
+	}
+
+	#[test]
+	fn low_skill_limits_depth() {
+		let limits = ActualLimit {
+			nodes: None,
+			depth: NonZeroU8::new(15),
+			time: None,
+		};
+		let limits = limit_search(limits, 3);
+		assert_eq!(limits.depth, NonZeroU8::new(4));
+		assert!(limits.nodes.is_some());
+	}
+
+	#[test]
+	fn high_skill_is_barely_weakened() {
+		assert_eq!(weakness(MAX_SKILL_LEVEL), 0.0);
+		assert_eq!(weakness(0), 1.0);
+		assert!(weakness(MAX_SKILL_LEVEL - 1) * MAX_NOISE < 0.001);
+		assert!(weakness(10) < skill_gap(10));
+	}
+}
@@ -0,0 +1,151 @@
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use parking_lot::Mutex;
+
+/// Counters which are updated while a search is running
+#[derive(Debug, Default)]
+pub struct SearchCounters {
+	tt_probes: AtomicUsize,
+	tt_hits: AtomicUsize,
+	interior_nodes: AtomicUsize,
+	cut_nodes: AtomicUsize,
+	re_searches: AtomicUsize,
+	iteration_nodes: Mutex<Vec<usize>>,
+	/// The number of moves between the root and the node being searched
+	ply: AtomicU8,
+	max_ply: AtomicU8,
+}
+
+/// Statistics about a search, used to measure how well the search is pruning
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SearchStats {
+	/// The total number of nodes searched
+	pub nodes: usize,
+	/// The number of times the transposition table was checked
+	pub tt_probes: usize,
+	/// The number of times the transposition table had a usable entry
+	pub tt_hits: usize,
+	/// The number of nodes whose moves were searched
+	pub interior_nodes: usize,
+	/// The number of interior nodes which failed high
+	pub cut_nodes: usize,
+	/// The number of times an iteration was searched again, because the
+	/// evaluation fell outside of the aspiration window
+	pub re_searches: usize,
+	/// The number of nodes searched by each completed iteration
+	pub iteration_nodes: Vec<usize>,
+	/// The deepest ply that the search reached
+	pub selective_depth: u8,
+}
+
+impl SearchCounters {
+	/// Records that the search has moved down to a child node
+	pub fn enter_node(&self) {
+		let ply = self.ply.fetch_add(1, Ordering::Relaxed) + 1;
+		self.max_ply.fetch_max(ply, Ordering::Relaxed);
+	}
+
+	pub fn exit_node(&self) {
+		self.ply.fetch_sub(1, Ordering::Relaxed);
+	}
+
+	/// The ply of the node being searched
+	pub fn ply(&self) -> u8 {
+		self.ply.load(Ordering::Relaxed)
+	}
+
+	/// The deepest ply that the search has reached
+	pub fn selective_depth(&self) -> u8 {
+		self.max_ply.load(Ordering::Relaxed)
+	}
+
+	pub fn probe_tt(&self, hit: bool) {
+		self.tt_probes.fetch_add(1, Ordering::Relaxed);
+		if hit {
+			self.tt_hits.fetch_add(1, Ordering::Relaxed);
+		}
+	}
+
+	pub fn interior_node(&self) {
+		self.interior_nodes.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub fn cut_node(&self) {
+		self.cut_nodes.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub fn re_search(&self) {
+		self.re_searches.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub fn finish_iteration(&self, nodes: usize) {
+		self.iteration_nodes.lock().push(nodes);
+	}
+
+	pub fn stats(&self, nodes: usize) -> SearchStats {
+		SearchStats {
+			nodes,
+			tt_probes: self.tt_probes.load(Ordering::Relaxed),
+			tt_hits: self.tt_hits.load(Ordering::Relaxed),
+			interior_nodes: self.interior_nodes.load(Ordering::Relaxed),
+			cut_nodes: self.cut_nodes.load(Ordering::Relaxed),
+			re_searches: self.re_searches.load(Ordering::Relaxed),
+			iteration_nodes: self.iteration_nodes.lock().clone(),
+			selective_depth: self.selective_depth(),
+		}
+	}
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f32 {
+	if denominator == 0 {
+		0.0
+	} else {
+		numerator as f32 / denominator as f32
+	}
+}
+
+impl SearchStats {
+	/// The fraction of transposition table probes that were hits
+	pub fn tt_hit_rate(&self) -> f32 {
+		ratio(self.tt_hits, self.tt_probes)
+	}
+
+	/// The fraction of interior nodes which failed high
+	pub fn cut_node_rate(&self) -> f32 {
+		ratio(self.cut_nodes, self.interior_nodes)
+	}
+
+	/// The number of nodes in the last completed iteration, divided by the
+	/// number in the iteration before it. Returns `None` if fewer than two
+	/// iterations were completed.
+	pub fn effective_branching_factor(&self) -> Option<f32> {
+		match self.iteration_nodes.as_slice() {
+			[.., previous, last] if *previous != 0 => Some(ratio(*last, *previous)),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn rates() {
+		let counters = SearchCounters::default();
+		counters.probe_tt(true);
+		counters.probe_tt(false);
+		counters.interior_node();
+		counters.enter_node();
+		counters.enter_node();
+		counters.exit_node();
+		counters.finish_iteration(10);
+		counters.finish_iteration(40);
+
+		let stats = counters.stats(50);
+		assert_eq!(stats.tt_hit_rate(), 0.5);
+		assert_eq!(stats.cut_node_rate(), 0.0);
+		assert_eq!(stats.effective_branching_factor(), Some(4.0));
+		assert_eq!(stats.selective_depth, 2);
+	}
+}
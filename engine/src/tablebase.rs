@@ -1,13 +1,19 @@
-use std::{io, string::FromUtf8Error};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::string::FromUtf8Error;
 
-use byteorder::{BigEndian, ReadBytesExt};
-use model::{CheckersBitBoard, PieceColor};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use model::{CheckersBitBoard, GameType, MoveNotation, PieceColor, Variant};
 use thiserror::Error;
 
 const MAGIC: u32 = u32::from_be_bytes(*b".amp");
 const SUPPORTED_VERSION: u16 = 0;
 const MAX_TABLE_LENGTH: u64 = 5_000_000_000;
 
+/// The factor that board hashes are multiplied by in new tablebases
+const DEFAULT_MAGIC_FACTOR: u64 = 0x9e37_79b9_7f4a_7c15;
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Tablebase {
 	header: FileHeader,
@@ -34,39 +40,6 @@ struct FileHeader {
 	publication_time: u64,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-struct GameType {
-	/// The type of game being played
-	game_type: Game,
-	/// The color that makes the first move
-	start_color: PieceColor,
-	/// The width of the board
-	board_width: u8,
-	/// The height of the board
-	board_height: u8,
-	/// The move notation
-	notation: MoveNotation,
-	/// True if the bottom-left square is a playing square
-	invert_flag: bool,
-}
-
-#[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Game {
-	EnglishDraughts = 21,
-}
-
-#[repr(u8)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum MoveNotation {
-	/// Standard Chess Notation, like e5
-	Standard = 0,
-	/// Alpha-numeric square representation, like e7-e5
-	Alpha = 1,
-	/// Numeric square representation, like 11-12
-	Numeric = 2,
-}
-
 #[derive(Debug, Clone, Copy, PartialEq)]
 struct TablebaseEntry {
 	board: CheckersBitBoard,
@@ -74,8 +47,45 @@ struct TablebaseEntry {
 	depth: u8,
 }
 
+/// The result of a position with perfect play, for the side to move. The
+/// number is how many moves are left until the game ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+	Win(u16),
+	Loss(u16),
+	Draw,
+}
+
+impl TablebaseEntry {
+	/// Wins and losses are stored as an evaluation of one or negative one,
+	/// and the number of moves is capped at 255
+	fn new(board: CheckersBitBoard, outcome: Outcome) -> Self {
+		let (evaluation, depth) = match outcome {
+			Outcome::Win(depth) => (1.0, depth),
+			Outcome::Loss(depth) => (-1.0, depth),
+			Outcome::Draw => (0.0, 0),
+		};
+
+		Self {
+			board,
+			evaluation,
+			depth: depth.min(u8::MAX as u16) as u8,
+		}
+	}
+
+	fn outcome(self) -> Outcome {
+		if self.evaluation > 0.0 {
+			Outcome::Win(self.depth as u16)
+		} else if self.evaluation < 0.0 {
+			Outcome::Loss(self.depth as u16)
+		} else {
+			Outcome::Draw
+		}
+	}
+}
+
 #[derive(Debug, Error)]
-enum TablebaseFileError {
+pub enum TablebaseFileError {
 	#[error("Invalid tablebase: the magic header field was incorrect")]
 	MagicError,
 	#[error("This version of the tablebase format is unsupported. Only {SUPPORTED_VERSION} is supported")]
@@ -90,6 +100,147 @@ enum TablebaseFileError {
 	IoError(#[from] io::Error),
 }
 
+/// The hash of a board, scaled by the tablebase's magic factor
+fn entry_hash(board: CheckersBitBoard, magic_factor: u64) -> u64 {
+	board.hash_code().wrapping_mul(magic_factor)
+}
+
+impl Tablebase {
+	/// Creates a tablebase from the outcome of every position in it
+	pub fn new(
+		name: &str,
+		author: &str,
+		publication_time: u64,
+		outcomes: impl IntoIterator<Item = (CheckersBitBoard, Outcome)>,
+	) -> Self {
+		let outcomes: Vec<_> = outcomes.into_iter().collect();
+		let header = FileHeader {
+			version: SUPPORTED_VERSION,
+			magic_factor: DEFAULT_MAGIC_FACTOR,
+			entries_count: outcomes.len() as u64,
+			// the table is kept half empty, so that lookups are short
+			table_length: (outcomes.len() as u64 * 2).max(1),
+			game_type: GameType::ENGLISH_DRAUGHTS,
+			tablebase_name: truncate(name),
+			author_name: truncate(author),
+			publication_time,
+		};
+
+		let mut tablebase = Self {
+			entries: vec![None; header.table_length as usize].into_boxed_slice(),
+			header,
+		};
+		for (board, outcome) in outcomes {
+			tablebase.insert(TablebaseEntry::new(board, outcome));
+		}
+
+		tablebase
+	}
+
+	pub fn name(&self) -> &str {
+		&self.header.tablebase_name
+	}
+
+	pub fn author(&self) -> &str {
+		&self.header.author_name
+	}
+
+	/// The Unix timestamp of when the tablebase was created
+	pub fn publication_time(&self) -> u64 {
+		self.header.publication_time
+	}
+
+	pub fn len(&self) -> usize {
+		self.header.entries_count as usize
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.header.entries_count == 0
+	}
+
+	/// The slots an entry for the board may be in, in the order they're
+	/// checked
+	fn slots(&self, board: CheckersBitBoard) -> impl Iterator<Item = usize> {
+		let length = self.entries.len();
+		let start = (entry_hash(board, self.header.magic_factor) % length as u64) as usize;
+		(0..length).map(move |offset| (start + offset) % length)
+	}
+
+	fn insert(&mut self, entry: TablebaseEntry) {
+		let slot = self
+			.slots(entry.board)
+			.find(|&slot| self.entries[slot].is_none_or(|other| other.board == entry.board));
+		if let Some(slot) = slot {
+			self.entries[slot] = Some(entry);
+		}
+	}
+
+	/// The outcome of a position, or `None` if it isn't in the tablebase
+	pub fn probe(&self, board: CheckersBitBoard) -> Option<Outcome> {
+		for slot in self.slots(board) {
+			match self.entries[slot] {
+				Some(entry) if entry.board == board => return Some(entry.outcome()),
+				Some(_) => continue,
+				None => return None,
+			}
+		}
+
+		None
+	}
+
+	/// Every position in the tablebase, and its outcome
+	pub fn outcomes(&self) -> impl Iterator<Item = (CheckersBitBoard, Outcome)> + '_ {
+		self.entries
+			.iter()
+			.flatten()
+			.map(|entry| (entry.board, entry.outcome()))
+	}
+
+	pub fn read(reader: &mut impl ReadBytesExt) -> Result<Self, TablebaseFileError> {
+		let header = read_header(reader)?;
+		let mut tablebase = Self {
+			entries: vec![None; header.table_length.max(1) as usize].into_boxed_slice(),
+			header,
+		};
+
+		for _ in 0..tablebase.header.entries_count {
+			let entry = read_entry(reader)?;
+			tablebase.insert(entry);
+		}
+
+		Ok(tablebase)
+	}
+
+	pub fn write(&self, writer: &mut impl WriteBytesExt) -> io::Result<()> {
+		write_header(writer, &self.header)?;
+		for entry in self.entries.iter().flatten() {
+			write_entry(writer, entry)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, TablebaseFileError> {
+		Self::read(&mut BufReader::new(File::open(path)?))
+	}
+
+	pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		self.write(&mut writer)?;
+		writer.flush()
+	}
+}
+
+/// Names are written with a one byte length
+fn truncate(name: &str) -> Box<str> {
+	let mut end = name.len().min(u8::MAX as usize);
+	while !name.is_char_boundary(end) {
+		end -= 1;
+	}
+
+	name[..end].into()
+}
+
 fn read_header(reader: &mut impl ReadBytesExt) -> Result<FileHeader, TablebaseFileError> {
 	// magic is used to verify that the file is valid
 	let magic = reader.read_u32::<BigEndian>()?;
@@ -136,19 +287,27 @@ fn read_header(reader: &mut impl ReadBytesExt) -> Result<FileHeader, TablebaseFi
 	})
 }
 
+fn write_header(writer: &mut impl WriteBytesExt, header: &FileHeader) -> io::Result<()> {
+	writer.write_u32::<BigEndian>(MAGIC)?;
+	writer.write_all(&[0; 2])?;
+	writer.write_u16::<BigEndian>(header.version)?;
+	writer.write_u64::<BigEndian>(header.magic_factor)?;
+	writer.write_u64::<BigEndian>(header.entries_count)?;
+	writer.write_u64::<BigEndian>(header.table_length)?;
+	write_game_type(writer, header.game_type)?;
+	writer.write_u64::<BigEndian>(header.publication_time)?;
+	writer.write_u8(header.tablebase_name.len() as u8)?;
+	writer.write_u8(header.author_name.len() as u8)?;
+	writer.write_all(&[0; 14])?;
+	writer.write_all(header.tablebase_name.as_bytes())?;
+	writer.write_all(header.author_name.as_bytes())
+}
+
 fn read_reserved_bytes<const NUM_BYTES: usize>(reader: &mut impl ReadBytesExt) -> io::Result<()> {
 	reader.read_exact([0; NUM_BYTES].as_mut_slice())?;
 	Ok(())
 }
 
-#[derive(Debug, Error)]
-enum ReadStringError {
-	#[error(transparent)]
-	InvalidUtf8(#[from] FromUtf8Error),
-	#[error(transparent)]
-	IoError(#[from] io::Error),
-}
-
 fn read_string(reader: &mut impl ReadBytesExt, len: u8) -> Result<Box<str>, TablebaseFileError> {
 	let mut buffer = vec![0; len as usize];
 	reader.read_exact(&mut buffer)?;
@@ -162,25 +321,101 @@ fn read_game_type(reader: &mut impl ReadBytesExt) -> Result<GameType, TablebaseF
 	let board_width = reader.read_u8()?;
 	let board_height = reader.read_u8()?;
 	let invert_flag = reader.read_u8()?;
-	let notation = reader.read_u8()?;
+	let notation = match reader.read_u8()? {
+		0 => MoveNotation::Standard,
+		1 => MoveNotation::Alpha,
+		2 => MoveNotation::Numeric,
+		_ => return Err(TablebaseFileError::UnsupportedGameType(game_type)),
+	};
 	read_reserved_bytes::<1>(reader)?;
 
-	if game_type != 21
-		|| start_color != 1
-		|| board_width != 8
-		|| board_height != 8
-		|| invert_flag != 1
-		|| notation != 2
-	{
-		Err(TablebaseFileError::UnsupportedGameType(game_type))
+	let start_color = match start_color {
+		0 => PieceColor::Light,
+		1 => PieceColor::Dark,
+		_ => return Err(TablebaseFileError::UnsupportedGameType(game_type)),
+	};
+	let invert_flag = match invert_flag {
+		0 => false,
+		1 => true,
+		_ => return Err(TablebaseFileError::UnsupportedGameType(game_type)),
+	};
+
+	let game_type = GameType {
+		variant: Variant::from_code(game_type),
+		start_color,
+		board_width,
+		board_height,
+		notation,
+		invert_flag,
+	};
+	if game_type.is_supported() {
+		Ok(game_type)
 	} else {
-		Ok(GameType {
-			game_type: Game::EnglishDraughts,
-			start_color: PieceColor::Dark,
-			board_width: 8,
-			board_height: 8,
-			notation: MoveNotation::Numeric,
-			invert_flag: true,
-		})
+		Err(TablebaseFileError::UnsupportedGameType(
+			game_type.variant.code(),
+		))
+	}
+}
+
+fn write_game_type(writer: &mut impl WriteBytesExt, game_type: GameType) -> io::Result<()> {
+	writer.write_u8(0)?;
+	writer.write_u8(game_type.variant.code())?;
+	writer.write_u8(game_type.start_color as u8)?;
+	writer.write_u8(game_type.board_width)?;
+	writer.write_u8(game_type.board_height)?;
+	writer.write_u8(game_type.invert_flag as u8)?;
+	writer.write_u8(game_type.notation as u8)?;
+	writer.write_u8(0)
+}
+
+fn read_entry(reader: &mut impl ReadBytesExt) -> Result<TablebaseEntry, TablebaseFileError> {
+	let pieces = reader.read_u32::<BigEndian>()?;
+	let color = reader.read_u32::<BigEndian>()?;
+	let kings = reader.read_u32::<BigEndian>()?;
+	let turn = if reader.read_u8()? == PieceColor::Dark as u8 {
+		PieceColor::Dark
+	} else {
+		PieceColor::Light
+	};
+	let evaluation = reader.read_f32::<BigEndian>()?;
+	let depth = reader.read_u8()?;
+
+	Ok(TablebaseEntry {
+		board: CheckersBitBoard::new(pieces, color, kings, turn),
+		evaluation,
+		depth,
+	})
+}
+
+fn write_entry(writer: &mut impl WriteBytesExt, entry: &TablebaseEntry) -> io::Result<()> {
+	let board = entry.board;
+	let pieces = board.pieces_bits();
+	writer.write_u32::<BigEndian>(pieces)?;
+	writer.write_u32::<BigEndian>(board.color_bits() & pieces)?;
+	writer.write_u32::<BigEndian>(board.king_bits() & pieces)?;
+	writer.write_u8(board.turn() as u8)?;
+	writer.write_f32::<BigEndian>(entry.evaluation)?;
+	writer.write_u8(entry.depth)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn file_round_trip() {
+		let start = CheckersBitBoard::starting_position();
+		let outcomes = [(start, Outcome::Draw), (start.flip_turn(), Outcome::Win(3))];
+		let tablebase = Tablebase::new("test", "someone", 1_700_000_000, outcomes);
+		assert_eq!(tablebase.probe(start.flip_turn()), Some(Outcome::Win(3)));
+
+		let mut buffer = Vec::new();
+		tablebase.write(&mut buffer).unwrap();
+		let read = Tablebase::read(&mut buffer.as_slice()).unwrap();
+		assert_eq!(read.name(), "test");
+		assert_eq!(read.len(), 2);
+		assert_eq!(read.probe(start), Some(Outcome::Draw));
+		assert_eq!(read.probe(start.flip_turn()), Some(Outcome::Win(3)));
+		assert_eq!(read.probe(start.clear_piece(0)), None);
 	}
 }
@@ -0,0 +1,119 @@
+use model::{CheckersBitBoard, Move, PieceColor, PossibleMoves};
+
+/// The material value of a king, where a man is worth one
+const KING_VALUE: f32 = 1.5;
+
+/// The score of a position where the side to move can't move, and has lost
+const LOSS_SCORE: f32 = -100.0;
+
+/// A sequence of forcing moves which wins material for the player to move
+#[derive(Debug, Clone, PartialEq)]
+pub struct Combination {
+	/// The moves of both players, starting with the player to move
+	pub moves: Vec<Move>,
+	/// The material won by the combination, where a man is worth one
+	pub gain: f32,
+}
+
+/// The material of `color` minus the material of their opponent
+fn material_balance(board: CheckersBitBoard, color: PieceColor) -> f32 {
+	let pieces = board.pieces_bits();
+	let dark = pieces & board.color_bits();
+	let light = pieces & !board.color_bits();
+	let kings = pieces & board.king_bits();
+	let material = |side: u32| {
+		(side & !kings).count_ones() as f32 + (side & kings).count_ones() as f32 * KING_VALUE
+	};
+
+	let balance = material(dark) - material(light);
+	if color == PieceColor::Dark {
+		balance
+	} else {
+		-balance
+	}
+}
+
+/// Checks if a quiet move is worth considering in a combination. Moves that
+/// offer a piece for capture, or that crown a king, are tactical.
+fn is_tactical(board: CheckersBitBoard, child: CheckersBitBoard, quiet_move: Move) -> bool {
+	let offers_capture = child.turn() != board.turn() && PossibleMoves::has_jumps(child);
+	let crowns = board.king_at(quiet_move.start() as usize) == Some(false)
+		&& child.king_at(quiet_move.end_position()) == Some(true);
+
+	offers_capture || crowns
+}
+
+/// Searches only forcing lines: captures, sacrifices, and promotions. When a
+/// player isn't forced to capture, they may stop the sequence instead. The
+/// defender always stops instead of playing a quiet move.
+fn search(board: CheckersBitBoard, attacker: PieceColor, plies_left: u8) -> (f32, Vec<Move>) {
+	let moves = PossibleMoves::moves(board);
+	let attacker_to_move = board.turn() == attacker;
+	if moves.is_empty() {
+		let score = if attacker_to_move {
+			LOSS_SCORE
+		} else {
+			-LOSS_SCORE
+		};
+		return (score, Vec::new());
+	}
+
+	let balance = material_balance(board, attacker);
+	let forced = moves.can_jump();
+	if plies_left == 0 || (!forced && !attacker_to_move) {
+		return (balance, Vec::new());
+	}
+
+	let mut best: Option<(f32, Vec<Move>)> = (!forced).then(|| (balance, Vec::new()));
+	for current_move in moves {
+		let child = unsafe { current_move.apply_to(board) };
+		if !forced && !is_tactical(board, child, current_move) {
+			continue;
+		}
+
+		let (score, mut line) = search(child, attacker, plies_left - 1);
+		let is_better = match &best {
+			None => true,
+			Some((best_score, _)) if attacker_to_move => score > *best_score,
+			Some((best_score, _)) => score < *best_score,
+		};
+
+		if is_better {
+			line.insert(0, current_move);
+			best = Some((score, line));
+		}
+	}
+
+	best.unwrap_or((balance, Vec::new()))
+}
+
+/// Looks for a combination of at most `max_plies` moves, considering only
+/// captures, sacrifices, and promotions. This is much cheaper than a full
+/// search, so it can be used to screen positions for tactics.
+pub fn find_combination(board: CheckersBitBoard, max_plies: u8) -> Option<Combination> {
+	let attacker = board.turn();
+	let (score, moves) = search(board, attacker, max_plies);
+	let gain = score - material_balance(board, attacker);
+
+	(gain > 0.0 && !moves.is_empty()).then_some(Combination { moves, gain })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn capture_is_a_combination() {
+		let board = CheckersBitBoard::new(1 << 20 | 1 << 21, 1 << 20, 0, PieceColor::Dark);
+		let combination = find_combination(board, 4).unwrap();
+		assert_eq!(combination.moves.len(), 1);
+	}
+
+	#[test]
+	fn no_combination_at_the_start() {
+		assert_eq!(
+			find_combination(CheckersBitBoard::starting_position(), 8),
+			None
+		);
+	}
+}
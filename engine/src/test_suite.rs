@@ -0,0 +1,217 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use model::{CheckersBitBoard, Move};
+use parking_lot::Mutex;
+use thiserror::Error;
+
+use crate::notation::{find_move, parse_fen, FullMove};
+use crate::options::MEGABYTE;
+use crate::{ActualLimit, Clock, Engine, EvaluationSettings, Frontend, SearchInfo, SearchLimit};
+
+/// A position, and the moves which solve it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestPosition {
+	/// The `id` of the position, or its line number if it doesn't have one
+	pub id: String,
+	pub position: CheckersBitBoard,
+	pub best_moves: Vec<FullMove>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TestSuiteError {
+	#[error("Line {line}: the position is not a valid FEN")]
+	InvalidFen { line: usize },
+	#[error("Line {line}: there is no best move")]
+	MissingBestMove { line: usize },
+	#[error("Line {line}: {notation:?} is not a legal move in this position")]
+	IllegalMove { line: usize, notation: String },
+}
+
+/// Reads a test suite. Each line is a FEN, followed by operations which end
+/// in semicolons, such as `B:W18,22:B14 bm 14x23; id "capture";`. The `bm`
+/// operation lists the best moves, separated by commas or spaces. Empty lines
+/// and lines starting with `#` are skipped.
+pub fn parse(text: &str) -> Result<Vec<TestPosition>, TestSuiteError> {
+	let mut tests = Vec::new();
+	for (index, line) in text.lines().enumerate() {
+		let line_number = index + 1;
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		let (fen, operations) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+		let position = parse_fen(fen).ok_or(TestSuiteError::InvalidFen { line: line_number })?;
+
+		let mut id = None;
+		let mut best_moves = Vec::new();
+		for operation in operations.split(';') {
+			let operation = operation.trim();
+			let (opcode, operands) = operation
+				.split_once(char::is_whitespace)
+				.unwrap_or((operation, ""));
+			match opcode {
+				"bm" => {
+					for notation in operands.split([',', ' ']).filter(|m| !m.is_empty()) {
+						let best_move = find_move(position, notation).ok_or_else(|| {
+							TestSuiteError::IllegalMove {
+								line: line_number,
+								notation: notation.to_string(),
+							}
+						})?;
+						best_moves.push(best_move);
+					}
+				}
+				"id" => id = Some(operands.trim().trim_matches('"').to_string()),
+				_ => {}
+			}
+		}
+
+		if best_moves.is_empty() {
+			return Err(TestSuiteError::MissingBestMove { line: line_number });
+		}
+
+		tests.push(TestPosition {
+			id: id.unwrap_or_else(|| format!("line {line_number}")),
+			position,
+			best_moves,
+		});
+	}
+
+	Ok(tests)
+}
+
+/// How the engine did on one position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+	/// The move the engine chose
+	pub found: Option<FullMove>,
+	pub solved: bool,
+	/// How long it took the engine to find a best move, and keep it until the
+	/// end of the search. This is `None` if the position wasn't solved.
+	pub time_to_solution: Option<Duration>,
+	pub nodes: usize,
+}
+
+/// Remembers the best move after every depth
+#[derive(Default)]
+struct HistoryFrontend {
+	history: Mutex<Vec<SearchInfo>>,
+}
+
+impl Frontend for HistoryFrontend {
+	fn on_depth_complete(&self, info: &SearchInfo) {
+		self.history.lock().push(*info);
+	}
+
+	fn on_string(&self, _msg: &str) {}
+
+	fn report_best_move(&self, _best_move: Move) {}
+}
+
+/// Searches each position for `time`, and calls `on_result` once each search
+/// is finished. The transposition table is kept between positions.
+pub fn run(
+	tests: &[TestPosition],
+	time: Duration,
+	hash_megabytes: usize,
+	mut on_result: impl FnMut(&TestPosition, &TestResult),
+) {
+	let frontend = Arc::new(HistoryFrontend::default());
+	let engine = Engine::new(hash_megabytes.max(1) * MEGABYTE, frontend.clone());
+	let settings = EvaluationSettings {
+		clock: Clock::Unlimited,
+		search_until: SearchLimit::Limited(ActualLimit {
+			nodes: None,
+			depth: None,
+			time: Some(time),
+		}),
+		..EvaluationSettings::default()
+	};
+
+	for test in tests {
+		frontend.history.lock().clear();
+		engine.set_position(test.position);
+		let (_, best_move) = engine.evaluate(None, settings.clone());
+
+		let found = best_move.and_then(|best_move| engine.best_full_move(test.position, best_move));
+
+		// two ways of capturing the same pieces are the same move
+		let is_solution = |hop: Move| {
+			test.best_moves
+				.iter()
+				.any(|best_move| best_move.hops[0] == hop)
+		};
+		let solved = found.as_ref().is_some_and(|found| {
+			test.best_moves
+				.iter()
+				.any(|best_move| best_move.result == found.result)
+		});
+
+		let history = frontend.history.lock();
+		let time_to_solution = solved.then(|| {
+			let unsolved = history
+				.iter()
+				.rposition(|info| !info.best_move.is_some_and(is_solution));
+			match unsolved {
+				Some(index) => history
+					.get(index + 1)
+					.map_or(Duration::ZERO, |info| info.time),
+				None => Duration::ZERO,
+			}
+		});
+		let nodes = history.last().map_or(0, |info| info.nodes);
+		drop(history);
+
+		on_result(
+			test,
+			&TestResult {
+				found,
+				solved,
+				time_to_solution,
+				nodes,
+			},
+		);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_suites() {
+		let suite =
+			"# a comment\n\nB:W18:B14 bm 14x23; id \"capture\";\nB:W21-32:B1-12 bm 11-15, 9-14;";
+		let tests = parse(suite).unwrap();
+		assert_eq!(tests.len(), 2);
+		assert_eq!(tests[0].id, "capture");
+		assert_eq!(tests[0].best_moves[0].to_string(), "14x23");
+		assert_eq!(tests[1].id, "line 4");
+		assert_eq!(tests[1].best_moves.len(), 2);
+
+		assert_eq!(
+			parse("B:W21-32:B1-12 bm 11-14;"),
+			Err(TestSuiteError::IllegalMove {
+				line: 1,
+				notation: "11-14".to_string()
+			})
+		);
+		assert_eq!(
+			parse("B:W21-32:B1-12 id \"none\";"),
+			Err(TestSuiteError::MissingBestMove { line: 1 })
+		);
+	}
+
+	#[test]
+	fn solves_forced_moves() {
+		let tests = parse("B:W18:B14 bm 14x23;").unwrap();
+		let mut results = Vec::new();
+		run(&tests, Duration::from_millis(50), 1, |_, result| {
+			results.push(result.clone())
+		});
+		assert!(results[0].solved);
+		assert!(results[0].time_to_solution.is_some());
+	}
+}
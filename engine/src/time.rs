@@ -0,0 +1,35 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) use std::time::Instant;
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) use web::Instant;
+
+#[cfg(target_arch = "wasm32")]
+mod web {
+	use std::ops::Add;
+	use std::time::Duration;
+
+	/// A replacement for `std::time::Instant`, which panics in the browser.
+	/// The time is read from JavaScript, in milliseconds.
+	#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+	pub struct Instant(f64);
+
+	impl Instant {
+		pub fn now() -> Self {
+			Self(js_sys::Date::now())
+		}
+
+		pub fn elapsed(&self) -> Duration {
+			let milliseconds = (Self::now().0 - self.0).max(0.0);
+			Duration::from_secs_f64(milliseconds / 1000.0)
+		}
+	}
+
+	impl Add<Duration> for Instant {
+		type Output = Self;
+
+		fn add(self, rhs: Duration) -> Self {
+			Self(self.0 + rhs.as_secs_f64() * 1000.0)
+		}
+	}
+}
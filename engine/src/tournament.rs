@@ -0,0 +1,1269 @@
+use std::fmt::{self, Display};
+use std::num::{NonZeroU8, NonZeroUsize};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use model::{CheckersBitBoard, Move, PieceColor, PossibleMoves};
+use parking_lot::Mutex;
+use pdn::GameResult;
+use rand::rngs::StdRng;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::{Rng, SeedableRng};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+use crate::ballot::{eleven_man_ballots, parse_eleven_man_ballot};
+use crate::external::{ExternalEngine, ExternalEngineError, Protocol};
+use crate::frontend::SilentFrontend;
+use crate::notation::{complete_line, fen, find_move, parse_fen, write_movetext, FullMove};
+use crate::options::{DEFAULT_HASH_SIZE, MEGABYTE};
+use crate::{
+	ActualLimit, Clock, Engine, EvaluationSettings, OptionError, SearchLimit, ENGINE_NAME,
+};
+
+/// The z-score of a 95% confidence interval
+const CONFIDENCE_Z: f64 = 1.96;
+
+/// One engine configuration in a tournament
+#[derive(Debug, Clone, PartialEq)]
+pub struct Participant {
+	pub name: String,
+	/// Engine options, which are set with [`Engine::set_option`] before the
+	/// first game
+	pub options: Vec<(String, String)>,
+	pub depth: Option<NonZeroU8>,
+	pub nodes: Option<NonZeroUsize>,
+	/// The time to spend on each move
+	pub move_time: Duration,
+	/// The command line of an external engine, or its address for DXP. If
+	/// this is `None`, Ampere plays.
+	pub command: Option<String>,
+	pub protocol: Protocol,
+}
+
+#[derive(Debug, Error)]
+pub enum TournamentError {
+	#[error(
+		"{0:?} is not a valid participant. Expected key=value pairs, such as \"name=fast,depth=8\""
+	)]
+	InvalidParticipant(String),
+	#[error("{name}: {error}")]
+	OptionError { name: String, error: OptionError },
+	#[error("{name}: {error}")]
+	ExternalEngineError {
+		name: String,
+		error: ExternalEngineError,
+	},
+	#[error(
+		"Line {line}: the opening is not a valid FEN or list of moves, or the game is already over"
+	)]
+	InvalidOpening { line: usize },
+	#[error("A tournament needs at least two participants")]
+	TooFewParticipants,
+	#[error("The checkpoint is not valid: {0}")]
+	InvalidCheckpoint(String),
+	#[error("The checkpoint is from a different tournament")]
+	CheckpointMismatch,
+}
+
+impl Participant {
+	pub fn new(name: impl Into<String>) -> Self {
+		Self {
+			name: name.into(),
+			options: Vec::new(),
+			depth: None,
+			nodes: None,
+			move_time: Duration::from_millis(100),
+			command: None,
+			protocol: Protocol::default(),
+		}
+	}
+
+	/// Reads a participant from comma-separated `key=value` pairs, such as
+	/// `name=weak,time=0.5,Skill Level=10`. The keys `name`, `depth`, `nodes`
+	/// and `time` (in seconds) are the search limits, `cmd` and `protocol`
+	/// start an external engine, and every other key is an engine option.
+	pub fn parse(spec: &str) -> Result<Self, TournamentError> {
+		let invalid = || TournamentError::InvalidParticipant(spec.to_string());
+		let mut participant = Self::new("");
+		for pair in spec.split(',') {
+			let (key, value) = pair.split_once('=').ok_or_else(invalid)?;
+			let (key, value) = (key.trim(), value.trim());
+			match key.to_ascii_lowercase().as_str() {
+				"name" => participant.name = value.to_string(),
+				"depth" => participant.depth = Some(value.parse().map_err(|_| invalid())?),
+				"nodes" => participant.nodes = Some(value.parse().map_err(|_| invalid())?),
+				"time" => {
+					let seconds = value.parse().map_err(|_| invalid())?;
+					participant.move_time =
+						Duration::try_from_secs_f64(seconds).map_err(|_| invalid())?;
+				}
+				"cmd" => participant.command = Some(value.to_string()),
+				"protocol" => participant.protocol = value.parse().map_err(|_| invalid())?,
+				_ => participant
+					.options
+					.push((key.to_string(), value.to_string())),
+			}
+		}
+
+		if participant.name.is_empty() {
+			participant.name = spec.to_string();
+		}
+
+		Ok(participant)
+	}
+
+	fn limit(&self) -> ActualLimit {
+		ActualLimit {
+			nodes: self.nodes,
+			depth: self.depth,
+			time: Some(self.move_time),
+		}
+	}
+
+	fn evaluation_settings(&self) -> EvaluationSettings {
+		let search_until = if self.depth.is_some() || self.nodes.is_some() {
+			SearchLimit::Limited(self.limit())
+		} else {
+			SearchLimit::Auto
+		};
+
+		EvaluationSettings {
+			clock: Clock::TimePerMove(self.move_time),
+			search_until,
+			..EvaluationSettings::default()
+		}
+	}
+
+	/// Starts the participant's engine, and sets its options
+	fn player(&self) -> Result<Player, TournamentError> {
+		let Some(command) = &self.command else {
+			let engine = Engine::new(
+				DEFAULT_HASH_SIZE as usize * MEGABYTE,
+				Arc::new(SilentFrontend),
+			);
+			for (name, value) in &self.options {
+				engine
+					.set_option(name, value)
+					.map_err(|error| TournamentError::OptionError {
+						name: self.name.clone(),
+						error,
+					})?;
+			}
+
+			return Ok(Player::Internal {
+				engine: Box::new(engine),
+				settings: self.evaluation_settings(),
+			});
+		};
+
+		let external_error = |error| TournamentError::ExternalEngineError {
+			name: self.name.clone(),
+			error,
+		};
+		let mut engine = ExternalEngine::start(self.protocol, command).map_err(external_error)?;
+		for (name, value) in &self.options {
+			engine.set_option(name, value).map_err(external_error)?;
+		}
+
+		Ok(Player::External {
+			engine: Mutex::new(engine),
+			limit: self.limit(),
+		})
+	}
+}
+
+/// A participant's engine, which is started before the first game
+enum Player {
+	Internal {
+		engine: Box<Engine>,
+		settings: EvaluationSettings,
+	},
+	External {
+		engine: Mutex<ExternalEngine>,
+		limit: ActualLimit,
+	},
+}
+
+impl Player {
+	fn new_game(
+		&self,
+		start: CheckersBitBoard,
+		color: PieceColor,
+	) -> Result<(), ExternalEngineError> {
+		match self {
+			Self::Internal { engine, .. } => {
+				engine.set_position(start);
+				Ok(())
+			}
+			Self::External { engine, limit } => engine.lock().new_game(start, color, limit),
+		}
+	}
+
+	/// Finds the player's move. Returns `None` if the engine resigned.
+	fn best_move(
+		&self,
+		position: CheckersBitBoard,
+	) -> Result<Option<FullMove>, ExternalEngineError> {
+		match self {
+			Self::Internal { engine, settings } => {
+				engine.set_position(position);
+				let (_, best_move) = engine.evaluate(None, settings.clone());
+				let Some(best_move) = best_move else {
+					return Ok(None);
+				};
+
+				Ok(engine.best_full_move(position, best_move))
+			}
+			Self::External { engine, limit } => engine.lock().best_move(position, limit).map(Some),
+		}
+	}
+
+	fn opponent_moved(
+		&self,
+		full_move: &FullMove,
+		time: Duration,
+	) -> Result<(), ExternalEngineError> {
+		match self {
+			Self::Internal { .. } => Ok(()),
+			Self::External { engine, .. } => engine.lock().opponent_moved(full_move, time),
+		}
+	}
+
+	fn finish_game(&self, result: GameResult) {
+		match self {
+			Self::Internal { engine, .. } => {
+				if let Err(error) = engine.finish_game(result) {
+					tracing::warn!(%error, "failed to update the learning file");
+				}
+			}
+			Self::External { engine, .. } => {
+				if let Err(error) = engine.lock().finish_game(result) {
+					tracing::warn!(%error, "an engine failed to finish the game");
+				}
+			}
+		}
+	}
+}
+
+/// How the games in a tournament are played
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TournamentSettings {
+	/// The number of rounds. In each round, every pair of participants plays
+	/// two games from the same opening, with the colors reversed.
+	pub rounds: usize,
+	/// The number of random moves to play at the start of each opening
+	pub random_opening_moves: u8,
+	/// The number of moves after which the game is declared a draw
+	pub max_moves: u16,
+	/// The positions each pairing starts from. Every opening is played twice
+	/// in each round, with the colors reversed. If this is empty, a random
+	/// opening is used instead.
+	pub openings: Vec<CheckersBitBoard>,
+	/// Random openings are chosen from the eleven-man ballots, instead of
+	/// playing random moves
+	pub eleven_man: bool,
+	/// The seed for the random openings
+	pub seed: u64,
+	/// The games which were played before the tournament was interrupted.
+	/// They're counted in the standings, but aren't played again.
+	pub completed: Vec<(usize, usize, GameResult)>,
+}
+
+impl Default for TournamentSettings {
+	fn default() -> Self {
+		Self {
+			rounds: 1,
+			random_opening_moves: 4,
+			max_moves: 300,
+			openings: Vec::new(),
+			eleven_man: false,
+			seed: rand::random(),
+			completed: Vec::new(),
+		}
+	}
+}
+
+/// A finished game. The players are indices into the list of participants.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameRecord {
+	pub dark: usize,
+	pub light: usize,
+	pub start: CheckersBitBoard,
+	pub moves: Vec<Move>,
+	pub result: GameResult,
+}
+
+/// Writes a result as it appears in PDN. The first number is black's score,
+/// since black moves first.
+pub const fn result_string(result: GameResult) -> &'static str {
+	match result {
+		GameResult::BlackWin => "1-0",
+		GameResult::WhiteWin => "0-1",
+		GameResult::Draw => "1/2-1/2",
+		GameResult::Unfinished => "*",
+	}
+}
+
+impl GameRecord {
+	/// Writes the game in PDN, with the given names for the players
+	pub fn to_pdn(&self, black: &str, white: &str) -> String {
+		let event = format!("{ENGINE_NAME} tournament");
+		write_pdn(&event, black, white, self.start, &self.moves, self.result)
+	}
+}
+
+/// Writes a finished game in PDN. The `FEN` tag is only written if the game
+/// didn't start from the starting position.
+pub fn write_pdn(
+	event: &str,
+	black: &str,
+	white: &str,
+	start: CheckersBitBoard,
+	moves: &[Move],
+	result: GameResult,
+) -> String {
+	let result = result_string(result);
+	let mut pdn = format!(
+		"[Event \"{event}\"]\n[Black \"{black}\"]\n[White \"{white}\"]\n[Result \"{result}\"]\n"
+	);
+	if start != CheckersBitBoard::starting_position() {
+		pdn.push_str(&format!("[FEN \"{}\"]\n", fen(start)));
+	}
+	pdn.push('\n');
+
+	write_movetext(&mut pdn, start, &complete_line(start, moves), &[], result);
+
+	pdn
+}
+
+/// The players and result of a game in a PDN file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdnResult {
+	pub black: String,
+	pub white: String,
+	pub result: GameResult,
+}
+
+/// Reads a PDN result, such as `1-0`, where the first number is black's
+/// score. Returns `None` for unfinished games, written as `*`.
+pub(crate) fn parse_result(result: &str) -> Option<GameResult> {
+	result
+		.parse()
+		.ok()
+		.filter(|&result| result != GameResult::Unfinished)
+}
+
+/// Reads the players and result of every game in a PDN file, using the
+/// `Black`, `White` and `Result` tags. If there isn't a `Result` tag, then
+/// the result at the end of the moves is used. Unfinished games are skipped.
+pub fn parse_results(pdn: &str) -> Vec<PdnResult> {
+	let mut results = Vec::new();
+	let mut tags: Vec<(String, String)> = Vec::new();
+	let mut last_token = None;
+	let mut in_moves = false;
+
+	let mut finish_game = |tags: &mut Vec<(String, String)>, last_token: Option<String>| {
+		let tag = |key: &str| {
+			tags.iter()
+				.find(|(name, _)| name.eq_ignore_ascii_case(key))
+				.map(|(_, value)| value.clone())
+		};
+		let result = tag("Result")
+			.and_then(|result| parse_result(&result))
+			.or_else(|| last_token.as_deref().and_then(parse_result));
+
+		if let Some(result) = result {
+			results.push(PdnResult {
+				black: tag("Black").unwrap_or_else(|| "?".to_string()),
+				white: tag("White").unwrap_or_else(|| "?".to_string()),
+				result,
+			});
+		}
+		tags.clear();
+	};
+
+	for line in pdn.lines() {
+		let line = line.trim();
+		if let Some(tag) = line.strip_prefix('[').and_then(|tag| tag.strip_suffix(']')) {
+			// a tag after the moves is the start of the next game
+			if in_moves {
+				finish_game(&mut tags, last_token.take());
+				in_moves = false;
+			}
+
+			if let Some((key, value)) = tag.split_once(char::is_whitespace) {
+				tags.push((key.to_string(), value.trim().trim_matches('"').to_string()));
+			}
+		} else if !line.is_empty() {
+			in_moves = true;
+			if let Some(token) = line.split_whitespace().last() {
+				last_token = Some(token.to_string());
+			}
+		}
+	}
+
+	if in_moves || !tags.is_empty() {
+		finish_game(&mut tags, last_token);
+	}
+
+	results
+}
+
+/// The results of one player against another
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Score {
+	pub wins: usize,
+	pub draws: usize,
+	pub losses: usize,
+}
+
+/// A difference in playing strength, with a 95% confidence interval of
+/// `elo - error` to `elo + error`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EloEstimate {
+	pub elo: f64,
+	pub error: f64,
+}
+
+/// The Elo difference which is expected to give this fraction of the points.
+/// This is infinite if every game was won or lost.
+fn elo_from_score(score: f64) -> f64 {
+	400.0 * (score / (1.0 - score)).log10()
+}
+
+/// The error function, with an error of less than 1.5e-7. This is formula
+/// 7.1.26 from Abramowitz and Stegun.
+fn erf(x: f64) -> f64 {
+	const A: [f64; 5] = [
+		0.254829592,
+		-0.284496736,
+		1.421413741,
+		-1.453152027,
+		1.061405429,
+	];
+	const P: f64 = 0.3275911;
+
+	let t = 1.0 / (1.0 + P * x.abs());
+	let polynomial = A.iter().rev().fold(0.0, |sum, a| sum * t + a) * t;
+	(1.0 - polynomial * (-x * x).exp()).copysign(x)
+}
+
+/// The fraction of the points which is expected with this Elo difference
+fn score_from_elo(elo: f64) -> f64 {
+	1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+impl Score {
+	pub const fn games(&self) -> usize {
+		self.wins + self.draws + self.losses
+	}
+
+	/// The points scored, where a draw is half of a point
+	pub fn points(&self) -> f64 {
+		self.wins as f64 + self.draws as f64 / 2.0
+	}
+
+	/// The score from the opponent's perspective
+	pub const fn reversed(self) -> Self {
+		Self {
+			wins: self.losses,
+			draws: self.draws,
+			losses: self.wins,
+		}
+	}
+
+	/// The fraction of the games which were drawn
+	pub fn draw_rate(&self) -> f64 {
+		if self.games() == 0 {
+			return 0.0;
+		}
+
+		self.draws as f64 / self.games() as f64
+	}
+
+	/// The likelihood of superiority, which is the chance that the player is
+	/// stronger than its opponents. Draws don't affect it.
+	pub fn los(&self) -> f64 {
+		let decisive = (self.wins + self.losses) as f64;
+		if decisive == 0.0 {
+			return 0.5;
+		}
+
+		let difference = self.wins as f64 - self.losses as f64;
+		0.5 * (1.0 + erf(difference / (2.0 * decisive).sqrt()))
+	}
+
+	/// Estimates how much stronger the player is than its opponents. Returns
+	/// `None` if no games have been played.
+	pub fn elo(&self) -> Option<EloEstimate> {
+		let games = self.games() as f64;
+		if self.games() == 0 {
+			return None;
+		}
+
+		let score = self.points() / games;
+		let variance = (self.wins as f64 * (1.0 - score).powi(2)
+			+ self.draws as f64 * (0.5 - score).powi(2)
+			+ self.losses as f64 * score.powi(2))
+			/ games;
+		let margin = CONFIDENCE_Z * (variance / games).sqrt();
+		let low = elo_from_score((score - margin).max(0.0));
+		let high = elo_from_score((score + margin).min(1.0));
+
+		let elo = elo_from_score(score);
+
+		// there's no bound on the error if every game was won or lost
+		Some(EloEstimate {
+			elo,
+			error: if elo.is_finite() {
+				(high - low) / 2.0
+			} else {
+				f64::INFINITY
+			},
+		})
+	}
+}
+
+impl std::ops::AddAssign for Score {
+	fn add_assign(&mut self, other: Self) {
+		self.wins += other.wins;
+		self.draws += other.draws;
+		self.losses += other.losses;
+	}
+}
+
+/// The hypotheses of a sequential probability ratio test
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hypothesis {
+	/// The candidate is at most `elo0` stronger than the baseline
+	H0,
+	/// The candidate is at least `elo1` stronger than the baseline
+	H1,
+}
+
+/// The settings of a sequential probability ratio test. The test stops once
+/// it's confident enough in one of the hypotheses.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SprtSettings {
+	pub elo0: f64,
+	pub elo1: f64,
+	/// The chance of accepting H1 when H0 is true
+	pub alpha: f64,
+	/// The chance of accepting H0 when H1 is true
+	pub beta: f64,
+}
+
+impl Default for SprtSettings {
+	fn default() -> Self {
+		Self {
+			elo0: 0.0,
+			elo1: 5.0,
+			alpha: 0.05,
+			beta: 0.05,
+		}
+	}
+}
+
+impl SprtSettings {
+	/// The log-likelihood ratios at which H0 and H1 are accepted
+	pub fn bounds(&self) -> (f64, f64) {
+		(
+			(self.beta / (1.0 - self.alpha)).ln(),
+			((1.0 - self.beta) / self.alpha).ln(),
+		)
+	}
+
+	/// The log-likelihood ratio of H1 to H0, given the candidate's score.
+	/// Both hypotheses are assumed to have the draw rate of the games so far.
+	pub fn llr(&self, score: &Score) -> f64 {
+		if score.games() == 0 {
+			return 0.0;
+		}
+
+		let (score0, score1) = (score_from_elo(self.elo0), score_from_elo(self.elo1));
+		// with too many draws, a hypothesis would need a negative win or loss
+		// rate, so the draw rate is kept just below that
+		let max_draw_rate = 2.0 * score0.min(1.0 - score0).min(score1).min(1.0 - score1);
+		let draw_rate = (score.draws as f64 / score.games() as f64).min(0.999 * max_draw_rate);
+
+		let (wins0, wins1) = (score0 - draw_rate / 2.0, score1 - draw_rate / 2.0);
+		let (losses0, losses1) = (1.0 - draw_rate - wins0, 1.0 - draw_rate - wins1);
+		score.wins as f64 * (wins1 / wins0).ln() + score.losses as f64 * (losses1 / losses0).ln()
+	}
+
+	/// The hypothesis to accept, or `None` if more games are needed
+	pub fn decision(&self, score: &Score) -> Option<Hypothesis> {
+		let llr = self.llr(score);
+		let (lower, upper) = self.bounds();
+		if llr <= lower {
+			Some(Hypothesis::H0)
+		} else if llr >= upper {
+			Some(Hypothesis::H1)
+		} else {
+			None
+		}
+	}
+}
+
+/// The results of a tournament so far
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Standings {
+	pub names: Vec<String>,
+	/// `scores[a][b]` is the score of `a` in its games against `b`
+	pub scores: Vec<Vec<Score>>,
+}
+
+impl Standings {
+	pub fn new(names: Vec<String>) -> Self {
+		let scores = vec![vec![Score::default(); names.len()]; names.len()];
+		Self { names, scores }
+	}
+
+	pub fn record(&mut self, game: &GameRecord) {
+		self.add_result(game.dark, game.light, game.result);
+	}
+
+	/// Adds a game where `dark` played `light`. Unfinished games aren't
+	/// counted.
+	pub fn add_result(&mut self, dark: usize, light: usize, result: GameResult) {
+		let score = match result {
+			GameResult::BlackWin => Score {
+				wins: 1,
+				..Score::default()
+			},
+			GameResult::WhiteWin => Score {
+				losses: 1,
+				..Score::default()
+			},
+			GameResult::Draw => Score {
+				draws: 1,
+				..Score::default()
+			},
+			GameResult::Unfinished => return,
+		};
+
+		self.scores[dark][light] += score;
+		self.scores[light][dark] += score.reversed();
+	}
+
+	/// The score of a player against every opponent
+	pub fn total(&self, player: usize) -> Score {
+		let mut total = Score::default();
+		for &score in &self.scores[player] {
+			total += score;
+		}
+
+		total
+	}
+
+	/// The players, from the most points to the fewest
+	pub fn ranking(&self) -> Vec<usize> {
+		let mut ranking: Vec<usize> = (0..self.names.len()).collect();
+		ranking.sort_by(|&a, &b| self.total(b).points().total_cmp(&self.total(a).points()));
+		ranking
+	}
+}
+
+/// A crosstable, with each player's Elo against the field. The columns are
+/// in the same order as the rows.
+impl Display for Standings {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let ranking = self.ranking();
+		let width = self.names.iter().map(String::len).max().unwrap_or(0).max(4);
+
+		write!(
+			f,
+			"{:>2}  {:width$}  {:>7}  {:>6}  {:>11}",
+			"#", "name", "elo", "+/-", "points"
+		)?;
+		for rank in 1..=ranking.len() {
+			write!(f, "  {rank:>7}")?;
+		}
+
+		for (rank, &player) in ranking.iter().enumerate() {
+			let total = self.total(player);
+			let (elo, error) = total
+				.elo()
+				.map_or((0.0, 0.0), |estimate| (estimate.elo, estimate.error));
+			let points = format!("{}/{}", total.points(), total.games());
+
+			writeln!(f)?;
+			write!(
+				f,
+				"{:>2}  {:width$}  {elo:>+7.0}  {error:>6.0}  {points:>11}",
+				rank + 1,
+				self.names[player]
+			)?;
+			for &opponent in &ranking {
+				let score = self.scores[player][opponent];
+				if opponent == player {
+					write!(f, "  {:>7}", "-")?;
+				} else {
+					let cell = format!("{}/{}", score.points(), score.games());
+					write!(f, "  {cell:>7}")?;
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// The progress of a tournament, which can be saved after every game, and
+/// used to resume the tournament if it's interrupted. The standings are
+/// rebuilt from the results, and the random openings are chosen again from
+/// the same seed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+	pub seed: u64,
+	pub names: Vec<String>,
+	/// The players and result of every finished game, in the order they were
+	/// played
+	pub games: Vec<(usize, usize, GameResult)>,
+}
+
+impl Checkpoint {
+	pub fn new(seed: u64, names: Vec<String>) -> Self {
+		Self {
+			seed,
+			names,
+			games: Vec::new(),
+		}
+	}
+
+	pub fn record(&mut self, game: &GameRecord) {
+		self.games.push((game.dark, game.light, game.result));
+	}
+
+	pub fn standings(&self) -> Standings {
+		let mut standings = Standings::new(self.names.clone());
+		for &(dark, light, result) in &self.games {
+			standings.add_result(dark, light, result);
+		}
+
+		standings
+	}
+
+	/// The checkpoint as JSON. Each game is written as `[dark, light, result]`,
+	/// where the result is written as in PDN.
+	pub fn to_json(&self) -> String {
+		let games: Vec<Value> = self
+			.games
+			.iter()
+			.map(|&(dark, light, result)| json!([dark, light, result_string(result)]))
+			.collect();
+		let standings = self.standings();
+		let points: Vec<f64> = (0..self.names.len())
+			.map(|player| standings.total(player).points())
+			.collect();
+
+		json!({
+			"seed": self.seed,
+			"names": self.names,
+			"points": points,
+			"games": games,
+		})
+		.to_string()
+	}
+
+	pub fn parse(text: &str) -> Result<Self, TournamentError> {
+		let invalid = |message: &str| TournamentError::InvalidCheckpoint(message.to_string());
+		let value: Value =
+			serde_json::from_str(text).map_err(|error| invalid(&error.to_string()))?;
+
+		let seed = value["seed"]
+			.as_u64()
+			.ok_or_else(|| invalid("missing seed"))?;
+		let names = value["names"]
+			.as_array()
+			.ok_or_else(|| invalid("missing names"))?
+			.iter()
+			.map(|name| name.as_str().map(str::to_string))
+			.collect::<Option<Vec<_>>>()
+			.ok_or_else(|| invalid("names must be strings"))?;
+
+		let player = |value: &Value| {
+			value
+				.as_u64()
+				.map(|player| player as usize)
+				.filter(|&player| player < names.len())
+		};
+		let games = value["games"]
+			.as_array()
+			.ok_or_else(|| invalid("missing games"))?
+			.iter()
+			.map(|game| {
+				let dark = player(&game[0])?;
+				let light = player(&game[1])?;
+				let result = game[2].as_str().and_then(parse_result)?;
+				Some((dark, light, result))
+			})
+			.collect::<Option<Vec<_>>>()
+			.ok_or_else(|| invalid("games must be written as [dark, light, result]"))?;
+
+		Ok(Self { seed, names, games })
+	}
+}
+
+/// Reads an opening suite. Each line is either a FEN, moves from the
+/// starting position, such as `11-15 23-19 8-11`, or an eleven-man ballot,
+/// such as `-5 -28 11-15 23-19`. Empty lines and lines
+/// starting with `#` are skipped.
+pub fn parse_openings(text: &str) -> Result<Vec<CheckersBitBoard>, TournamentError> {
+	let mut openings = Vec::new();
+	for (index, line) in text.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue;
+		}
+
+		let invalid = || TournamentError::InvalidOpening { line: index + 1 };
+		let opening = if line.contains(':') {
+			parse_fen(line).ok_or_else(invalid)?
+		} else if line.starts_with('-') {
+			parse_eleven_man_ballot(line)
+				.map(|ballot| ballot.position())
+				.ok_or_else(invalid)?
+		} else {
+			line.split_whitespace()
+				.try_fold(CheckersBitBoard::starting_position(), |board, notation| {
+					find_move(board, notation).map(|full_move| full_move.result)
+				})
+				.ok_or_else(invalid)?
+		};
+
+		if PossibleMoves::moves(opening).is_empty() {
+			return Err(invalid());
+		}
+		openings.push(opening);
+	}
+
+	Ok(openings)
+}
+
+/// Plays random moves from the starting position. The opening stops early if
+/// the game would end.
+fn random_opening(rng: &mut impl Rng, moves: u8) -> CheckersBitBoard {
+	let mut board = CheckersBitBoard::starting_position();
+	for _ in 0..moves {
+		let possible_moves = PossibleMoves::moves(board);
+		let Some(next_move) = possible_moves.into_iter().choose(rng) else {
+			break;
+		};
+
+		// safety: the move was generated from this position
+		let next = unsafe { next_move.apply_to(board) };
+		if PossibleMoves::moves(next).is_empty() {
+			break;
+		}
+		board = next;
+	}
+
+	board
+}
+
+/// Plays one game between two engines. If an engine fails, or plays an
+/// illegal move, it loses the game.
+fn play_game(
+	dark: &Player,
+	light: &Player,
+	start: CheckersBitBoard,
+	max_moves: u16,
+) -> (Vec<Move>, GameResult) {
+	let mut board = start;
+	let mut moves = Vec::new();
+	let result = 'game: {
+		for (player, color) in [(dark, PieceColor::Dark), (light, PieceColor::Light)] {
+			if let Err(error) = player.new_game(start, color) {
+				tracing::warn!(%error, "an engine failed to start a game");
+				break 'game GameResult::loss_for(color);
+			}
+		}
+
+		loop {
+			if moves.len() >= max_moves as usize {
+				break GameResult::Draw;
+			}
+
+			if PossibleMoves::moves(board).is_empty() {
+				break GameResult::loss_for(board.turn());
+			}
+
+			let (player, opponent) = match board.turn() {
+				PieceColor::Dark => (dark, light),
+				PieceColor::Light => (light, dark),
+			};
+
+			let started = Instant::now();
+			let full_move = match player.best_move(board) {
+				Ok(Some(full_move)) => full_move,
+				Ok(None) => break GameResult::loss_for(board.turn()),
+				Err(error) => {
+					tracing::warn!(%error, "an engine failed to move");
+					break GameResult::loss_for(board.turn());
+				}
+			};
+
+			if let Err(error) = opponent.opponent_moved(&full_move, started.elapsed()) {
+				tracing::warn!(%error, "an engine failed to receive a move");
+				break GameResult::loss_for(board.turn().flip());
+			}
+
+			board = full_move.result;
+			moves.extend(full_move.hops);
+		}
+	};
+
+	for player in [dark, light] {
+		player.finish_game(result);
+	}
+
+	(moves, result)
+}
+
+/// Plays every pairing, calling `on_game` after each game with the standings
+/// so far. The tournament ends early if `on_game` breaks. Every participant
+/// has its own engine. Games from `settings.completed` are skipped, and must
+/// be in the same order as they would be played.
+fn play_pairings(
+	participants: &[Participant],
+	pairings: &[(usize, usize)],
+	settings: &TournamentSettings,
+	mut on_game: impl FnMut(&GameRecord, &Standings) -> ControlFlow<()>,
+) -> Result<Standings, TournamentError> {
+	if participants.len() < 2 {
+		return Err(TournamentError::TooFewParticipants);
+	}
+
+	let players = participants
+		.iter()
+		.map(Participant::player)
+		.collect::<Result<Vec<_>, _>>()?;
+	let mut standings = Standings::new(participants.iter().map(|p| p.name.clone()).collect());
+	let mut rng = StdRng::seed_from_u64(settings.seed);
+	let mut completed = settings.completed.iter();
+	let ballots = if settings.eleven_man {
+		eleven_man_ballots()
+	} else {
+		Vec::new()
+	};
+
+	'tournament: for _ in 0..settings.rounds {
+		for &(first, second) in pairings {
+			// skipped games still choose their opening, so the rest match
+			let openings = if !settings.openings.is_empty() {
+				settings.openings.clone()
+			} else if let Some(ballot) = ballots.choose(&mut rng) {
+				vec![ballot.position()]
+			} else {
+				vec![random_opening(&mut rng, settings.random_opening_moves)]
+			};
+
+			for start in openings {
+				for (dark, light) in [(first, second), (second, first)] {
+					if let Some(&(played_dark, played_light, result)) = completed.next() {
+						if (played_dark, played_light) != (dark, light) {
+							return Err(TournamentError::CheckpointMismatch);
+						}
+						standings.add_result(dark, light, result);
+						continue;
+					}
+
+					let (moves, result) =
+						play_game(&players[dark], &players[light], start, settings.max_moves);
+					let game = GameRecord {
+						dark,
+						light,
+						start,
+						moves,
+						result,
+					};
+					standings.record(&game);
+					if on_game(&game, &standings).is_break() {
+						break 'tournament;
+					}
+				}
+			}
+		}
+	}
+
+	Ok(standings)
+}
+
+/// Plays a round-robin tournament, where every participant plays every other
+/// participant
+pub fn round_robin(
+	participants: &[Participant],
+	settings: &TournamentSettings,
+	mut on_game: impl FnMut(&GameRecord, &Standings),
+) -> Result<Standings, TournamentError> {
+	let pairings: Vec<_> = (0..participants.len())
+		.flat_map(|first| (first + 1..participants.len()).map(move |second| (first, second)))
+		.collect();
+	play_pairings(participants, &pairings, settings, |game, standings| {
+		on_game(game, standings);
+		ControlFlow::Continue(())
+	})
+}
+
+/// Plays a gauntlet, where the candidate plays each of the baselines, and the
+/// baselines don't play each other. In the standings, the candidate is the
+/// first player.
+pub fn gauntlet(
+	candidate: &Participant,
+	baselines: &[Participant],
+	settings: &TournamentSettings,
+	mut on_game: impl FnMut(&GameRecord, &Standings),
+) -> Result<Standings, TournamentError> {
+	let participants: Vec<_> = std::iter::once(candidate)
+		.chain(baselines)
+		.cloned()
+		.collect();
+	let pairings: Vec<_> = (1..participants.len())
+		.map(|baseline| (0, baseline))
+		.collect();
+	play_pairings(&participants, &pairings, settings, |game, standings| {
+		on_game(game, standings);
+		ControlFlow::Continue(())
+	})
+}
+
+/// Plays a match between the candidate and the baseline until the SPRT
+/// accepts a hypothesis, or `settings.rounds` rounds have been played. In the
+/// standings, the candidate is the first player.
+pub fn sprt(
+	candidate: &Participant,
+	baseline: &Participant,
+	settings: &TournamentSettings,
+	sprt: &SprtSettings,
+	mut on_game: impl FnMut(&GameRecord, &Standings),
+) -> Result<(Standings, Option<Hypothesis>), TournamentError> {
+	let participants = [candidate.clone(), baseline.clone()];
+	let resumed = Checkpoint {
+		seed: settings.seed,
+		names: vec![candidate.name.clone(), baseline.name.clone()],
+		games: settings.completed.clone(),
+	}
+	.standings();
+	if let Some(hypothesis) = sprt.decision(&resumed.total(0)) {
+		return Ok((resumed, Some(hypothesis)));
+	}
+
+	let mut hypothesis = None;
+	let standings = play_pairings(&participants, &[(0, 1)], settings, |game, standings| {
+		on_game(game, standings);
+		hypothesis = sprt.decision(&standings.total(0));
+		match hypothesis {
+			Some(_) => ControlFlow::Break(()),
+			None => ControlFlow::Continue(()),
+		}
+	})?;
+
+	Ok((standings, hypothesis))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_participants() {
+		let participant = Participant::parse("name=weak, depth=4, Skill Level=3").unwrap();
+		assert_eq!(participant.name, "weak");
+		assert_eq!(participant.depth, NonZeroU8::new(4));
+		assert_eq!(
+			participant.options,
+			vec![("Skill Level".to_string(), "3".to_string())]
+		);
+		assert!(Participant::parse("name=weak,depth").is_err());
+	}
+
+	#[test]
+	fn elo_estimates() {
+		let even = Score {
+			wins: 10,
+			draws: 10,
+			losses: 10,
+		};
+		let estimate = even.elo().unwrap();
+		assert_eq!(estimate.elo, 0.0);
+		assert!(estimate.error > 0.0);
+
+		let better = Score {
+			wins: 3,
+			draws: 0,
+			losses: 1,
+		};
+		assert!((better.elo().unwrap().elo - 190.8).abs() < 0.1);
+		assert_eq!(Score::default().elo(), None);
+
+		let lost = Score {
+			losses: 2,
+			..Score::default()
+		};
+		assert_eq!(lost.elo().unwrap().error, f64::INFINITY);
+	}
+
+	#[test]
+	fn plays_every_pair() {
+		let participants: Vec<_> = ["a", "b", "c"]
+			.into_iter()
+			.map(|name| Participant::parse(&format!("name={name},depth=1")).unwrap())
+			.collect();
+		let settings = TournamentSettings {
+			max_moves: 20,
+			..TournamentSettings::default()
+		};
+
+		let mut games = 0;
+		let standings = round_robin(&participants, &settings, |_, _| games += 1).unwrap();
+		assert_eq!(games, 6);
+		for player in 0..3 {
+			assert_eq!(standings.total(player).games(), 4);
+		}
+	}
+
+	#[test]
+	fn resumes_from_checkpoints() {
+		let participants: Vec<_> = ["a", "b"]
+			.into_iter()
+			.map(|name| Participant::parse(&format!("name={name},depth=1")).unwrap())
+			.collect();
+		let mut settings = TournamentSettings {
+			rounds: 2,
+			max_moves: 20,
+			seed: 7,
+			..TournamentSettings::default()
+		};
+
+		let mut checkpoint = Checkpoint::new(7, vec!["a".to_string(), "b".to_string()]);
+		let mut starts = Vec::new();
+		round_robin(&participants, &settings, |game, _| {
+			checkpoint.record(game);
+			starts.push(game.start);
+		})
+		.unwrap();
+
+		checkpoint.games.truncate(2);
+		let checkpoint = Checkpoint::parse(&checkpoint.to_json()).unwrap();
+		assert_eq!(checkpoint.games.len(), 2);
+		settings.completed = checkpoint.games;
+
+		let mut resumed_starts = Vec::new();
+		let standings = round_robin(&participants, &settings, |game, _| {
+			resumed_starts.push(game.start)
+		})
+		.unwrap();
+		assert_eq!(resumed_starts, starts[2..]);
+		assert_eq!(standings.total(0).games(), 4);
+
+		settings.completed = vec![(0, 1, GameResult::Draw), (0, 1, GameResult::Draw)];
+		assert!(matches!(
+			round_robin(&participants, &settings, |_, _| {}),
+			Err(TournamentError::CheckpointMismatch)
+		));
+	}
+
+	#[test]
+	fn pdn_round_trip() {
+		let start = CheckersBitBoard::starting_position();
+		let line = crate::notation::parse_pdn("1. 11-15 23-19 2. 8-11 22-17").unwrap();
+		let game = GameRecord {
+			dark: 0,
+			light: 1,
+			start,
+			moves: line.moves.iter().flat_map(|m| m.hops.clone()).collect(),
+			result: GameResult::Draw,
+		};
+
+		let pdn = game.to_pdn("a", "b");
+		assert!(pdn.contains("1. 11-15 23-19 2. 8-11 22-17 1/2-1/2"));
+		assert_eq!(crate::notation::parse_pdn(&pdn).unwrap().moves, line.moves);
+
+		let two_games = format!("{pdn}\n[Black \"b\"]\n[White \"a\"]\n\n1. 11-15 0-1\n");
+		let results = parse_results(&two_games);
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].result, GameResult::Draw);
+		assert_eq!(results[1].black, "b");
+		assert_eq!(results[1].result, GameResult::WhiteWin);
+	}
+
+	#[test]
+	fn likelihood_of_superiority() {
+		let even = Score {
+			wins: 5,
+			draws: 10,
+			losses: 5,
+		};
+		assert!((even.los() - 0.5).abs() < 1e-6);
+		assert_eq!(even.draw_rate(), 0.5);
+
+		let better = Score {
+			wins: 10,
+			draws: 0,
+			losses: 4,
+		};
+		// 0.5 * (1 + erf(6 / sqrt(28)))
+		assert!((better.los() - 0.9456).abs() < 0.001);
+	}
+
+	#[test]
+	fn sprt_decisions() {
+		let sprt = SprtSettings::default();
+		let (lower, upper) = sprt.bounds();
+		assert!((lower + 2.944).abs() < 0.001);
+		assert!((upper - 2.944).abs() < 0.001);
+
+		let even = Score {
+			wins: 10,
+			draws: 10,
+			losses: 10,
+		};
+		assert_eq!(sprt.decision(&even), None);
+
+		let winning = Score {
+			wins: 600,
+			draws: 300,
+			losses: 100,
+		};
+		assert_eq!(sprt.decision(&winning), Some(Hypothesis::H1));
+		assert_eq!(sprt.decision(&winning.reversed()), Some(Hypothesis::H0));
+
+		let undefeated = Score {
+			wins: 20,
+			..Score::default()
+		};
+		let sprt = SprtSettings {
+			elo1: 100.0,
+			..SprtSettings::default()
+		};
+		assert_eq!(sprt.decision(&undefeated), Some(Hypothesis::H1));
+	}
+
+	#[test]
+	fn gauntlet_plays_each_opening() {
+		let openings = parse_openings("# openings\n11-15 23-19\n\nB:W21-32:B1-12\n").unwrap();
+		assert_eq!(openings.len(), 2);
+		assert!(parse_openings("11-15 11-15").is_err());
+
+		let participant = |name| Participant::parse(&format!("name={name},depth=1")).unwrap();
+		let settings = TournamentSettings {
+			max_moves: 10,
+			openings,
+			..TournamentSettings::default()
+		};
+
+		let mut starts = Vec::new();
+		let baselines = [participant("b"), participant("c")];
+		let standings = gauntlet(&participant("a"), &baselines, &settings, |game, _| {
+			starts.push(game.start)
+		})
+		.unwrap();
+		assert_eq!(standings.total(0).games(), 8);
+		assert_eq!(standings.scores[1][2].games(), 0);
+		assert_eq!(starts[0], starts[1]);
+		assert_ne!(starts[1], starts[2]);
+	}
+}
@@ -1,4 +1,4 @@
-use crate::{eval::Evaluation, CheckersBitBoard};
+use crate::{eval::Evaluation, options::MEGABYTE, CheckersBitBoard};
 use model::Move;
 use parking_lot::RwLock;
 use std::num::NonZeroU8;
@@ -113,6 +113,22 @@ impl<'a> TranspositionTableRef<'a> {
 		}
 	}
 
+	/// Gets the entry for a board at any depth, along with the depth it was
+	/// searched to. The depth table is preferred.
+	pub fn get_entry(self, board: CheckersBitBoard) -> Option<(Evaluation, Move, NonZeroU8)> {
+		let table_len = self.replace_table.as_ref().len();
+		let index = board.hash_code() as usize % table_len;
+
+		[self.depth_table, self.replace_table]
+			.into_iter()
+			.find_map(|table| {
+				let entry = unsafe { table.get_unchecked(index).read() };
+				entry
+					.filter(|entry| entry.board == board)
+					.map(|entry| (entry.eval, entry.best_move, entry.depth))
+			})
+	}
+
 	pub fn insert(
 		&self,
 		board: CheckersBitBoard,
@@ -147,10 +163,36 @@ impl<'a> TranspositionTableRef<'a> {
 	}
 }
 
+/// Reads the amount of memory that can be allocated without swapping, in
+/// bytes. Returns `None` on platforms where this isn't supported.
+fn available_memory() -> Option<usize> {
+	if !cfg!(target_os = "linux") {
+		return None;
+	}
+
+	let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+	let line = meminfo
+		.lines()
+		.find(|line| line.starts_with("MemAvailable:"))?;
+	let kilobytes: usize = line
+		.trim_start_matches("MemAvailable:")
+		.trim()
+		.trim_end_matches("kB")
+		.trim()
+		.parse()
+		.ok()?;
+
+	Some(kilobytes * 1024)
+}
+
 impl TranspositionTable {
+	/// The number of bytes used by each entry in the table
+	pub const ENTRY_SIZE: usize = std::mem::size_of::<RwLock<Option<TranspositionTableEntry>>>();
+
+	/// Creates a table that uses at most `table_size` bytes. The table always
+	/// has room for at least one entry, even if `table_size` is smaller.
 	pub fn new(table_size: usize) -> Self {
-		let table_size =
-			table_size / 2 / std::mem::size_of::<RwLock<Option<TranspositionTableEntry>>>();
+		let table_size = (table_size / 2 / Self::ENTRY_SIZE).max(1);
 		let mut replace_table = Box::new_uninit_slice(table_size);
 		let mut depth_table = Box::new_uninit_slice(table_size);
 
@@ -168,6 +210,38 @@ impl TranspositionTable {
 		}
 	}
 
+	/// Creates a table that uses at most the given number of megabytes
+	pub fn with_megabytes(megabytes: usize) -> Self {
+		Self::new(megabytes * MEGABYTE)
+	}
+
+	/// Creates a table that uses the given fraction of the system's available
+	/// memory. Returns `None` if the available memory couldn't be detected.
+	pub fn with_memory_fraction(fraction: f32) -> Option<Self> {
+		let available = available_memory()?;
+		Some(Self::new(
+			(available as f64 * fraction.clamp(0.0, 1.0) as f64) as usize,
+		))
+	}
+
+	/// The number of bytes used by the table
+	pub fn size(&self) -> usize {
+		(self.replace_table.len() + self.depth_table.len()) * Self::ENTRY_SIZE
+	}
+
+	/// Every entry in the table. The depth table's entries come first, so
+	/// inserting them in order into an empty table of the same size gives the
+	/// same table.
+	pub(crate) fn entries(
+		&self,
+	) -> impl Iterator<Item = (CheckersBitBoard, Evaluation, Move, NonZeroU8)> + '_ {
+		self.depth_table
+			.iter()
+			.chain(self.replace_table.iter())
+			.filter_map(|entry| *entry.read())
+			.map(|entry| (entry.board, entry.eval, entry.best_move, entry.depth))
+	}
+
 	pub fn get_ref(&self) -> TranspositionTableRef {
 		TranspositionTableRef {
 			replace_table: &self.replace_table,
@@ -175,3 +249,52 @@ impl TranspositionTable {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn sized_by_megabytes() {
+		let table = TranspositionTable::with_megabytes(1);
+		assert!(table.size() <= MEGABYTE);
+		assert!(table.size() > MEGABYTE - 2 * TranspositionTable::ENTRY_SIZE);
+	}
+
+	#[test]
+	fn empty_size_still_probes() {
+		let table = TranspositionTable::with_megabytes(0);
+		assert_eq!(table.size(), 2 * TranspositionTable::ENTRY_SIZE);
+
+		let board = CheckersBitBoard::starting_position();
+		let best_move = model::PossibleMoves::moves(board)
+			.into_iter()
+			.next()
+			.unwrap();
+		let depth = NonZeroU8::new(1).unwrap();
+		table
+			.get_ref()
+			.insert(board, Evaluation::DRAW, best_move, depth);
+		assert!(table.get_ref().get_entry(board).is_some());
+	}
+
+	#[test]
+	fn power_of_two_table_spreads_positions() {
+		// a megabyte table has a power of two entries, so the low bits of the
+		// hash pick the slot
+		let table = TranspositionTable::with_megabytes(1);
+		let board = CheckersBitBoard::starting_position();
+		let depth = NonZeroU8::new(1).unwrap();
+		let children: Vec<_> = model::PossibleMoves::moves(board)
+			.into_iter()
+			.map(|m| (m, unsafe { m.apply_to(board) }))
+			.collect();
+		for &(m, child) in &children {
+			table.get_ref().insert(child, Evaluation::DRAW, m, depth);
+		}
+
+		for &(_, child) in &children {
+			assert!(table.get_ref().get_entry(child).is_some());
+		}
+	}
+}
@@ -0,0 +1,227 @@
+use std::io::{self, Write};
+
+use model::{CheckersBitBoard, Move, PossibleMoves};
+
+use crate::eval::Evaluation;
+
+/// What happened in the transposition table at a node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableEvent {
+	/// The table had no usable entry, and nothing was stored
+	Miss,
+	/// The node's evaluation was taken from the table
+	Hit,
+	/// The node was searched, and its evaluation was stored in the table
+	Store,
+}
+
+/// A node that was visited by the search
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeNode {
+	/// The index of the parent node, or `None` if this is the root of an
+	/// iteration
+	pub parent: Option<usize>,
+	pub position: CheckersBitBoard,
+	/// The move that was played to reach this node from its parent
+	pub last_move: Option<Move>,
+	pub depth: u8,
+	pub alpha: Evaluation,
+	pub beta: Evaluation,
+	/// The evaluation returned by the node, or `None` if the search was
+	/// stopped before the node was finished
+	pub score: Option<Evaluation>,
+	pub best_move: Option<Move>,
+	pub table_event: TableEvent,
+}
+
+/// A record of the nodes explored by a search, up to a node budget
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchTree {
+	nodes: Vec<TreeNode>,
+	path: Vec<usize>,
+	budget: usize,
+	/// The number of nodes on the current path which weren't recorded
+	skipped: usize,
+	truncated: bool,
+}
+
+fn format_eval(eval: Evaluation) -> String {
+	if eval == Evaluation::NULL_MAX {
+		String::from("inf")
+	} else if eval == Evaluation::NULL_MIN {
+		String::from("-inf")
+	} else {
+		eval.to_string()
+	}
+}
+
+impl SearchTree {
+	pub fn new(budget: usize) -> Self {
+		Self {
+			nodes: Vec::new(),
+			path: Vec::new(),
+			budget,
+			skipped: 0,
+			truncated: false,
+		}
+	}
+
+	pub fn nodes(&self) -> &[TreeNode] {
+		&self.nodes
+	}
+
+	/// Returns `true` if nodes were left out because the budget ran out
+	pub fn is_truncated(&self) -> bool {
+		self.truncated
+	}
+
+	/// Records that the search has entered a node. Returns `None` if the node
+	/// budget has been used up.
+	pub(crate) fn enter(
+		&mut self,
+		position: CheckersBitBoard,
+		depth: u8,
+		alpha: Evaluation,
+		beta: Evaluation,
+	) -> Option<usize> {
+		if self.nodes.len() >= self.budget {
+			self.skipped += 1;
+			self.truncated = true;
+			return None;
+		}
+
+		let parent = self.path.last().copied();
+		let last_move = parent.and_then(|parent| {
+			let parent = self.nodes[parent].position;
+			PossibleMoves::moves(parent)
+				.into_iter()
+				.find(|m| unsafe { m.apply_to(parent) } == position)
+		});
+
+		let id = self.nodes.len();
+		self.nodes.push(TreeNode {
+			parent,
+			position,
+			last_move,
+			depth,
+			alpha,
+			beta,
+			score: None,
+			best_move: None,
+			table_event: TableEvent::Miss,
+		});
+		self.path.push(id);
+		Some(id)
+	}
+
+	/// Records what happened in the transposition table at the current node
+	pub(crate) fn table_event(&mut self, event: TableEvent) {
+		if self.skipped != 0 {
+			return;
+		}
+
+		if let Some(&id) = self.path.last() {
+			self.nodes[id].table_event = event;
+		}
+	}
+
+	/// Records that the search has left a node. The id is the one that was
+	/// returned when the node was entered.
+	pub(crate) fn exit(
+		&mut self,
+		id: Option<usize>,
+		result: (Evaluation, Option<Move>),
+		finished: bool,
+	) {
+		let Some(id) = id else {
+			self.skipped -= 1;
+			return;
+		};
+
+		if finished {
+			self.nodes[id].score = Some(result.0);
+			self.nodes[id].best_move = result.1;
+		}
+
+		self.path.pop();
+	}
+
+	/// Writes the tree as a JSON array of nodes. Each node refers to its
+	/// parent by its index in the array.
+	pub fn write_json(&self, writer: &mut impl Write) -> io::Result<()> {
+		writeln!(writer, "[")?;
+		for (i, node) in self.nodes.iter().enumerate() {
+			let optional = |value: Option<String>| {
+				value.map_or_else(|| String::from("null"), |value| format!("\"{value}\""))
+			};
+			let separator = if i + 1 == self.nodes.len() { "" } else { "," };
+
+			writeln!(
+				writer,
+				"  {{\"id\": {i}, \"parent\": {}, \"move\": {}, \"depth\": {}, \"alpha\": \"{}\", \"beta\": \"{}\", \"score\": {}, \"best_move\": {}, \"table\": \"{:?}\"}}{separator}",
+				node.parent.map_or_else(|| String::from("null"), |parent| parent.to_string()),
+				optional(node.last_move.map(|m| m.to_string())),
+				node.depth,
+				format_eval(node.alpha),
+				format_eval(node.beta),
+				optional(node.score.map(format_eval)),
+				optional(node.best_move.map(|m| m.to_string())),
+				node.table_event,
+			)?;
+		}
+		writeln!(writer, "]")
+	}
+
+	/// Writes the tree in the Graphviz DOT format
+	pub fn write_dot(&self, writer: &mut impl Write) -> io::Result<()> {
+		writeln!(writer, "digraph search {{")?;
+		writeln!(writer, "  node [shape=box];")?;
+		for (i, node) in self.nodes.iter().enumerate() {
+			let score = node.score.map_or_else(|| String::from("?"), format_eval);
+			let color = match node.table_event {
+				TableEvent::Miss => "black",
+				TableEvent::Hit => "blue",
+				TableEvent::Store => "darkgreen",
+			};
+			writeln!(
+				writer,
+				"  n{i} [label=\"depth {}\\n[{}, {}]\\nscore {score}\", color={color}];",
+				node.depth,
+				format_eval(node.alpha),
+				format_eval(node.beta),
+			)?;
+
+			if let Some(parent) = node.parent {
+				let label = node.last_move.map(|m| m.to_string()).unwrap_or_default();
+				writeln!(writer, "  n{parent} -> n{i} [label=\"{label}\"];")?;
+			}
+		}
+		writeln!(writer, "}}")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn budget_limits_nodes() {
+		let board = CheckersBitBoard::starting_position();
+		let mut tree = SearchTree::new(2);
+		let root = tree.enter(board, 2, Evaluation::NULL_MIN, Evaluation::NULL_MAX);
+		let child_move = PossibleMoves::moves(board).into_iter().next().unwrap();
+		let child = unsafe { child_move.apply_to(board) };
+		let node = tree.enter(child, 1, Evaluation::NULL_MIN, Evaluation::NULL_MAX);
+		assert_eq!(tree.nodes()[1].last_move, Some(child_move));
+		let skipped = tree.enter(child, 0, Evaluation::DRAW, Evaluation::DRAW);
+		assert_eq!(skipped, None);
+
+		tree.table_event(TableEvent::Hit);
+		tree.exit(skipped, (Evaluation::DRAW, None), true);
+		tree.exit(node, (Evaluation::DRAW, None), true);
+		tree.exit(root, (Evaluation::DRAW, Some(child_move)), true);
+		assert_eq!(tree.nodes()[1].table_event, TableEvent::Miss);
+		assert!(tree.is_truncated());
+		assert_eq!(tree.nodes()[0].best_move, Some(child_move));
+	}
+}
@@ -0,0 +1,537 @@
+use std::collections::VecDeque;
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+
+use model::{CheckersBitBoard, Move, PieceColor};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph};
+use ratatui::Frame;
+
+use crate::diagram::{row_and_column, square, Square};
+use crate::engine::{Engine, EvaluationSettings, SearchLimit};
+use crate::eval::Evaluation;
+use crate::frontend::{Frontend, SearchInfo};
+use crate::notation::{complete_line, find_move, full_moves, square_number, FullMove};
+
+/// The number of lines of engine output that are kept
+const OUTPUT_LINES: usize = 200;
+
+/// How long to wait for a key before checking for engine output
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+const LIGHT_SQUARE_COLOR: Color = Color::Rgb(0xf3, 0xe3, 0xc3);
+const DARK_SQUARE_COLOR: Color = Color::Rgb(0x5b, 0x8a, 0x5a);
+const LAST_MOVE_COLOR: Color = Color::Rgb(0xc8, 0xb8, 0x4a);
+
+const HELP: &str = "type a move and press enter · ←/→ browse · g engine move · \
+                    a analyze · u take back · n new game · q quit";
+
+/// Something the engine reports while it searches
+enum SearchEvent {
+	Depth(SearchInfo),
+	Message(String),
+	BestMove(Move),
+}
+
+/// Sends the engine's search results to the TUI's event loop
+struct ChannelFrontend(Sender<SearchEvent>);
+
+impl Frontend for ChannelFrontend {
+	fn on_depth_complete(&self, info: &SearchInfo) {
+		let _ = self.0.send(SearchEvent::Depth(*info));
+	}
+
+	fn on_string(&self, msg: &str) {
+		let _ = self.0.send(SearchEvent::Message(msg.to_string()));
+	}
+
+	fn report_best_move(&self, best_move: Move) {
+		let _ = self.0.send(SearchEvent::BestMove(best_move));
+	}
+}
+
+/// The moves of the game, and the position that is being looked at
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Game {
+	start: CheckersBitBoard,
+	moves: Vec<FullMove>,
+	/// The number of moves which were played before the viewed position
+	viewed: usize,
+}
+
+impl Game {
+	fn new(start: CheckersBitBoard) -> Self {
+		Self {
+			start,
+			moves: Vec::new(),
+			viewed: 0,
+		}
+	}
+
+	/// The position that is being looked at
+	fn position(&self) -> CheckersBitBoard {
+		match self.viewed {
+			0 => self.start,
+			viewed => self.moves[viewed - 1].result,
+		}
+	}
+
+	/// The move which led to the viewed position
+	fn last_move(&self) -> Option<&FullMove> {
+		self.viewed.checked_sub(1).map(|index| &self.moves[index])
+	}
+
+	/// Plays a move in the viewed position. If an earlier position is being
+	/// viewed, then the moves after it are replaced.
+	fn play(&mut self, full_move: FullMove) {
+		self.moves.truncate(self.viewed);
+		self.moves.push(full_move);
+		self.viewed = self.moves.len();
+	}
+
+	/// Removes the last move of the game
+	fn take_back(&mut self) {
+		self.moves.pop();
+		self.viewed = self.moves.len();
+	}
+
+	/// Moves the viewed position by some number of moves, staying within the
+	/// game
+	fn browse(&mut self, offset: isize) {
+		self.viewed = self
+			.viewed
+			.saturating_add_signed(offset)
+			.min(self.moves.len());
+	}
+
+	/// The row and column of a move in the move list. If the game starts with
+	/// white to move, then the first row only has white's move.
+	fn move_cell(&self, index: usize) -> (usize, usize) {
+		let ply = index + usize::from(self.start.turn() == PieceColor::Light);
+		(ply / 2, ply % 2)
+	}
+}
+
+/// What the engine is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+	Idle,
+	/// Searching the viewed position until it changes
+	Analyzing,
+	/// Choosing a move to play in the viewed position
+	Thinking,
+}
+
+struct App {
+	engine: Arc<Engine>,
+	events: Receiver<SearchEvent>,
+	/// The settings used when the engine plays a move
+	settings: EvaluationSettings,
+	game: Game,
+	mode: Mode,
+	input: String,
+	status: String,
+	output: VecDeque<String>,
+	latest: Option<SearchInfo>,
+	quit: bool,
+}
+
+impl App {
+	fn new(start: CheckersBitBoard, hash_size: usize, settings: EvaluationSettings) -> Self {
+		let (sender, events) = mpsc::channel();
+		let engine = Arc::new(Engine::new(hash_size, Arc::new(ChannelFrontend(sender))));
+		Self {
+			engine,
+			events,
+			settings,
+			game: Game::new(start),
+			mode: Mode::Idle,
+			input: String::new(),
+			status: String::new(),
+			output: VecDeque::new(),
+			latest: None,
+			quit: false,
+		}
+	}
+
+	fn log(&mut self, line: String) {
+		if self.output.len() == OUTPUT_LINES {
+			self.output.pop_front();
+		}
+		self.output.push_back(line);
+	}
+
+	/// Stops the search, and forgets anything it reported
+	fn stop(&mut self) {
+		self.engine.stop_evaluation();
+		while self.events.try_recv().is_ok() {}
+		self.latest = None;
+	}
+
+	/// Starts a search of the viewed position, if the engine should be
+	/// searching in the current mode
+	fn start(&mut self) {
+		let position = self.game.position();
+		if full_moves(position).is_empty() {
+			self.mode = Mode::Idle;
+			return;
+		}
+
+		let settings = match self.mode {
+			Mode::Idle => return,
+			Mode::Analyzing => EvaluationSettings {
+				search_until: SearchLimit::Infinite,
+				..EvaluationSettings::default()
+			},
+			Mode::Thinking => self.settings.clone(),
+		};
+		self.engine.set_position(position);
+		self.engine.start_evaluation(settings);
+	}
+
+	/// Searches the viewed position again, after it changes
+	fn restart(&mut self) {
+		self.stop();
+		if self.mode == Mode::Thinking {
+			self.mode = Mode::Idle;
+		}
+		self.start();
+	}
+
+	fn play(&mut self, full_move: FullMove) {
+		self.status.clear();
+		self.game.play(full_move);
+		self.restart();
+	}
+
+	fn play_input(&mut self) {
+		let notation = std::mem::take(&mut self.input);
+		match find_move(self.game.position(), notation.trim()) {
+			Some(full_move) => self.play(full_move),
+			None => self.status = format!("{} is not a legal move", notation.trim()),
+		}
+	}
+
+	fn handle_key(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+		match code {
+			KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => self.quit = true,
+			KeyCode::Char(c) if c.is_ascii_digit() || c == '-' || c == 'x' => self.input.push(c),
+			KeyCode::Backspace => {
+				self.input.pop();
+			}
+			KeyCode::Esc => self.input.clear(),
+			KeyCode::Enter => self.play_input(),
+			KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End => {
+				let offset = match code {
+					KeyCode::Left => -1,
+					KeyCode::Right => 1,
+					KeyCode::Home => isize::MIN,
+					_ => isize::MAX,
+				};
+				self.game.browse(offset);
+				self.restart();
+			}
+			KeyCode::Char('g') if full_moves(self.game.position()).is_empty() => {
+				self.status = "the game is over".to_string();
+			}
+			KeyCode::Char('g') => {
+				self.stop();
+				self.mode = Mode::Thinking;
+				self.status = "thinking...".to_string();
+				self.start();
+			}
+			KeyCode::Char('a') => {
+				self.stop();
+				self.mode = match self.mode {
+					Mode::Analyzing => Mode::Idle,
+					_ => Mode::Analyzing,
+				};
+				self.status.clear();
+				self.start();
+			}
+			KeyCode::Char('u') => {
+				self.game.take_back();
+				self.restart();
+			}
+			KeyCode::Char('n') => {
+				self.game = Game::new(self.game.start);
+				self.status.clear();
+				self.output.clear();
+				self.restart();
+			}
+			KeyCode::Char('q') => self.quit = true,
+			_ => {}
+		}
+	}
+
+	fn handle_search_event(&mut self, event: SearchEvent) {
+		let position = self.game.position();
+		match event {
+			SearchEvent::Depth(info) => {
+				self.latest = Some(info);
+				let pv = complete_line(position, &self.engine.expected_line(None));
+				let pv: Vec<String> = pv.iter().map(ToString::to_string).collect();
+				self.log(format!(
+					"depth {:>2}  {:>8}  {}",
+					info.depth,
+					short_eval(info.eval),
+					pv.join(" ")
+				));
+			}
+			SearchEvent::Message(message) => self.log(message),
+			SearchEvent::BestMove(best_move) if self.mode == Mode::Thinking => {
+				self.mode = Mode::Idle;
+				match complete_line(position, &[best_move]).into_iter().next() {
+					Some(full_move) => self.play(full_move),
+					None => self.status = format!("the engine chose an illegal move: {best_move}"),
+				}
+			}
+			SearchEvent::BestMove(_) => {}
+		}
+	}
+
+	fn draw(&self, frame: &mut Frame) {
+		let [main, input, help] = Layout::vertical([
+			Constraint::Min(10),
+			Constraint::Length(3),
+			Constraint::Length(1),
+		])
+		.areas(frame.area());
+		let [board, moves, output] = Layout::horizontal([
+			Constraint::Length(26),
+			Constraint::Length(22),
+			Constraint::Min(20),
+		])
+		.areas(main);
+
+		self.draw_board(frame, board);
+		self.draw_moves(frame, moves);
+		self.draw_output(frame, output);
+
+		let prompt = Line::from(vec![
+			Span::raw("> "),
+			Span::styled(&self.input, Style::new().add_modifier(Modifier::BOLD)),
+			Span::raw("  "),
+			Span::styled(&self.status, Style::new().fg(Color::Yellow)),
+		]);
+		frame.render_widget(Paragraph::new(prompt).block(Block::bordered()), input);
+		frame.render_widget(
+			Paragraph::new(HELP).style(Style::new().add_modifier(Modifier::DIM)),
+			help,
+		);
+	}
+
+	fn draw_board(&self, frame: &mut Frame, area: Rect) {
+		let position = self.game.position();
+		let last_move = self
+			.game
+			.last_move()
+			.map(|full_move| [full_move.from(), full_move.to()].map(square_number));
+
+		let mut rows: Vec<Vec<Span>> = (0..8)
+			.map(|row| {
+				(0..8)
+					.map(|column| {
+						let style = if (row + column) % 2 == 0 {
+							Style::new().bg(LIGHT_SQUARE_COLOR)
+						} else {
+							Style::new().bg(DARK_SQUARE_COLOR)
+						};
+						Span::styled("   ", style)
+					})
+					.collect()
+			})
+			.collect();
+
+		for number in 1..=32 {
+			let (row, column) = row_and_column(number);
+			let background = if last_move.is_some_and(|squares| squares.contains(&number)) {
+				LAST_MOVE_COLOR
+			} else {
+				DARK_SQUARE_COLOR
+			};
+			let style = Style::new().bg(background);
+			rows[row][column] = match square(position, number) {
+				Square::Empty => Span::styled(
+					format!("{number:>2} "),
+					style.fg(Color::Gray).add_modifier(Modifier::DIM),
+				),
+				Square::Man(color) => Span::styled(" ● ", style.fg(piece_color(color))),
+				Square::King(color) => Span::styled(" ◉ ", style.fg(piece_color(color))),
+			};
+		}
+
+		let mut lines: Vec<Line> = rows.into_iter().map(Line::from).collect();
+		lines.push(Line::default());
+		lines.push(Line::from(
+			match (position.turn(), full_moves(position).is_empty()) {
+				(PieceColor::Dark, false) => "black to move",
+				(PieceColor::Light, false) => "white to move",
+				(PieceColor::Dark, true) => "white wins",
+				(PieceColor::Light, true) => "black wins",
+			},
+		));
+		if let Some(info) = self.latest {
+			lines.push(Line::from(format!(
+				"eval {} at depth {}",
+				short_eval(info.eval),
+				info.depth
+			)));
+		}
+
+		let title = match self.mode {
+			Mode::Idle => " Board ",
+			Mode::Analyzing => " Board (analyzing) ",
+			Mode::Thinking => " Board (thinking) ",
+		};
+		frame.render_widget(
+			Paragraph::new(lines).block(Block::bordered().title(title)),
+			area,
+		);
+	}
+
+	fn draw_moves(&self, frame: &mut Frame, area: Rect) {
+		let mut rows: Vec<[(String, Style); 2]> = Vec::new();
+		for (index, full_move) in self.game.moves.iter().enumerate() {
+			let (row, column) = self.game.move_cell(index);
+			if rows.len() <= row {
+				rows.push([
+					("...".to_string(), Style::new()),
+					(String::new(), Style::new()),
+				]);
+			}
+
+			let style = if index + 1 == self.game.viewed {
+				Style::new().add_modifier(Modifier::REVERSED)
+			} else {
+				Style::new()
+			};
+			rows[row][column] = (full_move.to_string(), style);
+		}
+
+		let lines: Vec<Line> = rows
+			.into_iter()
+			.enumerate()
+			.map(|(number, [(black, black_style), (white, white_style)])| {
+				Line::from(vec![
+					Span::raw(format!("{:>3}. ", number + 1)),
+					Span::styled(format!("{black:<7}"), black_style),
+					Span::styled(white, white_style),
+				])
+			})
+			.collect();
+
+		// keep the viewed move on screen
+		let height = usize::from(area.height.saturating_sub(2));
+		let viewed_row = self
+			.game
+			.viewed
+			.checked_sub(1)
+			.map_or(0, |index| self.game.move_cell(index).0);
+		let scroll = (viewed_row + 1).saturating_sub(height);
+		frame.render_widget(
+			Paragraph::new(lines)
+				.scroll((scroll as u16, 0))
+				.block(Block::bordered().title(" Moves ")),
+			area,
+		);
+	}
+
+	fn draw_output(&self, frame: &mut Frame, area: Rect) {
+		let height = usize::from(area.height.saturating_sub(2));
+		let lines: Vec<Line> = self
+			.output
+			.iter()
+			.skip(self.output.len().saturating_sub(height))
+			.map(|line| Line::from(line.as_str()))
+			.collect();
+		frame.render_widget(
+			Paragraph::new(lines).block(Block::bordered().title(" Engine ")),
+			area,
+		);
+	}
+}
+
+/// An evaluation, rounded so that it fits in the engine output
+fn short_eval(eval: Evaluation) -> String {
+	match eval.to_f32() {
+		Some(eval) => format!("{eval:+.3}"),
+		None => eval.to_string(),
+	}
+}
+
+fn piece_color(color: PieceColor) -> Color {
+	match color {
+		PieceColor::Dark => Color::Black,
+		PieceColor::Light => Color::White,
+	}
+}
+
+/// Runs a full-screen game in the terminal, starting from `start`. The user
+/// types moves, and can ask the engine to play a move, using `settings`, or
+/// to analyze the position on the board until it changes. The transposition
+/// table uses at most `hash_size` bytes. Returns when the user quits.
+pub fn run(
+	start: CheckersBitBoard,
+	hash_size: usize,
+	settings: EvaluationSettings,
+) -> io::Result<()> {
+	let mut app = App::new(start, hash_size, settings);
+	let mut terminal = ratatui::try_init()?;
+	let result = event_loop(&mut app, &mut terminal);
+	ratatui::restore();
+	app.stop();
+	result
+}
+
+fn event_loop(app: &mut App, terminal: &mut ratatui::DefaultTerminal) -> io::Result<()> {
+	while !app.quit {
+		terminal.draw(|frame| app.draw(frame))?;
+
+		if event::poll(POLL_INTERVAL)? {
+			if let Event::Key(key) = event::read()? {
+				if key.kind == KeyEventKind::Press {
+					app.handle_key(key.code, key.modifiers);
+				}
+			}
+		}
+
+		while let Ok(event) = app.events.try_recv() {
+			app.handle_search_event(event);
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn browses_and_replaces_moves() {
+		let start = CheckersBitBoard::starting_position();
+		let mut game = Game::new(start);
+		game.play(find_move(start, "11-15").unwrap());
+		game.play(find_move(game.position(), "23-19").unwrap());
+		assert_eq!(game.move_cell(1), (0, 1));
+
+		game.browse(-5);
+		assert_eq!(game.position(), start);
+		game.browse(1);
+		assert_eq!(game.last_move().unwrap().to_string(), "11-15");
+
+		game.play(find_move(game.position(), "22-18").unwrap());
+		assert_eq!(game.moves.len(), 2);
+		assert_eq!(game.moves[1].to_string(), "22-18");
+
+		game.take_back();
+		game.take_back();
+		assert_eq!(game.position(), start);
+	}
+}
@@ -0,0 +1,60 @@
+#ifndef AMPERE_H
+#define AMPERE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define AMPERE_OK 0
+#define AMPERE_NULL_POINTER -1
+#define AMPERE_INVALID_STRING -2
+#define AMPERE_INVALID_FEN -3
+#define AMPERE_ILLEGAL_MOVE -4
+#define AMPERE_INVALID_OPTION -5
+
+typedef struct AmpereEngine AmpereEngine;
+
+/* Receives each line of search information. This may be called from a
+ * background thread. */
+typedef void (*AmpereInfoCallback)(const char *line, void *user_data);
+
+/* A zero means there is no limit of that kind. If there are no limits, the
+ * engine decides how long to think. */
+typedef struct AmpereLimits {
+	uint8_t depth;
+	uint64_t nodes;
+	double seconds;
+	int infinite;
+} AmpereLimits;
+
+typedef struct AmpereResult {
+	/* every jump is written out, such as "9x18x27" */
+	char best_move[64];
+	/* such as "+0.25" or "+M5" */
+	char score[16];
+	uint8_t depth;
+	uint64_t nodes;
+} AmpereResult;
+
+AmpereEngine *ampere_engine_new(size_t hash_megabytes, AmpereInfoCallback callback,
+                                void *user_data);
+void ampere_engine_free(AmpereEngine *engine);
+
+/* fen and moves may be NULL, for the starting position and no moves */
+int ampere_set_position(const AmpereEngine *engine, const char *fen, const char *moves);
+int ampere_set_option(const AmpereEngine *engine, const char *name, const char *value);
+
+int ampere_start_search(const AmpereEngine *engine, AmpereLimits limits);
+/* returns 1 and fills in the result once the search has finished */
+int ampere_poll(const AmpereEngine *engine, AmpereResult *result);
+/* returns 1 and fills in the result if a search was running */
+int ampere_stop(const AmpereEngine *engine, AmpereResult *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,450 @@
+use std::ffi::{c_char, c_double, c_int, c_void, CStr};
+use std::num::{NonZeroU8, NonZeroUsize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use engine::{
+	parse_fen, ActualLimit, Clock, Engine, EvaluationSettings, Frontend, FullMove, SearchInfo,
+	SearchLimit, MEGABYTE,
+};
+use model::{CheckersBitBoard, Move};
+use parking_lot::Mutex;
+
+pub const AMPERE_OK: c_int = 0;
+pub const AMPERE_NULL_POINTER: c_int = -1;
+pub const AMPERE_INVALID_STRING: c_int = -2;
+pub const AMPERE_INVALID_FEN: c_int = -3;
+pub const AMPERE_ILLEGAL_MOVE: c_int = -4;
+pub const AMPERE_INVALID_OPTION: c_int = -5;
+
+/// Receives each line of search information, along with the user data that
+/// was given when the engine was created
+pub type AmpereInfoCallback = extern "C" fn(line: *const c_char, user_data: *mut c_void);
+
+/// The limits on a search. A zero means there is no limit of that kind. If
+/// there are no limits, the engine decides how long to think.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[repr(C)]
+pub struct AmpereLimits {
+	pub depth: u8,
+	pub nodes: u64,
+	pub seconds: c_double,
+	/// If this isn't zero, then the search runs until it is stopped
+	pub infinite: c_int,
+}
+
+/// The result of a search
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(C)]
+pub struct AmpereResult {
+	/// The best move, with every jump written out, such as `9x18x27`. This
+	/// is empty if there were no legal moves.
+	pub best_move: [c_char; 64],
+	/// The evaluation, such as `+0.25` or `+M5`
+	pub score: [c_char; 16],
+	pub depth: u8,
+	pub nodes: u64,
+}
+
+impl Default for AmpereResult {
+	fn default() -> Self {
+		Self {
+			best_move: [0; 64],
+			score: [0; 16],
+			depth: 0,
+			nodes: 0,
+		}
+	}
+}
+
+/// The user data pointer which is passed back to the callback
+struct UserData(*mut c_void);
+
+// safety: the caller of ampere_engine_new promises that the callback can be
+// called from any thread with this pointer
+unsafe impl Send for UserData {}
+unsafe impl Sync for UserData {}
+
+/// Sends each line of search information to the callback, and keeps the
+/// result of the latest search
+struct FfiFrontend {
+	callback: Option<AmpereInfoCallback>,
+	user_data: UserData,
+	last_info: Mutex<Option<SearchInfo>>,
+	best_move: Mutex<Option<Move>>,
+}
+
+impl FfiFrontend {
+	fn send(&self, line: &str) {
+		let Some(callback) = self.callback else {
+			return;
+		};
+
+		// a line with a null byte can't be sent, but the engine never makes one
+		if let Ok(line) = std::ffi::CString::new(line) {
+			callback(line.as_ptr(), self.user_data.0);
+		}
+	}
+}
+
+impl Frontend for FfiFrontend {
+	fn on_depth_complete(&self, info: &SearchInfo) {
+		*self.last_info.lock() = Some(*info);
+		self.send(&format!("info {info}"));
+	}
+
+	fn on_string(&self, msg: &str) {
+		self.send(&format!("info string {msg}"));
+	}
+
+	fn report_best_move(&self, best_move: Move) {
+		*self.best_move.lock() = Some(best_move);
+		self.send(&format!("bestmove {best_move}"));
+	}
+}
+
+/// An engine, created by `ampere_engine_new`
+pub struct AmpereEngine {
+	engine: Arc<Engine>,
+	frontend: Arc<FfiFrontend>,
+	/// The position at the start of the latest search
+	root: Mutex<CheckersBitBoard>,
+}
+
+impl AmpereEngine {
+	/// Writes a move with each of its jumps, such as `9x18x27`
+	fn full_move(&self, best_move: Move) -> Option<FullMove> {
+		self.engine.best_full_move(*self.root.lock(), best_move)
+	}
+
+	fn result(&self, best_move: Option<Move>) -> AmpereResult {
+		let mut result = AmpereResult::default();
+		if let Some(info) = *self.frontend.last_info.lock() {
+			copy_string(&mut result.score, &info.eval.to_string());
+			result.depth = info.depth;
+			result.nodes = info.nodes as u64;
+		}
+
+		if let Some(full_move) = best_move.and_then(|best_move| self.full_move(best_move)) {
+			copy_string(&mut result.best_move, &full_move.long_notation());
+		}
+
+		result
+	}
+}
+
+/// Copies a string into a buffer, truncating it to leave room for the null
+/// byte
+fn copy_string(buffer: &mut [c_char], value: &str) {
+	let len = value.len().min(buffer.len() - 1);
+	for (destination, byte) in buffer.iter_mut().zip(&value.as_bytes()[..len]) {
+		*destination = *byte as c_char;
+	}
+	buffer[len] = 0;
+}
+
+/// Reads a C string, which may be null
+///
+/// # Safety
+///
+/// `string` must be null, or a valid null-terminated string
+unsafe fn read_string<'a>(string: *const c_char) -> Result<Option<&'a str>, c_int> {
+	if string.is_null() {
+		return Ok(None);
+	}
+
+	CStr::from_ptr(string)
+		.to_str()
+		.map(Some)
+		.map_err(|_| AMPERE_INVALID_STRING)
+}
+
+/// Creates an engine with a transposition table of `hash_megabytes`. Each
+/// line of search information is passed to `callback`, which may be null,
+/// along with `user_data`. The engine must be freed with
+/// `ampere_engine_free`.
+///
+/// # Safety
+///
+/// The callback may be called from a background thread, so it must be safe
+/// to call with `user_data` from any thread.
+#[no_mangle]
+pub unsafe extern "C" fn ampere_engine_new(
+	hash_megabytes: usize,
+	callback: Option<AmpereInfoCallback>,
+	user_data: *mut c_void,
+) -> *mut AmpereEngine {
+	let frontend = Arc::new(FfiFrontend {
+		callback,
+		user_data: UserData(user_data),
+		last_info: Mutex::new(None),
+		best_move: Mutex::new(None),
+	});
+	let engine = Engine::new(hash_megabytes.max(1) * MEGABYTE, frontend.clone());
+
+	Box::into_raw(Box::new(AmpereEngine {
+		root: Mutex::new(engine.current_position()),
+		engine: Arc::new(engine),
+		frontend,
+	}))
+}
+
+/// Stops any search, and frees the engine
+///
+/// # Safety
+///
+/// `engine` must have been created by `ampere_engine_new`, and must not be
+/// used afterwards. It may be null.
+#[no_mangle]
+pub unsafe extern "C" fn ampere_engine_free(engine: *mut AmpereEngine) {
+	if engine.is_null() {
+		return;
+	}
+
+	let engine = Box::from_raw(engine);
+	engine.engine.stop_evaluation();
+}
+
+/// Sets the position to `fen`, and then plays `moves`, which are separated
+/// by spaces. Either may be null, which means the starting position, or no
+/// moves. If a move is illegal, the position isn't changed.
+///
+/// # Safety
+///
+/// `engine` must be a valid engine, and the strings must be null or valid
+/// null-terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn ampere_set_position(
+	engine: *const AmpereEngine,
+	fen: *const c_char,
+	moves: *const c_char,
+) -> c_int {
+	let Some(engine) = engine.as_ref() else {
+		return AMPERE_NULL_POINTER;
+	};
+	let (fen, moves) = match (read_string(fen), read_string(moves)) {
+		(Ok(fen), Ok(moves)) => (fen, moves.unwrap_or_default()),
+		(Err(error), _) | (_, Err(error)) => return error,
+	};
+
+	let position = match fen {
+		Some(fen) => match parse_fen(fen) {
+			Some(position) => position,
+			None => return AMPERE_INVALID_FEN,
+		},
+		None => CheckersBitBoard::starting_position(),
+	};
+
+	match engine.engine.play_notation(position, moves) {
+		Ok(()) => AMPERE_OK,
+		Err(_) => AMPERE_ILLEGAL_MOVE,
+	}
+}
+
+/// Sets an engine option, such as `Hash` or `Skill Level`
+///
+/// # Safety
+///
+/// `engine` must be a valid engine, and the strings must be valid
+/// null-terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn ampere_set_option(
+	engine: *const AmpereEngine,
+	name: *const c_char,
+	value: *const c_char,
+) -> c_int {
+	let Some(engine) = engine.as_ref() else {
+		return AMPERE_NULL_POINTER;
+	};
+	let (name, value) = match (read_string(name), read_string(value)) {
+		(Ok(Some(name)), Ok(Some(value))) => (name, value),
+		(Err(error), _) | (_, Err(error)) => return error,
+		_ => return AMPERE_NULL_POINTER,
+	};
+
+	match engine.engine.set_option(name, value) {
+		Ok(()) => AMPERE_OK,
+		Err(_) => AMPERE_INVALID_OPTION,
+	}
+}
+
+/// Starts searching the current position in a background thread. Use
+/// `ampere_poll` to find out when it's finished.
+///
+/// # Safety
+///
+/// `engine` must be a valid engine
+#[no_mangle]
+pub unsafe extern "C" fn ampere_start_search(
+	engine: *const AmpereEngine,
+	limits: AmpereLimits,
+) -> c_int {
+	let Some(engine) = engine.as_ref() else {
+		return AMPERE_NULL_POINTER;
+	};
+
+	let depth = NonZeroU8::new(limits.depth);
+	let nodes = NonZeroUsize::new(limits.nodes as usize);
+	let time = Duration::try_from_secs_f64(limits.seconds)
+		.ok()
+		.filter(|time| !time.is_zero());
+	let search_until = if limits.infinite != 0 {
+		SearchLimit::Infinite
+	} else if depth.is_some() || nodes.is_some() {
+		SearchLimit::Limited(ActualLimit { nodes, depth, time })
+	} else {
+		SearchLimit::Auto
+	};
+
+	engine.engine.stop_evaluation();
+	*engine.frontend.best_move.lock() = None;
+	*engine.frontend.last_info.lock() = None;
+	*engine.root.lock() = engine.engine.current_position();
+	engine.engine.start_evaluation(EvaluationSettings {
+		clock: time.map_or_else(Clock::default, Clock::TimePerMove),
+		search_until,
+		..EvaluationSettings::default()
+	});
+
+	AMPERE_OK
+}
+
+/// Checks whether the search has finished. If it has, then 1 is returned,
+/// and the result is written to `result`. Otherwise, 0 is returned.
+///
+/// # Safety
+///
+/// `engine` must be a valid engine, and `result` must be valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn ampere_poll(
+	engine: *const AmpereEngine,
+	result: *mut AmpereResult,
+) -> c_int {
+	let (Some(engine), false) = (engine.as_ref(), result.is_null()) else {
+		return AMPERE_NULL_POINTER;
+	};
+
+	let best_move = *engine.frontend.best_move.lock();
+	if best_move.is_none() {
+		return 0;
+	}
+
+	*result = engine.result(best_move);
+	1
+}
+
+/// Stops the search, and writes the best move it found to `result`. Returns
+/// 1 if there was a search running, and 0 otherwise.
+///
+/// # Safety
+///
+/// `engine` must be a valid engine, and `result` must be valid for writes
+#[no_mangle]
+pub unsafe extern "C" fn ampere_stop(
+	engine: *const AmpereEngine,
+	result: *mut AmpereResult,
+) -> c_int {
+	let (Some(engine), false) = (engine.as_ref(), result.is_null()) else {
+		return AMPERE_NULL_POINTER;
+	};
+
+	match engine.engine.stop_evaluation() {
+		Some((_, best_move)) => {
+			*result = engine.result(best_move);
+			1
+		}
+		None => 0,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::ffi::CString;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	use super::*;
+
+	extern "C" fn count_lines(_line: *const c_char, user_data: *mut c_void) {
+		let lines = unsafe { &*(user_data as *const AtomicUsize) };
+		lines.fetch_add(1, Ordering::Relaxed);
+	}
+
+	#[test]
+	fn searches_a_position() {
+		let lines = AtomicUsize::new(0);
+		let fen = CString::new("B:W21-32:B1-12").unwrap();
+		let moves = CString::new("11-15 23-19").unwrap();
+
+		unsafe {
+			let engine = ampere_engine_new(
+				1,
+				Some(count_lines),
+				&lines as *const AtomicUsize as *mut c_void,
+			);
+			assert_eq!(
+				ampere_set_position(engine, fen.as_ptr(), moves.as_ptr()),
+				AMPERE_OK
+			);
+
+			let limits = AmpereLimits {
+				depth: 4,
+				..AmpereLimits::default()
+			};
+			assert_eq!(ampere_start_search(engine, limits), AMPERE_OK);
+
+			let mut result = AmpereResult::default();
+			while ampere_poll(engine, &mut result) == 0 {
+				std::thread::sleep(Duration::from_millis(1));
+			}
+
+			let best_move = CStr::from_ptr(result.best_move.as_ptr());
+			assert!(!best_move.to_bytes().is_empty());
+			assert_eq!(result.depth, 4);
+			ampere_engine_free(engine);
+		}
+
+		assert!(lines.load(Ordering::Relaxed) > 0);
+	}
+
+	#[test]
+	fn finishes_a_forced_win() {
+		// white captures the last black piece with 18x9
+		let fen = CString::new("W:W18:B14").unwrap();
+
+		unsafe {
+			let engine = ampere_engine_new(1, None, std::ptr::null_mut());
+			assert_eq!(
+				ampere_set_position(engine, fen.as_ptr(), std::ptr::null()),
+				AMPERE_OK
+			);
+
+			let limits = AmpereLimits {
+				depth: 5,
+				..AmpereLimits::default()
+			};
+			assert_eq!(ampere_start_search(engine, limits), AMPERE_OK);
+
+			let mut result = AmpereResult::default();
+			while ampere_poll(engine, &mut result) == 0 {
+				std::thread::sleep(Duration::from_millis(1));
+			}
+
+			let best_move = CStr::from_ptr(result.best_move.as_ptr());
+			assert_eq!(best_move.to_str(), Ok("18x9"));
+			ampere_engine_free(engine);
+		}
+	}
+
+	#[test]
+	fn rejects_invalid_fen() {
+		let fen = CString::new("not a position").unwrap();
+		unsafe {
+			let engine = ampere_engine_new(1, None, std::ptr::null_mut());
+			assert_eq!(
+				ampere_set_position(engine, fen.as_ptr(), std::ptr::null()),
+				AMPERE_INVALID_FEN
+			);
+			ampere_engine_free(engine);
+		}
+	}
+}
@@ -48,8 +48,16 @@ impl PartialEq for CheckersBitBoard {
 	}
 }
 
+/// The finalizer from splitmix64, which spreads every input bit across the
+/// whole output
+const fn splitmix64(mut hash: u64) -> u64 {
+	hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+	hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d049bb133111eb);
+	hash ^ (hash >> 31)
+}
+
 impl Hash for CheckersBitBoard {
-	/// Hashes with only the pieces part, to ensure correctness and efficiency
+	/// Hashes the same parts that are compared for equality
 	fn hash<H: Hasher>(&self, hasher: &mut H) {
 		self.hash_code().hash(hasher)
 	}
@@ -98,9 +106,15 @@ impl CheckersBitBoard {
 		STARTING_BITBOARD
 	}
 
+	/// A hash of the pieces on the board, their colors, and whose turn it is.
+	/// The bits are mixed, so any of them can be used as an index into a table.
 	#[must_use]
 	pub const fn hash_code(self) -> u64 {
-		(((self.color & self.pieces) as u64) << 32) | (((!self.color & self.pieces) as u64) << 32)
+		// the color and king bits of empty squares are ignored, like in `eq`
+		let colors = (self.color & self.pieces) as u64;
+		let kings = (self.kings & self.pieces) as u64;
+		let hash = splitmix64(self.pieces as u64 | colors << 32);
+		splitmix64(hash ^ (kings | (self.turn as u64) << 32))
 	}
 
 	/// Gets the bits that represent where pieces are on the board
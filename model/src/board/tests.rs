@@ -29,10 +29,12 @@ proptest! {
 		let board1 = CheckersBitBoard {
 			pieces, color, kings, turn: PieceColor::Dark
 		};
+		// the boards only differ on the empty squares
+		let empty = !pieces;
 		let board2 = CheckersBitBoard {
 			pieces,
-			color: c,
-			kings: k,
+			color: (color & pieces) | (c & empty),
+			kings: (kings & pieces) | (k & empty),
 			turn: PieceColor::Dark
 		};
 		let mut hasher1 = DefaultHasher::new();
@@ -682,6 +684,27 @@ fn test_jump_backward_right_specific() {
 	assert_eq!(board2.turn, PieceColor::Light);
 }
 
+#[test]
+fn test_hash_code_uses_every_part() {
+	let board = CheckersBitBoard::starting_position();
+	let flipped_turn = CheckersBitBoard {
+		turn: PieceColor::Light,
+		..board
+	};
+	let flipped_color = CheckersBitBoard {
+		color: board.color ^ 1,
+		..board
+	};
+	let crowned = CheckersBitBoard {
+		kings: board.kings | 1,
+		..board
+	};
+
+	assert_ne!(board.hash_code(), flipped_turn.hash_code());
+	assert_ne!(board.hash_code(), flipped_color.hash_code());
+	assert_ne!(board.hash_code(), crowned.hash_code());
+}
+
 #[test]
 fn test_send() {
 	fn assert_send<T: Send>() {}
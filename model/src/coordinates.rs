@@ -131,12 +131,15 @@ impl SquareCoordinate {
 		}
 	}
 
+	/// The number of the square in standard notation, from 1 to 32. This is
+	/// one more than the value given to [`SquareCoordinate::from_normal_value`].
+	/// Returns `None` for squares that can't be played on.
 	pub fn to_normal_value(self) -> Option<usize> {
-		if self.rank % 2 == 0 {
-			Some(self.rank as usize * 4 + self.file as usize % 4)
-		} else {
-			Some(self.rank as usize * 4 + self.file as usize % 4 + 1)
+		if !(self.rank + self.file).is_multiple_of(2) {
+			return None;
 		}
+
+		Some(self.rank as usize * 4 + (7 - self.file as usize) / 2 + 1)
 	}
 }
 
@@ -150,3 +153,21 @@ impl Display for SquareCoordinate {
 		)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn normal_values_are_standard_notation() {
+		for value in 0..32 {
+			let square = SquareCoordinate::from_normal_value(value);
+			assert_eq!(square.to_normal_value(), Some(value + 1));
+		}
+
+		// square 1 is on the first rank, and square 32 is on the last
+		assert_eq!(SquareCoordinate::new(0, 6).to_normal_value(), Some(1));
+		assert_eq!(SquareCoordinate::new(7, 1).to_normal_value(), Some(32));
+		assert_eq!(SquareCoordinate::new(0, 7).to_normal_value(), None);
+	}
+}
@@ -0,0 +1,201 @@
+use std::str::FromStr;
+
+use crate::PieceColor;
+
+/// A game which can be described by a PDN `GameType` tag. Only English
+/// draughts can be played by Ampere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Variant {
+	InternationalDraughts,
+	EnglishDraughts,
+	ItalianDraughts,
+	AmericanPool,
+	SpanishDraughts,
+	RussianDraughts,
+	BrazilianDraughts,
+	CanadianDraughts,
+	PortugueseDraughts,
+	CzechDraughts,
+	TurkishDraughts,
+	ThaiDraughts,
+	FrisianDraughts,
+	/// A game with a number that isn't listed here
+	Other(u8),
+}
+
+impl Variant {
+	/// The number of the game in the `GameType` tag
+	pub const fn code(self) -> u8 {
+		match self {
+			Self::InternationalDraughts => 20,
+			Self::EnglishDraughts => 21,
+			Self::ItalianDraughts => 22,
+			Self::AmericanPool => 23,
+			Self::SpanishDraughts => 24,
+			Self::RussianDraughts => 25,
+			Self::BrazilianDraughts => 26,
+			Self::CanadianDraughts => 27,
+			Self::PortugueseDraughts => 28,
+			Self::CzechDraughts => 29,
+			Self::TurkishDraughts => 30,
+			Self::ThaiDraughts => 31,
+			Self::FrisianDraughts => 40,
+			Self::Other(code) => code,
+		}
+	}
+
+	pub const fn from_code(code: u8) -> Self {
+		match code {
+			20 => Self::InternationalDraughts,
+			21 => Self::EnglishDraughts,
+			22 => Self::ItalianDraughts,
+			23 => Self::AmericanPool,
+			24 => Self::SpanishDraughts,
+			25 => Self::RussianDraughts,
+			26 => Self::BrazilianDraughts,
+			27 => Self::CanadianDraughts,
+			28 => Self::PortugueseDraughts,
+			29 => Self::CzechDraughts,
+			30 => Self::TurkishDraughts,
+			31 => Self::ThaiDraughts,
+			40 => Self::FrisianDraughts,
+			code => Self::Other(code),
+		}
+	}
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveNotation {
+	/// Standard Chess Notation, like e5
+	Standard = 0,
+	/// Alpha-numeric square representation, like e7-e5
+	Alpha = 1,
+	/// Numeric square representation, like 11-12
+	Numeric = 2,
+}
+
+/// The rules and board of a game, as they're written in a PDN `GameType`
+/// tag, such as `21,B,8,8,N1,1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GameType {
+	/// The type of game being played
+	pub variant: Variant,
+	/// The color that makes the first move
+	pub start_color: PieceColor,
+	/// The width of the board
+	pub board_width: u8,
+	/// The height of the board
+	pub board_height: u8,
+	/// The move notation
+	pub notation: MoveNotation,
+	/// True if the bottom-left square is a playing square
+	pub invert_flag: bool,
+}
+
+impl GameType {
+	/// The only game type which is supported
+	pub const ENGLISH_DRAUGHTS: Self = Self {
+		variant: Variant::EnglishDraughts,
+		start_color: PieceColor::Dark,
+		board_width: 8,
+		board_height: 8,
+		notation: MoveNotation::Numeric,
+		invert_flag: true,
+	};
+
+	/// The usual board and notation for a variant, which are used when the
+	/// `GameType` tag only has the game's number
+	pub const fn default_for(variant: Variant) -> Self {
+		let size = match variant {
+			Variant::InternationalDraughts | Variant::FrisianDraughts => 10,
+			Variant::CanadianDraughts => 12,
+			_ => 8,
+		};
+
+		match variant {
+			Variant::EnglishDraughts => Self::ENGLISH_DRAUGHTS,
+			_ => Self {
+				variant,
+				start_color: PieceColor::Light,
+				board_width: size,
+				board_height: size,
+				notation: MoveNotation::Numeric,
+				invert_flag: false,
+			},
+		}
+	}
+
+	/// Whether Ampere can play games of this type
+	pub fn is_supported(self) -> bool {
+		self == Self::ENGLISH_DRAUGHTS
+	}
+}
+
+/// Reads a `GameType` tag, such as `21` or `20,W,10,10,N2,0`. Fields that
+/// are left out are given the usual values for the variant. The number after
+/// the notation's letter, which is the corner that the numbering starts
+/// from, is ignored.
+impl FromStr for GameType {
+	type Err = ();
+
+	fn from_str(game_type: &str) -> Result<Self, Self::Err> {
+		let mut fields = game_type.split(',').map(str::trim);
+		let code = fields.next().ok_or(())?.parse().map_err(|_| ())?;
+		let mut game_type = Self::default_for(Variant::from_code(code));
+
+		if let Some(color) = fields.next() {
+			game_type.start_color = match color {
+				"W" => PieceColor::Light,
+				"B" => PieceColor::Dark,
+				_ => return Err(()),
+			};
+		}
+		if let Some(width) = fields.next() {
+			game_type.board_width = width.parse().map_err(|_| ())?;
+		}
+		if let Some(height) = fields.next() {
+			game_type.board_height = height.parse().map_err(|_| ())?;
+		}
+		if let Some(notation) = fields.next() {
+			game_type.notation = match notation.chars().next() {
+				Some('S') => MoveNotation::Standard,
+				Some('A') => MoveNotation::Alpha,
+				Some('N') => MoveNotation::Numeric,
+				_ => return Err(()),
+			};
+		}
+		if let Some(invert_flag) = fields.next() {
+			game_type.invert_flag = match invert_flag {
+				"0" => false,
+				"1" => true,
+				_ => return Err(()),
+			};
+		}
+
+		if fields.next().is_some() {
+			return Err(());
+		}
+		Ok(game_type)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_game_types() {
+		assert_eq!("21".parse(), Ok(GameType::ENGLISH_DRAUGHTS));
+		assert_eq!("21,B,8,8,N1,1".parse(), Ok(GameType::ENGLISH_DRAUGHTS));
+
+		let international: GameType = "20,W,10,10,N2,0".parse().unwrap();
+		assert_eq!(international.variant, Variant::InternationalDraughts);
+		assert_eq!(international.board_width, 10);
+		assert!(!international.is_supported());
+
+		assert_eq!("21,X".parse::<GameType>(), Err(()));
+		assert_eq!("English".parse::<GameType>(), Err(()));
+		assert_eq!(Variant::from_code(99).code(), 99);
+	}
+}
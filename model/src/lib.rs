@@ -1,6 +1,7 @@
 mod board;
 mod color;
 mod coordinates;
+mod game_type;
 mod moves;
 mod piece;
 mod possible_moves;
@@ -8,6 +9,7 @@ mod possible_moves;
 pub use board::CheckersBitBoard;
 pub use color::PieceColor;
 pub use coordinates::SquareCoordinate;
+pub use game_type::{GameType, MoveNotation, Variant};
 pub use moves::{Move, MoveDirection};
 pub use piece::Piece;
 pub use possible_moves::PossibleMoves;
@@ -292,4 +292,18 @@ mod tests {
 		let move_test = Move::new(start, direction, true);
 		assert_eq!(move_test.end_position(), 14);
 	}
+
+	#[test]
+	fn display_uses_standard_notation() {
+		let board = crate::CheckersBitBoard::starting_position();
+		let mut moves: Vec<String> = crate::PossibleMoves::moves(board)
+			.into_iter()
+			.map(|m| m.to_string())
+			.collect();
+		moves.sort();
+		assert_eq!(
+			moves,
+			["10-14", "10-15", "11-15", "11-16", "12-16", "9-13", "9-14"]
+		);
+	}
 }
@@ -366,28 +366,32 @@ impl PossibleMoves {
 	/// The highest possible number of valid moves
 	pub const MAX_POSSIBLE_MOVES: usize = POSSIBLE_MOVES_ITER_SIZE;
 
-	const fn slides_dark(board: CheckersBitBoard) -> Self {
-		const FORWARD_LEFT_MASK: u32 = 0b01111001111110111111001111011011;
-		const FORWARD_RIGHT_MASK: u32 = 0b01111101111111011111010111011101;
-		const BACKWARD_LEFT_MASK: u32 = 0b11111011111110111110101110111010;
-		const BACKWARD_RIGHT_MASK: u32 = 0b11111001111110011110110110111100;
+	/// The squares a piece can slide forward and to the left from
+	pub const FORWARD_LEFT_SLIDES: u32 = 0b01111001111110111111001111011011;
+	/// The squares a piece can slide forward and to the right from
+	pub const FORWARD_RIGHT_SLIDES: u32 = 0b01111101111111011111010111011101;
+	/// The squares a piece can slide backward and to the left from
+	pub const BACKWARD_LEFT_SLIDES: u32 = 0b11111011111110111110101110111010;
+	/// The squares a piece can slide backward and to the right from
+	pub const BACKWARD_RIGHT_SLIDES: u32 = 0b11111101111110011110110110111100;
 
+	const fn slides_dark(board: CheckersBitBoard) -> Self {
 		let not_occupied = !board.pieces_bits();
 		let friendly_pieces = board.pieces_bits() & board.color_bits();
 		let friendly_kings = friendly_pieces & board.king_bits();
 
 		let forward_left_movers =
-			not_occupied.rotate_right(7) & friendly_pieces & FORWARD_LEFT_MASK;
+			not_occupied.rotate_right(7) & friendly_pieces & Self::FORWARD_LEFT_SLIDES;
 		let forward_right_movers =
-			not_occupied.rotate_right(1) & friendly_pieces & FORWARD_RIGHT_MASK;
+			not_occupied.rotate_right(1) & friendly_pieces & Self::FORWARD_RIGHT_SLIDES;
 		let backward_left_movers;
 		let backward_right_movers;
 
 		if friendly_kings > 0 {
 			backward_left_movers =
-				not_occupied.rotate_left(1) & friendly_kings & BACKWARD_LEFT_MASK;
+				not_occupied.rotate_left(1) & friendly_kings & Self::BACKWARD_LEFT_SLIDES;
 			backward_right_movers =
-				not_occupied.rotate_left(7) & friendly_kings & BACKWARD_RIGHT_MASK;
+				not_occupied.rotate_left(7) & friendly_kings & Self::BACKWARD_RIGHT_SLIDES;
 		} else {
 			backward_left_movers = 0;
 			backward_right_movers = 0;
@@ -402,26 +406,22 @@ impl PossibleMoves {
 	}
 
 	const fn slides_light(board: CheckersBitBoard) -> Self {
-		const FORWARD_LEFT_MASK: u32 = 0b01111001111110111111001111011011;
-		const FORWARD_RIGHT_MASK: u32 = 0b01111101111111011111010111011101;
-		const BACKWARD_LEFT_MASK: u32 = 0b11111011111110111110101110111010;
-		const BACKWARD_RIGHT_MASK: u32 = 0b11111001111110011110110110111100;
-
 		let not_occupied = !board.pieces_bits();
 		let friendly_pieces = board.pieces_bits() & !board.color_bits();
 		let friendly_kings = friendly_pieces & board.king_bits();
 
 		let backward_left_movers =
-			not_occupied.rotate_left(1) & friendly_pieces & BACKWARD_LEFT_MASK;
+			not_occupied.rotate_left(1) & friendly_pieces & Self::BACKWARD_LEFT_SLIDES;
 		let backward_right_movers =
-			not_occupied.rotate_left(7) & friendly_pieces & BACKWARD_RIGHT_MASK;
+			not_occupied.rotate_left(7) & friendly_pieces & Self::BACKWARD_RIGHT_SLIDES;
 		let forward_left_movers;
 		let forward_right_movers;
 
 		if friendly_kings > 0 {
-			forward_left_movers = not_occupied.rotate_right(7) & friendly_kings & FORWARD_LEFT_MASK;
+			forward_left_movers =
+				not_occupied.rotate_right(7) & friendly_kings & Self::FORWARD_LEFT_SLIDES;
 			forward_right_movers =
-				not_occupied.rotate_right(1) & friendly_kings & FORWARD_RIGHT_MASK;
+				not_occupied.rotate_right(1) & friendly_kings & Self::FORWARD_RIGHT_SLIDES;
 		} else {
 			forward_left_movers = 0;
 			forward_right_movers = 0;
@@ -460,10 +460,12 @@ impl PossibleMoves {
 		if friendly_kings > 0 {
 			backward_left_movers = not_occupied.rotate_left(2)
 				& enemy_pieces.rotate_left(1)
-				& friendly_kings & BACKWARD_LEFT_MASK;
+				& friendly_kings
+				& BACKWARD_LEFT_MASK;
 			backward_right_movers = not_occupied.rotate_left(14)
 				& enemy_pieces.rotate_left(7)
-				& friendly_kings & BACKWARD_RIGHT_MASK;
+				& friendly_kings
+				& BACKWARD_RIGHT_MASK;
 		} else {
 			backward_left_movers = 0;
 			backward_right_movers = 0;
@@ -512,10 +514,12 @@ impl PossibleMoves {
 		if friendly_kings > 0 {
 			forward_left_movers = not_occupied.rotate_right(14)
 				& enemy_pieces.rotate_right(7)
-				& friendly_kings & FORWARD_LEFT_MASK;
+				& friendly_kings
+				& FORWARD_LEFT_MASK;
 			forward_right_movers = not_occupied.rotate_right(2)
 				& enemy_pieces.rotate_right(1)
-				& friendly_kings & FORWARD_RIGHT_MASK;
+				& friendly_kings
+				& FORWARD_RIGHT_MASK;
 		} else {
 			forward_left_movers = 0;
 			forward_right_movers = 0;
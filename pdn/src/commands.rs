@@ -0,0 +1,160 @@
+use std::fmt::{self, Display};
+use std::time::Duration;
+
+/// An engine's evaluation of the position after a move, written in a comment
+/// as `[%eval +0.43/17]`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Evaluation {
+	/// The score, in pieces, for the player who made the move
+	pub score: f32,
+	/// How many plies the engine searched, if it's known
+	pub depth: Option<u32>,
+}
+
+impl Display for Evaluation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{:+.2}", self.score)?;
+		if let Some(depth) = self.depth {
+			write!(f, "/{depth}")?;
+		}
+		Ok(())
+	}
+}
+
+/// The commands that were taken out of a comment after a move
+#[derive(Debug, Default)]
+pub(crate) struct MoveCommands {
+	pub clock: Option<Duration>,
+	pub evaluation: Option<Evaluation>,
+	/// The text of the comment, without the commands
+	pub rest: String,
+}
+
+/// Parses a clock time, such as `0:05:32` or `5:32.5`
+fn parse_clock(time: &str) -> Option<Duration> {
+	let mut seconds = 0.0;
+	for (i, part) in time.rsplit(':').enumerate() {
+		let value: f64 = part.parse().ok().filter(|value: &f64| *value >= 0.0)?;
+		if i > 0 && part.contains('.') {
+			return None;
+		}
+		seconds += match i {
+			0 => value,
+			1 => value * 60.0,
+			2 => value * 3600.0,
+			_ => return None,
+		};
+	}
+
+	Some(Duration::from_secs_f64(seconds))
+}
+
+/// Writes a clock time as `h:mm:ss`, with fractions of a second if there are
+/// any
+fn write_clock(time: Duration) -> String {
+	let seconds = time.as_secs();
+	let mut text = format!(
+		"{}:{:02}:{:02}",
+		seconds / 3600,
+		seconds / 60 % 60,
+		seconds % 60
+	);
+	let millis = time.subsec_millis();
+	if millis != 0 {
+		text.push_str(format!(".{millis:03}").trim_end_matches('0'));
+	}
+	text
+}
+
+fn parse_evaluation(evaluation: &str) -> Option<Evaluation> {
+	let (score, depth) = match evaluation.split_once('/') {
+		Some((score, depth)) => (score, Some(depth.parse().ok()?)),
+		None => (evaluation, None),
+	};
+
+	let score: f32 = score.parse().ok()?;
+	score.is_finite().then_some(Evaluation { score, depth })
+}
+
+/// Takes the `[%clk]` and `[%eval]` commands out of a comment. Other
+/// commands, and commands which can't be parsed, are left in the text.
+pub(crate) fn take_commands(comment: &str) -> MoveCommands {
+	let mut commands = MoveCommands::default();
+	let mut rest = String::new();
+	let mut text = comment;
+	while let Some(start) = text.find("[%") {
+		let Some(len) = text[start..].find(']') else {
+			break;
+		};
+
+		let command = text[start + 2..start + len].trim();
+		let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+		let argument = argument.trim();
+		let taken = match (name, parse_clock(argument), parse_evaluation(argument)) {
+			("clk", Some(clock), _) => {
+				commands.clock = Some(clock);
+				true
+			}
+			("eval", _, Some(evaluation)) => {
+				commands.evaluation = Some(evaluation);
+				true
+			}
+			_ => false,
+		};
+
+		let end = start + len + 1;
+		if taken {
+			rest.push_str(&text[..start]);
+		} else {
+			rest.push_str(&text[..end]);
+		}
+		text = &text[end..];
+	}
+	rest.push_str(text);
+
+	commands.rest = if commands.clock.is_some() || commands.evaluation.is_some() {
+		rest.split_whitespace().collect::<Vec<_>>().join(" ")
+	} else {
+		rest
+	};
+	commands
+}
+
+/// Writes the commands for a move's clock and evaluation, such as
+/// `[%clk 0:05:32] [%eval +0.43/17]`
+pub(crate) fn write_commands(clock: Option<Duration>, evaluation: Option<Evaluation>) -> String {
+	let clock = clock.map(|clock| format!("[%clk {}]", write_clock(clock)));
+	let evaluation = evaluation.map(|evaluation| format!("[%eval {evaluation}]"));
+	clock
+		.into_iter()
+		.chain(evaluation)
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn takes_commands() {
+		let commands = take_commands("[%clk 0:05:32] a good move [%csl Ga4] [%eval +0.43/17]");
+		assert_eq!(commands.clock, Some(Duration::from_secs(332)));
+		assert_eq!(
+			commands.evaluation,
+			Some(Evaluation {
+				score: 0.43,
+				depth: Some(17)
+			})
+		);
+		assert_eq!(commands.rest, "a good move [%csl Ga4]");
+
+		let commands = take_commands("[%clk soon] unchanged ");
+		assert_eq!(commands.clock, None);
+		assert_eq!(commands.rest, "[%clk soon] unchanged ");
+
+		let clock = parse_clock("1:02:03.5").unwrap();
+		assert_eq!(write_clock(clock), "1:02:03.5");
+		assert_eq!(parse_clock("4:30"), Some(Duration::from_secs(270)));
+	}
+}
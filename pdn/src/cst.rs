@@ -0,0 +1,497 @@
+use std::fmt::{self, Display};
+use std::iter::Peekable;
+
+use crate::grammar::{PdnTag, Square};
+use crate::tokens::{PdnScanner, PdnTokenBody, TokenErrorType};
+use crate::writer::{wrap, write_square, write_tag, WriterOptions};
+
+/// What a node of the syntax tree is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SyntaxKind {
+	File,
+	Game,
+	Tag,
+	/// A move, with its move number and strength, if they were written
+	Move,
+	Variation,
+}
+
+/// A token of the syntax tree, with the exact text it was read from
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyntaxToken {
+	body: Result<PdnTokenBody, TokenErrorType>,
+	text: String,
+}
+
+impl SyntaxToken {
+	/// A comment, such as `{a good move}`
+	pub fn comment(comment: &str) -> Self {
+		Self {
+			body: Ok(PdnTokenBody::Comment(comment.into())),
+			text: format!("{{{comment}}}"),
+		}
+	}
+
+	/// Whitespace, which should only contain whitespace characters
+	pub fn space(space: &str) -> Self {
+		Self {
+			body: Ok(PdnTokenBody::Space(space.into())),
+			text: space.to_string(),
+		}
+	}
+
+	/// The token, or the reason the text isn't a valid token
+	pub fn body(&self) -> Result<&PdnTokenBody, &TokenErrorType> {
+		self.body.as_ref()
+	}
+
+	/// The text of the token, exactly as it was written
+	pub fn text(&self) -> &str {
+		&self.text
+	}
+
+	fn is_space(&self) -> bool {
+		matches!(self.body, Ok(PdnTokenBody::Space(_)))
+	}
+
+	fn is(&self, body: &PdnTokenBody) -> bool {
+		self.body.as_ref() == Ok(body)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SyntaxElement {
+	Node(SyntaxNode),
+	Token(SyntaxToken),
+}
+
+impl Display for SyntaxElement {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Node(node) => node.fmt(f),
+			Self::Token(token) => f.write_str(token.text()),
+		}
+	}
+}
+
+/// A node of the syntax tree. Every character of the source is kept,
+/// including whitespace, comments, and text that couldn't be parsed, so the
+/// tree can be written back out exactly as it was read.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyntaxNode {
+	kind: SyntaxKind,
+	children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+	pub fn kind(&self) -> SyntaxKind {
+		self.kind
+	}
+
+	pub fn children(&self) -> &[SyntaxElement] {
+		&self.children
+	}
+
+	/// The children can be edited, such as to add comments. The rest of the
+	/// text is left as it was.
+	pub fn children_mut(&mut self) -> &mut Vec<SyntaxElement> {
+		&mut self.children
+	}
+
+	/// The child nodes of a kind, such as the games of a file
+	pub fn nodes(&self, kind: SyntaxKind) -> impl Iterator<Item = &SyntaxNode> {
+		self.children.iter().filter_map(move |child| match child {
+			SyntaxElement::Node(node) if node.kind == kind => Some(node),
+			_ => None,
+		})
+	}
+
+	/// The child nodes of a kind, which can be edited
+	pub fn nodes_mut(&mut self, kind: SyntaxKind) -> impl Iterator<Item = &mut SyntaxNode> {
+		self.children
+			.iter_mut()
+			.filter_map(move |child| match child {
+				SyntaxElement::Node(node) if node.kind == kind => Some(node),
+				_ => None,
+			})
+	}
+
+	/// The tokens of the node and its children, in order
+	pub fn tokens(&self) -> Vec<&SyntaxToken> {
+		let mut tokens = Vec::new();
+		for child in &self.children {
+			match child {
+				SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+				SyntaxElement::Token(token) => tokens.push(token),
+			}
+		}
+		tokens
+	}
+}
+
+/// Writes the node exactly as it was read
+impl Display for SyntaxNode {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		for child in &self.children {
+			child.fmt(f)?;
+		}
+		Ok(())
+	}
+}
+
+/// Reads the text of every token, including the invalid ones
+fn lex(source: &str) -> Vec<SyntaxToken> {
+	let chars: Vec<char> = source.chars().collect();
+	PdnScanner::new(source)
+		.map(|token| {
+			let (header, body) = match token {
+				Ok(token) => (token.header, Ok(token.body)),
+				Err(error) => (error.header(), Err(error.error_type().clone())),
+			};
+			let text = chars[header.start()..header.start() + header.len()]
+				.iter()
+				.collect();
+			SyntaxToken { body, text }
+		})
+		.collect()
+}
+
+type Tokens = Peekable<std::vec::IntoIter<SyntaxToken>>;
+
+fn next_is(tokens: &mut Tokens, matches: impl Fn(&PdnTokenBody) -> bool) -> bool {
+	tokens
+		.peek()
+		.is_some_and(|token| token.body.as_ref().is_ok_and(&matches))
+}
+
+fn is_square(body: &PdnTokenBody) -> bool {
+	matches!(
+		body,
+		PdnTokenBody::NumSquare(_) | PdnTokenBody::AlphaSquare(..)
+	)
+}
+
+/// Reads a tag, until its closing bracket, or until a token which can't be
+/// in a tag
+fn tag(tokens: &mut Tokens) -> SyntaxNode {
+	let mut children = vec![SyntaxElement::Token(
+		tokens.next().expect("the tag was peeked"),
+	)];
+	while next_is(tokens, |body| {
+		matches!(
+			body,
+			PdnTokenBody::Space(_)
+				| PdnTokenBody::Identifier(_)
+				| PdnTokenBody::String(_)
+				| PdnTokenBody::RightBracket
+		)
+	}) {
+		let token = tokens.next().expect("the token was peeked");
+		let end = token.is(&PdnTokenBody::RightBracket);
+		children.push(SyntaxElement::Token(token));
+		if end {
+			break;
+		}
+	}
+
+	SyntaxNode {
+		kind: SyntaxKind::Tag,
+		children,
+	}
+}
+
+/// Reads a move, which may only be a move number, if there are no squares
+/// after it
+fn game_move(tokens: &mut Tokens) -> SyntaxNode {
+	let mut children = Vec::new();
+	let mut push = |tokens: &mut Tokens| {
+		children.push(SyntaxElement::Token(
+			tokens.next().expect("the token was peeked"),
+		))
+	};
+
+	if next_is(tokens, |body| matches!(body, PdnTokenBody::MoveNumber(..))) {
+		push(tokens);
+		if next_is(tokens, |body| matches!(body, PdnTokenBody::Space(_))) {
+			push(tokens);
+		}
+	}
+
+	if next_is(tokens, is_square) {
+		push(tokens);
+		while next_is(tokens, |body| {
+			matches!(
+				body,
+				PdnTokenBody::MoveSeparator | PdnTokenBody::CaptureSeparator
+			)
+		}) {
+			push(tokens);
+			if next_is(tokens, is_square) {
+				push(tokens);
+			}
+		}
+		if next_is(tokens, |body| matches!(body, PdnTokenBody::MoveStrength(_))) {
+			push(tokens);
+		}
+	}
+
+	SyntaxNode {
+		kind: SyntaxKind::Move,
+		children,
+	}
+}
+
+/// Reads move text, until the end of the variation or game
+fn move_text(tokens: &mut Tokens, children: &mut Vec<SyntaxElement>, in_variation: bool) {
+	while let Some(token) = tokens.peek() {
+		let body = token.body.as_ref().ok();
+		match body {
+			Some(PdnTokenBody::RightParenthesis) if in_variation => {
+				children.push(SyntaxElement::Token(
+					tokens.next().expect("the token was peeked"),
+				));
+				return;
+			}
+			// a variation that isn't closed ends with the game
+			Some(PdnTokenBody::GameResult(_) | PdnTokenBody::LeftBracket) if in_variation => {
+				return;
+			}
+			Some(PdnTokenBody::LeftBracket) => return,
+			Some(PdnTokenBody::GameResult(_)) => {
+				children.push(SyntaxElement::Token(
+					tokens.next().expect("the token was peeked"),
+				));
+				return;
+			}
+			Some(PdnTokenBody::LeftParenthesis) => {
+				let mut variation = vec![SyntaxElement::Token(
+					tokens.next().expect("the token was peeked"),
+				)];
+				move_text(tokens, &mut variation, true);
+				children.push(SyntaxElement::Node(SyntaxNode {
+					kind: SyntaxKind::Variation,
+					children: variation,
+				}));
+			}
+			Some(
+				PdnTokenBody::MoveNumber(..)
+				| PdnTokenBody::NumSquare(_)
+				| PdnTokenBody::AlphaSquare(..),
+			) => children.push(SyntaxElement::Node(game_move(tokens))),
+			_ => children.push(SyntaxElement::Token(
+				tokens.next().expect("the token was peeked"),
+			)),
+		}
+	}
+}
+
+/// Reads a game's tags and moves. The game ends after its result, or before
+/// a tag that comes after its moves.
+fn game(tokens: &mut Tokens) -> SyntaxNode {
+	let mut children = Vec::new();
+	while let Some(token) = tokens.peek() {
+		if token.is(&PdnTokenBody::LeftBracket) {
+			children.push(SyntaxElement::Node(tag(tokens)));
+		} else if token.is_space() {
+			children.push(SyntaxElement::Token(
+				tokens.next().expect("the token was peeked"),
+			));
+		} else {
+			move_text(tokens, &mut children, false);
+			break;
+		}
+	}
+
+	SyntaxNode {
+		kind: SyntaxKind::Game,
+		children,
+	}
+}
+
+/// Reads a PDN file into a syntax tree. This never fails, because text that
+/// can't be parsed is kept as invalid tokens.
+pub fn parse(source: &str) -> SyntaxNode {
+	let mut tokens = lex(source).into_iter().peekable();
+	let mut children = Vec::new();
+	while let Some(token) = tokens.peek() {
+		if token.is_space() {
+			children.push(SyntaxElement::Token(
+				tokens.next().expect("the token was peeked"),
+			));
+		} else {
+			children.push(SyntaxElement::Node(game(&mut tokens)));
+		}
+	}
+
+	SyntaxNode {
+		kind: SyntaxKind::File,
+		children,
+	}
+}
+
+/// The text of a token, with its square written in the chosen notation
+fn token_text(token: &SyntaxToken, options: &WriterOptions) -> String {
+	match token.body() {
+		Ok(&PdnTokenBody::NumSquare(number)) => {
+			write_square(&Square::num(number), options.notation)
+		}
+		Ok(&PdnTokenBody::AlphaSquare(file, rank)) => {
+			write_square(&Square::alpha(file, rank), options.notation)
+		}
+		_ => token.text().to_string(),
+	}
+}
+
+/// Splits move text into words at its whitespace. Tokens that were written
+/// without whitespace between them stay in the same word, and the whitespace
+/// in comments is collapsed.
+fn move_text_words(tokens: &[&SyntaxToken], options: &WriterOptions) -> Vec<String> {
+	let mut words = Vec::new();
+	let mut word = String::new();
+	for token in tokens {
+		match token.body() {
+			Ok(PdnTokenBody::Space(_)) => {
+				if !word.is_empty() {
+					words.push(std::mem::take(&mut word));
+				}
+			}
+			Ok(PdnTokenBody::Comment(comment)) => {
+				let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+				let comment = format!("{{{comment}}}");
+				let mut pieces = comment.split(' ');
+				word.push_str(pieces.next().expect("there is a piece"));
+				for piece in pieces {
+					words.push(std::mem::take(&mut word));
+					word.push_str(piece);
+				}
+			}
+			_ => word.push_str(&token_text(token, options)),
+		}
+	}
+
+	if !word.is_empty() {
+		words.push(word);
+	}
+	words
+}
+
+/// Writes a tag with a single space between its name and value. Tags which
+/// couldn't be parsed have their whitespace collapsed.
+fn format_tag(tag: &SyntaxNode) -> String {
+	let tokens: Vec<&SyntaxToken> = tag
+		.tokens()
+		.into_iter()
+		.filter(|token| !token.is_space())
+		.collect();
+	match tokens.as_slice() {
+		[left, identifier, value, right]
+			if left.is(&PdnTokenBody::LeftBracket) && right.is(&PdnTokenBody::RightBracket) =>
+		{
+			if let (Ok(PdnTokenBody::Identifier(identifier)), Ok(PdnTokenBody::String(value))) =
+				(identifier.body(), value.body())
+			{
+				return write_tag(&PdnTag::new(identifier, value));
+			}
+		}
+		_ => (),
+	}
+
+	tokens
+		.iter()
+		.map(|token| token.text())
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+/// Writes a game with one tag on each line, a blank line, and then the
+/// wrapped move text
+fn format_game(game: &SyntaxNode, options: &WriterOptions) -> String {
+	let mut pdn = String::new();
+	let mut move_text = Vec::new();
+	for child in &game.children {
+		match child {
+			SyntaxElement::Node(node) if node.kind == SyntaxKind::Tag => {
+				pdn.push_str(&format_tag(node));
+				pdn.push('\n');
+			}
+			SyntaxElement::Node(node) => move_text.extend(node.tokens()),
+			SyntaxElement::Token(token) => move_text.push(token),
+		}
+	}
+
+	let words = move_text_words(&move_text, options);
+	if !words.is_empty() {
+		if !pdn.is_empty() {
+			pdn.push('\n');
+		}
+		pdn.push_str(&wrap(&words, options.line_width));
+		pdn.push('\n');
+	}
+	pdn
+}
+
+/// Reformats a PDN file, re-wrapping the move text and spacing the tags
+/// consistently. Comments, variations, and text that can't be parsed are
+/// kept, but the whitespace between them is not.
+pub fn format(source: &str, options: &WriterOptions) -> String {
+	parse(source)
+		.nodes(SyntaxKind::Game)
+		.map(|game| format_game(game, options))
+		.collect::<Vec<_>>()
+		.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::writer::SquareNotation;
+
+	const PDN: &str = "[Event  \"Test\"]\r\n[Round \"1\"\n\n1. 11-15 {a  comment}  23-19!\n\
+	                   (1... 22-18 $2) 2. 8x11 junk (2. 9-13 *\n[Event \"Two\"]\n1. c3-d4 *";
+
+	#[test]
+	fn keeps_every_character() {
+		let tree = parse(PDN);
+		assert_eq!(tree.to_string(), PDN);
+		assert_eq!(tree.nodes(SyntaxKind::Game).count(), 2);
+		assert_eq!(parse("").to_string(), "");
+		assert_eq!(parse("{ unterminated").to_string(), "{ unterminated");
+	}
+
+	#[test]
+	fn edits_keep_the_rest_of_the_file() {
+		let mut tree = parse(PDN);
+		let game = tree.nodes_mut(SyntaxKind::Game).next().unwrap();
+		let first_move = game
+			.children()
+			.iter()
+			.position(
+				|child| matches!(child, SyntaxElement::Node(node) if node.kind() == SyntaxKind::Move),
+			)
+			.unwrap();
+		game.children_mut().splice(
+			first_move + 1..first_move + 1,
+			[
+				SyntaxElement::Token(SyntaxToken::space(" ")),
+				SyntaxElement::Token(SyntaxToken::comment("best")),
+			],
+		);
+		assert_eq!(
+			tree.to_string(),
+			PDN.replace("1. 11-15 {a", "1. 11-15 {best} {a")
+		);
+	}
+
+	#[test]
+	fn formats_files() {
+		let options = WriterOptions {
+			line_width: Some(30),
+			notation: SquareNotation::Numeric,
+		};
+		assert_eq!(
+			format(PDN, &options),
+			"[Event \"Test\"]\n[ Round \"1\"\n\n1. 11-15 {a comment} 23-19!\n(1... 22-18 $2) 2. 8x11 junk\n(2. 9-13 *\n\n\
+			 [Event \"Two\"]\n\n1. 11-15 *\n"
+		);
+	}
+}
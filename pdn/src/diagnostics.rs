@@ -0,0 +1,435 @@
+use std::fmt::{self, Display};
+
+use crate::grammar::{
+	BodyError, BodyPartError, GameError, GameMoveError, HeaderError, MoveError, PdnTagError,
+	VariationError,
+};
+use crate::tokens::{Color, PdnToken, PdnTokenBody, TokenError, TokenErrorType};
+use crate::ParseError;
+
+const RESULT_NOTE: &str = "games end with a result, such as `2-0`, `1-1`, `0-2`, or `*`";
+const SQUARE_NOTE: &str =
+	"squares are written as a number, such as `11`, or as a file and a rank, such as `c3`";
+const TAG_NOTE: &str = "tags are written as a name and a quoted value, such as `[Event \"Open\"]`";
+
+/// Whether a diagnostic stopped the file from being read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+	Error,
+	/// The file was read, but something in it is probably a mistake
+	Warning,
+}
+
+impl Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Error => f.write_str("error"),
+			Self::Warning => f.write_str("warning"),
+		}
+	}
+}
+
+/// A problem with a PDN file, and where it is in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	pub message: String,
+	/// The line the problem is on, starting from one
+	pub line: usize,
+	/// The column the problem starts at, in characters, starting from one
+	pub column: usize,
+	/// The line of source that the problem is on
+	pub excerpt: String,
+	/// The number of characters to underline, which is at least one
+	pub width: usize,
+	/// Extra help for fixing the problem
+	pub note: Option<String>,
+}
+
+/// Writes the diagnostic with the line it's on, such as:
+///
+/// ```text
+/// error: expected `]` to end the tag, found move number `1.`
+///  --> 1:14
+///   |
+/// 1 | [Event "One" 1. 11-15 *
+///   |              ^^
+/// ```
+impl Display for Diagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let gutter = " ".repeat(self.line.to_string().len());
+		writeln!(f, "{}: {}", self.severity, self.message)?;
+		writeln!(f, "{gutter}--> {}:{}", self.line, self.column)?;
+		writeln!(f, "{gutter} |")?;
+		writeln!(f, "{} | {}", self.line, self.excerpt)?;
+		write!(
+			f,
+			"{gutter} | {}{}",
+			" ".repeat(self.column - 1),
+			"^".repeat(self.width)
+		)?;
+		if let Some(note) = &self.note {
+			write!(f, "\n{gutter} = note: {note}")?;
+		}
+		Ok(())
+	}
+}
+
+/// A short description of a token, for error messages
+fn describe(token: &PdnTokenBody) -> String {
+	match token {
+		PdnTokenBody::MoveNumber(number, Color::White) => format!("move number `{number}.`"),
+		PdnTokenBody::MoveNumber(number, Color::Black) => format!("move number `{number}...`"),
+		PdnTokenBody::MoveSeparator => "`-`".to_string(),
+		PdnTokenBody::CaptureSeparator => "`x`".to_string(),
+		PdnTokenBody::AlphaSquare(file, rank) => format!("square `{file}{rank}`"),
+		PdnTokenBody::NumSquare(number) => format!("square `{number}`"),
+		PdnTokenBody::MoveStrength(strength) => format!("`{strength}`"),
+		PdnTokenBody::Nag(nag) => format!("`${nag}`"),
+		PdnTokenBody::LeftParenthesis => "`(`".to_string(),
+		PdnTokenBody::RightParenthesis => "`)`".to_string(),
+		PdnTokenBody::LeftBracket => "`[`".to_string(),
+		PdnTokenBody::RightBracket => "`]`".to_string(),
+		PdnTokenBody::GameResult(result) => format!("result `{}`", result.notation()),
+		PdnTokenBody::Setup(_) => "a setup".to_string(),
+		PdnTokenBody::String(_) => "a string".to_string(),
+		PdnTokenBody::Comment(_) => "a comment".to_string(),
+		PdnTokenBody::Identifier(identifier) => format!("`{identifier}`"),
+		PdnTokenBody::Space(_) => "whitespace".to_string(),
+	}
+}
+
+/// Makes a diagnostic for the `len` characters from `start` in the source
+pub(crate) fn locate(
+	source: &str,
+	start: usize,
+	len: usize,
+	severity: Severity,
+	message: String,
+	note: Option<&str>,
+) -> Diagnostic {
+	let mut line = 1;
+	let (mut line_start, mut line_start_byte) = (0, 0);
+	for (index, (byte, c)) in source.char_indices().enumerate() {
+		if index == start {
+			break;
+		}
+		if c == '\n' {
+			line += 1;
+			line_start = index + 1;
+			line_start_byte = byte + 1;
+		}
+	}
+
+	let excerpt = source[line_start_byte..]
+		.lines()
+		.next()
+		.unwrap_or_default()
+		.to_string();
+	let column = start - line_start + 1;
+	let remaining = excerpt.chars().count().saturating_sub(column - 1);
+	Diagnostic {
+		severity,
+		message,
+		line,
+		column,
+		excerpt,
+		width: len.min(remaining).max(1),
+		note: note.map(str::to_string),
+	}
+}
+
+/// Collects the diagnostics of the nested errors
+struct Collector<'a> {
+	source: &'a str,
+	diagnostics: Vec<Diagnostic>,
+}
+
+impl Collector<'_> {
+	/// Adds a diagnostic for the characters from `start`
+	fn push(&mut self, start: usize, len: usize, message: String, note: Option<&str>) {
+		let diagnostic = locate(self.source, start, len, Severity::Error, message, note);
+		self.diagnostics.push(diagnostic);
+	}
+
+	/// Adds a diagnostic at a token, or at the end of the source if there isn't
+	/// a token
+	fn push_at(&mut self, token: Option<&PdnToken>, message: String, note: Option<&str>) {
+		match token {
+			Some(token) => self.push(token.header.start(), token.header.len(), message, note),
+			None => self.push(self.source.chars().count(), 0, message, note),
+		}
+	}
+
+	fn token_error(&mut self, error: &TokenError) {
+		let (message, note) = match error.error_type() {
+			TokenErrorType::InvalidNumber(number) => (
+				format!("`{number}` is too large to be a square"),
+				Some("squares are numbered from 1 to 32"),
+			),
+			TokenErrorType::InvalidNag => (
+				"expected a number after `$`".to_string(),
+				Some("NAGs are written as `$` and a number, such as `$1`"),
+			),
+			TokenErrorType::InvalidSquare => (
+				"expected a rank after the file of the square".to_string(),
+				Some("squares may be written as a file and a rank, such as `c3`"),
+			),
+			TokenErrorType::UnterminatedSetup => ("the setup has no closing `/`".to_string(), None),
+			TokenErrorType::UnterminatedComment => {
+				("the comment has no closing `}`".to_string(), None)
+			}
+			TokenErrorType::UnterminatedString => {
+				("the string has no closing quote".to_string(), None)
+			}
+			TokenErrorType::LowercaseIdentifier => (
+				"the name of the tag starts with a lowercase letter".to_string(),
+				Some(TAG_NOTE),
+			),
+			TokenErrorType::InvalidToken => ("unexpected characters".to_string(), None),
+		};
+		let header = error.header();
+		self.push(header.start(), header.len(), message, note);
+	}
+
+	fn tag_error(&mut self, error: &PdnTagError) {
+		match error {
+			PdnTagError::EndOfFile => {
+				self.push_at(None, "expected a tag".to_string(), Some(TAG_NOTE))
+			}
+			PdnTagError::NoStartBracket(token) => self.push_at(
+				Some(token),
+				format!(
+					"expected `[` to start a tag, found {}",
+					describe(&token.body)
+				),
+				Some(TAG_NOTE),
+			),
+			PdnTagError::Unterminated(tokens) => self.push_at(
+				tokens.first(),
+				"the file ended in the middle of this tag".to_string(),
+				Some(TAG_NOTE),
+			),
+			PdnTagError::NoIdentifier(token) => self.push_at(
+				Some(token),
+				format!(
+					"expected the name of the tag, found {}",
+					describe(&token.body)
+				),
+				Some(TAG_NOTE),
+			),
+			PdnTagError::NoString(token) => self.push_at(
+				Some(token),
+				format!(
+					"expected the value of the tag, found {}",
+					describe(&token.body)
+				),
+				Some(TAG_NOTE),
+			),
+			PdnTagError::NoEndBracket(token) => self.push_at(
+				Some(token),
+				format!(
+					"expected `]` to end the tag, found {}",
+					describe(&token.body)
+				),
+				Some(TAG_NOTE),
+			),
+		}
+	}
+
+	fn header_error(&mut self, error: &HeaderError) {
+		for tag in error {
+			if let Err(error) = tag {
+				self.tag_error(error);
+			}
+		}
+	}
+
+	/// Adds a diagnostic for a missing square, where `token` was found instead
+	fn square_error(&mut self, token: &Option<PdnToken>, expected: &str) {
+		let message = match token {
+			Some(token) => format!("expected {expected}, found {}", describe(&token.body)),
+			None => format!("expected {expected}"),
+		};
+		self.push_at(token.as_ref(), message, Some(SQUARE_NOTE));
+	}
+
+	fn move_error(&mut self, error: &MoveError) {
+		match error {
+			MoveError::EndOfFile => self.push_at(None, "expected a move".to_string(), None),
+			MoveError::NoStartSquare(token) => {
+				self.square_error(token, "the square the move starts on")
+			}
+			MoveError::NoEndSquare(token) => {
+				self.square_error(token, "the square the move ends on")
+			}
+			MoveError::InvalidCaptureSquares(tokens) => {
+				for token in tokens {
+					self.square_error(token, "a square after `x`");
+				}
+			}
+			MoveError::NoMoveSeparator(token) => {
+				let message = match token {
+					Some(token) => format!("expected `-` or `x`, found {}", describe(&token.body)),
+					None => "expected `-` or `x`".to_string(),
+				};
+				self.push_at(
+					token.as_ref(),
+					message,
+					Some("moves are written as squares separated by `-`, or `x` for captures"),
+				);
+			}
+		}
+	}
+
+	fn body_error(&mut self, error: &BodyError) {
+		for part in error {
+			let Err(error) = part else {
+				continue;
+			};
+
+			match error {
+				BodyPartError::EndOfFile | BodyPartError::BadMove(GameMoveError::EndOfFile) => {
+					self.push_at(None, "expected a move".to_string(), None)
+				}
+				BodyPartError::InvalidToken(token) => self.push_at(
+					Some(token),
+					format!("unexpected {} in the moves", describe(&token.body)),
+					None,
+				),
+				BodyPartError::BadMove(GameMoveError::BadMove(error)) => self.move_error(error),
+				BodyPartError::BadMove(GameMoveError::NoMoveNumber(token)) => self.push_at(
+					Some(token),
+					"expected a move number before the move".to_string(),
+					Some("moves are numbered such as `1. 11-15`, or `1... 23-19` for the second player"),
+				),
+				BodyPartError::BadVariation(error) => self.variation_error(error),
+			}
+		}
+	}
+
+	fn variation_error(&mut self, error: &VariationError) {
+		match error {
+			VariationError::UnexpectedEnd(body) => {
+				self.body_error(body);
+				self.push_at(
+					None,
+					"the file ended in the middle of a variation".to_string(),
+					Some("variations end with `)`"),
+				);
+			}
+			VariationError::Unclosed(left_parenthesis, body) => {
+				self.body_error(body);
+				self.push(
+					left_parenthesis.start(),
+					left_parenthesis.len(),
+					"this variation isn't closed".to_string(),
+					Some("variations end with `)`"),
+				);
+			}
+			VariationError::BadBody(body) => self.body_error(body),
+		}
+	}
+
+	fn game_error(&mut self, error: &GameError) {
+		if let Err(error) = error.header() {
+			self.header_error(error);
+		}
+
+		match error.body() {
+			// the game ended without a result, which is reported below
+			Err(VariationError::UnexpectedEnd(body)) => self.body_error(body),
+			Err(error) => self.variation_error(error),
+			Ok(_) => (),
+		}
+
+		if let Err(token) = error.result() {
+			let message = match token {
+				Some(token) => format!(
+					"expected the result of the game, found {}",
+					describe(&token.body)
+				),
+				None => "expected the result of the game".to_string(),
+			};
+			self.push_at(token.as_ref(), message, Some(RESULT_NOTE));
+		}
+	}
+}
+
+/// Describes every problem in a PDN file that couldn't be parsed. `source`
+/// must be the text that was parsed.
+pub fn diagnostics(source: &str, error: &ParseError) -> Vec<Diagnostic> {
+	let mut collector = Collector {
+		source,
+		diagnostics: Vec::new(),
+	};
+
+	match error {
+		ParseError::Tokens(errors) => {
+			for error in errors {
+				collector.token_error(error);
+			}
+		}
+		ParseError::Games(games) => {
+			for game in games {
+				if let Err(error) = game {
+					collector.game_error(error);
+				}
+			}
+		}
+	}
+
+	collector.diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn recovers_after_errors() {
+		let pdn = "[Event \"One\" 1. 11-15 *\n\n[Event \"Two\"]\n1. 11-15 23-19 *\n";
+		let Err(error) = crate::parse(pdn) else {
+			panic!("the first game's tag isn't closed");
+		};
+		let ParseError::Games(games) = &error else {
+			panic!("the tokens are valid");
+		};
+		assert_eq!(games.len(), 2);
+		assert!(games[0].is_err());
+		assert!(games[1].is_ok());
+
+		let diagnostics = diagnostics(pdn, &error);
+		assert_eq!(
+			diagnostics[0].message,
+			"expected `]` to end the tag, found move number `1.`"
+		);
+		assert_eq!((diagnostics[0].line, diagnostics[0].column), (1, 14));
+		assert_eq!(
+			diagnostics[0].to_string(),
+			"error: expected `]` to end the tag, found move number `1.`\n \
+			 --> 1:14\n  |\n1 | [Event \"One\" 1. 11-15 *\n  |              ^^\n  \
+			 = note: tags are written as a name and a quoted value, such as `[Event \"Open\"]`"
+		);
+	}
+
+	#[test]
+	fn reports_missing_results() {
+		let pdn = "[Event \"One\"]\n1. 11-15 (1. 9-13\n\n[Event \"Two\"]\n1. 11-15 *";
+		let Err(error) = crate::parse(pdn) else {
+			panic!("the first game has no result");
+		};
+		let diagnostics = diagnostics(pdn, &error);
+		let messages: Vec<_> = diagnostics
+			.iter()
+			.map(|diagnostic| (diagnostic.line, diagnostic.message.as_str()))
+			.collect();
+		assert_eq!(
+			messages,
+			[
+				(2, "this variation isn't closed"),
+				(4, "expected the result of the game, found `[`"),
+			]
+		);
+	}
+}
@@ -0,0 +1,50 @@
+use std::borrow::Cow;
+
+/// The characters of the bytes from `0x80` to `0x9F` in Windows-1252. The
+/// five bytes that Windows-1252 doesn't use are read as the control
+/// characters with the same value, like in Latin-1.
+const WINDOWS_1252: [char; 32] = [
+	'\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+	'\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+	'\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+	'\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+fn windows_1252(byte: u8) -> char {
+	match byte {
+		0x80..=0x9F => WINDOWS_1252[(byte - 0x80) as usize],
+		_ => byte as char,
+	}
+}
+
+/// Reads the text of a PDN file. Files which aren't valid UTF-8 are read as
+/// Windows-1252, which includes Latin-1, and is used by many older files.
+/// Each byte of such a file becomes one character, so the positions in a
+/// [`TokenHeader`](crate::TokenHeader) are also the byte offsets in the
+/// file.
+pub fn decode(bytes: &[u8]) -> Cow<'_, str> {
+	match std::str::from_utf8(bytes) {
+		Ok(text) => Cow::Borrowed(text),
+		Err(_) => Cow::Owned(bytes.iter().copied().map(windows_1252).collect()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn decodes_legacy_text() {
+		assert_eq!(decode("Sjaak Dürr".as_bytes()), "Sjaak Dürr");
+		assert_eq!(
+			decode(b"Sjaak D\xFCrr \x93Open\x94"),
+			"Sjaak Dürr \u{201C}Open\u{201D}"
+		);
+
+		let pdn = b"[White \"Dr\xF6ge\"]\n1. 11-15 *";
+		let file = crate::parse(&decode(pdn)).unwrap();
+		assert_eq!(file.games()[0].white(), Some("Dröge"));
+		let game = crate::games(&pdn[..]).next().unwrap().unwrap();
+		assert_eq!(game.white(), Some("Dröge"));
+	}
+}
@@ -1,17 +1,20 @@
-use std::{iter::Peekable, sync::Arc};
+use std::{iter::Peekable, sync::Arc, time::Duration};
 
-use crate::tokens::{Color, PdnToken, PdnTokenBody, TokenHeader};
+use crate::commands::{take_commands, Evaluation};
+use crate::nag::Nag;
+use crate::tokens::{Color, GameResult, PdnToken, PdnTokenBody, TokenHeader};
+use crate::ParseMode;
 
 #[derive(Debug, Clone)]
 pub struct PdnFile {
 	games: Vec<Game>,
-	game_separators: Vec<TokenHeader>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Game {
 	header: Vec<PdnTag>,
 	body: Vec<BodyPart>,
+	result: (TokenHeader, GameResult),
 }
 
 #[derive(Debug, Clone)]
@@ -31,7 +34,7 @@ pub enum BodyPart {
 	Variation(Variation),
 	Comment(TokenHeader, Arc<str>),
 	Setup(TokenHeader, Arc<str>),
-	Nag(TokenHeader, usize),
+	Nag(TokenHeader, Nag),
 }
 
 #[derive(Debug, Clone)]
@@ -46,6 +49,8 @@ pub struct GameMove {
 	move_number: Option<(TokenHeader, usize, Color)>,
 	game_move: Move,
 	move_strength: Option<(TokenHeader, Arc<str>)>,
+	clock: Option<Duration>,
+	evaluation: Option<Evaluation>,
 }
 
 #[derive(Debug, Clone)]
@@ -60,6 +65,205 @@ pub enum Square {
 	Num(TokenHeader, u8),
 }
 
+impl PdnFile {
+	pub fn new(games: Vec<Game>) -> Self {
+		Self { games }
+	}
+
+	pub fn games(&self) -> &[Game] {
+		&self.games
+	}
+
+	pub fn into_games(self) -> Vec<Game> {
+		self.games
+	}
+}
+
+impl Game {
+	pub fn new(header: Vec<PdnTag>, body: Vec<BodyPart>, result: GameResult) -> Self {
+		Self {
+			header,
+			body,
+			result: (TokenHeader::default(), result),
+		}
+	}
+
+	/// The tag pairs, in the order they were written
+	pub fn tags(&self) -> &[PdnTag] {
+		&self.header
+	}
+
+	/// The moves, comments, and variations, in the order they were written
+	pub fn body(&self) -> &[BodyPart] {
+		&self.body
+	}
+
+	/// The result written at the end of the move text. This may not agree
+	/// with the `Result` tag.
+	pub fn result(&self) -> GameResult {
+		self.result.1
+	}
+}
+
+impl PdnTag {
+	pub fn new(identifier: &str, string: &str) -> Self {
+		Self {
+			left_bracket: TokenHeader::default(),
+			identifier_token: TokenHeader::default(),
+			string_token: TokenHeader::default(),
+			right_bracket: TokenHeader::default(),
+			identifier: identifier.into(),
+			string: string.into(),
+		}
+	}
+
+	pub fn identifier(&self) -> &str {
+		&self.identifier
+	}
+
+	/// The value of the tag, without quotes or escapes
+	pub fn value(&self) -> &str {
+		&self.string
+	}
+
+	/// Where the tag is in the source, from one bracket to the other
+	pub(crate) fn span(&self) -> TokenHeader {
+		self.left_bracket.through(self.right_bracket)
+	}
+
+	/// Where the tag's name is in the source
+	pub fn identifier_span(&self) -> TokenHeader {
+		self.identifier_token
+	}
+
+	/// Where the tag's value is in the source, including the quotes
+	pub fn value_span(&self) -> TokenHeader {
+		self.string_token
+	}
+}
+
+impl Variation {
+	pub fn new(body: Vec<BodyPart>) -> Self {
+		Self {
+			left_parenthesis: TokenHeader::default(),
+			body,
+			right_parenthesis: TokenHeader::default(),
+		}
+	}
+
+	pub fn body(&self) -> &[BodyPart] {
+		&self.body
+	}
+
+	/// Where the variation is in the source, from one parenthesis to the other
+	pub fn span(&self) -> TokenHeader {
+		self.left_parenthesis.through(self.right_parenthesis)
+	}
+}
+
+impl GameMove {
+	pub fn new(game_move: Move, move_strength: Option<&str>) -> Self {
+		Self {
+			move_number: None,
+			game_move,
+			move_strength: move_strength.map(|strength| (TokenHeader::default(), strength.into())),
+			clock: None,
+			evaluation: None,
+		}
+	}
+
+	/// Sets the time left on the player's clock after the move
+	pub fn with_clock(mut self, clock: Duration) -> Self {
+		self.clock = Some(clock);
+		self
+	}
+
+	pub fn with_evaluation(mut self, evaluation: Evaluation) -> Self {
+		self.evaluation = Some(evaluation);
+		self
+	}
+
+	/// The move number that was written before the move, if there was one
+	pub fn move_number(&self) -> Option<(usize, Color)> {
+		self.move_number.map(|(_, number, color)| (number, color))
+	}
+
+	pub fn game_move(&self) -> &Move {
+		&self.game_move
+	}
+
+	/// Symbols such as `!` or `?!` which were written after the move
+	pub fn move_strength(&self) -> Option<&str> {
+		self.move_strength
+			.as_ref()
+			.map(|(_, strength)| strength.as_ref())
+	}
+
+	/// The time left on the player's clock after the move, from a
+	/// `[%clk 0:05:32]` command in the comment after the move
+	pub fn clock(&self) -> Option<Duration> {
+		self.clock
+	}
+
+	/// The evaluation of the position after the move, from an
+	/// `[%eval +0.43/17]` command in the comment after the move
+	pub fn evaluation(&self) -> Option<Evaluation> {
+		self.evaluation
+	}
+}
+
+impl Move {
+	/// A move which isn't a capture, with synthetic token headers
+	pub fn normal(from: Square, to: Square) -> Self {
+		Self::Normal(from, TokenHeader::default(), to)
+	}
+
+	/// A capture, with synthetic token headers
+	pub fn capture(from: Square, to: impl IntoIterator<Item = Square>) -> Self {
+		let to = to
+			.into_iter()
+			.map(|square| (TokenHeader::default(), square))
+			.collect();
+		Self::Capture(from, to)
+	}
+
+	/// Where the move is in the source, from the first square to the last
+	pub(crate) fn span(&self) -> TokenHeader {
+		let squares = self.squares();
+		let first = squares.first().expect("a move has squares").header();
+		let last = squares.last().expect("a move has squares").header();
+		first.through(last)
+	}
+
+	/// The squares the piece stands on, from the start to the end
+	pub fn squares(&self) -> Vec<&Square> {
+		match self {
+			Self::Normal(from, _, to) => vec![from, to],
+			Self::Capture(from, to) => std::iter::once(from)
+				.chain(to.iter().map(|(_, square)| square))
+				.collect(),
+		}
+	}
+}
+
+impl Square {
+	/// A numeric square, with a synthetic token header
+	pub fn num(number: u8) -> Self {
+		Self::Num(TokenHeader::default(), number)
+	}
+
+	/// An alphanumeric square, such as `c3`, with a synthetic token header
+	pub fn alpha(file: char, rank: char) -> Self {
+		Self::Alpha(TokenHeader::default(), file, rank)
+	}
+
+	pub(crate) fn header(&self) -> TokenHeader {
+		match *self {
+			Self::Alpha(header, ..) | Self::Num(header, _) => header,
+		}
+	}
+}
+
 /// Returns `Ok` if parsed successfully. If there are no tokens left,
 /// `Err(None)` is returned. If the next token is not a square position, then
 /// `Err(Some(token))` is returned.
@@ -83,7 +287,7 @@ pub enum MoveError {
 	NoStartSquare(Option<PdnToken>),
 	NoEndSquare(Option<PdnToken>),
 	InvalidCaptureSquares(Vec<Option<PdnToken>>),
-	NoMoveSeparator,
+	NoMoveSeparator(Option<PdnToken>),
 }
 
 fn parse_normal_move(
@@ -91,7 +295,7 @@ fn parse_normal_move(
 	scanner: &mut impl Iterator<Item = PdnToken>,
 ) -> Result<Move, MoveError> {
 	let Some(separator) = scanner.next() else {
-		return Err(MoveError::NoMoveSeparator);
+		return Err(MoveError::NoMoveSeparator(None));
 	};
 	let square = match parse_square(scanner) {
 		Ok(square) => square,
@@ -133,14 +337,14 @@ fn parse_move(scanner: &mut Peekable<impl Iterator<Item = PdnToken>>) -> Result<
 	};
 
 	let Some(token) = scanner.peek() else {
-		return Err(MoveError::NoMoveSeparator);
+		return Err(MoveError::NoMoveSeparator(None));
 	};
 	let body = &token.body;
 
 	match body {
 		PdnTokenBody::MoveSeparator => parse_normal_move(square, scanner),
 		PdnTokenBody::CaptureSeparator => parse_capture_move(square, scanner),
-		_ => Err(MoveError::NoMoveSeparator),
+		_ => Err(MoveError::NoMoveSeparator(Some(token.clone()))),
 	}
 }
 
@@ -148,6 +352,10 @@ fn parse_move(scanner: &mut Peekable<impl Iterator<Item = PdnToken>>) -> Result<
 pub enum GameMoveError {
 	EndOfFile,
 	BadMove(MoveError),
+	/// In strict mode, the first player's moves, and moves after a comment or
+	/// a variation, must have a move number. This is the first token of the
+	/// move.
+	NoMoveNumber(PdnToken),
 }
 
 fn whitespace_if_found(
@@ -163,6 +371,7 @@ fn whitespace_if_found(
 
 fn parse_game_move(
 	scanner: &mut Peekable<impl Iterator<Item = PdnToken>>,
+	needs_number: bool,
 ) -> Result<GameMove, GameMoveError> {
 	let Some(next_token) = scanner.peek() else {
 		return Err(GameMoveError::EndOfFile);
@@ -173,9 +382,10 @@ fn parse_game_move(
 		_ => None,
 	};
 
-	if move_number.is_some() {
-		scanner.next();
-	}
+	let first_token = match move_number {
+		Some(_) => scanner.next(),
+		None => Some(next_token.clone()),
+	};
 
 	whitespace_if_found(scanner);
 
@@ -196,10 +406,16 @@ fn parse_game_move(
 	}
 
 	match game_move {
+		// the move is read first, so that parsing continues after it
+		Ok(_) if needs_number && move_number.is_none() => Err(GameMoveError::NoMoveNumber(
+			first_token.expect("the token was peeked"),
+		)),
 		Ok(game_move) => Ok(GameMove {
 			move_number,
 			game_move,
 			move_strength,
+			clock: None,
+			evaluation: None,
 		}),
 		Err(error) => Err(GameMoveError::BadMove(error)),
 	}
@@ -208,15 +424,44 @@ fn parse_game_move(
 #[derive(Debug, Clone)]
 pub enum VariationError {
 	UnexpectedEnd(BodyError),
+	/// The game ended before the variation starting at this parenthesis was
+	/// closed
+	Unclosed(TokenHeader, BodyError),
 	BadBody(BodyError),
 }
 
 fn parse_variation(
 	scanner: &mut Peekable<impl Iterator<Item = PdnToken>>,
+	mode: ParseMode,
 ) -> Result<Variation, VariationError> {
 	let left_parenthesis = scanner.next().expect("should start with left paren").header;
-	let body = parse_body_until(scanner, PdnTokenBody::RightParenthesis)?;
-	let right_parenthesis = scanner.next().expect("should end with right paren").header;
+	let body = parse_body_until(scanner, mode, |body| {
+		matches!(
+			body,
+			PdnTokenBody::RightParenthesis
+				| PdnTokenBody::GameResult(_)
+				| PdnTokenBody::LeftBracket
+		)
+	});
+
+	let closed = scanner
+		.peek()
+		.is_some_and(|token| token.body == PdnTokenBody::RightParenthesis);
+	if !closed {
+		return match body {
+			Ok(body) => Err(VariationError::Unclosed(
+				left_parenthesis,
+				body.into_iter().map(Ok).collect(),
+			)),
+			Err(VariationError::BadBody(body)) => {
+				Err(VariationError::Unclosed(left_parenthesis, body))
+			}
+			Err(error) => Err(error),
+		};
+	}
+
+	let right_parenthesis = scanner.next().expect("the token was peeked").header;
+	let body = body?;
 
 	Ok(Variation {
 		left_parenthesis,
@@ -235,6 +480,8 @@ pub enum BodyPartError {
 
 fn parse_body_part(
 	scanner: &mut Peekable<impl Iterator<Item = PdnToken>>,
+	mode: ParseMode,
+	needs_number: bool,
 ) -> Result<BodyPart, BodyPartError> {
 	let Some(token) = scanner.peek() else {
 		return Err(BodyPartError::EndOfFile);
@@ -243,18 +490,28 @@ fn parse_body_part(
 	match &token.body {
 		PdnTokenBody::MoveNumber(..)
 		| PdnTokenBody::AlphaSquare(..)
-		| PdnTokenBody::NumSquare(..) => match parse_game_move(scanner) {
+		| PdnTokenBody::NumSquare(..) => match parse_game_move(scanner, needs_number) {
 			Ok(mov) => Ok(BodyPart::Move(mov)),
 			Err(error) => Err(BodyPartError::BadMove(error)),
 		},
-		PdnTokenBody::LeftParenthesis => match parse_variation(scanner) {
+		PdnTokenBody::LeftParenthesis => match parse_variation(scanner, mode) {
 			Ok(variation) => Ok(BodyPart::Variation(variation)),
 			Err(error) => Err(BodyPartError::BadVariation(error)),
 		},
-		PdnTokenBody::Comment(string) => Ok(BodyPart::Comment(token.header, string.clone())),
-		PdnTokenBody::Setup(string) => Ok(BodyPart::Setup(token.header, string.clone())),
-		PdnTokenBody::Nag(number) => Ok(BodyPart::Nag(token.header, *number)),
-		_ => Err(BodyPartError::InvalidToken(token.clone())),
+		PdnTokenBody::Comment(_) | PdnTokenBody::Setup(_) | PdnTokenBody::Nag(_) => {
+			let token = scanner.next().expect("the token was peeked");
+			match token.body {
+				PdnTokenBody::Comment(string) => Ok(BodyPart::Comment(token.header, string)),
+				PdnTokenBody::Setup(string) => Ok(BodyPart::Setup(token.header, string)),
+				PdnTokenBody::Nag(number) => {
+					Ok(BodyPart::Nag(token.header, Nag::from_code(number)))
+				}
+				_ => unreachable!("the token was peeked"),
+			}
+		}
+		_ => Err(BodyPartError::InvalidToken(
+			scanner.next().expect("the token was peeked"),
+		)),
 	}
 }
 
@@ -262,9 +519,13 @@ pub type BodyError = Vec<Result<BodyPart, BodyPartError>>;
 
 fn parse_body_until(
 	scanner: &mut Peekable<impl Iterator<Item = PdnToken>>,
-	until: PdnTokenBody,
+	mode: ParseMode,
+	until: fn(&PdnTokenBody) -> bool,
 ) -> Result<Vec<BodyPart>, VariationError> {
 	let mut parts = Vec::new();
+	// only the second player's moves may leave out the move number, when
+	// they come right after the first player's move
+	let mut needs_number = mode == ParseMode::Strict;
 
 	loop {
 		whitespace_if_found(scanner);
@@ -273,11 +534,42 @@ fn parse_body_until(
 			return Err(VariationError::UnexpectedEnd(parts));
 		};
 
-		if token.body == until {
+		if until(&token.body) {
 			break;
 		}
 
-		parts.push(parse_body_part(scanner));
+		let part = parse_body_part(scanner, mode, needs_number);
+		needs_number = mode == ParseMode::Strict
+			&& match &part {
+				Ok(BodyPart::Move(game_move)) => {
+					!matches!(game_move.move_number(), Some((_, Color::White)))
+				}
+				Ok(BodyPart::Nag(..)) => needs_number,
+				_ => true,
+			};
+
+		// clock and evaluation commands are moved from a comment to its move,
+		// which may have NAGs between them
+		let last_move = parts
+			.iter_mut()
+			.rev()
+			.find(|part| !matches!(part, Ok(BodyPart::Nag(..))));
+		let part = match (part, last_move) {
+			(Ok(BodyPart::Comment(header, comment)), Some(Ok(BodyPart::Move(game_move)))) => {
+				let commands = take_commands(&comment);
+				let found = commands.clock.is_some() || commands.evaluation.is_some();
+				game_move.clock = commands.clock.or(game_move.clock);
+				game_move.evaluation = commands.evaluation.or(game_move.evaluation);
+				match (found, commands.rest.is_empty()) {
+					(false, _) => Some(Ok(BodyPart::Comment(header, comment))),
+					(true, true) => None,
+					(true, false) => Some(Ok(BodyPart::Comment(header, commands.rest.into()))),
+				}
+			}
+			(part, _) => Some(part),
+		};
+
+		parts.extend(part);
 		whitespace_if_found(scanner);
 	}
 
@@ -293,9 +585,9 @@ pub enum PdnTagError {
 	EndOfFile,
 	NoStartBracket(PdnToken),
 	Unterminated(Vec<PdnToken>),
-	NoIdentifier,
-	NoString,
-	NoEndBracket,
+	NoIdentifier(PdnToken),
+	NoString(PdnToken),
+	NoEndBracket(PdnToken),
 }
 
 fn parse_pdn_tag(
@@ -318,7 +610,7 @@ fn parse_pdn_tag(
 	};
 
 	let PdnTokenBody::Identifier(identifier) = &identifier_token.body else {
-		return Err(PdnTagError::NoIdentifier);
+		return Err(PdnTagError::NoIdentifier(identifier_token));
 	};
 
 	whitespace_if_found(scanner);
@@ -331,7 +623,7 @@ fn parse_pdn_tag(
 	};
 
 	let PdnTokenBody::String(value) = &value_token.body else {
-		return Err(PdnTagError::NoIdentifier);
+		return Err(PdnTagError::NoString(value_token));
 	};
 
 	whitespace_if_found(scanner);
@@ -345,7 +637,7 @@ fn parse_pdn_tag(
 	};
 
 	if right_bracket.body != PdnTokenBody::RightBracket {
-		return Err(PdnTagError::NoEndBracket);
+		return Err(PdnTagError::NoEndBracket(right_bracket));
 	}
 
 	whitespace_if_found(scanner);
@@ -367,15 +659,10 @@ fn parse_header(
 ) -> Result<Vec<PdnTag>, HeaderError> {
 	let mut tags = Vec::new();
 
-	loop {
-		let Some(token) = scanner.peek() else {
-			break;
-		};
-
-		if token.body != PdnTokenBody::LeftBracket {
-			break;
-		}
-
+	while scanner
+		.peek()
+		.is_some_and(|token| token.body == PdnTokenBody::LeftBracket)
+	{
 		tags.push(parse_pdn_tag(scanner));
 	}
 
@@ -390,54 +677,86 @@ fn parse_header(
 pub struct GameError {
 	header: Result<Vec<PdnTag>, HeaderError>,
 	body: Result<Vec<BodyPart>, VariationError>,
+	/// The token found instead of a result, or `None` if the file ended
+	result: Result<GameResult, Option<PdnToken>>,
+}
+
+impl GameError {
+	pub fn header(&self) -> &Result<Vec<PdnTag>, HeaderError> {
+		&self.header
+	}
+
+	pub fn body(&self) -> &Result<Vec<BodyPart>, VariationError> {
+		&self.body
+	}
+
+	pub fn result(&self) -> &Result<GameResult, Option<PdnToken>> {
+		&self.result
+	}
 }
 
-fn parse_game(scanner: &mut Peekable<impl Iterator<Item = PdnToken>>) -> Result<Game, GameError> {
+fn parse_game(
+	scanner: &mut Peekable<impl Iterator<Item = PdnToken>>,
+	mode: ParseMode,
+) -> Result<Game, GameError> {
 	let header = parse_header(scanner);
-	let body = parse_body_until(scanner, PdnTokenBody::Asterisk);
+	// a tag can't be in the move text, so it must be the start of the next
+	// game, after a game whose result is missing
+	let body = parse_body_until(scanner, mode, |body| {
+		matches!(
+			body,
+			PdnTokenBody::GameResult(_) | PdnTokenBody::LeftBracket
+		)
+	});
+	let result = match scanner.peek() {
+		Some(PdnToken {
+			header,
+			body: PdnTokenBody::GameResult(result),
+		}) => {
+			let result = (*header, *result);
+			scanner.next();
+			Ok(result)
+		}
+		token => Err(token.cloned()),
+	};
 	whitespace_if_found(scanner);
 
-	if let Ok(header) = header {
-		if let Ok(body) = body {
-			Ok(Game { header, body })
-		} else {
-			Err(GameError {
-				header: Ok(header),
-				body,
-			})
-		}
-	} else {
-		Err(GameError { header, body })
+	match (header, body, result) {
+		(Ok(header), Ok(body), Ok(result)) => Ok(Game {
+			header,
+			body,
+			result,
+		}),
+		(header, body, result) => Err(GameError {
+			header,
+			body,
+			result: result.map(|(_, result)| result),
+		}),
 	}
 }
 
 pub type PdnError = Vec<Result<Game, GameError>>;
 
-fn parse(scanner: &mut impl Iterator<Item = PdnToken>) -> Result<PdnFile, PdnError> {
+pub(crate) fn parse(
+	scanner: &mut impl Iterator<Item = PdnToken>,
+	mode: ParseMode,
+) -> Result<PdnFile, PdnError> {
 	let mut scanner = scanner.peekable();
 	let mut games = Vec::new();
-	let mut game_separators = Vec::new();
 
 	loop {
-		let Some(token) = scanner.peek() else {
-			break;
-		};
-
-		if token.body != PdnTokenBody::LeftBracket {
+		whitespace_if_found(&mut scanner);
+		if scanner.peek().is_none() {
 			break;
 		}
 
-		games.push(parse_game(&mut scanner));
-		game_separators.push(scanner.next().unwrap().header);
+		games.push(parse_game(&mut scanner, mode));
 	}
 
 	if games.iter().any(|r| r.is_err()) {
 		Err(games)
 	} else {
 		let games = games.iter().map(|r| r.as_ref().cloned().unwrap()).collect();
-		Ok(PdnFile {
-			games,
-			game_separators,
-		})
+		Ok(PdnFile { games })
 	}
 }
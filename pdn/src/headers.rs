@@ -0,0 +1,170 @@
+use std::ops::Range;
+
+use crate::grammar::PdnTag;
+use crate::tokens::{PdnScanner, PdnTokenBody};
+
+/// The tags of a game, and where the game is in the file, found without
+/// parsing the moves
+#[derive(Debug, Clone)]
+pub struct GameHeaders {
+	tags: Vec<PdnTag>,
+	range: Range<usize>,
+}
+
+impl GameHeaders {
+	/// The tag pairs, in the order they were written
+	pub fn tags(&self) -> &[PdnTag] {
+		&self.tags
+	}
+
+	/// The value of a tag. Tag names are matched without regard to case. If
+	/// the tag was written more than once, the first value is used.
+	pub fn tag(&self, name: &str) -> Option<&str> {
+		self.tags
+			.iter()
+			.find(|tag| tag.identifier().eq_ignore_ascii_case(name))
+			.map(|tag| tag.value())
+	}
+
+	/// The bytes of the source that the game was written in, so that the game
+	/// can be parsed later, with `parse(&source[headers.range()])`
+	pub fn range(&self) -> Range<usize> {
+		self.range.clone()
+	}
+}
+
+/// A tag which is being read
+#[derive(Default)]
+struct PartialTag {
+	identifier: Option<String>,
+	value: Option<String>,
+}
+
+/// Finds the tags and positions of every game in a PDN file. The moves are
+/// only read far enough to find where each game ends, and invalid tokens are
+/// skipped, so this is much faster than parsing the whole file.
+pub fn scan_headers(source: &str) -> Vec<GameHeaders> {
+	// token positions are in characters, but ranges are in bytes
+	let mut bytes = source
+		.char_indices()
+		.map(|(byte, _)| byte)
+		.enumerate()
+		.peekable();
+	let mut byte_at = |char_index: usize| {
+		while let Some(&(index, byte)) = bytes.peek() {
+			if index >= char_index {
+				return byte;
+			}
+			bytes.next();
+		}
+		source.len()
+	};
+
+	let mut games = Vec::new();
+	let mut start = None;
+	let mut tags = Vec::new();
+	let mut tag: Option<PartialTag> = None;
+	let mut in_moves = false;
+	let mut depth = 0usize;
+	for token in PdnScanner::new(source).flatten() {
+		if matches!(token.body, PdnTokenBody::Space(_)) {
+			continue;
+		}
+
+		let token_start = token.header.start();
+		// a tag after the moves starts the next game, if the result is missing
+		if in_moves && depth == 0 && token.body == PdnTokenBody::LeftBracket {
+			let end = byte_at(token_start);
+			games.push(GameHeaders {
+				tags: std::mem::take(&mut tags),
+				range: start.take().unwrap_or(end)..end,
+			});
+			in_moves = false;
+		}
+
+		if start.is_none() {
+			start = Some(byte_at(token_start));
+		}
+
+		if in_moves {
+			match token.body {
+				PdnTokenBody::LeftParenthesis => depth += 1,
+				PdnTokenBody::RightParenthesis => depth = depth.saturating_sub(1),
+				PdnTokenBody::GameResult(_) if depth == 0 => {
+					let end = byte_at(token_start + token.header.len());
+					games.push(GameHeaders {
+						tags: std::mem::take(&mut tags),
+						range: start.take().unwrap_or(end)..end,
+					});
+					in_moves = false;
+				}
+				_ => (),
+			}
+			continue;
+		}
+
+		match (token.body, &mut tag) {
+			(PdnTokenBody::LeftBracket, _) => tag = Some(PartialTag::default()),
+			(PdnTokenBody::Identifier(identifier), Some(tag)) if tag.identifier.is_none() => {
+				tag.identifier = Some(identifier.to_string());
+			}
+			(PdnTokenBody::String(value), Some(tag)) if tag.value.is_none() => {
+				tag.value = Some(value.to_string());
+			}
+			(PdnTokenBody::RightBracket, Some(_)) => {
+				if let Some(PartialTag {
+					identifier: Some(identifier),
+					value: Some(value),
+				}) = tag.take()
+				{
+					tags.push(PdnTag::new(&identifier, &value));
+				}
+			}
+			(PdnTokenBody::GameResult(_), _) => {
+				// a game with no moves
+				let end = byte_at(token_start + token.header.len());
+				games.push(GameHeaders {
+					tags: std::mem::take(&mut tags),
+					range: start.take().unwrap_or(end)..end,
+				});
+				tag = None;
+			}
+			(body, _) => {
+				tag = None;
+				in_moves = true;
+				depth = usize::from(body == PdnTokenBody::LeftParenthesis);
+			}
+		}
+	}
+
+	if let Some(start) = start {
+		games.push(GameHeaders {
+			tags,
+			range: start..source.len(),
+		});
+	}
+
+	games
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn scans_headers() {
+		let pdn =
+			"[Event \"Ünïcode\"]\n[Round \"1\"]\n1. 11-15 (1. 9-13) {a * comment} 23-19 2-0\n\n\
+		           [Event \"Two\"]\n1. 9-13\n[Event \"Three\"] *\n";
+		let games = scan_headers(pdn);
+		assert_eq!(games.len(), 3);
+		assert_eq!(games[0].tag("event"), Some("Ünïcode"));
+		assert_eq!(games[0].tags().len(), 2);
+		assert_eq!(games[1].tag("Event"), Some("Two"));
+		assert_eq!(&pdn[games[1].range()], "[Event \"Two\"]\n1. 9-13\n");
+		assert_eq!(&pdn[games[2].range()], "[Event \"Three\"] *");
+
+		let file = crate::parse(&pdn[games[0].range()]).unwrap();
+		assert_eq!(file.games()[0].body().len(), 4);
+	}
+}
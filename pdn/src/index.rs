@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use model::{CheckersBitBoard, PieceColor};
+
+use crate::grammar::Game;
+use crate::semantic::{replay, SemanticError};
+use crate::tokens::GameResult;
+
+const MAGIC: u32 = u32::from_be_bytes(*b".apx");
+const SUPPORTED_VERSION: u16 = 0;
+
+/// A ply of a game in which a position was reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Occurrence {
+	/// The number of games added to the index before this one
+	pub game: usize,
+	/// The number of moves played before the position was reached
+	pub ply: usize,
+}
+
+/// How often a move was played from a position, and how those games ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NextMove {
+	/// The Ampere value of the square the moving piece starts on
+	pub from: usize,
+	/// The Ampere value of the square the moving piece ends on
+	pub to: usize,
+	pub black_wins: u32,
+	pub white_wins: u32,
+	pub draws: u32,
+	pub unfinished: u32,
+}
+
+impl NextMove {
+	fn new(from: usize, to: usize) -> Self {
+		Self {
+			from,
+			to,
+			black_wins: 0,
+			white_wins: 0,
+			draws: 0,
+			unfinished: 0,
+		}
+	}
+
+	/// The number of games the move was played in
+	pub fn games(&self) -> u32 {
+		self.black_wins + self.white_wins + self.draws + self.unfinished
+	}
+
+	fn add_result(&mut self, result: GameResult) {
+		match result {
+			GameResult::BlackWin => self.black_wins += 1,
+			GameResult::WhiteWin => self.white_wins += 1,
+			GameResult::Draw => self.draws += 1,
+			GameResult::Unfinished => self.unfinished += 1,
+		}
+	}
+}
+
+/// Everything the index knows about one position
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PositionEntry {
+	occurrences: Vec<Occurrence>,
+	next_moves: Vec<NextMove>,
+}
+
+impl PositionEntry {
+	/// Every game and ply in which the position was reached
+	pub fn occurrences(&self) -> &[Occurrence] {
+		&self.occurrences
+	}
+
+	/// The moves played from the position, in the order they were first seen
+	pub fn next_moves(&self) -> &[NextMove] {
+		&self.next_moves
+	}
+
+	fn next_move_mut(&mut self, from: usize, to: usize) -> &mut NextMove {
+		let position = self
+			.next_moves
+			.iter()
+			.position(|next| next.from == from && next.to == to);
+		match position {
+			Some(i) => &mut self.next_moves[i],
+			None => {
+				self.next_moves.push(NextMove::new(from, to));
+				self.next_moves.last_mut().expect("a move was just added")
+			}
+		}
+	}
+}
+
+#[derive(Debug)]
+pub enum IndexFileError {
+	/// The file doesn't start with the index's magic number
+	MagicError,
+	/// The file was written by a different version of the index format
+	UnsupportedVersion(u16),
+	IoError(io::Error),
+}
+
+impl From<io::Error> for IndexFileError {
+	fn from(error: io::Error) -> Self {
+		Self::IoError(error)
+	}
+}
+
+/// Finds the games and plies in which positions were reached, and which moves
+/// were played next. Positions are looked up by their hash, and compared in
+/// full, so positions with the same hash are kept apart.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PositionIndex {
+	positions: HashMap<CheckersBitBoard, PositionEntry>,
+	games: usize,
+}
+
+impl PositionIndex {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Indexes every game in a collection. Games whose moves can't be played
+	/// are skipped, but still count towards the ids of the games after them.
+	pub fn from_games<'a>(games: impl IntoIterator<Item = &'a Game>) -> Self {
+		let mut index = Self::new();
+		for game in games {
+			let _ = index.add_game(game);
+		}
+		index
+	}
+
+	/// Adds the positions in a game's main line. The game's id is the number
+	/// of games added before it, including games which returned an error.
+	pub fn add_game(&mut self, game: &Game) -> Result<(), SemanticError> {
+		let id = self.games;
+		self.games += 1;
+
+		let moves = replay(game)?;
+		for (ply, played) in moves.iter().enumerate() {
+			let entry = self.positions.entry(played.before).or_default();
+			entry.occurrences.push(Occurrence { game: id, ply });
+			entry
+				.next_move_mut(played.from(), played.to())
+				.add_result(game.result());
+		}
+
+		if let Some(last) = moves.last() {
+			let entry = self.positions.entry(last.after).or_default();
+			entry.occurrences.push(Occurrence {
+				game: id,
+				ply: moves.len(),
+			});
+		}
+
+		Ok(())
+	}
+
+	pub fn get(&self, board: CheckersBitBoard) -> Option<&PositionEntry> {
+		self.positions.get(&board)
+	}
+
+	/// Every indexed position, in no particular order
+	pub fn positions(&self) -> impl Iterator<Item = (CheckersBitBoard, &PositionEntry)> + '_ {
+		self.positions.iter().map(|(board, entry)| (*board, entry))
+	}
+
+	/// The number of games which have been added
+	pub fn games(&self) -> usize {
+		self.games
+	}
+
+	/// The number of different positions
+	pub fn len(&self) -> usize {
+		self.positions.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.positions.is_empty()
+	}
+
+	pub fn read(reader: &mut impl Read) -> Result<Self, IndexFileError> {
+		if read_u32(reader)? != MAGIC {
+			return Err(IndexFileError::MagicError);
+		}
+
+		reader.read_exact(&mut [0; 2])?;
+		let version = read_u16(reader)?;
+		if version != SUPPORTED_VERSION {
+			return Err(IndexFileError::UnsupportedVersion(version));
+		}
+
+		let games = read_u64(reader)? as usize;
+		let positions_count = read_u64(reader)?;
+		let mut positions = HashMap::new();
+		for _ in 0..positions_count {
+			let (board, entry) = read_position(reader)?;
+			positions.insert(board, entry);
+		}
+
+		Ok(Self { positions, games })
+	}
+
+	pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+		writer.write_all(&MAGIC.to_be_bytes())?;
+		writer.write_all(&[0; 2])?;
+		writer.write_all(&SUPPORTED_VERSION.to_be_bytes())?;
+		writer.write_all(&(self.games as u64).to_be_bytes())?;
+		writer.write_all(&(self.positions.len() as u64).to_be_bytes())?;
+		for (board, entry) in &self.positions {
+			write_position(writer, *board, entry)?;
+		}
+
+		Ok(())
+	}
+
+	pub fn load(path: impl AsRef<Path>) -> Result<Self, IndexFileError> {
+		Self::read(&mut BufReader::new(File::open(path)?))
+	}
+
+	pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let mut writer = BufWriter::new(File::create(path)?);
+		self.write(&mut writer)?;
+		writer.flush()
+	}
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+	let mut bytes = [0; N];
+	reader.read_exact(&mut bytes)?;
+	Ok(bytes)
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+	Ok(u16::from_be_bytes(read_bytes(reader)?))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+	Ok(u32::from_be_bytes(read_bytes(reader)?))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+	Ok(u64::from_be_bytes(read_bytes(reader)?))
+}
+
+fn read_position(reader: &mut impl Read) -> io::Result<(CheckersBitBoard, PositionEntry)> {
+	let pieces = read_u32(reader)?;
+	let color = read_u32(reader)?;
+	let kings = read_u32(reader)?;
+	let turn = if read_bytes::<1>(reader)?[0] == PieceColor::Dark as u8 {
+		PieceColor::Dark
+	} else {
+		PieceColor::Light
+	};
+	let board = CheckersBitBoard::new(pieces, color, kings, turn);
+
+	let mut entry = PositionEntry::default();
+	for _ in 0..read_u32(reader)? {
+		let game = read_u64(reader)? as usize;
+		let ply = read_u32(reader)? as usize;
+		entry.occurrences.push(Occurrence { game, ply });
+	}
+
+	for _ in 0..read_u32(reader)? {
+		let [from, to] = read_bytes(reader)?;
+		entry.next_moves.push(NextMove {
+			from: from as usize,
+			to: to as usize,
+			black_wins: read_u32(reader)?,
+			white_wins: read_u32(reader)?,
+			draws: read_u32(reader)?,
+			unfinished: read_u32(reader)?,
+		});
+	}
+
+	Ok((board, entry))
+}
+
+fn write_position(
+	writer: &mut impl Write,
+	board: CheckersBitBoard,
+	entry: &PositionEntry,
+) -> io::Result<()> {
+	let pieces = board.pieces_bits();
+	writer.write_all(&pieces.to_be_bytes())?;
+	writer.write_all(&(board.color_bits() & pieces).to_be_bytes())?;
+	writer.write_all(&(board.king_bits() & pieces).to_be_bytes())?;
+	writer.write_all(&[board.turn() as u8])?;
+
+	writer.write_all(&(entry.occurrences.len() as u32).to_be_bytes())?;
+	for occurrence in &entry.occurrences {
+		writer.write_all(&(occurrence.game as u64).to_be_bytes())?;
+		writer.write_all(&(occurrence.ply as u32).to_be_bytes())?;
+	}
+
+	writer.write_all(&(entry.next_moves.len() as u32).to_be_bytes())?;
+	for next in &entry.next_moves {
+		writer.write_all(&[next.from as u8, next.to as u8])?;
+		for count in [
+			next.black_wins,
+			next.white_wins,
+			next.draws,
+			next.unfinished,
+		] {
+			writer.write_all(&count.to_be_bytes())?;
+		}
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn indexes_games() {
+		let pdn = "1. 11-15 23-19 2-0\n\n1. 11-15 24-20 1-1\n\n1. 9-14 1. 11-15 *\n\n1. 9-13 *";
+		let file = crate::parse(pdn).unwrap();
+		let index = PositionIndex::from_games(file.games());
+		assert_eq!(index.games(), 4);
+
+		let start = index.get(CheckersBitBoard::starting_position()).unwrap();
+		assert_eq!(start.occurrences().len(), 3);
+		assert_eq!(start.occurrences()[2], Occurrence { game: 3, ply: 0 });
+		let first = start.next_moves()[0];
+		assert_eq!((first.games(), first.black_wins, first.draws), (2, 1, 1));
+		assert_eq!(start.next_moves()[1].unfinished, 1);
+
+		let mut buffer = Vec::new();
+		index.write(&mut buffer).unwrap();
+		let read = PositionIndex::read(&mut buffer.as_slice()).unwrap();
+		assert_eq!(read, index);
+		assert!(matches!(
+			PositionIndex::read(&mut &buffer[1..]),
+			Err(IndexFileError::MagicError)
+		));
+	}
+}
@@ -1,2 +1,148 @@
+mod commands;
+pub mod cst;
+pub mod diagnostics;
+mod encoding;
 mod grammar;
+mod headers;
+pub mod index;
+mod nag;
+pub mod semantic;
+pub mod statistics;
+mod stream;
+mod tags;
 mod tokens;
+pub mod validation;
+pub mod writer;
+
+pub use commands::Evaluation;
+pub use encoding::decode;
+pub use grammar::{
+	BodyError, BodyPart, BodyPartError, Game, GameError, GameMove, GameMoveError, HeaderError,
+	Move, MoveError, PdnError, PdnFile, PdnTag, PdnTagError, Square, Variation, VariationError,
+};
+pub use headers::{scan_headers, GameHeaders};
+pub use nag::Nag;
+pub use stream::{games, Games, StreamError};
+pub use tags::PdnDate;
+pub use tokens::{
+	Color, GameResult, PdnScanner, PdnToken, PdnTokenBody, TokenError, TokenErrorType, TokenHeader,
+};
+
+/// The reason a PDN file couldn't be parsed
+#[derive(Debug, Clone)]
+pub enum ParseError {
+	/// Some of the text isn't a valid token
+	Tokens(Vec<TokenError>),
+	/// The tokens don't make valid games
+	Games(PdnError),
+}
+
+/// How much of the PDN that doesn't follow the standard is accepted
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseMode {
+	/// Only PDN which follows the standard is accepted. Moves must be
+	/// numbered, and tag names must start with an uppercase letter.
+	Strict,
+	/// Missing move numbers and lowercase tag names are accepted. Stray
+	/// characters between games are skipped, and line breaks in comments and
+	/// tags are read as `\n`, whether they were written with `\r\n` or `\n`.
+	#[default]
+	Lenient,
+}
+
+/// Whether a token can be the first token of a game
+fn starts_game(token: &PdnTokenBody) -> bool {
+	matches!(
+		token,
+		PdnTokenBody::LeftBracket
+			| PdnTokenBody::MoveNumber(..)
+			| PdnTokenBody::NumSquare(_)
+			| PdnTokenBody::AlphaSquare(..)
+			| PdnTokenBody::Comment(_)
+			| PdnTokenBody::Setup(_)
+			| PdnTokenBody::GameResult(_)
+	)
+}
+
+/// Parses every game in a PDN file, in lenient mode
+pub fn parse(source: &str) -> Result<PdnFile, ParseError> {
+	parse_with(source, ParseMode::Lenient)
+}
+
+/// Parses every game in a PDN file
+pub fn parse_with(source: &str, mode: ParseMode) -> Result<PdnFile, ParseError> {
+	let mut tokens: Vec<PdnToken> = Vec::new();
+	let mut errors = Vec::new();
+	// whether the last token was the end of a game, or the start of the file
+	let mut between_games = true;
+	for token in PdnScanner::with_mode(source, mode) {
+		let skipped = match &token {
+			Ok(token) if matches!(token.body, PdnTokenBody::Space(_)) => {
+				// whitespace on both sides of a skipped token is kept once
+				tokens
+					.last()
+					.is_some_and(|last| matches!(last.body, PdnTokenBody::Space(_)))
+			}
+			Ok(token) => between_games && !starts_game(&token.body),
+			Err(_) => between_games,
+		};
+		if skipped && mode == ParseMode::Lenient {
+			continue;
+		}
+
+		match token {
+			Ok(token) => {
+				if !matches!(token.body, PdnTokenBody::Space(_)) {
+					between_games = matches!(token.body, PdnTokenBody::GameResult(_));
+				}
+				tokens.push(token);
+			}
+			Err(error) => {
+				between_games = false;
+				errors.push(error);
+			}
+		}
+	}
+
+	if !errors.is_empty() {
+		return Err(ParseError::Tokens(errors));
+	}
+
+	grammar::parse(&mut tokens.into_iter(), mode).map_err(ParseError::Games)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_leniently() {
+		let pdn = "\u{feff}[event \"One\"]\r\n{two\r\nlines} 11-15 23-19 2-0\n-- \n\n[Event \"Two\"]\n1. 9-13 *";
+		let file = parse(pdn).unwrap();
+		assert_eq!(file.games().len(), 2);
+		assert_eq!(file.games()[0].tag("Event"), Some("One"));
+		let BodyPart::Comment(_, comment) = &file.games()[0].body()[0] else {
+			panic!("the game starts with a comment");
+		};
+		assert_eq!(comment.as_ref(), "two\nlines");
+
+		let Err(ParseError::Tokens(errors)) = parse_with(pdn, ParseMode::Strict) else {
+			panic!("strict mode doesn't skip stray characters");
+		};
+		assert!(errors
+			.iter()
+			.any(|error| error.error_type() == &TokenErrorType::LowercaseIdentifier));
+
+		let pdn = "[Event \"One\"]\n1. 11-15 23-19 2. 8-11 {a comment} 24-20 *";
+		assert!(parse_with(pdn, ParseMode::Lenient).is_ok());
+		let Err(error) = parse_with(pdn, ParseMode::Strict) else {
+			panic!("the last move has no move number");
+		};
+		let diagnostics = diagnostics::diagnostics(pdn, &error);
+		assert_eq!(diagnostics.len(), 1);
+		assert_eq!(diagnostics[0].column, 36);
+
+		let pdn = "[Event \"One\"]\n1. 11-15 23-19 2. 8-11 2... 24-20 *";
+		assert!(parse_with(pdn, ParseMode::Strict).is_ok());
+	}
+}
@@ -0,0 +1,191 @@
+use std::fmt::{self, Display};
+
+/// A numeric annotation glyph, such as `$1`, which describes a move or a
+/// position. The first player is black in English draughts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Nag {
+	/// `$0`
+	Null,
+	/// `$1`, written as `!`
+	GoodMove,
+	/// `$2`, written as `?`
+	Mistake,
+	/// `$3`, written as `!!`
+	BrilliantMove,
+	/// `$4`, written as `??`
+	Blunder,
+	/// `$5`, written as `!?`
+	InterestingMove,
+	/// `$6`, written as `?!`
+	DubiousMove,
+	/// `$7`
+	ForcedMove,
+	/// `$8`
+	OnlyMove,
+	/// `$9`
+	WorstMove,
+	/// `$10`, written as `=`
+	EqualPosition,
+	/// `$11`
+	QuietEqualPosition,
+	/// `$12`
+	ActiveEqualPosition,
+	/// `$13`, written as `∞`
+	UnclearPosition,
+	/// `$14`, written as `⩲`
+	FirstPlayerSlightAdvantage,
+	/// `$15`, written as `⩱`
+	SecondPlayerSlightAdvantage,
+	/// `$16`, written as `±`
+	FirstPlayerModerateAdvantage,
+	/// `$17`, written as `∓`
+	SecondPlayerModerateAdvantage,
+	/// `$18`, written as `+-`
+	FirstPlayerDecisiveAdvantage,
+	/// `$19`, written as `-+`
+	SecondPlayerDecisiveAdvantage,
+	/// `$20`
+	FirstPlayerCrushingAdvantage,
+	/// `$21`
+	SecondPlayerCrushingAdvantage,
+	/// `$22`
+	FirstPlayerZugzwang,
+	/// `$23`
+	SecondPlayerZugzwang,
+	/// A NAG which isn't listed here, such as one defined by a program
+	Other(usize),
+}
+
+impl Nag {
+	/// The number written after the `$`
+	pub const fn code(self) -> usize {
+		match self {
+			Self::Null => 0,
+			Self::GoodMove => 1,
+			Self::Mistake => 2,
+			Self::BrilliantMove => 3,
+			Self::Blunder => 4,
+			Self::InterestingMove => 5,
+			Self::DubiousMove => 6,
+			Self::ForcedMove => 7,
+			Self::OnlyMove => 8,
+			Self::WorstMove => 9,
+			Self::EqualPosition => 10,
+			Self::QuietEqualPosition => 11,
+			Self::ActiveEqualPosition => 12,
+			Self::UnclearPosition => 13,
+			Self::FirstPlayerSlightAdvantage => 14,
+			Self::SecondPlayerSlightAdvantage => 15,
+			Self::FirstPlayerModerateAdvantage => 16,
+			Self::SecondPlayerModerateAdvantage => 17,
+			Self::FirstPlayerDecisiveAdvantage => 18,
+			Self::SecondPlayerDecisiveAdvantage => 19,
+			Self::FirstPlayerCrushingAdvantage => 20,
+			Self::SecondPlayerCrushingAdvantage => 21,
+			Self::FirstPlayerZugzwang => 22,
+			Self::SecondPlayerZugzwang => 23,
+			Self::Other(code) => code,
+		}
+	}
+
+	pub const fn from_code(code: usize) -> Self {
+		match code {
+			0 => Self::Null,
+			1 => Self::GoodMove,
+			2 => Self::Mistake,
+			3 => Self::BrilliantMove,
+			4 => Self::Blunder,
+			5 => Self::InterestingMove,
+			6 => Self::DubiousMove,
+			7 => Self::ForcedMove,
+			8 => Self::OnlyMove,
+			9 => Self::WorstMove,
+			10 => Self::EqualPosition,
+			11 => Self::QuietEqualPosition,
+			12 => Self::ActiveEqualPosition,
+			13 => Self::UnclearPosition,
+			14 => Self::FirstPlayerSlightAdvantage,
+			15 => Self::SecondPlayerSlightAdvantage,
+			16 => Self::FirstPlayerModerateAdvantage,
+			17 => Self::SecondPlayerModerateAdvantage,
+			18 => Self::FirstPlayerDecisiveAdvantage,
+			19 => Self::SecondPlayerDecisiveAdvantage,
+			20 => Self::FirstPlayerCrushingAdvantage,
+			21 => Self::SecondPlayerCrushingAdvantage,
+			22 => Self::FirstPlayerZugzwang,
+			23 => Self::SecondPlayerZugzwang,
+			code => Self::Other(code),
+		}
+	}
+
+	/// The symbol often shown instead of the number, such as `!` or `?!`
+	pub const fn symbol(self) -> Option<&'static str> {
+		match self {
+			Self::GoodMove => Some("!"),
+			Self::Mistake => Some("?"),
+			Self::BrilliantMove => Some("!!"),
+			Self::Blunder => Some("??"),
+			Self::InterestingMove => Some("!?"),
+			Self::DubiousMove => Some("?!"),
+			Self::EqualPosition => Some("="),
+			Self::UnclearPosition => Some("∞"),
+			Self::FirstPlayerSlightAdvantage => Some("⩲"),
+			Self::SecondPlayerSlightAdvantage => Some("⩱"),
+			Self::FirstPlayerModerateAdvantage => Some("±"),
+			Self::SecondPlayerModerateAdvantage => Some("∓"),
+			Self::FirstPlayerDecisiveAdvantage => Some("+-"),
+			Self::SecondPlayerDecisiveAdvantage => Some("-+"),
+			_ => None,
+		}
+	}
+}
+
+/// Writes what the NAG means, such as `good move`
+impl Display for Nag {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let meaning = match self {
+			Self::Null => "no annotation",
+			Self::GoodMove => "good move",
+			Self::Mistake => "poor move",
+			Self::BrilliantMove => "very good move",
+			Self::Blunder => "very poor move",
+			Self::InterestingMove => "speculative move",
+			Self::DubiousMove => "questionable move",
+			Self::ForcedMove => "forced move",
+			Self::OnlyMove => "the only move",
+			Self::WorstMove => "the worst move",
+			Self::EqualPosition => "drawish position",
+			Self::QuietEqualPosition => "equal chances, quiet position",
+			Self::ActiveEqualPosition => "equal chances, active position",
+			Self::UnclearPosition => "unclear position",
+			Self::FirstPlayerSlightAdvantage => "the first player has a slight advantage",
+			Self::SecondPlayerSlightAdvantage => "the second player has a slight advantage",
+			Self::FirstPlayerModerateAdvantage => "the first player has a moderate advantage",
+			Self::SecondPlayerModerateAdvantage => "the second player has a moderate advantage",
+			Self::FirstPlayerDecisiveAdvantage => "the first player has a decisive advantage",
+			Self::SecondPlayerDecisiveAdvantage => "the second player has a decisive advantage",
+			Self::FirstPlayerCrushingAdvantage => "the first player has a crushing advantage",
+			Self::SecondPlayerCrushingAdvantage => "the second player has a crushing advantage",
+			Self::FirstPlayerZugzwang => "the first player is in zugzwang",
+			Self::SecondPlayerZugzwang => "the second player is in zugzwang",
+			Self::Other(code) => return write!(f, "annotation ${code}"),
+		};
+		f.write_str(meaning)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn codes_round_trip() {
+		for code in 0..40 {
+			assert_eq!(Nag::from_code(code).code(), code);
+		}
+		assert_eq!(Nag::from_code(4), Nag::Blunder);
+		assert_eq!(Nag::Blunder.symbol(), Some("??"));
+		assert_eq!(Nag::GoodMove.to_string(), "good move");
+		assert_eq!(Nag::Other(146).to_string(), "annotation $146");
+	}
+}
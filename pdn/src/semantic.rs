@@ -0,0 +1,350 @@
+use model::{CheckersBitBoard, Move as ModelMove, PieceColor, PossibleMoves, SquareCoordinate};
+
+use crate::grammar::{BodyPart, Game, Move, Square};
+
+/// A move of a game, after it's been checked against the position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayedMove {
+	/// The moves that make up the full move. There is only more than one if
+	/// this is a multi-jump.
+	pub hops: Vec<ModelMove>,
+	/// The position before the move is played
+	pub before: CheckersBitBoard,
+	/// The position after the move is played
+	pub after: CheckersBitBoard,
+}
+
+impl PlayedMove {
+	/// The Ampere value of the square the moving piece starts on
+	pub fn from(&self) -> usize {
+		self.hops[0].start() as usize
+	}
+
+	/// The Ampere value of the square the moving piece ends on
+	pub fn to(&self) -> usize {
+		self.hops[self.hops.len() - 1].end_position()
+	}
+
+	pub fn is_capture(&self) -> bool {
+		self.hops[0].is_jump()
+	}
+}
+
+/// The reason the moves of a game couldn't be played
+#[derive(Debug, Clone)]
+pub enum SemanticError {
+	/// The game isn't English draughts, or its `GameType` tag isn't valid
+	UnsupportedGameType(Option<String>),
+	/// The `FEN` tag, or a setup in the move text, isn't a valid position
+	InvalidFen(String),
+	/// A square isn't one of the 32 playable squares, such as `33` or `a2`
+	InvalidSquare { ply: usize, square: Square },
+	/// There isn't a legal move with these squares
+	IllegalMove { ply: usize, game_move: Move },
+	/// More than one legal move has these squares. This can happen when only
+	/// the start and end of a multi-jump are written.
+	AmbiguousMove { ply: usize, game_move: Move },
+}
+
+/// The Ampere value of a square, or `None` if it isn't a playable square
+pub fn square_value(square: &Square) -> Option<usize> {
+	match *square {
+		Square::Num(_, number) => {
+			if !(1..=32).contains(&number) {
+				return None;
+			}
+			SquareCoordinate::from_normal_value(number as usize - 1).to_ampere_value()
+		}
+		Square::Alpha(_, file, rank) => {
+			if !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) {
+				return None;
+			}
+			SquareCoordinate::new(rank as u8 - b'1', file as u8 - b'a').to_ampere_value()
+		}
+	}
+}
+
+/// The Ampere value of a square in a FEN, such as `11` or `c3`
+fn fen_square(square: &str) -> Option<usize> {
+	let mut chars = square.chars();
+	match (chars.next()?, chars.next(), chars.next()) {
+		(file @ 'a'..='h', Some(rank), None) => square_value(&Square::alpha(file, rank)),
+		_ => square_value(&Square::num(square.parse().ok()?)),
+	}
+}
+
+/// Parses a position in FEN, such as `B:W21,22,K30:B1,2,3`. Ranges of
+/// numeric squares may be used, such as `B:W21-32:B1-12`, and squares may
+/// also be written as a file and a rank, such as `W:Wc3:Bd6`.
+pub fn parse_fen(fen: &str) -> Option<CheckersBitBoard> {
+	let fen = fen.trim().trim_end_matches('.');
+	let mut fields = fen.split(':');
+	let turn = match fields.next()?.trim() {
+		"B" => PieceColor::Dark,
+		"W" => PieceColor::Light,
+		_ => return None,
+	};
+
+	let (mut pieces, mut dark, mut kings) = (0u32, 0u32, 0u32);
+	for field in fields {
+		let field = field.trim();
+		let is_dark = match field.chars().next()? {
+			'B' => true,
+			'W' => false,
+			_ => return None,
+		};
+
+		for square in field[1..]
+			.split(',')
+			.map(str::trim)
+			.filter(|square| !square.is_empty())
+		{
+			let (is_king, square) = match square.strip_prefix('K') {
+				Some(square) => (true, square),
+				None => (false, square),
+			};
+
+			let squares = match square.split_once('-') {
+				Some((first, last)) => {
+					let (first, last): (u8, u8) = (first.parse().ok()?, last.parse().ok()?);
+					(first..=last)
+						.map(|number| square_value(&Square::num(number)))
+						.collect::<Option<Vec<_>>>()?
+				}
+				None => vec![fen_square(square)?],
+			};
+
+			for square in squares {
+				let bit = 1 << square;
+				pieces |= bit;
+				if is_dark {
+					dark |= bit;
+				}
+				if is_king {
+					kings |= bit;
+				}
+			}
+		}
+	}
+
+	Some(CheckersBitBoard::new(pieces, dark, kings, turn))
+}
+
+/// The position a game starts from. This is the position in the `FEN` tag,
+/// or the starting position if there isn't one. Games of variants other
+/// than English draughts have no position.
+pub fn start_position(game: &Game) -> Result<CheckersBitBoard, SemanticError> {
+	if !game.is_supported() {
+		let game_type = game.tag("GameType").map(str::to_string);
+		return Err(SemanticError::UnsupportedGameType(game_type));
+	}
+
+	match game.fen() {
+		Some(fen) => parse_fen(fen).ok_or_else(|| SemanticError::InvalidFen(fen.to_string())),
+		None => Ok(CheckersBitBoard::starting_position()),
+	}
+}
+
+/// The Ampere value of the square between the start and end of a jump, or
+/// `None` if the move isn't a jump
+pub fn captured_square(hop: ModelMove) -> Option<usize> {
+	if !hop.is_jump() {
+		return None;
+	}
+
+	let start = SquareCoordinate::from_ampere_value(hop.start() as usize);
+	let end = SquareCoordinate::from_ampere_value(hop.end_position());
+	SquareCoordinate::new(
+		(start.rank() + end.rank()) / 2,
+		(start.file() + end.file()) / 2,
+	)
+	.to_ampere_value()
+}
+
+/// Lists every legal complete move in a position. Multi-jumps are followed
+/// until the turn changes.
+pub fn full_moves(board: CheckersBitBoard) -> Vec<PlayedMove> {
+	full_moves_from(board, None)
+		.into_iter()
+		.map(|(hops, after)| PlayedMove {
+			hops,
+			before: board,
+			after,
+		})
+		.collect()
+}
+
+/// `jumping_from` is the square of the piece in the middle of a multi-jump,
+/// because the board doesn't remember which piece is jumping.
+fn full_moves_from(
+	board: CheckersBitBoard,
+	jumping_from: Option<usize>,
+) -> Vec<(Vec<ModelMove>, CheckersBitBoard)> {
+	let mut moves = Vec::new();
+	for hop in PossibleMoves::moves(board) {
+		if jumping_from.is_some_and(|square| hop.start() as usize != square) {
+			continue;
+		}
+
+		// the move came from the list of possible moves, so it's safe to play
+		let child = unsafe { hop.apply_to(board) };
+		if hop.is_jump() && child.turn() == board.turn() {
+			for (mut hops, after) in full_moves_from(child, Some(hop.end_position())) {
+				hops.insert(0, hop);
+				moves.push((hops, after));
+			}
+		} else {
+			moves.push((vec![hop], child));
+		}
+	}
+
+	moves
+}
+
+/// Finds the legal move that a PDN move describes. Captures may list every
+/// square the piece lands on, or only the start and the end.
+pub fn resolve_move(
+	board: CheckersBitBoard,
+	game_move: &Move,
+	ply: usize,
+) -> Result<PlayedMove, SemanticError> {
+	let mut squares = Vec::new();
+	for square in game_move.squares() {
+		match square_value(square) {
+			Some(value) => squares.push(value),
+			None => {
+				return Err(SemanticError::InvalidSquare {
+					ply,
+					square: square.clone(),
+				})
+			}
+		}
+	}
+
+	let is_capture = matches!(game_move, Move::Capture(..));
+	let candidates: Vec<_> = full_moves(board)
+		.into_iter()
+		.filter(|played| {
+			if played.is_capture() != is_capture {
+				return false;
+			}
+
+			let landings: Vec<usize> = std::iter::once(played.from())
+				.chain(played.hops.iter().map(|hop| hop.end_position()))
+				.collect();
+			landings == squares
+				|| (squares.len() == 2 && landings[0] == squares[0] && played.to() == squares[1])
+		})
+		.collect();
+
+	let error_move = game_move.clone();
+	match <[_; 1]>::try_from(candidates) {
+		Ok([played]) => Ok(played),
+		Err(candidates) if candidates.is_empty() => Err(SemanticError::IllegalMove {
+			ply,
+			game_move: error_move,
+		}),
+		Err(_) => Err(SemanticError::AmbiguousMove {
+			ply,
+			game_move: error_move,
+		}),
+	}
+}
+
+/// Plays the moves of a game's main line from `start`. A setup in the move
+/// text replaces the position. Variations, comments, and NAGs are skipped.
+pub fn replay_from(start: CheckersBitBoard, game: &Game) -> Result<Vec<PlayedMove>, SemanticError> {
+	let mut board = start;
+	let mut moves = Vec::new();
+	for part in game.body() {
+		let game_move = match part {
+			BodyPart::Move(game_move) => game_move,
+			BodyPart::Setup(_, fen) => {
+				board = parse_fen(fen).ok_or_else(|| SemanticError::InvalidFen(fen.to_string()))?;
+				continue;
+			}
+			_ => continue,
+		};
+
+		let played = resolve_move(board, game_move.game_move(), moves.len())?;
+		board = played.after;
+		moves.push(played);
+	}
+
+	Ok(moves)
+}
+
+/// Plays the moves of a game's main line from the position in its `FEN` tag,
+/// or from the starting position
+pub fn replay(game: &Game) -> Result<Vec<PlayedMove>, SemanticError> {
+	replay_from(start_position(game)?, game)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn resolves_squares() {
+		assert_eq!(square_value(&Square::num(1)), Some(0));
+		assert_eq!(square_value(&Square::alpha('g', '1')), Some(0));
+		assert_eq!(square_value(&Square::num(33)), None);
+		assert_eq!(square_value(&Square::alpha('a', '2')), None);
+	}
+
+	#[test]
+	fn replays_games() {
+		let file = crate::parse(
+			"[Event \"Test\"]\n\n1. 11-15 24-20 2. 8-11 28-24 3. 15-19 23x16 4. 12x28 *",
+		)
+		.unwrap();
+		let moves = replay(&file.games()[0]).unwrap();
+		assert_eq!(moves.len(), 7);
+		assert!(moves[5].is_capture());
+		assert_eq!(moves[6].hops.len(), 2);
+		assert_eq!(moves[4].after, moves[5].before);
+
+		let file = crate::parse("[Event \"Test\"]\n\n1. c3-d4 f6-e5 2. d4xf6 *").unwrap();
+		let moves = replay(&file.games()[0]).unwrap();
+		assert_eq!(moves.len(), 3);
+
+		let file = crate::parse("[Event \"Test\"]\n\n1. 11-15 15-19 *").unwrap();
+		assert!(matches!(
+			replay(&file.games()[0]),
+			Err(SemanticError::IllegalMove { ply: 1, .. })
+		));
+	}
+
+	#[test]
+	fn parses_fens() {
+		let start = parse_fen("B:W21-32:B1-12").unwrap();
+		assert_eq!(start, CheckersBitBoard::starting_position());
+		assert_eq!(parse_fen("W:Wc3,K30:Bd6."), parse_fen("W:W11,K30:B23"));
+		assert_eq!(parse_fen("X:W1:B2"), None);
+		assert_eq!(parse_fen("B:W33:B1"), None);
+
+		let pdn = "[SetUp \"1\"]\n[FEN \"B:WK14:B9\"]\n\n1. 9x18 *";
+		let file = crate::parse(pdn).unwrap();
+		let moves = replay(&file.games()[0]).unwrap();
+		assert_eq!(moves.len(), 1);
+		assert_eq!(moves[0].after.pieces_bits().count_ones(), 1);
+	}
+
+	#[test]
+	fn lists_full_moves() {
+		let board = parse_fen("W:W27:B23,14,1").unwrap();
+		let moves = full_moves(board);
+		assert_eq!(moves.len(), 1);
+		assert_eq!(moves[0].hops.len(), 2);
+
+		let captured: Vec<_> = moves[0]
+			.hops
+			.iter()
+			.filter_map(|hop| captured_square(*hop))
+			.collect();
+		assert_eq!(
+			captured,
+			[23, 14].map(|n| square_value(&Square::num(n)).unwrap())
+		);
+	}
+}
@@ -0,0 +1,199 @@
+use std::collections::HashMap;
+
+use crate::grammar::{BodyPart, Game, Move};
+use crate::tokens::GameResult;
+use crate::writer::{write_square, SquareNotation};
+
+/// How a player's games ended
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Record {
+	pub wins: usize,
+	pub draws: usize,
+	pub losses: usize,
+	/// Games which were unfinished, or whose result isn't known
+	pub unfinished: usize,
+}
+
+impl Record {
+	pub fn games(&self) -> usize {
+		self.wins + self.draws + self.losses + self.unfinished
+	}
+
+	/// Adds the result of a game, from the view of the first player if
+	/// `is_black`, or the second player otherwise
+	fn add(&mut self, result: GameResult, is_black: bool) {
+		match (result, is_black) {
+			(GameResult::BlackWin, true) | (GameResult::WhiteWin, false) => self.wins += 1,
+			(GameResult::BlackWin, false) | (GameResult::WhiteWin, true) => self.losses += 1,
+			(GameResult::Draw, _) => self.draws += 1,
+			(GameResult::Unfinished, _) => self.unfinished += 1,
+		}
+	}
+}
+
+/// A player's games, from their `Black` and `White` tags
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PlayerStatistics {
+	/// The games the player played as black, who moves first
+	pub as_black: Record,
+	pub as_white: Record,
+}
+
+impl PlayerStatistics {
+	/// Every game the player played, with either color
+	pub fn total(&self) -> Record {
+		Record {
+			wins: self.as_black.wins + self.as_white.wins,
+			draws: self.as_black.draws + self.as_white.draws,
+			losses: self.as_black.losses + self.as_white.losses,
+			unfinished: self.as_black.unfinished + self.as_white.unfinished,
+		}
+	}
+}
+
+/// Totals over a collection of games
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Statistics {
+	/// The results from black's view, since black moves first
+	results: Record,
+	plies: usize,
+	first_moves: HashMap<String, usize>,
+	players: HashMap<String, PlayerStatistics>,
+}
+
+/// The first move, in numeric notation, such as `11-15`
+fn write_first_move(game_move: &Move) -> String {
+	let separator = match game_move {
+		Move::Normal(..) => "-",
+		Move::Capture(..) => "x",
+	};
+	game_move
+		.squares()
+		.into_iter()
+		.map(|square| write_square(square, SquareNotation::Numeric))
+		.collect::<Vec<_>>()
+		.join(separator)
+}
+
+impl Statistics {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn from_games<'a>(games: impl IntoIterator<Item = &'a Game>) -> Self {
+		let mut statistics = Self::new();
+		for game in games {
+			statistics.add_game(game);
+		}
+		statistics
+	}
+
+	/// Adds a game to the totals. The result after the moves is used, rather
+	/// than the `Result` tag.
+	pub fn add_game(&mut self, game: &Game) {
+		let result = game.result();
+		self.results.add(result, true);
+
+		let moves: Vec<_> = game
+			.body()
+			.iter()
+			.filter_map(|part| match part {
+				BodyPart::Move(game_move) => Some(game_move),
+				_ => None,
+			})
+			.collect();
+		self.plies += moves.len();
+		if let Some(first) = moves.first() {
+			let first = write_first_move(first.game_move());
+			*self.first_moves.entry(first).or_default() += 1;
+		}
+
+		if let Some(black) = game.black() {
+			let player = self.players.entry(black.to_string()).or_default();
+			player.as_black.add(result, true);
+		}
+		if let Some(white) = game.white() {
+			let player = self.players.entry(white.to_string()).or_default();
+			player.as_white.add(result, false);
+		}
+	}
+
+	/// The number of games which have been added
+	pub fn games(&self) -> usize {
+		self.results.games()
+	}
+
+	pub fn black_wins(&self) -> usize {
+		self.results.wins
+	}
+
+	pub fn white_wins(&self) -> usize {
+		self.results.losses
+	}
+
+	pub fn draws(&self) -> usize {
+		self.results.draws
+	}
+
+	pub fn unfinished(&self) -> usize {
+		self.results.unfinished
+	}
+
+	/// The average number of plies in the main line of each game, or `None`
+	/// if there are no games
+	pub fn average_length(&self) -> Option<f64> {
+		(self.games() > 0).then(|| self.plies as f64 / self.games() as f64)
+	}
+
+	/// The `count` most common first moves, in numeric notation, with the
+	/// number of games they were played in. Moves which were played equally
+	/// often are sorted by their notation.
+	pub fn most_common_first_moves(&self, count: usize) -> Vec<(&str, usize)> {
+		let mut moves: Vec<_> = self
+			.first_moves
+			.iter()
+			.map(|(first_move, games)| (first_move.as_str(), *games))
+			.collect();
+		moves.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+		moves.truncate(count);
+		moves
+	}
+
+	/// The games of a player, by the name in the `Black` and `White` tags
+	pub fn player(&self, name: &str) -> Option<&PlayerStatistics> {
+		self.players.get(name)
+	}
+
+	/// Every player, in no particular order
+	pub fn players(&self) -> impl Iterator<Item = (&str, &PlayerStatistics)> + '_ {
+		self.players
+			.iter()
+			.map(|(name, player)| (name.as_str(), player))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn counts_games() {
+		let pdn = "[Black \"Tinsley\"]\n[White \"Chinook\"]\n1. 11-15 23-19 1-1\n\n\
+		           [Black \"Chinook\"]\n[White \"Tinsley\"]\n1. 9-13 22-18 2. 11-15 0-2\n\n\
+		           [Black \"Tinsley\"]\n1. 11-15 *";
+		let file = crate::parse(pdn).unwrap();
+		let statistics = Statistics::from_games(file.games());
+		assert_eq!(statistics.games(), 3);
+		assert_eq!((statistics.white_wins(), statistics.draws()), (1, 1));
+		assert_eq!(statistics.average_length(), Some(2.0));
+		assert_eq!(
+			statistics.most_common_first_moves(5),
+			[("11-15", 2), ("9-13", 1)]
+		);
+
+		let tinsley = statistics.player("Tinsley").unwrap().total();
+		assert_eq!((tinsley.wins, tinsley.draws, tinsley.unfinished), (1, 1, 1));
+		assert_eq!(tinsley.games(), 3);
+		assert_eq!(statistics.player("Chinook").unwrap().as_black.losses, 1);
+	}
+}
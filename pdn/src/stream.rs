@@ -0,0 +1,273 @@
+use std::io::{self, BufRead, BufReader, Read};
+
+use crate::diagnostics::{diagnostics, Diagnostic};
+use crate::encoding::decode;
+use crate::grammar::Game;
+use crate::tokens::{PdnScanner, PdnTokenBody, TokenErrorType};
+use crate::ParseError;
+
+/// Reads the games of a PDN file one at a time, so that only one game's text
+/// is in memory at once. Lines which aren't valid UTF-8 are read as
+/// Windows-1252.
+#[derive(Debug)]
+pub struct Games<R> {
+	reader: R,
+	/// The text which has been read, but not parsed
+	buffer: String,
+	/// The bytes of the line being read
+	line_bytes: Vec<u8>,
+	/// The line of the file that the buffer starts on, starting from one
+	line: usize,
+	/// How much of the buffer has been searched for the end of a game
+	scan: Scan,
+	finished: bool,
+}
+
+/// The state of the search for the end of a game, so that each line of the
+/// buffer only needs to be scanned once
+#[derive(Debug, Default, Clone, Copy)]
+struct Scan {
+	/// The byte of the buffer to continue scanning from
+	offset: usize,
+	/// How deeply nested the variations at `offset` are
+	depth: usize,
+	/// Whether the game's moves have started before `offset`
+	in_moves: bool,
+	/// The character that closes the comment, string, or setup which starts
+	/// at `offset`, and how far the buffer has been searched for it
+	unterminated: Option<(char, usize)>,
+}
+
+/// The longest comment, string, or setup that is waited on to be closed. A
+/// longer one ends the game, so that it's reported as unterminated instead of
+/// buffering the rest of the file.
+const MAX_UNTERMINATED_LEN: usize = 1 << 20;
+
+#[derive(Debug)]
+pub enum StreamError {
+	IoError(io::Error),
+	/// A game couldn't be parsed. The error's positions are counted from the
+	/// start of `text`, which begins on `line` of the file.
+	ParseError {
+		text: String,
+		line: usize,
+		error: ParseError,
+	},
+}
+
+impl StreamError {
+	/// Describes the problems with a game, with line numbers counted from the
+	/// start of the file. Errors reading the file have no diagnostics.
+	pub fn diagnostics(&self) -> Vec<Diagnostic> {
+		let Self::ParseError { text, line, error } = self else {
+			return Vec::new();
+		};
+
+		let mut diagnostics = diagnostics(text, error);
+		for diagnostic in &mut diagnostics {
+			diagnostic.line += line - 1;
+		}
+		diagnostics
+	}
+}
+
+impl From<io::Error> for StreamError {
+	fn from(error: io::Error) -> Self {
+		Self::IoError(error)
+	}
+}
+
+impl<R: BufRead> Games<R> {
+	pub fn new(reader: R) -> Self {
+		Self {
+			reader,
+			buffer: String::new(),
+			line_bytes: Vec::new(),
+			line: 1,
+			scan: Scan::default(),
+			finished: false,
+		}
+	}
+
+	/// The number of bytes at the start of the buffer that make up a whole
+	/// game, or `None` if more text is needed. A game ends after its result,
+	/// or before a tag that comes after its moves, if its result is missing.
+	/// Comments and strings that haven't been closed yet may continue on the
+	/// next line, unless the file has ended.
+	fn game_end(&mut self) -> Option<usize> {
+		if let Some((closer, searched)) = self.scan.unterminated.filter(|_| !self.finished) {
+			if !self.buffer[searched..].contains(closer) {
+				self.scan.unterminated = Some((closer, self.buffer.len()));
+				let len = self.buffer.len() - self.scan.offset;
+				return (len > MAX_UNTERMINATED_LEN).then_some(self.buffer.len());
+			}
+		}
+
+		let offset = self.scan.offset;
+		let text = &self.buffer[offset..];
+		let byte = |position: usize| {
+			offset
+				+ text
+					.char_indices()
+					.nth(position)
+					.map_or(text.len(), |(byte, _)| byte)
+		};
+
+		let Scan {
+			mut depth,
+			mut in_moves,
+			..
+		} = self.scan;
+		// a tag that hasn't ended is scanned again, from the state before it
+		let mut tag_start = None;
+		for token in PdnScanner::new(text) {
+			let token = match token {
+				Ok(token) => token,
+				Err(error) => {
+					let closer = match error.error_type() {
+						TokenErrorType::UnterminatedComment => '}',
+						TokenErrorType::UnterminatedSetup => '/',
+						TokenErrorType::UnterminatedString => '"',
+						_ => continue,
+					};
+					if self.finished {
+						continue;
+					}
+
+					self.scan = Scan {
+						offset: byte(error.header().start()),
+						depth,
+						in_moves,
+						unterminated: Some((closer, self.buffer.len())),
+					};
+					return None;
+				}
+			};
+
+			match token.body {
+				PdnTokenBody::LeftBracket if in_moves && depth == 0 => {
+					return Some(byte(token.header.start()));
+				}
+				PdnTokenBody::LeftBracket => {
+					tag_start = Some((token.header.start(), depth, in_moves));
+				}
+				PdnTokenBody::RightBracket => tag_start = None,
+				PdnTokenBody::GameResult(_) if depth == 0 => {
+					return Some(byte(token.header.start() + token.header.len()));
+				}
+				PdnTokenBody::LeftParenthesis => depth += 1,
+				PdnTokenBody::RightParenthesis => depth = depth.saturating_sub(1),
+				_ => (),
+			}
+
+			let trivia = matches!(
+				token.body,
+				PdnTokenBody::Space(_) | PdnTokenBody::RightBracket
+			);
+			if tag_start.is_none() && !trivia {
+				in_moves = true;
+			}
+		}
+
+		let (position, depth, in_moves) =
+			tag_start.unwrap_or((text.chars().count(), depth, in_moves));
+		self.scan = Scan {
+			offset: byte(position),
+			depth,
+			in_moves,
+			unterminated: None,
+		};
+		None
+	}
+
+	/// Removes the first `len` bytes from the buffer, and parses them
+	fn take_game(&mut self, len: usize) -> Option<Result<Game, StreamError>> {
+		self.scan = Scan::default();
+		let rest = self.buffer.split_off(len);
+		let text = std::mem::replace(&mut self.buffer, rest);
+		let line = self.line;
+		self.line += text.matches('\n').count();
+
+		if text.trim().is_empty() {
+			return None;
+		}
+
+		match crate::parse(&text) {
+			Ok(file) => file.into_games().into_iter().next().map(Ok),
+			Err(error) => Some(Err(StreamError::ParseError { text, line, error })),
+		}
+	}
+}
+
+impl<R: BufRead> Iterator for Games<R> {
+	type Item = Result<Game, StreamError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		loop {
+			if let Some(len) = self.game_end() {
+				match self.take_game(len) {
+					Some(game) => return Some(game),
+					None => continue,
+				}
+			}
+
+			if self.finished {
+				return self.take_game(self.buffer.len());
+			}
+
+			self.line_bytes.clear();
+			match self.reader.read_until(b'\n', &mut self.line_bytes) {
+				Ok(0) => self.finished = true,
+				Ok(_) => self.buffer.push_str(&decode(&self.line_bytes)),
+				Err(error) => {
+					self.finished = true;
+					return Some(Err(error.into()));
+				}
+			}
+		}
+	}
+}
+
+/// Reads the games of a PDN file one at a time. Games which can't be parsed
+/// are returned as errors, and the games after them are still read.
+pub fn games<R: Read>(reader: R) -> Games<BufReader<R>> {
+	Games::new(BufReader::new(reader))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn reads_one_game_at_a_time() {
+		let pdn =
+			"[Event \"One\"]\n1. 11-15 {a comment\nwith a * in it} 23-19 2-0 [Event \"Two\"]\n\
+		           1. 11-15 15 *\n\n[Event \"Three\"]\n1. 9-13 (1. 9-14) 1-1\n";
+		let games: Vec<_> = games(pdn.as_bytes()).collect();
+		assert_eq!(games.len(), 3);
+		assert_eq!(games[0].as_ref().unwrap().body().len(), 3);
+
+		let Err(error) = &games[1] else {
+			panic!("the second game has a square without a move");
+		};
+		let diagnostics = error.diagnostics();
+		assert!(!diagnostics.is_empty());
+		assert_eq!(diagnostics[0].line, 4);
+
+		let game = games[2].as_ref().unwrap();
+		assert_eq!(game.tags()[0].value(), "Three");
+		assert_eq!(game.body().len(), 2);
+	}
+
+	#[test]
+	fn unterminated_comments_are_reported() {
+		let filler = "this comment is never closed\n".repeat(MAX_UNTERMINATED_LEN / 16);
+		let pdn = format!("[Event \"One\"]\n1. 11-15 {{{filler}");
+		let mut games = games(pdn.as_bytes());
+
+		let Some(Err(StreamError::ParseError { text, .. })) = games.next() else {
+			panic!("the comment is never closed");
+		};
+		assert!(text.len() < pdn.len());
+	}
+}
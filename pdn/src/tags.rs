@@ -0,0 +1,184 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use model::GameType;
+
+use crate::grammar::Game;
+use crate::tokens::GameResult;
+
+/// The value of a `Date` tag, such as `2024.05.17`. Parts of the date which
+/// aren't known are written as question marks, such as `1998.??.??`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PdnDate {
+	pub year: Option<u16>,
+	pub month: Option<u8>,
+	pub day: Option<u8>,
+}
+
+impl FromStr for PdnDate {
+	type Err = ();
+
+	fn from_str(date: &str) -> Result<Self, Self::Err> {
+		/// Reads a part of the date, which is `None` if it's all question marks
+		fn part<T: FromStr>(part: &str, len: usize) -> Result<Option<T>, ()> {
+			if part.len() != len {
+				Err(())
+			} else if part.chars().all(|c| c == '?') {
+				Ok(None)
+			} else if part.chars().all(|c| c.is_ascii_digit()) {
+				part.parse().map(Some).map_err(|_| ())
+			} else {
+				Err(())
+			}
+		}
+
+		let mut parts = date.trim().split('.');
+		let (Some(year), Some(month), Some(day), None) =
+			(parts.next(), parts.next(), parts.next(), parts.next())
+		else {
+			return Err(());
+		};
+
+		let date = Self {
+			year: part(year, 4)?,
+			month: part(month, 2)?,
+			day: part(day, 2)?,
+		};
+		let valid_month = date.month.is_none_or(|month| (1..=12).contains(&month));
+		let valid_day = date.day.is_none_or(|day| (1..=31).contains(&day));
+		if valid_month && valid_day {
+			Ok(date)
+		} else {
+			Err(())
+		}
+	}
+}
+
+/// Writes the date as it's written in PDN, such as `2024.05.17`
+impl Display for PdnDate {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self.year {
+			Some(year) => write!(f, "{year:04}")?,
+			None => f.write_str("????")?,
+		}
+		for part in [self.month, self.day] {
+			match part {
+				Some(part) => write!(f, ".{part:02}")?,
+				None => f.write_str(".??")?,
+			}
+		}
+		Ok(())
+	}
+}
+
+impl Game {
+	/// The value of a tag. Tag names are matched without regard to case. If
+	/// the tag was written more than once, the first value is used.
+	pub fn tag(&self, name: &str) -> Option<&str> {
+		self.tags()
+			.iter()
+			.find(|tag| tag.identifier().eq_ignore_ascii_case(name))
+			.map(|tag| tag.value())
+	}
+
+	/// The name of the tournament or match
+	pub fn event(&self) -> Option<&str> {
+		self.tag("Event")
+	}
+
+	/// Where the game was played
+	pub fn site(&self) -> Option<&str> {
+		self.tag("Site")
+	}
+
+	/// When the game was played, or `None` if the `Date` tag is missing or
+	/// isn't a valid date
+	pub fn date(&self) -> Option<PdnDate> {
+		self.tag("Date")?.parse().ok()
+	}
+
+	pub fn round(&self) -> Option<&str> {
+		self.tag("Round")
+	}
+
+	/// The player of the light pieces
+	pub fn white(&self) -> Option<&str> {
+		self.tag("White")
+	}
+
+	/// The player of the dark pieces
+	pub fn black(&self) -> Option<&str> {
+		self.tag("Black")
+	}
+
+	/// The value of the `Result` tag. Use [`Game::result`] for the result at
+	/// the end of the moves.
+	pub fn result_tag(&self) -> Option<GameResult> {
+		self.tag("Result")?.parse().ok()
+	}
+
+	/// The rules the game was played with. Games without a `GameType` tag
+	/// are English draughts. Returns `None` if the tag isn't valid.
+	pub fn game_type(&self) -> Option<GameType> {
+		match self.tag("GameType") {
+			Some(game_type) => game_type.parse().ok(),
+			None => Some(GameType::ENGLISH_DRAUGHTS),
+		}
+	}
+
+	/// Whether the game can be played by Ampere. Games of other variants, or
+	/// with an invalid `GameType` tag, aren't supported.
+	pub fn is_supported(&self) -> bool {
+		self.game_type().is_some_and(GameType::is_supported)
+	}
+
+	/// The position the game starts from, in FEN
+	pub fn fen(&self) -> Option<&str> {
+		self.tag("FEN")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_dates() {
+		let date: PdnDate = "2024.05.17".parse().unwrap();
+		assert_eq!(
+			date,
+			PdnDate {
+				year: Some(2024),
+				month: Some(5),
+				day: Some(17)
+			}
+		);
+		assert_eq!(date.to_string(), "2024.05.17");
+
+		let date: PdnDate = "1998.??.??".parse().unwrap();
+		assert_eq!(date.month, None);
+		assert_eq!(date.to_string(), "1998.??.??");
+
+		assert!("2024.13.01".parse::<PdnDate>().is_err());
+		assert!("2024-05-17".parse::<PdnDate>().is_err());
+		assert!("24.05.17".parse::<PdnDate>().is_err());
+	}
+
+	#[test]
+	fn reads_tags() {
+		let pdn = "[Event \"Open\"]\n[DATE \"2001.??.03\"]\n[Result \"1/2-1/2\"]\n\n1. 11-15 *";
+		let file = crate::parse(pdn).unwrap();
+		let game = &file.games()[0];
+		assert_eq!(game.event(), Some("Open"));
+		assert_eq!(game.date().unwrap().day, Some(3));
+		assert_eq!(game.result_tag(), Some(GameResult::Draw));
+		assert_eq!(game.result(), GameResult::Unfinished);
+		assert_eq!(game.white(), None);
+		assert!(game.is_supported());
+
+		let pdn = "[GameType \"20,W,10,10,N2,0\"]\n\n1. 32-28 *";
+		let file = crate::parse(pdn).unwrap();
+		assert_eq!(file.games()[0].game_type().unwrap().board_width, 10);
+		assert!(!file.games()[0].is_supported());
+	}
+}
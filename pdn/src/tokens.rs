@@ -1,13 +1,65 @@
+use std::str::FromStr;
 use std::sync::Arc;
 
+use model::PieceColor;
 use snob::{csets, csets::CharacterSet, Scanner};
 
+use crate::ParseMode;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Color {
 	White,
 	Black,
 }
 
+/// How a game ended, as it's written at the end of the move text. The first
+/// number is black's score, such as `2-0` or `1-0` for a win for black.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameResult {
+	BlackWin,
+	WhiteWin,
+	Draw,
+	/// The game hasn't finished, or the result isn't known, written as `*`
+	Unfinished,
+}
+
+impl GameResult {
+	/// The result of a game where `color` has no moves left. Black is the
+	/// dark player, who moves first.
+	pub const fn loss_for(color: PieceColor) -> Self {
+		match color {
+			PieceColor::Dark => Self::WhiteWin,
+			PieceColor::Light => Self::BlackWin,
+		}
+	}
+
+	/// The result, as it's written in PDN. Wins are written as `2-0` or
+	/// `0-2`, and draws as `1-1`.
+	pub fn notation(self) -> &'static str {
+		match self {
+			Self::BlackWin => "2-0",
+			Self::WhiteWin => "0-2",
+			Self::Draw => "1-1",
+			Self::Unfinished => "*",
+		}
+	}
+}
+
+/// Reads a result, such as `2-0`, `1-0`, `1/2-1/2`, or `*`
+impl FromStr for GameResult {
+	type Err = ();
+
+	fn from_str(result: &str) -> Result<Self, Self::Err> {
+		match result.trim() {
+			"2-0" | "1-0" => Ok(Self::BlackWin),
+			"0-2" | "0-1" => Ok(Self::WhiteWin),
+			"1-1" | "1/2-1/2" => Ok(Self::Draw),
+			"*" => Ok(Self::Unfinished),
+			_ => Err(()),
+		}
+	}
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum PdnTokenBody {
 	MoveNumber(usize, Color),
@@ -21,7 +73,7 @@ pub enum PdnTokenBody {
 	RightParenthesis,
 	LeftBracket,
 	RightBracket,
-	Asterisk,
+	GameResult(GameResult),
 	Setup(Arc<str>),
 	String(Arc<str>),
 	Comment(Arc<str>),
@@ -29,12 +81,39 @@ pub enum PdnTokenBody {
 	Space(Arc<str>),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// Where a token is in the source. Tokens which weren't read from a source,
+/// such as those made by a builder, have an empty header.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TokenHeader {
 	start: usize,
 	len: usize,
 }
 
+impl TokenHeader {
+	/// The position of the first character of the token, counted in
+	/// characters from the start of the source
+	pub fn start(self) -> usize {
+		self.start
+	}
+
+	/// The length of the token, in characters
+	pub fn len(self) -> usize {
+		self.len
+	}
+
+	pub fn is_empty(self) -> bool {
+		self.len == 0
+	}
+
+	/// The text from the start of this token to the end of `end`
+	pub(crate) fn through(self, end: TokenHeader) -> TokenHeader {
+		TokenHeader {
+			start: self.start,
+			len: (end.start + end.len).saturating_sub(self.start),
+		}
+	}
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PdnToken {
 	pub header: TokenHeader,
@@ -49,19 +128,69 @@ pub enum TokenErrorType {
 	UnterminatedSetup,
 	UnterminatedComment,
 	UnterminatedString,
+	/// The name of a tag doesn't start with an uppercase letter. This is only
+	/// an error in strict mode.
+	LowercaseIdentifier,
 	InvalidToken,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TokenError {
 	header: TokenHeader,
 	ty: TokenErrorType,
 }
 
+impl TokenError {
+	/// Where the invalid text is in the source
+	pub fn header(&self) -> TokenHeader {
+		self.header
+	}
+
+	pub fn error_type(&self) -> &TokenErrorType {
+		&self.ty
+	}
+}
+
 pub struct PdnScanner {
 	scanner: Scanner,
+	mode: ParseMode,
+	/// Whether the last token, other than whitespace, was a `[`, so that the
+	/// next token should be the name of a tag
+	after_bracket: bool,
+}
+
+/// Uses `\n` for every line break, whether the file was written with `\r\n`
+/// or `\r`
+fn normalize_line_endings(text: String) -> String {
+	if text.contains('\r') {
+		text.replace("\r\n", "\n").replace('\r', "\n")
+	} else {
+		text
+	}
 }
 
 impl PdnScanner {
+	pub fn new(source: &str) -> Self {
+		Self::with_mode(source, ParseMode::default())
+	}
+
+	/// Reads the tokens of the source. In lenient mode, the line breaks in
+	/// comments and strings are normalized, and tag names may be lowercase.
+	pub fn with_mode(source: &str, mode: ParseMode) -> Self {
+		Self {
+			scanner: Scanner::new(source),
+			mode,
+			after_bracket: false,
+		}
+	}
+
+	fn normalize(&self, text: String) -> String {
+		match self.mode {
+			ParseMode::Strict => text,
+			ParseMode::Lenient => normalize_line_endings(text),
+		}
+	}
+
 	fn scan_string(&mut self) -> Option<String> {
 		let mut string = String::new();
 		loop {
@@ -101,6 +230,31 @@ impl PdnScanner {
 		Some(string)
 	}
 
+	/// Scans a result other than `*`, such as `2-0` or `1/2-1/2`. A result
+	/// can't be followed by a digit, so that moves such as `1-12` aren't
+	/// mistaken for results.
+	fn scan_result(&mut self) -> Option<GameResult> {
+		const RESULTS: [(&str, GameResult); 6] = [
+			("1/2-1/2", GameResult::Draw),
+			("2-0", GameResult::BlackWin),
+			("1-0", GameResult::BlackWin),
+			("0-2", GameResult::WhiteWin),
+			("0-1", GameResult::WhiteWin),
+			("1-1", GameResult::Draw),
+		];
+
+		let (position, result) = RESULTS.iter().find_map(|(notation, result)| {
+			let position = self.scanner.starts_with(notation)?;
+			let followed_by_digit = self
+				.scanner
+				.char_at(position)
+				.is_some_and(|c| c.is_ascii_digit());
+			(!followed_by_digit).then_some((position, *result))
+		})?;
+		self.scanner.goto(position);
+		Some(result)
+	}
+
 	fn scan_number(&mut self) -> Option<usize> {
 		let position = self.scanner.many(csets::AsciiDigits)?;
 		let number = self
@@ -168,7 +322,7 @@ impl PdnScanner {
 			Ok(PdnTokenBody::RightBracket)
 		} else if let Some(position) = self.scanner.any('*') {
 			self.scanner.goto(position);
-			Ok(PdnTokenBody::Asterisk)
+			Ok(PdnTokenBody::GameResult(GameResult::Unfinished))
 		} else if let Some(position) = self.scanner.any('$') {
 			self.scanner.goto(position);
 			match self.scan_number() {
@@ -184,13 +338,13 @@ impl PdnScanner {
 		} else if let Some(position) = self.scanner.any('{') {
 			self.scanner.goto(position);
 			match self.scan_unescaped_string('}') {
-				Some(string) => Ok(PdnTokenBody::Comment(string.into())),
+				Some(string) => Ok(PdnTokenBody::Comment(self.normalize(string).into())),
 				None => Err(TokenErrorType::UnterminatedComment),
 			}
 		} else if let Some(position) = self.scanner.any('"') {
 			self.scanner.goto(position);
 			match self.scan_string() {
-				Some(string) => Ok(PdnTokenBody::String(string.into())),
+				Some(string) => Ok(PdnTokenBody::String(self.normalize(string).into())),
 				None => Err(TokenErrorType::UnterminatedString),
 			}
 		} else if let Some(position) = self.scanner.many("?!") {
@@ -199,6 +353,16 @@ impl PdnScanner {
 				.goto(position)
 				.expect("position should be valid");
 			Ok(PdnTokenBody::MoveStrength(strength.into()))
+		} else if self.after_bracket && self.scanner.any(csets::AsciiLetters).is_some() {
+			let identifier = self
+				.scan_identifier()
+				.expect("should be a valid identifier");
+			let lowercase = identifier.starts_with(|c: char| c.is_ascii_lowercase());
+			if lowercase && self.mode == ParseMode::Strict {
+				Err(TokenErrorType::LowercaseIdentifier)
+			} else {
+				Ok(PdnTokenBody::Identifier(identifier.into()))
+			}
 		} else if let Some(position) = self.scanner.any("abcdefgh") {
 			let letter = self
 				.scanner
@@ -225,6 +389,8 @@ impl PdnScanner {
 				.scan_identifier()
 				.expect("should be a valid identifier");
 			Ok(PdnTokenBody::Identifier(identifier.into()))
+		} else if let Some(result) = self.scan_result() {
+			Ok(PdnTokenBody::GameResult(result))
 		} else if self.scanner.any(csets::AsciiDigits).is_some() {
 			let number = self.scan_number().expect("should be a valid number");
 			if let Some(position) = self.scanner.starts_with("...") {
@@ -245,9 +411,15 @@ impl PdnScanner {
 				.expect("position should be valid");
 			Ok(PdnTokenBody::Space(whitespace.into()))
 		} else {
+			// the invalid character may be a letter, so it's skipped first
+			self.scanner.advance(1);
 			let position = self
 				.scanner
-				.upto(csets::AsciiLetters.union(csets::AsciiDigits.union("-x(?!)[]")))
+				.upto(
+					csets::AsciiLetters
+						.union(csets::AsciiDigits.union("-x(?!)[]"))
+						.union(csets::AsciiWhitespace),
+				)
 				.unwrap_or_else(|| self.scanner.len());
 
 			self.scanner
@@ -271,6 +443,10 @@ impl Iterator for PdnScanner {
 		let len = end - start;
 		let header = TokenHeader { start, len };
 
+		if !matches!(token, Ok(PdnTokenBody::Space(_))) {
+			self.after_bracket = token == Ok(PdnTokenBody::LeftBracket);
+		}
+
 		let token = match token {
 			Ok(token) => Ok(PdnToken {
 				header,
@@ -0,0 +1,184 @@
+use crate::diagnostics::{locate, Diagnostic, Severity};
+use crate::grammar::{BodyPart, Game, PdnTag};
+use crate::semantic::{replay, SemanticError};
+use crate::tokens::{GameResult, TokenHeader};
+
+/// Something in a game which doesn't agree with the rest of the game. The
+/// game can still be read, but statistics about it may be wrong.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+	pub message: String,
+	/// Where the problem is in the source
+	pub span: TokenHeader,
+}
+
+impl Warning {
+	fn new(span: TokenHeader, message: String) -> Self {
+		Self { message, span }
+	}
+
+	/// Shows the warning with the line it's on. `source` must be the text that
+	/// the game was parsed from.
+	pub fn diagnostic(&self, source: &str) -> Diagnostic {
+		locate(
+			source,
+			self.span.start(),
+			self.span.len(),
+			Severity::Warning,
+			self.message.clone(),
+			None,
+		)
+	}
+}
+
+fn find_tag<'a>(game: &'a Game, name: &str) -> Option<&'a PdnTag> {
+	game.tags()
+		.iter()
+		.find(|tag| tag.identifier().eq_ignore_ascii_case(name))
+}
+
+/// Checks that the `FEN` and `SetUp` tags agree, and that the moves can be
+/// played from the position they describe
+fn check_setup(game: &Game, warnings: &mut Vec<Warning>) {
+	let fen = find_tag(game, "FEN");
+	let setup = find_tag(game, "SetUp");
+	match (fen, setup) {
+		(Some(fen), setup) if setup.map(PdnTag::value) != Some("1") => {
+			warnings.push(Warning::new(
+				fen.span(),
+				"the game has a `FEN` tag, but no `SetUp` tag with the value \"1\"".to_string(),
+			));
+		}
+		(None, Some(setup)) if setup.value() == "1" => {
+			warnings.push(Warning::new(
+				setup.span(),
+				"the `SetUp` tag is \"1\", but the game has no `FEN` tag".to_string(),
+			));
+		}
+		_ => (),
+	}
+
+	// other games can't be played by Ampere, so their moves aren't checked
+	if !game.is_supported() {
+		return;
+	}
+
+	let from = match fen {
+		Some(_) => "the position in the `FEN` tag",
+		None => "the starting position",
+	};
+	let warning = match replay(game) {
+		Ok(_) | Err(SemanticError::UnsupportedGameType(_)) => return,
+		Err(SemanticError::InvalidFen(fen)) => {
+			let span = find_tag(game, "FEN").map_or_else(TokenHeader::default, PdnTag::span);
+			Warning::new(span, format!("`{fen}` isn't a valid position"))
+		}
+		Err(SemanticError::InvalidSquare { square, .. }) => {
+			Warning::new(square.header(), "the square isn't on the board".to_string())
+		}
+		Err(SemanticError::IllegalMove { ply, game_move }) => Warning::new(
+			game_move.span(),
+			format!(
+				"move {} can't be played, when the game starts from {from}",
+				ply + 1
+			),
+		),
+		Err(SemanticError::AmbiguousMove { ply, game_move }) => Warning::new(
+			game_move.span(),
+			format!("move {} could be more than one capture", ply + 1),
+		),
+	};
+	warnings.push(warning);
+}
+
+/// Checks that the `Result` tag is the same as the result after the moves
+fn check_result(game: &Game, warnings: &mut Vec<Warning>) {
+	let Some(tag) = find_tag(game, "Result") else {
+		return;
+	};
+
+	match tag.value().parse::<GameResult>() {
+		Ok(result) if result != game.result() => warnings.push(Warning::new(
+			tag.span(),
+			format!(
+				"the `Result` tag is `{}`, but the moves end with `{}`",
+				result.notation(),
+				game.result().notation()
+			),
+		)),
+		Ok(_) => (),
+		Err(()) => warnings.push(Warning::new(
+			tag.span(),
+			format!("`{}` isn't a result, such as `2-0` or `1-1`", tag.value()),
+		)),
+	}
+}
+
+/// Checks that the `PlyCount` tag is the number of moves in the main line
+fn check_ply_count(game: &Game, warnings: &mut Vec<Warning>) {
+	let Some(tag) = find_tag(game, "PlyCount") else {
+		return;
+	};
+
+	let plies = game
+		.body()
+		.iter()
+		.filter(|part| matches!(part, BodyPart::Move(_)))
+		.count();
+	match tag.value().trim().parse::<usize>() {
+		Ok(count) if count != plies => warnings.push(Warning::new(
+			tag.span(),
+			format!("the `PlyCount` tag is {count}, but the game has {plies} plies"),
+		)),
+		Ok(_) => (),
+		Err(_) => warnings.push(Warning::new(
+			tag.span(),
+			format!(
+				"the `PlyCount` tag should be a number, not `{}`",
+				tag.value()
+			),
+		)),
+	}
+}
+
+/// Finds the tags which don't agree with the moves of a game. The warnings
+/// for the tags come before the warnings for the moves.
+pub fn validate(game: &Game) -> Vec<Warning> {
+	let mut warnings = Vec::new();
+	check_result(game, &mut warnings);
+	check_ply_count(game, &mut warnings);
+	check_setup(game, &mut warnings);
+	warnings
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn finds_inconsistent_tags() {
+		let pdn = "[Result \"2-0\"]\n[PlyCount \"3\"]\n1. 11-15 23-19 1-1";
+		let file = crate::parse(pdn).unwrap();
+		let warnings = validate(&file.games()[0]);
+		assert_eq!(warnings.len(), 2);
+		assert_eq!(
+			warnings[0].message,
+			"the `Result` tag is `2-0`, but the moves end with `1-1`"
+		);
+		let diagnostic = warnings[1].diagnostic(pdn);
+		assert_eq!((diagnostic.line, diagnostic.width), (2, 14));
+		assert!(diagnostic
+			.to_string()
+			.starts_with("warning: the `PlyCount` tag is 3"));
+
+		let pdn = "[FEN \"W:W21:B1\"]\n1. 11-15 *";
+		let file = crate::parse(pdn).unwrap();
+		let warnings = validate(&file.games()[0]);
+		assert_eq!(warnings.len(), 2);
+		assert_eq!(warnings[1].diagnostic(pdn).column, 4);
+
+		let pdn = "[Result \"1-1\"]\n[PlyCount \"2\"]\n1. 11-15 23-19 1/2-1/2";
+		let file = crate::parse(pdn).unwrap();
+		assert!(validate(&file.games()[0]).is_empty());
+	}
+}
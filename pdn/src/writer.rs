@@ -0,0 +1,383 @@
+use std::time::Duration;
+
+use model::SquareCoordinate;
+
+use crate::commands::{write_commands, Evaluation};
+use crate::grammar::{BodyPart, Game, GameMove, Move, PdnFile, PdnTag, Square, Variation};
+use crate::nag::Nag;
+use crate::tokens::{Color, GameResult};
+
+/// How the squares of moves are written
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SquareNotation {
+	/// Each square is written the way it was read
+	#[default]
+	AsWritten,
+	/// Squares are numbered from 1 to 32, such as `11-15`
+	Numeric,
+	/// Squares are written as a file and a rank, such as `c3-d4`
+	Alpha,
+}
+
+/// Settings for writing PDN
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WriterOptions {
+	/// The longest a line of move text can be. Lines aren't wrapped if this
+	/// is `None`. Tokens longer than this are put on their own line.
+	pub line_width: Option<usize>,
+	pub notation: SquareNotation,
+}
+
+impl Default for WriterOptions {
+	fn default() -> Self {
+		Self {
+			line_width: Some(80),
+			notation: SquareNotation::AsWritten,
+		}
+	}
+}
+
+/// Builds a game, one move at a time, so that it can be written as PDN
+#[derive(Debug, Clone)]
+pub struct GameBuilder {
+	header: Vec<PdnTag>,
+	body: Vec<BodyPart>,
+	result: GameResult,
+}
+
+impl Default for GameBuilder {
+	fn default() -> Self {
+		Self {
+			header: Vec::new(),
+			body: Vec::new(),
+			result: GameResult::Unfinished,
+		}
+	}
+}
+
+impl GameBuilder {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn tag(mut self, identifier: &str, value: &str) -> Self {
+		self.header.push(PdnTag::new(identifier, value));
+		self
+	}
+
+	/// Adds a move which isn't a capture, using numeric squares
+	pub fn normal_move(mut self, from: u8, to: u8) -> Self {
+		let game_move = Move::normal(Square::num(from), Square::num(to));
+		self.body
+			.push(BodyPart::Move(GameMove::new(game_move, None)));
+		self
+	}
+
+	/// Adds a capture, from the first square to the last, using numeric squares
+	pub fn capture(mut self, squares: &[u8]) -> Self {
+		let Some((&from, to)) = squares.split_first() else {
+			return self;
+		};
+		let game_move = Move::capture(Square::num(from), to.iter().map(|&to| Square::num(to)));
+		self.body
+			.push(BodyPart::Move(GameMove::new(game_move, None)));
+		self
+	}
+
+	/// Sets the time left on the player's clock after the last move
+	pub fn clock(mut self, clock: Duration) -> Self {
+		if let Some(game_move) = self.last_move() {
+			*game_move = game_move.clone().with_clock(clock);
+		}
+		self
+	}
+
+	/// Sets the evaluation of the position after the last move
+	pub fn evaluation(mut self, evaluation: Evaluation) -> Self {
+		if let Some(game_move) = self.last_move() {
+			*game_move = game_move.clone().with_evaluation(evaluation);
+		}
+		self
+	}
+
+	fn last_move(&mut self) -> Option<&mut GameMove> {
+		self.body.iter_mut().rev().find_map(|part| match part {
+			BodyPart::Move(game_move) => Some(game_move),
+			_ => None,
+		})
+	}
+
+	pub fn comment(mut self, comment: &str) -> Self {
+		self.body
+			.push(BodyPart::Comment(Default::default(), comment.into()));
+		self
+	}
+
+	pub fn nag(mut self, nag: Nag) -> Self {
+		self.body.push(BodyPart::Nag(Default::default(), nag));
+		self
+	}
+
+	/// Adds a variation to the last move. The variation's tags are ignored.
+	pub fn variation(mut self, variation: GameBuilder) -> Self {
+		self.body
+			.push(BodyPart::Variation(Variation::new(variation.body)));
+		self
+	}
+
+	/// Sets the result written after the moves. Games are unfinished by
+	/// default.
+	pub fn result(mut self, result: GameResult) -> Self {
+		self.result = result;
+		self
+	}
+
+	pub fn build(self) -> Game {
+		Game::new(self.header, self.body, self.result)
+	}
+}
+
+pub(crate) fn write_square(square: &Square, notation: SquareNotation) -> String {
+	match (square, notation) {
+		(Square::Num(_, number), SquareNotation::Alpha) if (1..=32).contains(number) => {
+			SquareCoordinate::from_normal_value(*number as usize - 1).to_string()
+		}
+		(Square::Alpha(_, file, rank), SquareNotation::Numeric)
+			if ('a'..='h').contains(file) && ('1'..='8').contains(rank) =>
+		{
+			let coordinate = SquareCoordinate::new(*rank as u8 - b'1', *file as u8 - b'a');
+			match coordinate.to_normal_value() {
+				Some(number) => number.to_string(),
+				None => format!("{file}{rank}"),
+			}
+		}
+		(Square::Num(_, number), _) => number.to_string(),
+		(Square::Alpha(_, file, rank), _) => format!("{file}{rank}"),
+	}
+}
+
+fn write_move(game_move: &GameMove, notation: SquareNotation) -> String {
+	let mut text = match game_move.game_move() {
+		Move::Normal(from, _, to) => {
+			format!(
+				"{}-{}",
+				write_square(from, notation),
+				write_square(to, notation)
+			)
+		}
+		Move::Capture(..) => game_move
+			.game_move()
+			.squares()
+			.into_iter()
+			.map(|square| write_square(square, notation))
+			.collect::<Vec<_>>()
+			.join("x"),
+	};
+
+	if let Some(strength) = game_move.move_strength() {
+		text.push_str(strength);
+	}
+	text
+}
+
+/// Escapes the quotes in a tag's value
+pub(crate) fn write_tag(tag: &PdnTag) -> String {
+	format!(
+		"[{} \"{}\"]",
+		tag.identifier(),
+		tag.value().replace('"', "\\\"")
+	)
+}
+
+/// Adds the words of a comment to `words`, with its braces
+fn write_comment(comment: &str, words: &mut Vec<String>) {
+	let mut comment_words: Vec<String> = comment.split_whitespace().map(str::to_string).collect();
+	if comment_words.is_empty() {
+		comment_words.push(String::new());
+	}
+	comment_words[0].insert(0, '{');
+	comment_words.last_mut().expect("there is a word").push('}');
+	words.extend(comment_words);
+}
+
+/// Adds the move text to `words`, numbering the moves from `ply`, which is
+/// zero for the first player's first move
+fn write_body(body: &[BodyPart], ply: usize, notation: SquareNotation, words: &mut Vec<String>) {
+	let mut ply = ply;
+	let mut last_ply = ply;
+	// the second player's moves are only numbered after an interruption
+	let mut interrupted = true;
+	for part in body {
+		match part {
+			BodyPart::Move(game_move) => {
+				if let Some((number, color)) = game_move.move_number() {
+					ply = number.saturating_sub(1) * 2 + usize::from(color == Color::Black);
+				}
+				if ply.is_multiple_of(2) {
+					words.push(format!("{}.", ply / 2 + 1));
+				} else if interrupted {
+					words.push(format!("{}...", ply / 2 + 1));
+				}
+				words.push(write_move(game_move, notation));
+				last_ply = ply;
+				ply += 1;
+				interrupted = false;
+
+				let commands = write_commands(game_move.clock(), game_move.evaluation());
+				if !commands.is_empty() {
+					write_comment(&commands, words);
+					interrupted = true;
+				}
+			}
+			BodyPart::Variation(variation) => {
+				let start = words.len();
+				write_body(variation.body(), last_ply, notation, words);
+				match words.get_mut(start) {
+					Some(first) => first.insert(0, '('),
+					None => words.push("(".to_string()),
+				}
+				words.last_mut().expect("there is a word").push(')');
+				interrupted = true;
+			}
+			BodyPart::Comment(_, comment) => {
+				write_comment(comment, words);
+				interrupted = true;
+			}
+			BodyPart::Setup(_, setup) => words.push(format!("/{setup}/")),
+			BodyPart::Nag(_, nag) => words.push(format!("${}", nag.code())),
+		}
+	}
+}
+
+/// Joins the words with spaces, starting a new line before any word which
+/// would make the line longer than `width`
+pub(crate) fn wrap(words: &[String], width: Option<usize>) -> String {
+	let mut text = String::new();
+	let mut line_length = 0;
+	for word in words {
+		let length = word.chars().count();
+		if line_length == 0 {
+			// the first word of a line
+		} else if width.is_some_and(|width| line_length + 1 + length > width) {
+			text.push('\n');
+			line_length = 0;
+		} else {
+			text.push(' ');
+			line_length += 1;
+		}
+		text.push_str(word);
+		line_length += length;
+	}
+	text
+}
+
+/// Writes a game as PDN, with its tags, a blank line, and then the moves and
+/// the result
+pub fn write_game(game: &Game, options: &WriterOptions) -> String {
+	let mut pdn = String::new();
+	for tag in game.tags() {
+		pdn.push_str(&write_tag(tag));
+		pdn.push('\n');
+	}
+	if !game.tags().is_empty() {
+		pdn.push('\n');
+	}
+
+	let mut words = Vec::new();
+	write_body(game.body(), 0, options.notation, &mut words);
+	words.push(game.result().notation().to_string());
+	pdn.push_str(&wrap(&words, options.line_width));
+	pdn.push('\n');
+	pdn
+}
+
+/// Writes every game in a file, with a blank line between each game
+pub fn write_file(file: &PdnFile, options: &WriterOptions) -> String {
+	file.games()
+		.iter()
+		.map(|game| write_game(game, options))
+		.collect::<Vec<_>>()
+		.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn writes_games() {
+		let variation = GameBuilder::new().normal_move(22, 18).comment("also good");
+		let game = GameBuilder::new()
+			.tag("Event", "The \"Open\"")
+			.normal_move(11, 15)
+			.normal_move(23, 19)
+			.variation(variation)
+			.capture(&[15, 24])
+			.nag(Nag::GoodMove)
+			.result(GameResult::Draw)
+			.build();
+
+		let options = WriterOptions::default();
+		assert_eq!(
+			write_game(&game, &options),
+			"[Event \"The \\\"Open\\\"\"]\n\n\
+			 1. 11-15 23-19 (1... 22-18 {also good}) 2. 15x24 $1 1-1\n"
+		);
+
+		let options = WriterOptions {
+			line_width: Some(12),
+			notation: SquareNotation::Alpha,
+		};
+		let game = GameBuilder::new()
+			.normal_move(11, 15)
+			.normal_move(23, 19)
+			.build();
+		assert_eq!(write_game(&game, &options), "1. c3-d4\nd6-c5 *\n");
+	}
+
+	#[test]
+	fn round_trips() {
+		let pdn = "[Event \"Test\"]\n\n1. 11-15 {a comment} 1... 23-19 2. 8-11 *\n";
+		let file = crate::parse(pdn).unwrap();
+		let options = WriterOptions {
+			line_width: None,
+			notation: SquareNotation::Numeric,
+		};
+		assert_eq!(write_file(&file, &options), pdn);
+
+		let pdn = "[Event \"Test\"]\n\n1. 11-15 0-2\n\n[Event \"Test\"]\n\n1. 1-10 1/2-1/2\n";
+		let file = crate::parse(pdn).unwrap();
+		assert_eq!(file.games()[0].result(), GameResult::WhiteWin);
+		assert_eq!(file.games()[1].result(), GameResult::Draw);
+		assert_eq!(write_file(&file, &options), pdn.replace("1/2-1/2", "1-1"));
+	}
+
+	#[test]
+	fn writes_clocks_and_evaluations() {
+		let evaluation = Evaluation {
+			score: -0.5,
+			depth: Some(12),
+		};
+		let game = GameBuilder::new()
+			.normal_move(11, 15)
+			.clock(Duration::from_secs(332))
+			.evaluation(evaluation)
+			.normal_move(23, 19)
+			.build();
+		let options = WriterOptions::default();
+		let pdn = "1. 11-15 {[%clk 0:05:32] [%eval -0.50/12]} 1... 23-19 *\n";
+		assert_eq!(write_game(&game, &options), pdn);
+
+		let file =
+			crate::parse("1. 11-15 $1 {[%clk 0:05:32] forced} 23-19 {[%eval -0.5/12]} *").unwrap();
+		let BodyPart::Move(game_move) = &file.games()[0].body()[0] else {
+			panic!("the game starts with a move");
+		};
+		assert_eq!(game_move.clock(), Some(Duration::from_secs(332)));
+		assert_eq!(file.games()[0].body().len(), 4);
+		let BodyPart::Move(game_move) = &file.games()[0].body()[3] else {
+			panic!("the comment after the second move only has an evaluation");
+		};
+		assert_eq!(game_move.evaluation(), Some(evaluation));
+	}
+}
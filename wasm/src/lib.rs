@@ -0,0 +1,192 @@
+use std::num::NonZeroU8;
+use std::sync::Arc;
+use std::time::Duration;
+
+use engine::{
+	complete_line, fen, find_move, full_moves, parse_fen, square_from_number, ActualLimit, Clock,
+	EvaluationSettings, FullMove, InfoFrontend, SearchLimit,
+};
+use model::{CheckersBitBoard, PieceColor};
+use wasm_bindgen::prelude::*;
+
+/// A checkers position. Squares are numbered from 1 to 32, and moves are
+/// written with every square the piece lands on, such as `9x18x27`.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Board {
+	board: CheckersBitBoard,
+}
+
+impl Default for Board {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+#[wasm_bindgen]
+impl Board {
+	/// The starting position
+	#[wasm_bindgen(constructor)]
+	pub fn new() -> Self {
+		Self {
+			board: CheckersBitBoard::starting_position(),
+		}
+	}
+
+	#[wasm_bindgen(js_name = fromFen)]
+	pub fn from_fen(fen: &str) -> Result<Board, JsError> {
+		parse_fen(fen)
+			.map(|board| Self { board })
+			.ok_or_else(|| JsError::new(&format!("invalid FEN: {fen}")))
+	}
+
+	pub fn fen(&self) -> String {
+		fen(self.board)
+	}
+
+	/// The side to move, either `black` or `white`
+	pub fn turn(&self) -> String {
+		match self.board.turn() {
+			PieceColor::Dark => "black".to_string(),
+			PieceColor::Light => "white".to_string(),
+		}
+	}
+
+	/// The piece on a square, as `b` or `w` for men, and `B` or `W` for kings.
+	/// Returns `undefined` for an empty square.
+	pub fn piece(&self, square: usize) -> Option<String> {
+		let square = square_from_number(square)?;
+		let piece = match (self.board.color_at(square)?, self.board.king_at(square)?) {
+			(PieceColor::Dark, false) => "b",
+			(PieceColor::Dark, true) => "B",
+			(PieceColor::Light, false) => "w",
+			(PieceColor::Light, true) => "W",
+		};
+
+		Some(piece.to_string())
+	}
+
+	#[wasm_bindgen(js_name = legalMoves)]
+	pub fn legal_moves(&self) -> Vec<String> {
+		full_moves(self.board)
+			.iter()
+			.map(FullMove::long_notation)
+			.collect()
+	}
+
+	/// The position after a move is played. The move may also be written
+	/// with only its starting and ending squares, such as `9x27`.
+	#[wasm_bindgen(js_name = playMove)]
+	pub fn play_move(&self, notation: &str) -> Result<Board, JsError> {
+		find_move(self.board, notation.trim())
+			.map(|full_move| Self {
+				board: full_move.result,
+			})
+			.ok_or_else(|| JsError::new(&format!("illegal move: {notation}")))
+	}
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+	/// The best move, or `undefined` if there are no legal moves
+	#[wasm_bindgen(js_name = bestMove)]
+	pub best_move: Option<String>,
+	/// The evaluation, such as `+0.25` or `+M5`
+	pub score: String,
+	pub depth: u8,
+	pub nodes: usize,
+	/// The line the engine expects to be played
+	pub pv: Vec<String>,
+}
+
+/// The engine, which searches on the calling thread
+#[wasm_bindgen]
+pub struct Engine {
+	engine: engine::Engine,
+	frontend: Arc<InfoFrontend>,
+}
+
+#[wasm_bindgen]
+impl Engine {
+	#[wasm_bindgen(constructor)]
+	pub fn new(hash_megabytes: usize) -> Self {
+		let frontend = Arc::new(InfoFrontend::default());
+		Self {
+			engine: engine::Engine::new(hash_megabytes.max(1) * engine::MEGABYTE, frontend.clone()),
+			frontend,
+		}
+	}
+
+	/// Searches a position. The search stops at `depth`, or after about
+	/// `milliseconds`. If neither is given, the engine decides how long to
+	/// think.
+	pub fn search(
+		&self,
+		board: &Board,
+		depth: Option<u8>,
+		milliseconds: Option<f64>,
+	) -> SearchResult {
+		let depth = depth.and_then(NonZeroU8::new);
+		let time = milliseconds.and_then(|ms| Duration::try_from_secs_f64(ms / 1000.0).ok());
+		let search_until = match depth {
+			Some(depth) => SearchLimit::Limited(ActualLimit {
+				nodes: None,
+				depth: Some(depth),
+				time,
+			}),
+			None => SearchLimit::Auto,
+		};
+
+		self.frontend.clear();
+		self.engine.set_position(board.board);
+		let (eval, best_move) = self.engine.evaluate(
+			None,
+			EvaluationSettings {
+				clock: time.map_or_else(Clock::default, Clock::TimePerMove),
+				search_until,
+				..EvaluationSettings::default()
+			},
+		);
+
+		let pv: Vec<String> = complete_line(board.board, &self.engine.expected_line(best_move))
+			.iter()
+			.map(FullMove::long_notation)
+			.collect();
+
+		let info = self.frontend.latest();
+		SearchResult {
+			best_move: pv.first().cloned(),
+			score: eval.to_string(),
+			depth: info.map_or(0, |info| info.depth),
+			nodes: info.map_or(0, |info| info.nodes),
+			pv,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn plays_moves() {
+		let board = Board::new();
+		assert_eq!(board.legal_moves().len(), 7);
+		assert_eq!(board.piece(1).as_deref(), Some("b"));
+
+		let board = board.play_move("11-15").unwrap();
+		assert_eq!(board.turn(), "white");
+		assert_eq!(board.piece(11), None);
+		assert_eq!(board.piece(15).as_deref(), Some("b"));
+	}
+
+	#[test]
+	fn searches() {
+		let engine = Engine::new(1);
+		let result = engine.search(&Board::new(), Some(3), None);
+		let best_move = result.best_move.unwrap();
+		assert!(Board::new().legal_moves().contains(&best_move));
+		assert_eq!(result.pv[0], best_move);
+	}
+}